@@ -0,0 +1,573 @@
+//! `#[derive(Patch)]` for [serde-patch](https://crates.io/crates/serde-patch).
+//!
+//! Use it through the `derive` feature of `serde-patch`, which re-exports the
+//! macro as `serde_patch::Patch`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Data, DeriveInput, Fields, Ident, Meta, Token, parse_macro_input};
+
+/// Serde options copied from the struct to its patch struct, so both read the same JSON.
+const CONTAINER_SERDE: &[&str] = &["rename_all", "deny_unknown_fields"];
+
+/// Serde options copied from each field to its patch field.
+const FIELD_SERDE: &[&str] = &["rename", "alias", "skip_serializing", "skip_deserializing"];
+
+/// Serde options that only matter for the original struct: patch fields are
+/// always optional and skipped when unset.
+const FIELD_SERDE_IGNORED: &[&str] = &["default", "skip_serializing_if"];
+
+/// Diesel options copied from each field to its patch field.
+const FIELD_DIESEL: &[&str] = &["column_name", "serialize_as"];
+
+/// Generates a `<Name>Patch` struct whose fields are all optional, and an
+/// `apply_patch(&mut self, patch)` method that overwrites the fields the patch sets.
+///
+/// The patch struct implements `Default`, `Serialize` and `Deserialize`,
+/// skipping unset fields when serialized, so it is also a valid JSON Merge
+/// Patch body, and has an `is_empty()` method. `rename_all`,
+/// `deny_unknown_fields`, `rename`, `alias`, `skip_serializing` and
+/// `skip_deserializing` serde options are carried over; `with`,
+/// `serialize_with` and `deserialize_with` apply to the value of a set patch
+/// field; `#[serde(skip)]` fields are left out of the patch. Other field
+/// options, e.g. `flatten`, are rejected.
+///
+/// Container options:
+///
+/// - `#[patch(name = "UserChanges")]` names the generated struct.
+/// - `#[patch(derive(Debug, Clone))]` adds derives to the generated struct.
+/// - `#[patch(diff)]` also generates `diff(&self, other) -> <Name>Patch`,
+///   comparing fields with `PartialEq` and cloning those that differ, for
+///   diffing without serializing either side.
+/// - `#[patch(diesel(table_name = users))]` (feature `diesel`) derives Diesel's
+///   `AsChangeset` with the given options, so the patch can be passed to
+///   `diesel::update(...).set(patch)`; `column_name` and `serialize_as` field
+///   options are carried over. Unset fields are left out of the changeset and
+///   `Some(None)` sets a nullable column to `NULL`.
+/// - `#[patch(sea_orm(active_model = "user::ActiveModel"))]` (feature `sea-orm`)
+///   implements `From<UserPatch>` for the SeaORM active model: fields the
+///   patch sets become `Set(...)`, all others stay `NotSet`.
+///
+/// Field options:
+///
+/// - `#[patch(redact)]` implements `serde_patch::Redact` for the struct, listing
+///   the serialized names of the fields marked this way (after `rename` and
+///   `rename_all`) as `REDACTED_PATHS`, so audit records never show their
+///   values. Without any such field no `Redact` impl is generated.
+#[proc_macro_derive(Patch, attributes(patch))]
+pub fn derive_patch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Patch can only be derived for structs with named fields",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Patch can only be derived for structs with named fields",
+        ));
+    };
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let options = PatchOptions::parse(&input.attrs)?;
+    let patch_name = options
+        .name
+        .unwrap_or_else(|| format_ident!("{}Patch", name));
+    let extra_derives = options.derives;
+    let container_serde = forwarded_options(&input.attrs, "serde", CONTAINER_SERDE)?;
+    let diesel = options.diesel.as_ref().map(|diesel| {
+        quote! {
+            #[derive(::diesel::AsChangeset)]
+            #[diesel(#diesel)]
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let generics = &input.generics;
+
+    let mut patch_fields = Vec::new();
+    let mut assignments = Vec::new();
+    let mut active_assignments = Vec::new();
+    let mut comparisons = Vec::new();
+    let mut diff_bounds = Vec::new();
+    let mut idents = Vec::new();
+    let mut helpers = Vec::new();
+    let mut redacted = Vec::new();
+    let rename_all = RenameRule::parse(&input.attrs)?;
+    for field in &fields.named {
+        let ident = field
+            .ident
+            .as_ref()
+            .ok_or_else(|| syn::Error::new(Span::call_site(), "Patch requires named fields"))?;
+        let ty = &field.ty;
+        let field_vis = &field.vis;
+        let serde = FieldSerde::parse(&field.attrs)?;
+        if FieldOptions::parse(&field.attrs)?.redact && !serde.skip {
+            let key = match &serde.rename {
+                Some(rename) => rename.clone(),
+                None => rename_all.apply(&ident.to_string()),
+            };
+            redacted.push(key);
+        }
+        if serde.skip {
+            continue;
+        }
+        let field_serde = serde.forwarded;
+        let field_diesel = match options.diesel {
+            Some(_) => forwarded_options(&field.attrs, "diesel", FIELD_DIESEL)?,
+            None => Vec::new(),
+        };
+        let serialize_with = serde.serialize_with.map(|path| {
+            let helper = format_ident!("__serialize_{}", ident);
+            helpers.push(quote! {
+                #[doc(hidden)]
+                fn #helper<__S>(
+                    value: &::core::option::Option<#ty>,
+                    serializer: __S,
+                ) -> ::core::result::Result<__S::Ok, __S::Error>
+                where
+                    __S: ::serde_patch::__private::serde::Serializer,
+                {
+                    match value {
+                        ::core::option::Option::Some(value) => #path(value, serializer),
+                        ::core::option::Option::None => {
+                            ::serde_patch::__private::serde::Serializer::serialize_none(serializer)
+                        }
+                    }
+                }
+            });
+            let helper = helper_path(&patch_name, &ty_generics, &helper);
+            quote! { #[serde(serialize_with = #helper)] }
+        });
+        let deserialize_with = match serde.deserialize_with {
+            Some(path) => {
+                let helper = format_ident!("__deserialize_{}", ident);
+                helpers.push(quote! {
+                    #[doc(hidden)]
+                    fn #helper<'de, __D>(
+                        deserializer: __D,
+                    ) -> ::core::result::Result<::core::option::Option<#ty>, __D::Error>
+                    where
+                        __D: ::serde_patch::__private::serde::Deserializer<'de>,
+                    {
+                        #path(deserializer).map(::core::option::Option::Some)
+                    }
+                });
+                let helper = helper_path(&patch_name, &ty_generics, &helper);
+                Some(quote! { #[serde(deserialize_with = #helper)] })
+            }
+            // `null` must clear an `Option` field instead of reading as "absent".
+            None => is_option(ty).then(|| {
+                quote! { #[serde(deserialize_with = "::serde_patch::double_option::deserialize")] }
+            }),
+        };
+        patch_fields.push(quote! {
+            #[serde(default, skip_serializing_if = "::core::option::Option::is_none")]
+            #serialize_with
+            #deserialize_with
+            #(#field_serde)*
+            #(#field_diesel)*
+            #field_vis #ident: ::core::option::Option<#ty>,
+        });
+        assignments.push(quote! {
+            if let ::core::option::Option::Some(value) = patch.#ident {
+                self.#ident = value;
+            }
+        });
+        active_assignments.push(quote! {
+            if let ::core::option::Option::Some(value) = patch.#ident {
+                model.#ident = ::sea_orm::ActiveValue::Set(value);
+            }
+        });
+        comparisons.push(quote! {
+            if self.#ident != other.#ident {
+                patch.#ident = ::core::option::Option::Some(::core::clone::Clone::clone(&other.#ident));
+            }
+        });
+        diff_bounds.push(quote! { #ty: ::core::cmp::PartialEq + ::core::clone::Clone });
+        idents.push(ident);
+    }
+
+    let diff = options.diff.then(|| {
+        let predicates = where_clause
+            .into_iter()
+            .flat_map(|clause| clause.predicates.iter());
+        quote! {
+            impl #impl_generics #name #ty_generics
+            where
+                #(#predicates,)*
+                #(#diff_bounds,)*
+            {
+                /// The patch turning `self` into `other`: every field that
+                /// differs, set to its value in `other`.
+                pub fn diff(&self, other: &Self) -> #patch_name #ty_generics {
+                    let mut patch = #patch_name {
+                        #(#idents: ::core::option::Option::None,)*
+                    };
+                    #(#comparisons)*
+                    patch
+                }
+            }
+        }
+    });
+
+    let active_model = options.active_model.as_ref().map(|active_model| {
+        quote! {
+            impl #impl_generics ::core::convert::From<#patch_name #ty_generics> for #active_model
+            #where_clause
+            {
+                fn from(patch: #patch_name #ty_generics) -> Self {
+                    let mut model = <Self as ::core::default::Default>::default();
+                    #(#active_assignments)*
+                    model
+                }
+            }
+        }
+    });
+
+    let redact = (!redacted.is_empty()).then(|| {
+        quote! {
+            impl #impl_generics ::serde_patch::Redact for #name #ty_generics #where_clause {
+                const REDACTED_PATHS: &'static [&'static str] = &[#(#redacted),*];
+            }
+        }
+    });
+
+    let doc = format!(
+        "Partial update of [`{}`]; unset fields are left unchanged.",
+        name
+    );
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(
+            ::serde_patch::__private::serde::Serialize,
+            ::serde_patch::__private::serde::Deserialize,
+            #(#extra_derives),*
+        )]
+        #[serde(crate = "::serde_patch::__private::serde")]
+        #(#container_serde)*
+        #diesel
+        #vis struct #patch_name #generics #where_clause {
+            #(#patch_fields)*
+        }
+
+        // Written out so generic patch structs don't require `T: Default`.
+        impl #impl_generics ::core::default::Default for #patch_name #ty_generics #where_clause {
+            fn default() -> Self {
+                Self {
+                    #(#idents: ::core::option::Option::None,)*
+                }
+            }
+        }
+
+        impl #impl_generics #patch_name #ty_generics #where_clause {
+            /// Returns `true` if the patch sets no field.
+            pub fn is_empty(&self) -> bool {
+                true #(&& self.#idents.is_none())*
+            }
+
+            #(#helpers)*
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Overwrites every field set in `patch`.
+            pub fn apply_patch(&mut self, patch: #patch_name #ty_generics) {
+                #(#assignments)*
+            }
+        }
+
+        #diff
+        #active_model
+        #redact
+    })
+}
+
+#[derive(Default)]
+struct PatchOptions {
+    name: Option<Ident>,
+    derives: Vec<syn::Path>,
+    diesel: Option<TokenStream2>,
+    active_model: Option<syn::Path>,
+    diff: bool,
+}
+
+impl PatchOptions {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("patch")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    let name: syn::LitStr = meta.value()?.parse()?;
+                    options.name = Some(name.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("diff") {
+                    options.diff = true;
+                    Ok(())
+                } else if meta.path.is_ident("derive") {
+                    meta.parse_nested_meta(|derive| {
+                        options.derives.push(derive.path);
+                        Ok(())
+                    })
+                } else if meta.path.is_ident("diesel") {
+                    if !cfg!(feature = "diesel") {
+                        return Err(meta.error("`diesel(...)` requires the `diesel` feature"));
+                    }
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    options.diesel = Some(content.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("sea_orm") {
+                    if !cfg!(feature = "sea-orm") {
+                        return Err(meta.error("`sea_orm(...)` requires the `sea-orm` feature"));
+                    }
+                    meta.parse_nested_meta(|sea_orm| {
+                        if sea_orm.path.is_ident("active_model") {
+                            let path: syn::LitStr = sea_orm.value()?.parse()?;
+                            options.active_model = Some(path.parse()?);
+                            Ok(())
+                        } else {
+                            Err(sea_orm.error("expected `active_model = \"...\"`"))
+                        }
+                    })
+                } else {
+                    Err(meta.error(
+                        "expected `name = \"...\"`, `derive(...)`, `diff`, `diesel(...)` or `sea_orm(...)`",
+                    ))
+                }
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+/// The `#[patch(...)]` options of a field.
+#[derive(Default)]
+struct FieldOptions {
+    /// `#[patch(redact)]`: listed in the generated `Redact` impl.
+    redact: bool,
+}
+
+impl FieldOptions {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("patch")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("redact") {
+                    options.redact = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `redact`"))
+                }
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+/// A container's `#[serde(rename_all = "...")]` rule, applied to snake_case
+/// field names as serde does.
+#[derive(Clone, Copy, Default)]
+enum RenameRule {
+    #[default]
+    None,
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut rule = Self::None;
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+            for meta in metas {
+                let Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    value:
+                        syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(name),
+                            ..
+                        }),
+                    ..
+                }) = &meta
+                else {
+                    continue;
+                };
+                if !path.is_ident("rename_all") {
+                    continue;
+                }
+                rule = match name.value().as_str() {
+                    "lowercase" => Self::Lower,
+                    "UPPERCASE" => Self::Upper,
+                    "PascalCase" => Self::Pascal,
+                    "camelCase" => Self::Camel,
+                    "snake_case" => Self::Snake,
+                    "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+                    "kebab-case" => Self::Kebab,
+                    "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+                    _ => return Err(syn::Error::new_spanned(name, "unknown `rename_all` rule")),
+                };
+            }
+        }
+        Ok(rule)
+    }
+
+    fn apply(self, field: &str) -> String {
+        let field = field.strip_prefix("r#").unwrap_or(field);
+        match self {
+            Self::None | Self::Lower | Self::Snake => field.to_string(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal | Self::Camel => {
+                let mut out = String::new();
+                let mut capitalize = matches!(self, Self::Pascal);
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        out.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        out.push(ch);
+                    }
+                }
+                out
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+}
+
+/// The serde options of a field, sorted by how the patch field handles them.
+#[derive(Default)]
+struct FieldSerde {
+    /// Options re-emitted unchanged on the patch field.
+    forwarded: Vec<TokenStream2>,
+    /// Function writing the field's value.
+    serialize_with: Option<TokenStream2>,
+    /// Function reading the field's value.
+    deserialize_with: Option<TokenStream2>,
+    /// `#[serde(skip)]`: the field can't be patched.
+    skip: bool,
+    /// `#[serde(rename = "...")]`: the field's serialized name.
+    rename: Option<String>,
+}
+
+impl FieldSerde {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut serde = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+            for meta in metas {
+                let path = meta.path();
+                if FIELD_SERDE.iter().any(|name| path.is_ident(name)) {
+                    if let Meta::NameValue(syn::MetaNameValue {
+                        value:
+                            syn::Expr::Lit(syn::ExprLit {
+                                lit: syn::Lit::Str(name),
+                                ..
+                            }),
+                        ..
+                    }) = &meta
+                        && path.is_ident("rename")
+                    {
+                        serde.rename = Some(name.value());
+                    }
+                    serde.forwarded.push(quote! { #[serde(#meta)] });
+                } else if FIELD_SERDE_IGNORED.iter().any(|name| path.is_ident(name)) {
+                    continue;
+                } else if path.is_ident("skip") {
+                    serde.skip = true;
+                } else if path.is_ident("with") {
+                    let module = function_path(&meta)?;
+                    serde.serialize_with = Some(quote! { #module::serialize });
+                    serde.deserialize_with = Some(quote! { #module::deserialize });
+                } else if path.is_ident("serialize_with") {
+                    serde.serialize_with = Some(function_path(&meta)?);
+                } else if path.is_ident("deserialize_with") {
+                    serde.deserialize_with = Some(function_path(&meta)?);
+                } else {
+                    return Err(syn::Error::new_spanned(
+                        path,
+                        "this serde option is not supported by `#[derive(Patch)]`",
+                    ));
+                }
+            }
+        }
+        Ok(serde)
+    }
+}
+
+/// The path in a `name = "path"` option.
+fn function_path(meta: &Meta) -> syn::Result<TokenStream2> {
+    let Meta::NameValue(syn::MetaNameValue {
+        value:
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(path),
+                ..
+            }),
+        ..
+    }) = meta
+    else {
+        return Err(syn::Error::new_spanned(meta, "expected `= \"path\"`"));
+    };
+    let path: syn::ExprPath = path.parse()?;
+    Ok(quote! { #path })
+}
+
+/// A serde `*_with` string naming a helper on the patch struct.
+fn helper_path(
+    patch_name: &Ident,
+    ty_generics: &syn::TypeGenerics<'_>,
+    helper: &Ident,
+) -> syn::LitStr {
+    let path = quote! { <#patch_name #ty_generics>::#helper };
+    syn::LitStr::new(&path.to_string(), Span::call_site())
+}
+
+/// Whether `ty` is spelled as `Option<...>` (possibly path-qualified).
+fn is_option(ty: &syn::Type) -> bool {
+    let syn::Type::Path(path) = ty else {
+        return false;
+    };
+    path.qself.is_none()
+        && path.path.segments.last().is_some_and(|segment| {
+            segment.ident == "Option"
+                && matches!(segment.arguments, syn::PathArguments::AngleBracketed(_))
+        })
+}
+
+/// The `#[<tool>(...)]` options among `allowed`, re-emitted one per attribute.
+fn forwarded_options(
+    attrs: &[Attribute],
+    tool: &str,
+    allowed: &[&str],
+) -> syn::Result<Vec<TokenStream2>> {
+    let tool_ident = Ident::new(tool, Span::call_site());
+    let mut options = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident(tool)) {
+        let metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        for meta in metas {
+            if allowed.iter().any(|name| meta.path().is_ident(name)) {
+                options.push(quote! { #[#tool_ident(#meta)] });
+            }
+        }
+    }
+    Ok(options)
+}
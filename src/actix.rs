@@ -0,0 +1,241 @@
+//! `actix-web` extractor for JSON Merge Patch (RFC 7396) request bodies.
+//!
+//! [`Patch`] mirrors `web::Json`: it checks the `application/merge-patch+json`
+//! content type, reads the payload up to a size limit, and parses it, with
+//! the limit and error responses set through a [`PatchConfig`] in app data.
+
+use ::actix_web::dev::Payload;
+use ::actix_web::http::StatusCode;
+use ::actix_web::http::header::{self, ContentType};
+use ::actix_web::{FromRequest, HttpRequest, HttpResponse, Responder, ResponseError, web};
+use serde::{Serialize, de::DeserializeOwned};
+use std::fmt;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Extracts a merge patch request body.
+///
+/// `T` is what the body is parsed into: a [`Patch`](crate::Patch) by default,
+/// ready for [`Patch::apply_to`](crate::Patch::apply_to), or a typed patch
+/// struct from `#[derive(Patch)]`. Failures are [`PatchPayloadError`]s,
+/// turned into responses by the [`PatchConfig`] error handler.
+///
+/// Returned from a handler, it responds with the patch and the merge patch
+/// content type.
+///
+/// # Example
+///
+/// ```
+/// use actix_web::{App, web};
+/// use serde_patch::actix::{Patch, PatchConfig};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// async fn update_user(patch: Patch) -> Result<String, actix_web::Error> {
+///     let user = User { name: "alice".into(), age: 30 };
+///     let user = patch
+///         .apply_to(user)
+///         .map_err(actix_web::error::ErrorUnprocessableEntity)?;
+///     Ok(format!("{} is {}", user.name, user.age))
+/// }
+///
+/// let app = App::new()
+///     .app_data(PatchConfig::default().limit(64 * 1024))
+///     .route("/users/{id}", web::patch().to(update_user));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Patch<T = crate::Patch>(pub T);
+
+impl<T> Patch<T> {
+    /// Unwraps the extracted value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Patch<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Patch<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for Patch<T> {
+    type Error = ::actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let config = PatchConfig::from_req(&req).clone();
+        let body = web::Payload::from_request(&req, payload);
+        Box::pin(async move {
+            let result = async {
+                if !is_merge_patch(&req) {
+                    return Err(PatchPayloadError::ContentType);
+                }
+                let body = body.await.map_err(PatchPayloadError::Payload)?;
+                let bytes = body
+                    .to_bytes_limited(config.limit)
+                    .await
+                    .map_err(|_| PatchPayloadError::Overflow {
+                        limit: config.limit,
+                    })?
+                    .map_err(PatchPayloadError::Payload)?;
+                let value: serde_json::Value =
+                    crate::backend::from_slice(&bytes).map_err(PatchPayloadError::Syntax)?;
+                serde_path_to_error::deserialize(value)
+                    .map(Patch)
+                    .map_err(|err| PatchPayloadError::Data(err.to_string()))
+            }
+            .await;
+            result.map_err(|err| match &config.error_handler {
+                Some(handler) => handler(err, &req),
+                None => err.into(),
+            })
+        })
+    }
+}
+
+impl<T: Serialize> Responder for Patch<T> {
+    type Body = ::actix_web::body::BoxBody;
+
+    fn respond_to(self, _: &HttpRequest) -> HttpResponse {
+        match serde_json::to_vec(&self.0) {
+            Ok(body) => HttpResponse::Ok()
+                .content_type(crate::MERGE_PATCH_MEDIA_TYPE)
+                .body(body),
+            Err(err) => HttpResponse::InternalServerError()
+                .content_type(ContentType::plaintext())
+                .body(err.to_string()),
+        }
+    }
+}
+
+type ErrorHandler =
+    Arc<dyn Fn(PatchPayloadError, &HttpRequest) -> ::actix_web::Error + Send + Sync>;
+
+/// Configuration of the [`Patch`] extractor, registered with `App::app_data`
+/// or `Resource::app_data`.
+#[derive(Clone)]
+pub struct PatchConfig {
+    limit: usize,
+    error_handler: Option<ErrorHandler>,
+}
+
+impl PatchConfig {
+    /// Sets the maximum accepted payload size in bytes; 2 MB by default.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets the function turning extraction failures into responses, e.g. to
+    /// answer with a problem document instead of plain text.
+    pub fn error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(PatchPayloadError, &HttpRequest) -> ::actix_web::Error + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// The config in the request's app data, as `T` or `Data<T>`, or the
+    /// default (internal).
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|data| data.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+}
+
+const DEFAULT_CONFIG: PatchConfig = PatchConfig {
+    limit: 2_097_152,
+    error_handler: None,
+};
+
+impl Default for PatchConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+impl fmt::Debug for PatchConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PatchConfig")
+            .field("limit", &self.limit)
+            .field("error_handler", &self.error_handler.is_some())
+            .finish()
+    }
+}
+
+/// Why [`Patch`] couldn't extract a request body.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PatchPayloadError {
+    /// The `Content-Type` isn't `application/merge-patch+json` (415).
+    ContentType,
+    /// The payload is larger than [`PatchConfig::limit`] (413).
+    Overflow { limit: usize },
+    /// The payload couldn't be read.
+    Payload(::actix_web::Error),
+    /// The body isn't valid JSON (400).
+    Syntax(serde_json::Error),
+    /// The JSON doesn't fit the patch type, with the offending path (422).
+    Data(String),
+}
+
+impl fmt::Display for PatchPayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ContentType => write!(
+                f,
+                "expected request with `Content-Type: {}`",
+                crate::MERGE_PATCH_MEDIA_TYPE
+            ),
+            Self::Overflow { limit } => {
+                write!(f, "merge patch is larger than the {limit} byte limit")
+            }
+            Self::Payload(err) => write!(f, "failed to read merge patch: {err}"),
+            Self::Syntax(err) => write!(f, "invalid merge patch: {err}"),
+            Self::Data(message) => write!(f, "invalid merge patch: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for PatchPayloadError {}
+
+impl ResponseError for PatchPayloadError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::ContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Overflow { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Payload(err) => err.as_response_error().status_code(),
+            Self::Syntax(_) => StatusCode::BAD_REQUEST,
+            Self::Data(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+/// Whether the request declares a merge patch body; parameters such as
+/// `charset` are allowed (internal).
+fn is_merge_patch(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|media_type| {
+            media_type
+                .trim()
+                .eq_ignore_ascii_case(crate::MERGE_PATCH_MEDIA_TYPE)
+        })
+}
@@ -1,9 +1,12 @@
+use crate::ApplyOptions;
+use serde::de::Error as _;
 use serde::{Serialize, de::DeserializeOwned};
 use serde_json::{Map, Value};
 
 /// Applies a JSON Merge Patch (RFC 7396).
 ///
-/// Consumes the current value and returns the updated value.
+/// Consumes the current value and returns the updated value. Errors are
+/// reported as [`Error`](crate::Error), with the JSON Pointer of the failing field.
 ///
 /// # Example
 ///
@@ -18,37 +21,443 @@ use serde_json::{Map, Value};
 /// assert_eq!(updated.name, "new");
 /// assert_eq!(updated.id, 1);
 /// ```
-pub fn apply<T>(current: T, patch_json: &str) -> Result<T, serde_json::Error>
+pub fn apply<T>(current: T, patch_json: &str) -> Result<T, crate::Error>
 where
     T: Serialize + DeserializeOwned,
 {
-    let mut current_val = serde_json::to_value(current)?;
+    let patch_val: Value =
+        crate::backend::from_slice(patch_json.as_bytes()).map_err(crate::Error::ParsePatch)?;
 
-    let patch_val: Value = serde_json::from_str(patch_json)?;
+    apply_value(current, &patch_val)
+}
 
-    merge_patch(&mut current_val, &patch_val);
+/// Applies an already-parsed JSON Merge Patch (RFC 7396).
+///
+/// Like [`apply`], for patches already held as a `serde_json::Value`, which
+/// would otherwise have to be serialized only to be parsed again.
+///
+/// `current` is serialized into a `Value` first; the patch is then merged as
+/// the result is deserialized from it, so no patched copy of that `Value` is
+/// built. Applying still takes two traversals of the document, one to
+/// serialize it and one to read it back.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+/// struct User { id: u32, name: String }
+///
+/// let current = User { id: 1, name: "old".to_string() };
+/// let patch = json!({ "name": "new" });
+///
+/// let updated = serde_patch::apply_value(current, &patch).unwrap();
+/// assert_eq!(updated, User { id: 1, name: "new".to_string() });
+/// ```
+pub fn apply_value<T>(current: T, patch: &Value) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let current_val = crate::error::serialize(&current)?;
+    crate::error::deserialize_patched(&current_val, patch)
+}
 
-    serde_json::from_value(current_val)
+/// Applies a typed patch, serialized as a JSON Merge Patch (RFC 7396).
+///
+/// For patch structs built from [`MaybeAbsent`](crate::MaybeAbsent) or
+/// `Option` fields skipped when unset, such as those generated by
+/// `#[derive(Patch)]`: fields the patch leaves out are kept, `null` fields
+/// are cleared.
+///
+/// # Example
+///
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { id: u32, name: String }
+///
+/// #[derive(serde::Serialize)]
+/// struct Rename { name: String }
+///
+/// let user = User { id: 1, name: "old".to_string() };
+/// let user = serde_patch::apply_typed(user, &Rename { name: "new".to_string() }).unwrap();
+/// assert_eq!((user.id, user.name.as_str()), (1, "new"));
+/// ```
+pub fn apply_typed<T, P>(current: T, patch: &P) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+    P: Serialize + ?Sized,
+{
+    let patch_val = crate::error::serialize(patch)?;
+    apply_value(current, &patch_val)
+}
+
+/// Applies a JSON Merge Patch (RFC 7396) using the given [`ApplyOptions`].
+///
+/// Consumes the current value and returns the updated value. Patches the
+/// options turn away, e.g. with unknown or protected fields, are reported as
+/// [`Error::Rejected`](crate::Error::Rejected).
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::ApplyOptions;
+///
+/// #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+/// struct User { id: u32, name: String }
+///
+/// let current = User { id: 1, name: "old".to_string() };
+/// let patch = r#"{ "name": "new" }"#;
+///
+/// let updated = serde_patch::apply_with(current, patch, &ApplyOptions::default()).unwrap();
+/// assert_eq!(updated.name, "new");
+/// ```
+pub fn apply_with<T, P>(current: T, patch: P, options: &ApplyOptions) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+    P: AsRef<[u8]>,
+{
+    let patch_val: Value =
+        crate::backend::from_slice(patch.as_ref()).map_err(crate::Error::ParsePatch)?;
+    apply_parsed(current, patch_val, options)
+}
+
+/// Builds a value by applying a JSON Merge Patch (RFC 7396) on top of `T::default()`.
+///
+/// Lets create and update endpoints share one request body shape. Errors name
+/// the offending field, e.g. a required field the patch removed or didn't
+/// provide inside a nested object.
+///
+/// # Example
+///
+/// ```
+/// #[derive(Default, serde::Serialize, serde::Deserialize)]
+/// struct Profile { bio: String, avatar_url: Option<String> }
+///
+/// #[derive(Default, serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, profile: Option<Profile> }
+///
+/// let user: User = serde_patch::from_patch(r#"{ "name": "alice" }"#).unwrap();
+/// assert_eq!(user.name, "alice");
+///
+/// let err = serde_patch::from_patch::<User, _>(r#"{ "profile": { "avatar_url": "a.png" } }"#)
+///     .err()
+///     .unwrap();
+/// assert_eq!(err.pointer(), Some("/profile/bio"));
+/// assert!(err.to_string().starts_with("missing field `bio`"));
+/// ```
+pub fn from_patch<T, P>(patch: P) -> Result<T, crate::Error>
+where
+    T: Default + Serialize + DeserializeOwned,
+    P: AsRef<[u8]>,
+{
+    let patch_val: Value =
+        crate::backend::from_slice(patch.as_ref()).map_err(crate::Error::ParsePatch)?;
+    let mut value = crate::error::serialize(&T::default())?;
+    merge_patch(&mut value, &patch_val);
+    crate::error::deserialize_merged(&value, &patch_val)
+}
+
+/// Applies an already-parsed patch using the given options (internal).
+pub(crate) fn apply_parsed<T>(
+    current: T,
+    patch_val: Value,
+    options: &ApplyOptions,
+) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    apply_resolved(&current, patch_val, options).map(|(updated, _)| updated)
+}
+
+/// Applies a patch, also returning it with external references and deltas resolved (internal).
+pub(crate) fn apply_resolved<T>(
+    current: &T,
+    patch_val: Value,
+    options: &ApplyOptions,
+) -> Result<(T, Value), crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let (updated, patch_val) = apply_to_value(current, patch_val, options)?;
+    let updated = crate::error::deserialize_merged(&updated, &patch_val)?;
+    check_unknown_fields(&updated, &patch_val, options).map_err(crate::Error::Rejected)?;
+    Ok((updated, patch_val))
+}
+
+/// Fails if [`ApplyOptions::deny_unknown_fields`] is set and the target type
+/// dropped fields the patch set (internal).
+pub(crate) fn check_unknown_fields<T: Serialize>(
+    updated: &T,
+    patch: &Value,
+    options: &ApplyOptions,
+) -> Result<(), serde_json::Error> {
+    if !options.deny_unknown_fields {
+        return Ok(());
+    }
+    let unknown = unknown_fields(updated, patch, options)?;
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    Err(serde_json::Error::custom(format!(
+        "unknown field{} {}",
+        if unknown.len() == 1 { "" } else { "s" },
+        unknown
+            .iter()
+            .map(|path| format!("`{}`", path))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )))
+}
+
+/// Dotted paths of fields the patch set that the target type dropped, in sorted order (internal).
+///
+/// The patched value is serialized again and compared with the patch, so the
+/// check follows the target type's shape: `Option` fields that were `None`
+/// and map entries are known; fields the type ignores on deserialization are not.
+pub(crate) fn unknown_fields<T: Serialize>(
+    updated: &T,
+    patch: &Value,
+    options: &ApplyOptions,
+) -> Result<Vec<String>, serde_json::Error> {
+    let mut applied = crate::value::to_value(updated)?;
+    crate::representation::to_views(&mut applied, &options.representations);
+    let mut unknown = Vec::new();
+    collect_unknown(patch, &applied, "", &mut unknown);
+    unknown.sort();
+    Ok(unknown)
+}
+
+/// Applies a patch up to the patched JSON value, before deserializing it into `T` (internal).
+pub(crate) fn apply_to_value<T>(
+    current: &T,
+    patch_val: Value,
+    options: &ApplyOptions,
+) -> Result<(Value, Value), crate::Error>
+where
+    T: Serialize,
+{
+    let (updated, patch_val, errors) = apply_unchecked(current, patch_val, options)?;
+    match errors.into_iter().next() {
+        Some(error) => Err(crate::Error::Rejected(serde_json::Error::custom(error))),
+        None => Ok((updated, patch_val)),
+    }
+}
+
+/// Like [`apply_to_value`], but returns protected-path and validator failures
+/// instead of failing on the first (internal).
+pub(crate) fn apply_unchecked<T>(
+    current: &T,
+    mut patch_val: Value,
+    options: &ApplyOptions,
+) -> Result<(Value, Value, Vec<crate::FieldError>), crate::Error>
+where
+    T: Serialize,
+{
+    check_depth(&patch_val, options.max_depth, "patch").map_err(crate::Error::Rejected)?;
+
+    let mut current_val = crate::error::serialize(current)?;
+    crate::representation::to_views(&mut current_val, &options.representations);
+
+    resolve_patch(&current_val, &mut patch_val, options).map_err(crate::Error::Rejected)?;
+    if options.nulls == NullSemantics::Ignore {
+        strip_nulls(&mut patch_val);
+    }
+    merge_patch_with(&mut current_val, &patch_val, options.nulls);
+
+    crate::representation::from_views(&mut current_val, &options.representations);
+    let errors = crate::validate::check(&patch_val, &current_val, options);
+    Ok((current_val, patch_val, errors))
+}
+
+/// Expands option-specific encodings in the patch before merging (internal).
+#[cfg_attr(not(feature = "binary-delta"), allow(unused_variables))]
+fn resolve_patch(
+    current: &Value,
+    patch: &mut Value,
+    options: &ApplyOptions,
+) -> Result<(), serde_json::Error> {
+    #[cfg(feature = "external-refs")]
+    if let Some(external) = &options.external {
+        crate::external_ref::resolve_refs(patch, external)?;
+    }
+
+    if options.array_strategy != crate::ArrayStrategy::Replace || !options.array_keys.is_empty() {
+        crate::array_diff::resolve_arrays(current, patch, &options.array_keys, "")?;
+    }
+
+    #[cfg(feature = "binary-delta")]
+    crate::binary_delta::resolve_deltas(current, patch, &options.binary_delta, "")?;
+
+    Ok(())
+}
+
+/// Applies a JSON Merge Patch (RFC 7396) to an untyped JSON document in place.
+///
+/// For documents without a Rust type, e.g. arbitrary user configuration blobs.
+/// Never fails: any value is a valid merge patch for any document.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let mut config = json!({ "theme": "dark", "editor": { "tabs": 4, "wrap": true } });
+/// serde_patch::merge_into(&mut config, &json!({ "editor": { "tabs": 2, "wrap": null } }));
+///
+/// assert_eq!(config, json!({ "theme": "dark", "editor": { "tabs": 2 } }));
+/// ```
+pub fn merge_into(target: &mut Value, patch: &Value) {
+    merge_patch(target, patch);
+}
+
+/// What a `null` in a patch does to the field it is set on.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::{ApplyOptions, NullSemantics};
+/// use std::collections::BTreeMap;
+///
+/// let tags = BTreeMap::from([("env".to_string(), Some("prod".to_string()))]);
+/// let patch = r#"{ "env": null }"#;
+///
+/// let removed = serde_patch::apply_with(tags.clone(), patch, &ApplyOptions::default()).unwrap();
+/// assert!(removed.is_empty());
+///
+/// let options = ApplyOptions { nulls: NullSemantics::Set, ..Default::default() };
+/// let set = serde_patch::apply_with(tags, patch, &options).unwrap();
+/// assert_eq!(set["env"], None);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NullSemantics {
+    /// Removes the field, as RFC 7396 specifies.
+    #[default]
+    Remove,
+    /// Stores `null` in the field, keeping map entries present.
+    Set,
+    /// Leaves the field untouched, for clients that send `null` for "no change".
+    Ignore,
 }
 
 /// Recursively merges a patch into a target JSON value (internal).
-fn merge_patch(target: &mut Value, patch: &Value) {
-    if let Value::Object(patch_map) = patch {
+///
+/// Total over all inputs: a non-object target under an object patch is
+/// replaced by an empty object before merging.
+pub(crate) fn merge_patch(target: &mut Value, patch: &Value) {
+    merge_patch_with(target, patch, NullSemantics::Remove);
+}
+
+/// [`merge_patch`] with configurable handling of `null` (internal).
+///
+/// Walks the patch with an explicit stack rather than recursion, so however
+/// deeply a patch is nested it can't overflow the call stack.
+fn merge_patch_with(target: &mut Value, patch: &Value, nulls: NullSemantics) {
+    let mut stack = vec![(target, patch)];
+    while let Some((target, patch)) = stack.pop() {
+        let Value::Object(patch_map) = patch else {
+            *target = patch.clone();
+            continue;
+        };
         if !target.is_object() {
             *target = Value::Object(Map::new());
         }
+        let Value::Object(target_map) = target else {
+            continue;
+        };
 
-        let target_map = target.as_object_mut().unwrap();
-
+        let mut nested = 0;
         for (key, patch_value) in patch_map {
-            if patch_value.is_null() {
-                target_map.remove(key);
-            } else {
-                let target_entry = target_map.entry(key.clone()).or_insert(Value::Null);
-                merge_patch(target_entry, patch_value);
+            match (patch_value, nulls) {
+                (Value::Null, NullSemantics::Remove) => remove_member(target_map, key),
+                (Value::Null, NullSemantics::Ignore) => {}
+                (Value::Object(_), _) => {
+                    target_map.entry(key.clone()).or_insert(Value::Null);
+                    nested += 1;
+                }
+                _ => {
+                    target_map.insert(key.clone(), patch_value.clone());
+                }
             }
         }
-    } else {
-        *target = patch.clone();
+        // Members merged into are borrowed all at once, in a single pass.
+        if nested > 0 {
+            stack.extend(target_map.iter_mut().filter_map(|(key, target_value)| {
+                match patch_map.get(key) {
+                    Some(patch_value @ Value::Object(_)) => Some((target_value, patch_value)),
+                    _ => None,
+                }
+            }));
+        }
+    }
+}
+
+/// Removes a member; with `preserve-order`, the others keep their order
+/// (internal).
+pub(crate) fn remove_member(map: &mut Map<String, Value>, key: &str) {
+    #[cfg(feature = "preserve-order")]
+    map.shift_remove(key);
+    #[cfg(not(feature = "preserve-order"))]
+    map.remove(key);
+}
+
+/// Removes `null` members from patch objects at every depth (internal).
+///
+/// Iterative, like [`merge_patch_with`], so deep patches can't overflow the stack.
+pub(crate) fn strip_nulls(patch: &mut Value) {
+    let mut stack = vec![patch];
+    while let Some(value) = stack.pop() {
+        if let Value::Object(map) = value {
+            map.retain(|_, value| !value.is_null());
+            stack.extend(map.values_mut());
+        }
+    }
+}
+
+/// Nesting depth of a value; scalars have depth 0 (internal).
+///
+/// Iterative, so measuring a hostile value can't overflow the stack itself.
+pub(crate) fn depth(value: &Value) -> usize {
+    let mut deepest = 0;
+    let mut stack = vec![(value, 0)];
+    while let Some((value, level)) = stack.pop() {
+        match value {
+            Value::Object(map) => stack.extend(map.values().map(|child| (child, level + 1))),
+            Value::Array(items) => stack.extend(items.iter().map(|child| (child, level + 1))),
+            _ => continue,
+        }
+        deepest = deepest.max(level + 1);
+    }
+    deepest
+}
+
+/// Fails if `value` is nested deeper than `max_depth` (internal).
+pub(crate) fn check_depth(
+    value: &Value,
+    max_depth: Option<usize>,
+    what: &str,
+) -> Result<(), serde_json::Error> {
+    match max_depth {
+        Some(max_depth) if depth(value) > max_depth => Err(serde_json::Error::custom(format!(
+            "{} nesting exceeds the maximum depth of {}",
+            what, max_depth
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn collect_unknown(patch: &Value, applied: &Value, path: &str, unknown: &mut Vec<String>) {
+    let (Value::Object(patch_map), Value::Object(applied_map)) = (patch, applied) else {
+        return;
+    };
+    for (key, patch_value) in patch_map {
+        if patch_value.is_null() {
+            continue;
+        }
+        let child = crate::paths::join(path, key);
+        match applied_map.get(key) {
+            Some(applied_value) => collect_unknown(patch_value, applied_value, &child, unknown),
+            None => unknown.push(child),
+        }
     }
 }
@@ -1,9 +1,9 @@
 use serde::{Serialize, de::DeserializeOwned};
-use serde_json::{Map, Value};
+use serde_json::Value;
 
 /// Applies a JSON Merge Patch (RFC 7396) in-place.
 ///
-/// Modifies the current value directly.
+/// Modifies the current value directly; on error it is left unchanged.
 ///
 /// # Example
 ///
@@ -18,34 +18,39 @@ use serde_json::{Map, Value};
 /// assert_eq!(user.name, "new");
 /// assert_eq!(user.id, 1);
 /// ```
-pub fn apply_mut<T, P>(current: &mut T, patch: P) -> Result<(), serde_json::Error>
+pub fn apply_mut<T, P>(current: &mut T, patch: P) -> Result<(), crate::Error>
 where
     T: Serialize + DeserializeOwned,
     P: AsRef<[u8]>,
 {
-    let mut current_val = serde_json::to_value(&current)?;
-    let patch_val: Value = serde_json::from_slice(patch.as_ref())?;
-    merge_patch(&mut current_val, &patch_val);
-    *current = serde_json::from_value(current_val)?;
-    Ok(())
+    let patch_val: Value =
+        crate::backend::from_slice(patch.as_ref()).map_err(crate::Error::ParsePatch)?;
+    apply_mut_value(current, &patch_val)
 }
 
-/// Recursively merges a patch into a target JSON value (internal).
-fn merge_patch(target: &mut Value, patch: &Value) {
-    if let Value::Object(patch_map) = patch {
-        if !target.is_object() {
-            *target = Value::Object(Map::new());
-        }
-        let target_map = target.as_object_mut().unwrap();
-        for (key, patch_value) in patch_map {
-            if patch_value.is_null() {
-                target_map.remove(key);
-            } else {
-                let target_entry = target_map.entry(key.clone()).or_insert(Value::Null);
-                merge_patch(target_entry, patch_value);
-            }
-        }
-    } else {
-        *target = patch.clone();
-    }
+/// Applies an already-parsed JSON Merge Patch (RFC 7396) in-place.
+///
+/// Like [`apply_mut`](crate::apply_mut), for patches already held as a
+/// `serde_json::Value`. On error the current value is left unchanged.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+///
+/// #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+/// struct User { id: u32, name: String }
+///
+/// let mut user = User { id: 1, name: "old".to_string() };
+///
+/// serde_patch::apply_mut_value(&mut user, &json!({ "name": "new" })).unwrap();
+/// assert_eq!(user.name, "new");
+/// ```
+pub fn apply_mut_value<T>(current: &mut T, patch: &Value) -> Result<(), crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let current_val = crate::error::serialize(&current)?;
+    *current = crate::error::deserialize_patched(&current_val, patch)?;
+    Ok(())
 }
@@ -0,0 +1,394 @@
+//! Element-level array patches (internal).
+//!
+//! Merge patches can only replace arrays as a whole. With an
+//! [`ArrayStrategy`] other than `Replace`, changed arrays are encoded as
+//! marker objects instead:
+//!
+//! - by index: `{ "$items": { "<index>": <patch>, ... }, "$len": <new length> }`
+//! - keyed: `{ "$key": "<field>", "$items": { "<key>": <patch> | null, ... }, "$added": [...], "$order": [...] }`
+//!
+//! Element patches are merge patches for changed elements and full values for
+//! new ones; `null` removes a keyed element. `$len` is present only if the
+//! length changed, `$added` lists the keys of new elements (so applying a
+//! patch whose elements were concurrently added or removed fails instead of
+//! producing a partial element), and `$order` is present only if the keys are
+//! not in their natural order (kept elements first, then new ones sorted by key).
+
+use crate::DiffOptions;
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+const ITEMS_KEY: &str = "$items";
+const LEN_KEY: &str = "$len";
+const KEY_KEY: &str = "$key";
+const ORDER_KEY: &str = "$order";
+const ADDED_KEY: &str = "$added";
+const MARKER_KEYS: [&str; 5] = [ITEMS_KEY, LEN_KEY, KEY_KEY, ORDER_KEY, ADDED_KEY];
+
+static NULL: Value = Value::Null;
+
+/// How [`diff_with`](crate::diff_with) encodes changes inside arrays.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{ApplyOptions, ArrayStrategy, DiffOptions};
+///
+/// #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+/// struct Line { sku: String, qty: u32 }
+///
+/// let line = |sku: &str, qty| Line { sku: sku.into(), qty };
+/// let old = vec![line("a", 1), line("b", 1)];
+/// let new = vec![line("x", 1), line("a", 1), line("b", 2)];
+///
+/// let strategy = ArrayStrategy::Keyed("sku".into());
+/// let options = DiffOptions { array_strategy: strategy.clone(), ..Default::default() };
+/// let patch = serde_patch::diff_with(&old, &new, &options).unwrap();
+/// assert_eq!(patch, json!({
+///     "$key": "sku",
+///     "$items": { "b": { "qty": 2 }, "x": { "sku": "x", "qty": 1 } },
+///     "$added": ["x"],
+///     "$order": ["x", "a", "b"]
+/// }));
+///
+/// let options = ApplyOptions { array_strategy: strategy, ..Default::default() };
+/// let applied = serde_patch::apply_with(old, patch.to_string(), &options).unwrap();
+/// assert_eq!(applied, new);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ArrayStrategy {
+    /// A changed array is replaced as a whole (plain RFC 7396).
+    #[default]
+    Replace,
+    /// Elements are compared position by position; only changed positions are sent.
+    ByIndex,
+    /// Elements are objects matched by the value of this field, so insertions
+    /// and reorderings don't show up as changes to every later element.
+    /// Arrays whose elements lack a unique scalar key fall back to `Replace`.
+    Keyed(String),
+}
+
+/// Encodes the changes between two arrays, or `None` to replace the array (internal).
+pub(crate) fn array_patch(
+    old: &[Value],
+    new: &[Value],
+    options: &DiffOptions,
+    current_path: &str,
+) -> Option<Value> {
+    if let Some(key) = options.array_keys.get(current_path) {
+        return keyed(old, new, key, options, current_path);
+    }
+    match &options.array_strategy {
+        ArrayStrategy::Replace => None,
+        ArrayStrategy::ByIndex => Some(by_index(old, new, options, current_path)),
+        ArrayStrategy::Keyed(key) => keyed(old, new, key, options, current_path),
+    }
+}
+
+fn by_index(old: &[Value], new: &[Value], options: &DiffOptions, current_path: &str) -> Value {
+    let mut items = Map::new();
+    for (index, new_item) in new.iter().enumerate() {
+        let item_path = crate::paths::join(current_path, &index.to_string());
+        let old_item = old.get(index);
+        let patch = match old_item {
+            Some(old_item) => {
+                crate::diff_patch::compute_diff(Some(old_item), new_item, options, &item_path)
+            }
+            None => Some(new_item.clone()),
+        };
+        if let Some(patch) = patch {
+            items.insert(index.to_string(), element_patch(old_item, new_item, patch));
+        }
+    }
+
+    let mut marker = Map::new();
+    marker.insert(ITEMS_KEY.to_string(), Value::Object(items));
+    if old.len() != new.len() {
+        marker.insert(LEN_KEY.to_string(), Value::from(new.len()));
+    }
+    Value::Object(marker)
+}
+
+fn keyed(
+    old: &[Value],
+    new: &[Value],
+    key: &str,
+    options: &DiffOptions,
+    current_path: &str,
+) -> Option<Value> {
+    let old_keys = keys_of(old, key)?;
+    let new_keys = keys_of(new, key)?;
+    let old_by_key: HashMap<&str, &Value> = old_keys.iter().map(String::as_str).zip(old).collect();
+    let new_set: HashSet<&str> = new_keys.iter().map(String::as_str).collect();
+
+    let mut items = Map::new();
+    let mut added = Vec::new();
+    for (item_key, new_item) in new_keys.iter().zip(new) {
+        let item_path = crate::paths::join(current_path, item_key);
+        let old_item = old_by_key.get(item_key.as_str()).copied();
+        let patch = match old_item {
+            Some(old_item) => {
+                crate::diff_patch::compute_diff(Some(old_item), new_item, options, &item_path)
+            }
+            None => {
+                added.push(Value::String(item_key.clone()));
+                Some(new_item.clone())
+            }
+        };
+        if let Some(patch) = patch {
+            items.insert(item_key.clone(), element_patch(old_item, new_item, patch));
+        }
+    }
+    for old_key in &old_keys {
+        if !new_set.contains(old_key.as_str()) {
+            items.insert(old_key.clone(), Value::Null);
+        }
+    }
+
+    let mut marker = Map::new();
+    marker.insert(KEY_KEY.to_string(), Value::String(key.to_string()));
+    let natural = natural_order(&old_keys, &items);
+    marker.insert(ITEMS_KEY.to_string(), Value::Object(items));
+    if !added.is_empty() {
+        marker.insert(ADDED_KEY.to_string(), Value::Array(added));
+    }
+    if natural != new_keys {
+        marker.insert(
+            ORDER_KEY.to_string(),
+            Value::Array(new_keys.into_iter().map(Value::String).collect()),
+        );
+    }
+    Some(Value::Object(marker))
+}
+
+/// Element patches must rebuild the element when merged into it.
+///
+/// A merge patch can only turn an element into an object by merging into it;
+/// any other kind of change replaces the element with its new value.
+fn element_patch(old: Option<&Value>, new: &Value, patch: Value) -> Value {
+    match (old, new) {
+        (Some(Value::Object(_)), Value::Object(_)) => patch,
+        _ => new.clone(),
+    }
+}
+
+/// Unique string forms of each element's key, or `None` if any is missing.
+fn keys_of(items: &[Value], key: &str) -> Option<Vec<String>> {
+    let keys: Vec<String> = items
+        .iter()
+        .map(|item| key_string(item.get(key)?))
+        .collect::<Option<_>>()?;
+    let unique: HashSet<&String> = keys.iter().collect();
+    (unique.len() == keys.len()).then_some(keys)
+}
+
+fn key_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Kept keys in their old order, then new keys sorted.
+fn natural_order(old_keys: &[String], items: &Map<String, Value>) -> Vec<String> {
+    let old_set: HashSet<&str> = old_keys.iter().map(String::as_str).collect();
+    let kept = old_keys
+        .iter()
+        .filter(|key| !items.get(key.as_str()).is_some_and(Value::is_null));
+    let added = items
+        .iter()
+        .filter(|(key, patch)| !old_set.contains(key.as_str()) && !patch.is_null())
+        .map(|(key, _)| key);
+    let mut added: Vec<&String> = added.collect();
+    added.sort();
+    kept.chain(added).cloned().collect()
+}
+
+/// Replaces array markers in the patch with the arrays they describe (internal).
+///
+/// `keys` are the per-path key fields from [`ApplyOptions::array_keys`](crate::ApplyOptions::array_keys);
+/// a keyed marker for one of those paths must use the configured field.
+pub(crate) fn resolve_arrays(
+    current: &Value,
+    patch: &mut Value,
+    keys: &HashMap<String, String>,
+    current_path: &str,
+) -> Result<(), serde_json::Error> {
+    if let Value::Array(items) = current
+        && let Some(marker) = as_marker(patch)
+    {
+        let rebuilt = match (marker.get(KEY_KEY), keys.get(current_path)) {
+            (Some(Value::String(key)), expected)
+                if expected.is_none_or(|expected| expected == key) =>
+            {
+                apply_keyed(items, marker, key, keys, current_path)?
+            }
+            (None, _) => apply_by_index(items, marker, keys, current_path)?,
+            _ => return Err(invalid(current_path)),
+        };
+        *patch = Value::Array(rebuilt);
+        return Ok(());
+    }
+
+    if let Value::Object(patch_map) = patch {
+        for (key, patch_value) in patch_map.iter_mut() {
+            let current_value = current.get(key).unwrap_or(&NULL);
+            resolve_arrays(
+                current_value,
+                patch_value,
+                keys,
+                &crate::paths::join(current_path, key),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn invalid(current_path: &str) -> serde_json::Error {
+    serde_json::Error::custom(format!("invalid array patch at `{}`", current_path))
+}
+
+fn as_marker(patch: &Value) -> Option<&Map<String, Value>> {
+    let map = patch.as_object()?;
+    map.get(ITEMS_KEY)?.as_object()?;
+    map.keys()
+        .all(|key| MARKER_KEYS.contains(&key.as_str()))
+        .then_some(map)
+}
+
+/// Whether the patch contains element-level array markers anywhere (internal).
+pub(crate) fn has_markers(patch: &Value) -> bool {
+    match patch {
+        Value::Object(map) => as_marker(patch).is_some() || map.values().any(has_markers),
+        _ => false,
+    }
+}
+
+/// Merges an element patch into an element, resolving nested markers first.
+fn patch_item(
+    item: &mut Value,
+    patch: &Value,
+    keys: &HashMap<String, String>,
+    item_path: &str,
+) -> Result<(), serde_json::Error> {
+    let mut patch = patch.clone();
+    resolve_arrays(item, &mut patch, keys, item_path)?;
+    crate::apply_patch::merge_patch(item, &patch);
+    Ok(())
+}
+
+fn apply_by_index(
+    items: &[Value],
+    marker: &Map<String, Value>,
+    keys: &HashMap<String, String>,
+    current_path: &str,
+) -> Result<Vec<Value>, serde_json::Error> {
+    let len = match marker.get(LEN_KEY) {
+        Some(len) => len
+            .as_u64()
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or_else(|| invalid(current_path))?,
+        None => items.len(),
+    };
+    let mut out = items.to_vec();
+    out.resize(len, Value::Null);
+    for (index, patch) in marker
+        .get(ITEMS_KEY)
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        let item = index
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| out.get_mut(index))
+            .ok_or_else(|| invalid(current_path))?;
+        patch_item(item, patch, keys, &crate::paths::join(current_path, index))?;
+    }
+    Ok(out)
+}
+
+fn apply_keyed(
+    items: &[Value],
+    marker: &Map<String, Value>,
+    key: &str,
+    keys: &HashMap<String, String>,
+    current_path: &str,
+) -> Result<Vec<Value>, serde_json::Error> {
+    let patches = marker
+        .get(ITEMS_KEY)
+        .and_then(Value::as_object)
+        .ok_or_else(|| invalid(current_path))?;
+    let old_keys = keys_of(items, key).ok_or_else(|| invalid(current_path))?;
+    let strings = |value: &Value| -> Result<Vec<String>, serde_json::Error> {
+        value
+            .as_array()
+            .and_then(|keys| {
+                keys.iter()
+                    .map(|key| key.as_str().map(str::to_string))
+                    .collect()
+            })
+            .ok_or_else(|| invalid(current_path))
+    };
+    let added = marker.get(ADDED_KEY).map(strings).transpose()?;
+
+    let conflict = |item_key: &str, problem: &str| {
+        serde_json::Error::custom(format!(
+            "element `{}` {} at `{}`",
+            item_key, problem, current_path
+        ))
+    };
+    if let Some(added) = &added {
+        for item_key in patches.keys() {
+            match (added.contains(item_key), old_keys.contains(item_key)) {
+                (true, true) => return Err(conflict(item_key, "already exists")),
+                (false, false) => return Err(conflict(item_key, "no longer exists")),
+                _ => {}
+            }
+        }
+    }
+
+    let mut by_key: HashMap<String, Value> = HashMap::new();
+    for (item_key, item) in old_keys.iter().zip(items) {
+        let mut item = item.clone();
+        match patches.get(item_key) {
+            Some(Value::Null) => continue,
+            Some(patch) => patch_item(
+                &mut item,
+                patch,
+                keys,
+                &crate::paths::join(current_path, item_key),
+            )?,
+            None => {}
+        }
+        by_key.insert(item_key.clone(), item);
+    }
+    for (item_key, patch) in patches {
+        if !patch.is_null() && !old_keys.contains(item_key) {
+            let mut item = Value::Null;
+            patch_item(
+                &mut item,
+                patch,
+                keys,
+                &crate::paths::join(current_path, item_key),
+            )?;
+            by_key.insert(item_key.clone(), item);
+        }
+    }
+
+    let order = match marker.get(ORDER_KEY) {
+        Some(order) => strings(order)?,
+        None => natural_order(&old_keys, patches),
+    };
+    if order.len() != by_key.len() {
+        return Err(invalid(current_path));
+    }
+    order
+        .iter()
+        .map(|key| by_key.remove(key).ok_or_else(|| invalid(current_path)))
+        .collect()
+}
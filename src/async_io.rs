@@ -0,0 +1,134 @@
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::io::{self, Read};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+
+/// Size of each chunk read from a stream.
+const CHUNK: usize = 8 * 1024;
+/// Chunks read ahead of the parser.
+const READ_AHEAD: usize = 4;
+
+/// Applies a JSON Merge Patch (RFC 7396) read from `patch` to the document
+/// read from `document`.
+///
+/// Both bodies are parsed as they arrive, on Tokio's blocking pool, with only
+/// a few chunks of each buffered at a time, so the raw body is never held in
+/// full alongside the parsed value. Requires a Tokio runtime.
+///
+/// A document that isn't valid JSON is reported as
+/// [`Error::Deserialize`](crate::Error::Deserialize) with an empty pointer,
+/// a patch that isn't as [`Error::ParsePatch`](crate::Error::ParsePatch).
+///
+/// # Example
+///
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { id: u64, name: String }
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let document = &br#"{ "id": 1, "name": "old" }"#[..];
+/// let patch = &br#"{ "name": "new" }"#[..];
+///
+/// let user: User = serde_patch::apply_async(document, patch).await.unwrap();
+/// assert_eq!(user.name, "new");
+/// # });
+/// ```
+pub async fn apply_async<T, D, P>(document: D, patch: P) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+    D: AsyncRead + Unpin,
+    P: AsyncRead + Unpin,
+{
+    let (document, patch) = tokio::join!(read_value(document), read_value(patch));
+    let document = document.map_err(|source| crate::Error::Deserialize {
+        pointer: String::new(),
+        source,
+    })?;
+    let patch = patch.map_err(crate::Error::ParsePatch)?;
+    crate::error::deserialize_patched(&document, &patch)
+}
+
+/// Computes the merge patch turning the document read from `old` into the
+/// one read from `new`, as [`diff`](crate::diff) does.
+///
+/// Both documents are parsed as they arrive, as in [`apply_async`].
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let old = &br#"{ "id": 1, "name": "old" }"#[..];
+/// let new = &br#"{ "id": 1, "name": "new" }"#[..];
+///
+/// let patch = serde_patch::diff_async(old, new).await.unwrap();
+/// assert_eq!(patch, json!({ "name": "new" }));
+/// # });
+/// ```
+pub async fn diff_async<O, N>(old: O, new: N) -> Result<Value, serde_json::Error>
+where
+    O: AsyncRead + Unpin,
+    N: AsyncRead + Unpin,
+{
+    let (old, new) = tokio::join!(read_value(old), read_value(new));
+    crate::diff(&old?, &new?)
+}
+
+/// Parses a JSON value from `reader`, feeding chunks to `serde_json` on the
+/// blocking pool as they are read (internal).
+async fn read_value<R: AsyncRead + Unpin>(mut reader: R) -> Result<Value, serde_json::Error> {
+    let (sender, receiver) = mpsc::channel(READ_AHEAD);
+    let parse = tokio::task::spawn_blocking(move || {
+        serde_json::from_reader::<_, Value>(Chunks {
+            receiver,
+            chunk: Vec::new(),
+            pos: 0,
+        })
+    });
+    let pump = async move {
+        loop {
+            let mut chunk = vec![0; CHUNK];
+            let read = reader.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            chunk.truncate(read);
+            if sender.send(chunk).await.is_err() {
+                // The parser stopped early and reports why.
+                return Ok(());
+            }
+        }
+    };
+    let (pumped, parsed): (io::Result<()>, _) = tokio::join!(pump, parse);
+    pumped.map_err(serde_json::Error::io)?;
+    parsed.map_err(|err| {
+        <serde_json::Error as serde::de::Error>::custom(format_args!("parser task failed: {err}"))
+    })?
+}
+
+/// The chunks sent by [`read_value`] as a blocking reader (internal).
+struct Chunks {
+    receiver: mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for Chunks {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.chunk.len() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{ApplyOptions, DiffOptions};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::time::SystemTime;
+
+/// Placeholder written in place of redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Fields whose values must never appear in audit records.
+///
+/// Changes to a redacted field are still recorded, but its before/after
+/// values are replaced by [`REDACTED`]. Nested paths are redacted inside
+/// parent values too (e.g. `credentials.secret` when `credentials` is replaced).
+/// With the `derive` feature, `#[derive(Patch)]` implements it from the
+/// fields marked `#[patch(redact)]`.
+pub trait Redact {
+    /// Dotted paths of redacted fields.
+    const REDACTED_PATHS: &'static [&'static str] = &[];
+}
+
+/// The change being audited: the new value or the patch applied to the old one.
+#[derive(Clone, Copy, Debug)]
+pub enum AuditChange<'a, T> {
+    /// The value after the change.
+    New(&'a T),
+    /// A merge patch applied to the old value.
+    Patch(&'a Value),
+}
+
+/// Before/after values of one changed field; `None` means absent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// Dotted path of the field.
+    pub path: String,
+    /// Value before the change.
+    pub before: Option<Value>,
+    /// Value after the change.
+    pub after: Option<Value>,
+}
+
+/// Serializable record of a change, produced by [`audit_record`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Who made the change.
+    pub actor: String,
+    /// When the change was made.
+    pub timestamp: SystemTime,
+    /// Changed fields, in path order.
+    pub changes: Vec<FieldChange>,
+}
+
+/// Builds an audit record of the fields changed between `old` and the new state.
+///
+/// For [`AuditChange::Patch`], the patch is applied to `old` first so the
+/// record reflects what the type actually stores. Fields listed in
+/// [`Redact::REDACTED_PATHS`] are recorded with [`REDACTED`] values.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{AuditChange, Redact};
+/// use std::time::SystemTime;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Account { email: String, password: String }
+///
+/// impl Redact for Account {
+///     const REDACTED_PATHS: &'static [&'static str] = &["password"];
+/// }
+///
+/// let old = Account { email: "a@example.com".into(), password: "hunter2".into() };
+/// let patch = json!({ "email": "b@example.com", "password": "correct horse" });
+///
+/// let record =
+///     serde_patch::audit_record(&old, AuditChange::Patch(&patch), "admin", SystemTime::now()).unwrap();
+/// assert_eq!(record.changes[0].after, Some(json!("b@example.com")));
+/// assert_eq!(record.changes[1].before, Some(json!(serde_patch::REDACTED)));
+/// ```
+pub fn audit_record<T>(
+    old: &T,
+    change: AuditChange<'_, T>,
+    actor: impl Into<String>,
+    timestamp: SystemTime,
+) -> Result<AuditRecord, serde_json::Error>
+where
+    T: Serialize + DeserializeOwned + Redact,
+{
+    let old_val = crate::value::to_value(old)?;
+    let new_val = match change {
+        AuditChange::New(new) => crate::value::to_value(new)?,
+        AuditChange::Patch(patch) => {
+            let current: T = serde_json::from_value(old_val.clone())?;
+            let updated =
+                crate::apply_patch::apply_parsed(current, patch.clone(), &ApplyOptions::default())?;
+            crate::value::to_value(&updated)?
+        }
+    };
+
+    let patch =
+        crate::diff_patch::compute_diff(Some(&old_val), &new_val, &DiffOptions::default(), "")
+            .unwrap_or_else(|| Value::Object(Default::default()));
+
+    let mut changes: Vec<FieldChange> = crate::review::Review::with_old_value(&patch, &old_val)
+        .entries()
+        .iter()
+        .map(|entry| FieldChange {
+            path: entry.path.clone(),
+            before: entry.old.clone(),
+            after: Some(entry.new.clone()).filter(|new| !new.is_null()),
+        })
+        .collect();
+    for change in &mut changes {
+        for value in [&mut change.before, &mut change.after]
+            .into_iter()
+            .flatten()
+        {
+            redact(value, &change.path, T::REDACTED_PATHS);
+        }
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(AuditRecord {
+        actor: actor.into(),
+        timestamp,
+        changes,
+    })
+}
+
+/// Masks the redacted paths within `value`, which sits at `path` (internal).
+///
+/// Paths are compared segment by segment, so escaped dots in keys are kept apart.
+fn redact(value: &mut Value, path: &str, redacted: &[&str]) {
+    let segments = segments_of(path);
+    for redacted_path in redacted {
+        let redacted_segments = segments_of(redacted_path);
+        if redacted_segments.len() <= segments.len() {
+            if segments.starts_with(&redacted_segments) {
+                *value = Value::String(REDACTED.to_string());
+                return;
+            }
+        } else if redacted_segments.starts_with(&segments) {
+            let mut target = Some(&mut *value);
+            for segment in &redacted_segments[segments.len()..] {
+                target = target.and_then(|t| t.get_mut(segment));
+            }
+            if let Some(target) = target {
+                *target = Value::String(REDACTED.to_string());
+            }
+        }
+    }
+}
+
+/// Unescaped segments of a dotted path; none for the root (internal).
+fn segments_of(path: &str) -> Vec<String> {
+    if path.is_empty() {
+        Vec::new()
+    } else {
+        crate::paths::split(path)
+    }
+}
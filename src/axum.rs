@@ -0,0 +1,190 @@
+//! `axum` extractors for JSON Merge Patch (RFC 7396) request bodies.
+//!
+//! [`MergePatch`] does what every `PATCH` handler otherwise repeats: it
+//! checks the `application/merge-patch+json` content type, buffers the body
+//! within the route's [`DefaultBodyLimit`](::axum::extract::DefaultBodyLimit),
+//! and parses it with the crate's JSON backend.
+
+use ::axum::body::Bytes;
+use ::axum::extract::rejection::BytesRejection;
+use ::axum::extract::{FromRequest, Request};
+use ::axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use ::axum::response::{IntoResponse, Response};
+use serde::{Serialize, de::DeserializeOwned};
+use std::fmt;
+
+/// Extracts a merge patch request body.
+///
+/// `T` is what the body is parsed into: a [`Patch`](crate::Patch) by default,
+/// ready for [`Patch::apply_to`](crate::Patch::apply_to), or any other
+/// deserializable type. Requests are rejected with [`MergePatchRejection`]
+/// when the `Content-Type` isn't `application/merge-patch+json`, the body is
+/// larger than the route's `DefaultBodyLimit` (2 MB unless configured), or it
+/// doesn't parse as `T`.
+///
+/// Returned from a handler, it responds with the patch and the merge patch
+/// content type.
+///
+/// # Example
+///
+/// ```
+/// use axum::{Router, extract::DefaultBodyLimit, routing::patch};
+/// use serde_patch::axum::MergePatch;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// async fn update_user(MergePatch(patch): MergePatch) -> Result<String, String> {
+///     let user = User { name: "alice".into(), age: 30 };
+///     let user = patch.apply_to(user).map_err(|err| err.to_string())?;
+///     Ok(format!("{} is {}", user.name, user.age))
+/// }
+///
+/// let app: Router = Router::new()
+///     .route("/users/{id}", patch(update_user))
+///     .layer(DefaultBodyLimit::max(64 * 1024));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+#[must_use]
+pub struct MergePatch<T = crate::Patch>(pub T);
+
+/// Extracts a body into a typed patch struct from `#[derive(Patch)]`.
+///
+/// Checks and rejects requests like [`MergePatch`]; named for handlers that
+/// take the generated `<Name>Patch` and call `apply_patch` with it. Fields of
+/// the wrong type are rejected with 422 and their path.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use serde_patch::{Patch, axum::TypedPatch};
+///
+/// #[derive(Patch, serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// async fn update_user(TypedPatch(patch): TypedPatch<UserPatch>) {
+///     let mut user = User { name: "alice".into(), age: 30 };
+///     user.apply_patch(patch);
+/// }
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+#[must_use]
+pub struct TypedPatch<P>(pub P);
+
+impl<P, S> FromRequest<S> for TypedPatch<P>
+where
+    P: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = MergePatchRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let MergePatch(patch) = MergePatch::from_request(req, state).await?;
+        Ok(Self(patch))
+    }
+}
+
+impl<T, S> FromRequest<S> for MergePatch<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = MergePatchRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_merge_patch(req.headers()) {
+            return Err(MergePatchRejection::UnsupportedMediaType);
+        }
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(MergePatchRejection::Body)?;
+        let value: serde_json::Value =
+            crate::backend::from_slice(&bytes).map_err(MergePatchRejection::Syntax)?;
+        serde_path_to_error::deserialize(value)
+            .map(MergePatch)
+            .map_err(|err| MergePatchRejection::Data(err.to_string()))
+    }
+}
+
+impl<T: Serialize> IntoResponse for MergePatch<T> {
+    fn into_response(self) -> Response {
+        match serde_json::to_vec(&self.0) {
+            Ok(body) => (
+                [(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(crate::MERGE_PATCH_MEDIA_TYPE),
+                )],
+                body,
+            )
+                .into_response(),
+            Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+        }
+    }
+}
+
+/// Why [`MergePatch`] rejected a request.
+///
+/// Responds with the matching status code and the message as plain text.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MergePatchRejection {
+    /// The `Content-Type` isn't `application/merge-patch+json` (415).
+    UnsupportedMediaType,
+    /// The body couldn't be read, e.g. it exceeded the body limit (413).
+    Body(BytesRejection),
+    /// The body isn't valid JSON (400).
+    Syntax(serde_json::Error),
+    /// The JSON doesn't fit the patch type, with the offending path (422).
+    Data(String),
+}
+
+impl MergePatchRejection {
+    /// The status code the rejection responds with.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Body(rejection) => rejection.status(),
+            Self::Syntax(_) => StatusCode::BAD_REQUEST,
+            Self::Data(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl fmt::Display for MergePatchRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedMediaType => write!(
+                f,
+                "expected request with `Content-Type: {}`",
+                crate::MERGE_PATCH_MEDIA_TYPE
+            ),
+            Self::Body(rejection) => write!(f, "{}", rejection.body_text()),
+            Self::Syntax(err) => write!(f, "invalid merge patch: {err}"),
+            Self::Data(message) => write!(f, "invalid merge patch: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for MergePatchRejection {}
+
+impl IntoResponse for MergePatchRejection {
+    fn into_response(self) -> Response {
+        (self.status(), self.to_string()).into_response()
+    }
+}
+
+/// Whether the request declares a merge patch body; parameters such as
+/// `charset` are allowed (internal).
+fn is_merge_patch(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|media_type| {
+            media_type
+                .trim()
+                .eq_ignore_ascii_case(crate::MERGE_PATCH_MEDIA_TYPE)
+        })
+}
@@ -0,0 +1,176 @@
+//! JSON parsing backend used for incoming patches (internal).
+//!
+//! Every entry point parses patch bytes through [`from_slice`], so the engine
+//! can be swapped with a Cargo feature without touching the public API, which
+//! keeps speaking `serde_json::Value` and `serde_json::Error`:
+//!
+//! - default: `serde_json`
+//! - `simd-json`: SIMD-accelerated parsing of a copy of the input, into
+//!   buffers reused across patches on the same thread
+//! - `sonic-rs`: SIMD-accelerated parsing without copying; wins if both are enabled
+//!
+//! Alternative backends only handle the success path. When they reject the
+//! input, it is parsed again with `serde_json` so errors keep their message,
+//! position and category regardless of the backend. Input they would read
+//! differently goes straight to `serde_json`: `\uD800`-`\uDFFF` escapes
+//! (lone surrogates are errors there) and `-0` (`-0.0` there, not `0`). With
+//! `arbitrary-precision`, every patch is parsed by `serde_json`, the only
+//! engine that keeps all digits of a number.
+
+use serde::de::DeserializeOwned;
+
+/// A JSON engine able to deserialize patch bytes (internal).
+pub(crate) trait Backend {
+    /// Parses `bytes`, reporting failures as `serde_json` errors.
+    fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, serde_json::Error>;
+}
+
+#[cfg(not(any(feature = "simd-json", feature = "sonic-rs")))]
+pub(crate) struct SerdeJson;
+
+#[cfg(not(any(feature = "simd-json", feature = "sonic-rs")))]
+impl Backend for SerdeJson {
+    fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+}
+
+#[cfg(feature = "simd-json")]
+#[cfg_attr(feature = "sonic-rs", allow(dead_code))]
+pub(crate) struct SimdJson;
+
+#[cfg(feature = "simd-json")]
+impl Backend for SimdJson {
+    fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, serde_json::Error> {
+        if cfg!(feature = "arbitrary-precision") || reads_differently(bytes) {
+            return serde_json::from_slice(bytes);
+        }
+        SIMD_SCRATCH
+            .with_borrow_mut(|(input, buffers)| {
+                // simd-json unescapes strings in place, so it needs its own copy.
+                input.clear();
+                input.extend_from_slice(bytes);
+                let parsed = simd_json::serde::from_slice_with_buffers(input, buffers);
+                if input.capacity() > MAX_RETAINED {
+                    *input = Vec::new();
+                    *buffers = simd_json::Buffers::default();
+                }
+                parsed
+            })
+            .or_else(|_| serde_json::from_slice(bytes))
+    }
+}
+
+/// Largest input whose buffers are kept for the next patch; one oversized
+/// patch shouldn't pin its memory to the thread.
+#[cfg(feature = "simd-json")]
+const MAX_RETAINED: usize = 1 << 20;
+
+#[cfg(feature = "simd-json")]
+thread_local! {
+    /// The input copy and simd-json's parsing buffers, reused so parsing a
+    /// patch doesn't allocate them anew.
+    static SIMD_SCRATCH: std::cell::RefCell<(Vec<u8>, simd_json::Buffers)> =
+        std::cell::RefCell::default();
+}
+
+#[cfg(feature = "sonic-rs")]
+pub(crate) struct SonicRs;
+
+#[cfg(feature = "sonic-rs")]
+impl Backend for SonicRs {
+    fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, serde_json::Error> {
+        // sonic-rs allows deeper nesting than serde_json, with frames large
+        // enough to exhaust the stack first; leave such input to serde_json.
+        if cfg!(feature = "arbitrary-precision")
+            || reads_differently(bytes)
+            || exceeds_depth(bytes, SERDE_JSON_DEPTH)
+        {
+            return serde_json::from_slice(bytes);
+        }
+        sonic_rs::from_slice(bytes).or_else(|_| serde_json::from_slice(bytes))
+    }
+}
+
+/// Nesting depth at which `serde_json` stops with a recursion limit error.
+#[cfg(feature = "sonic-rs")]
+const SERDE_JSON_DEPTH: usize = 128;
+
+/// Returns `true` if arrays and objects nest deeper than `limit` (internal).
+#[cfg(feature = "sonic-rs")]
+fn exceeds_depth(bytes: &[u8], limit: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for &byte in bytes {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > limit {
+                    return true;
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Returns `true` for input an alternative backend would parse differently
+/// from `serde_json`: surrogate escapes and `-0` (internal).
+#[cfg(any(feature = "simd-json", feature = "sonic-rs"))]
+fn reads_differently(bytes: &[u8]) -> bool {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, &byte) in bytes.iter().enumerate() {
+        let next = |offset: usize| bytes.get(index + offset).copied();
+        if in_string {
+            match byte {
+                b'u' if escaped => {
+                    escaped = false;
+                    if matches!(next(1), Some(b'd' | b'D'))
+                        && next(2).is_some_and(|digit| b"89abcdefABCDEF".contains(&digit))
+                    {
+                        return true;
+                    }
+                }
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'-' if next(1) == Some(b'0') && next(2) != Some(b'.') => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(feature = "sonic-rs")]
+type Selected = SonicRs;
+
+#[cfg(all(feature = "simd-json", not(feature = "sonic-rs")))]
+type Selected = SimdJson;
+
+#[cfg(not(any(feature = "simd-json", feature = "sonic-rs")))]
+type Selected = SerdeJson;
+
+/// Parses JSON bytes with the selected backend (internal).
+pub(crate) fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, serde_json::Error> {
+    Selected::from_slice(bytes)
+}
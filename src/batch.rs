@@ -0,0 +1,117 @@
+use serde_json::Value;
+use std::fmt;
+
+/// Groups sequential patches into batches bounded by count and size.
+///
+/// Patches are kept in order and handed to the sink as a `Vec` once the batch
+/// holds `max_count` patches, or before a patch would push its serialized size
+/// (compact JSON) past `max_bytes`. A patch larger than `max_bytes` on its own
+/// is delivered as a single-patch batch. Remaining patches are delivered on
+/// [`flush`](Batcher::flush) or when the batcher is dropped.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::{Value, json};
+/// use serde_patch::Batcher;
+///
+/// let mut batches: Vec<Vec<Value>> = Vec::new();
+/// {
+///     let mut batcher = Batcher::new(2, 1024, |batch| batches.push(batch));
+///     batcher.push(json!({ "a": 1 }));
+///     batcher.push(json!({ "b": 2 }));
+///     batcher.push(json!({ "c": 3 }));
+/// }
+/// assert_eq!(batches, vec![
+///     vec![json!({ "a": 1 }), json!({ "b": 2 })],
+///     vec![json!({ "c": 3 })],
+/// ]);
+/// ```
+pub struct Batcher<F>
+where
+    F: FnMut(Vec<Value>),
+{
+    max_count: usize,
+    max_bytes: usize,
+    sink: F,
+    batch: Vec<Value>,
+    bytes: usize,
+}
+
+impl<F> Batcher<F>
+where
+    F: FnMut(Vec<Value>),
+{
+    /// Creates a batcher delivering batches of at most `max_count` patches and `max_bytes` bytes.
+    pub fn new(max_count: usize, max_bytes: usize, sink: F) -> Self {
+        Self {
+            max_count: max_count.max(1),
+            max_bytes,
+            sink,
+            batch: Vec::new(),
+            bytes: 0,
+        }
+    }
+
+    /// Adds a patch, delivering the current batch first if the patch would not fit.
+    pub fn push(&mut self, patch: Value) {
+        let size = serde_json::to_vec(&patch).map_or(0, |bytes| bytes.len());
+        if !self.batch.is_empty() && self.bytes + size > self.max_bytes {
+            self.flush();
+        }
+
+        self.batch.push(patch);
+        self.bytes += size;
+        if self.batch.len() >= self.max_count || self.bytes >= self.max_bytes {
+            self.flush();
+        }
+    }
+
+    /// Delivers the current batch now; returns `true` if it was not empty.
+    pub fn flush(&mut self) -> bool {
+        if self.batch.is_empty() {
+            return false;
+        }
+        self.bytes = 0;
+        (self.sink)(std::mem::take(&mut self.batch));
+        true
+    }
+
+    /// Number of patches in the current batch.
+    pub fn len(&self) -> usize {
+        self.batch.len()
+    }
+
+    /// Returns `true` if the current batch is empty.
+    pub fn is_empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+
+    /// Serialized size of the current batch, in bytes.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl<F> Drop for Batcher<F>
+where
+    F: FnMut(Vec<Value>),
+{
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl<F> fmt::Debug for Batcher<F>
+where
+    F: FnMut(Vec<Value>),
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batcher")
+            .field("max_count", &self.max_count)
+            .field("max_bytes", &self.max_bytes)
+            .field("batch", &self.batch)
+            .field("bytes", &self.bytes)
+            .finish_non_exhaustive()
+    }
+}
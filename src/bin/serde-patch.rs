@@ -0,0 +1,163 @@
+//! `serde-patch`: the library's diff and apply from the shell.
+//!
+//! ```text
+//! serde-patch diff <old.json> <new.json> [--include <path>]... [--format merge|json-patch] [--pretty]
+//! serde-patch apply <document.json> <patch.json> [--format merge|json-patch] [--pretty]
+//! ```
+//!
+//! `-` reads a file from standard input. Results are written to standard
+//! output; errors go to standard error with exit code 1, usage errors with 2.
+
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: serde-patch diff <old> <new> [--include <path>]... [--format merge|json-patch] [--pretty]
+       serde-patch apply <document> <patch> [--format merge|json-patch] [--pretty]
+
+Files are JSON; `-` reads standard input.
+
+  --include <path>   always include this field in a merge patch (repeatable)
+  --format <format>  `merge` for JSON Merge Patch (default), `json-patch` for RFC 6902
+  --pretty           pretty-print the output";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Merge,
+    JsonPatch,
+}
+
+enum Command {
+    Diff,
+    Apply,
+}
+
+struct Args {
+    command: Command,
+    files: Vec<String>,
+    including: Vec<String>,
+    format: Format,
+    pretty: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let command = match args.next().as_deref() {
+        Some("diff") => Command::Diff,
+        Some("apply") => Command::Apply,
+        Some(other) => return Err(format!("unknown command `{other}`")),
+        None => return Err("missing command".to_string()),
+    };
+    let mut parsed = Args {
+        command,
+        files: Vec::new(),
+        including: Vec::new(),
+        format: Format::Merge,
+        pretty: false,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--include" => {
+                let path = args.next().ok_or("`--include` needs a path")?;
+                parsed.including.push(path);
+            }
+            "--format" => {
+                parsed.format = match args.next().as_deref() {
+                    Some("merge") => Format::Merge,
+                    Some("json-patch") => Format::JsonPatch,
+                    Some(other) => return Err(format!("unknown format `{other}`")),
+                    None => return Err("`--format` needs a format".to_string()),
+                };
+            }
+            "--pretty" => parsed.pretty = true,
+            "-" => parsed.files.push(arg),
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{flag}`")),
+            _ => parsed.files.push(arg),
+        }
+    }
+    if parsed.files.len() != 2 {
+        return Err(format!("expected 2 files, got {}", parsed.files.len()));
+    }
+    if !parsed.including.is_empty() && parsed.format == Format::JsonPatch {
+        return Err("`--include` only applies to merge patches".to_string());
+    }
+    if !parsed.including.is_empty() && matches!(parsed.command, Command::Apply) {
+        return Err("`--include` only applies to `diff`".to_string());
+    }
+    Ok(parsed)
+}
+
+fn read_json(path: &str) -> Result<Value, String> {
+    let mut text = String::new();
+    let read = if path == "-" {
+        std::io::stdin().read_to_string(&mut text).map(|_| ())
+    } else {
+        std::fs::read_to_string(path).map(|contents| text = contents)
+    };
+    read.map_err(|err| format!("{path}: {err}"))?;
+    serde_json::from_str(&text).map_err(|err| format!("{path}: {err}"))
+}
+
+fn run(args: Args) -> Result<Value, String> {
+    let first = read_json(&args.files[0])?;
+    let second = read_json(&args.files[1])?;
+    match (args.command, args.format) {
+        (Command::Diff, Format::Merge) => {
+            let including: Vec<&str> = args.including.iter().map(String::as_str).collect();
+            serde_patch::diff_including(&first, &second, &including).map_err(|err| err.to_string())
+        }
+        (Command::Diff, Format::JsonPatch) => serde_patch::json_patch::diff_ops(&first, &second)
+            .and_then(serde_json::to_value)
+            .map_err(|err| err.to_string()),
+        (Command::Apply, Format::Merge) => {
+            serde_patch::apply_value(first, &second).map_err(|err| err.to_string())
+        }
+        (Command::Apply, Format::JsonPatch) => {
+            let ops: Vec<serde_patch::json_patch::Op> =
+                serde_json::from_value(second).map_err(|err| err.to_string())?;
+            serde_patch::json_patch::apply_ops(first, &ops).map_err(|err| err.to_string())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    if std::env::args()
+        .skip(1)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("serde-patch: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let pretty = args.pretty;
+    let output = run(args).and_then(|value| {
+        let text = if pretty {
+            serde_json::to_string_pretty(&value)
+        } else {
+            serde_json::to_string(&value)
+        };
+        text.map_err(|err| err.to_string())
+    });
+    match output {
+        Ok(text) => {
+            let mut stdout = std::io::stdout().lock();
+            match writeln!(stdout, "{text}") {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("serde-patch: {err}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Err(message) => {
+            eprintln!("serde-patch: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Key marking a binary delta object inside a patch.
+pub const DELTA_KEY: &str = "$delta";
+
+const BLOCK: usize = 16;
+const ADD: u8 = 0;
+const COPY: u8 = 1;
+
+static NULL: Value = Value::Null;
+
+/// Encodes `new` as copy/add instructions against `old` (internal).
+///
+/// Layout: varint target length, then a sequence of `ADD len bytes` or
+/// `COPY offset len` instructions, all integers LEB128-encoded.
+pub fn encode(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut index: HashMap<&[u8], usize> = HashMap::new();
+    if old.len() >= BLOCK {
+        for start in (0..=old.len() - BLOCK).step_by(BLOCK) {
+            index.entry(&old[start..start + BLOCK]).or_insert(start);
+        }
+    }
+
+    let mut out = Vec::new();
+    write_varint(&mut out, new.len());
+
+    let mut pending = 0;
+    let mut pos = 0;
+    while pos + BLOCK <= new.len() {
+        let Some(&start) = index.get(&new[pos..pos + BLOCK]) else {
+            pos += 1;
+            continue;
+        };
+
+        let (mut old_start, mut new_start) = (start, pos);
+        while old_start > 0 && new_start > pending && old[old_start - 1] == new[new_start - 1] {
+            old_start -= 1;
+            new_start -= 1;
+        }
+
+        let (mut old_end, mut new_end) = (start + BLOCK, pos + BLOCK);
+        while old_end < old.len() && new_end < new.len() && old[old_end] == new[new_end] {
+            old_end += 1;
+            new_end += 1;
+        }
+
+        write_add(&mut out, &new[pending..new_start]);
+        out.push(COPY);
+        write_varint(&mut out, old_start);
+        write_varint(&mut out, new_end - new_start);
+
+        pending = new_end;
+        pos = new_end;
+    }
+    write_add(&mut out, &new[pending..]);
+
+    out
+}
+
+/// Rebuilds the target bytes from `old` and a delta produced by [`encode`] (internal).
+///
+/// Returns `None` if the delta is malformed or does not fit `old`.
+pub fn decode(old: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let mut cursor = delta;
+    let target_len = read_varint(&mut cursor)?;
+    let mut out = Vec::with_capacity(target_len.min(delta.len().saturating_mul(64)));
+
+    while let Some((&tag, rest)) = cursor.split_first() {
+        cursor = rest;
+        match tag {
+            ADD => {
+                let len = read_varint(&mut cursor)?;
+                if len > cursor.len() {
+                    return None;
+                }
+                let (bytes, rest) = cursor.split_at(len);
+                out.extend_from_slice(bytes);
+                cursor = rest;
+            }
+            COPY => {
+                let offset = read_varint(&mut cursor)?;
+                let len = read_varint(&mut cursor)?;
+                out.extend_from_slice(old.get(offset..offset.checked_add(len)?)?);
+            }
+            _ => return None,
+        }
+    }
+
+    (out.len() == target_len).then_some(out)
+}
+
+/// Returns a `{ "$delta": ... }` object if it is smaller than the new blob (internal).
+///
+/// Both values must be base64 strings; anything else yields `None` so the
+/// caller falls back to emitting the full value.
+pub fn delta_value(old: &Value, new: &Value) -> Option<Value> {
+    let (Value::String(old_b64), Value::String(new_b64)) = (old, new) else {
+        return None;
+    };
+    let old_bytes = STANDARD.decode(old_b64).ok()?;
+    let new_bytes = STANDARD.decode(new_b64).ok()?;
+
+    let delta = STANDARD.encode(encode(&old_bytes, &new_bytes));
+    if delta.len() < new_b64.len() {
+        let mut map = Map::new();
+        map.insert(DELTA_KEY.to_string(), Value::String(delta));
+        Some(Value::Object(map))
+    } else {
+        None
+    }
+}
+
+/// Replaces binary delta objects at the given paths with the reconstructed blobs (internal).
+pub fn resolve_deltas(
+    current: &Value,
+    patch: &mut Value,
+    paths: &HashSet<String>,
+    current_path: &str,
+) -> Result<(), serde_json::Error> {
+    if crate::paths::matches_any(paths, current_path)
+        && let Some(delta_b64) = as_delta(patch)
+    {
+        let invalid =
+            || serde_json::Error::custom(format!("invalid binary delta at `{}`", current_path));
+
+        let old_bytes = match current {
+            Value::String(s) => STANDARD.decode(s).map_err(|_| invalid())?,
+            _ => return Err(invalid()),
+        };
+        let delta = STANDARD.decode(delta_b64).map_err(|_| invalid())?;
+        let new_bytes = decode(&old_bytes, &delta).ok_or_else(invalid)?;
+
+        *patch = Value::String(STANDARD.encode(new_bytes));
+        return Ok(());
+    }
+
+    if let Value::Object(patch_map) = patch {
+        for (key, patch_value) in patch_map.iter_mut() {
+            let full_path = crate::paths::join(current_path, key);
+            let current_value = current.get(key).unwrap_or(&NULL);
+            resolve_deltas(current_value, patch_value, paths, &full_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn as_delta(value: &Value) -> Option<&str> {
+    match value.as_object() {
+        Some(map) if map.len() == 1 => map.get(DELTA_KEY)?.as_str(),
+        _ => None,
+    }
+}
+
+fn write_add(out: &mut Vec<u8>, bytes: &[u8]) {
+    if !bytes.is_empty() {
+        out.push(ADD);
+        write_varint(out, bytes.len());
+        out.extend_from_slice(bytes);
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(cursor: &mut &[u8]) -> Option<usize> {
+    let mut value: usize = 0;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = cursor.split_first()?;
+        *cursor = rest;
+        if shift >= usize::BITS {
+            return None;
+        }
+        value |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
@@ -0,0 +1,168 @@
+use bson::{Bson, Document};
+use serde::de::Error as _;
+use serde_json::Value;
+
+/// Computes a JSON Merge Patch (RFC 7396) between two BSON documents.
+///
+/// Works on BSON values directly, so ObjectIds, dates, `Decimal128` and the
+/// distinction between `Int32`, `Int64` and `Double` are kept. Embedded
+/// documents are diffed recursively; any other changed value, arrays included,
+/// is replaced wholesale. Removed fields are set to `Null`.
+///
+/// # Example
+///
+/// ```
+/// use bson::{DateTime, doc, oid::ObjectId};
+///
+/// let id = ObjectId::new();
+/// let old = doc! { "_id": id, "name": "alice", "seen": DateTime::from_millis(0), "tmp": 1 };
+/// let new = doc! { "_id": id, "name": "alice", "seen": DateTime::from_millis(1_000) };
+///
+/// let patch = serde_patch::diff_bson(&old, &new);
+/// assert_eq!(patch, doc! { "seen": DateTime::from_millis(1_000), "tmp": null });
+/// assert_eq!(serde_patch::apply_bson(old, &patch), new);
+/// ```
+pub fn diff_bson(old: &Document, new: &Document) -> Document {
+    let mut patch = Document::new();
+    for (key, old_value) in old {
+        match (old_value, new.get(key)) {
+            (_, None) => {
+                patch.insert(key, Bson::Null);
+            }
+            (Bson::Document(old_doc), Some(Bson::Document(new_doc))) => {
+                let nested = diff_bson(old_doc, new_doc);
+                if !nested.is_empty() {
+                    patch.insert(key, nested);
+                }
+            }
+            (old_value, Some(new_value)) if old_value != new_value => {
+                patch.insert(key, new_value.clone());
+            }
+            _ => {}
+        }
+    }
+    for (key, new_value) in new {
+        if !old.contains_key(key) {
+            patch.insert(key, new_value.clone());
+        }
+    }
+    patch
+}
+
+/// Applies a JSON Merge Patch (RFC 7396) held as a BSON document.
+///
+/// `Null` removes a field; embedded documents are merged recursively.
+pub fn apply_bson(mut document: Document, patch: &Document) -> Document {
+    merge(&mut document, patch);
+    document
+}
+
+/// Translates a merge patch into a MongoDB update document.
+///
+/// Leaves of the patch become dotted paths: values go to `$set`, `Null` to
+/// `$unset`. Empty operators are left out, so an empty patch gives an empty
+/// document. A nested empty document counts as a leaf and is `$set`. MongoDB
+/// has no escaping for `.` in field names, so keys are joined as they are.
+///
+/// # Example
+///
+/// ```
+/// use bson::doc;
+///
+/// let old = doc! { "name": "alice", "profile": { "bio": "hi", "avatar": "a.png" } };
+/// let new = doc! { "name": "alice", "profile": { "bio": "hello" } };
+///
+/// let update = serde_patch::to_mongo_update(&serde_patch::diff_bson(&old, &new));
+/// assert_eq!(
+///     update,
+///     doc! { "$set": { "profile.bio": "hello" }, "$unset": { "profile.avatar": "" } }
+/// );
+/// ```
+pub fn to_mongo_update(patch: &Document) -> Document {
+    let mut set = Document::new();
+    let mut unset = Document::new();
+    flatten(patch, "", &mut set, &mut unset);
+
+    let mut update = Document::new();
+    if !set.is_empty() {
+        update.insert("$set", set);
+    }
+    if !unset.is_empty() {
+        update.insert("$unset", unset);
+    }
+    update
+}
+
+/// Converts a patch to a BSON document.
+///
+/// Both canonical and relaxed MongoDB Extended JSON are understood, so
+/// `{ "$oid": ... }`, `{ "$date": ... }` and `{ "$numberDecimal": ... }`
+/// become the corresponding BSON types.
+///
+/// # Example
+///
+/// ```
+/// use bson::{Bson, Decimal128};
+/// use serde_json::json;
+///
+/// let patch = json!({ "price": { "$numberDecimal": "9.99" }, "stock": null });
+/// let document = serde_patch::to_bson(&patch).unwrap();
+/// assert_eq!(document.get("price"), Some(&Bson::Decimal128("9.99".parse::<Decimal128>().unwrap())));
+/// assert_eq!(serde_patch::from_bson(&document), patch);
+/// ```
+pub fn to_bson(patch: &Value) -> Result<Document, serde_json::Error> {
+    let Value::Object(map) = patch else {
+        return Err(serde_json::Error::custom(
+            "a BSON document can only be built from an object",
+        ));
+    };
+    Document::try_from(map.clone()).map_err(serde_json::Error::custom)
+}
+
+/// Converts a BSON patch to canonical MongoDB Extended JSON.
+///
+/// The result converts back with [`to_bson`] without losing any type.
+pub fn from_bson(patch: &Document) -> Value {
+    Bson::Document(patch.clone()).into_canonical_extjson()
+}
+
+fn flatten(patch: &Document, prefix: &str, set: &mut Document, unset: &mut Document) {
+    for (key, value) in patch {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Bson::Null => {
+                unset.insert(path, "");
+            }
+            Bson::Document(nested) if !nested.is_empty() => flatten(nested, &path, set, unset),
+            _ => {
+                set.insert(path, value.clone());
+            }
+        }
+    }
+}
+
+fn merge(target: &mut Document, patch: &Document) {
+    for (key, value) in patch {
+        match value {
+            Bson::Null => {
+                target.remove(key);
+            }
+            Bson::Document(nested) => {
+                if let Some(Bson::Document(existing)) = target.get_mut(key) {
+                    merge(existing, nested);
+                } else {
+                    let mut document = Document::new();
+                    merge(&mut document, nested);
+                    target.insert(key, document);
+                }
+            }
+            _ => {
+                target.insert(key, value.clone());
+            }
+        }
+    }
+}
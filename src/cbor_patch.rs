@@ -0,0 +1,68 @@
+use crate::Format;
+use serde::de::Error as _;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+/// CBOR (RFC 8949) as a [`Format`].
+///
+/// Map keys must be text strings; byte strings and tags other than those
+/// `serde` understands are rejected.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cbor;
+
+impl Format for Cbor {
+    fn parse(bytes: &[u8]) -> Result<Value, serde_json::Error> {
+        ciborium::from_reader(bytes).map_err(serde_json::Error::custom)
+    }
+
+    fn serialize(value: &Value) -> Result<Vec<u8>, serde_json::Error> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(serde_json::Error::custom)?;
+        Ok(bytes)
+    }
+}
+
+/// Computes a JSON Merge Patch (RFC 7396) and encodes it as CBOR (RFC 8949).
+///
+/// The patch is the one [`diff`](crate::diff) returns; removed fields are
+/// encoded as CBOR `null`.
+///
+/// # Example
+///
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Reading { sensor: String, celsius: f32 }
+///
+/// let old = Reading { sensor: "t1".into(), celsius: 20.5 };
+/// let new = Reading { sensor: "t1".into(), celsius: 21.0 };
+///
+/// let patch = serde_patch::diff_to_cbor(&old, &new).unwrap();
+/// let updated = serde_patch::apply_cbor(old, &patch).unwrap();
+/// assert_eq!(updated.celsius, 21.0);
+/// ```
+pub fn diff_to_cbor<T: Serialize>(old: &T, new: &T) -> Result<Vec<u8>, serde_json::Error> {
+    Cbor::diff(old, new)
+}
+
+/// Encodes a merge patch as CBOR.
+pub fn to_cbor(patch: &Value) -> Result<Vec<u8>, serde_json::Error> {
+    Cbor::serialize(patch)
+}
+
+/// Decodes a CBOR-encoded merge patch.
+///
+/// Accepts the same input as [`Cbor`].
+pub fn parse_cbor(patch: &[u8]) -> Result<Value, serde_json::Error> {
+    Cbor::parse(patch)
+}
+
+/// Applies a CBOR-encoded JSON Merge Patch (RFC 7396).
+///
+/// Like [`apply`](crate::apply); a patch that can't be decoded is reported as
+/// [`Error::ParsePatch`](crate::Error::ParsePatch).
+pub fn apply_cbor<T>(current: T, patch: &[u8]) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    Cbor::apply(current, patch)
+}
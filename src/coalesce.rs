@@ -0,0 +1,200 @@
+use serde_json::{Map, Value};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Accumulates patches and delivers them to a sink as one composed patch.
+///
+/// Patches pushed within `window` of the first pending patch are composed
+/// into a single merge patch with the same effect as applying them in order.
+/// The composed patch is delivered once the window has elapsed (checked on
+/// [`push`](Coalescer::push) and [`poll`](Coalescer::poll)), on
+/// [`flush`](Coalescer::flush), or when the coalescer is dropped.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::{Value, json};
+/// use serde_patch::Coalescer;
+/// use std::time::Duration;
+///
+/// let mut sent: Vec<Value> = Vec::new();
+/// {
+///     let mut coalescer = Coalescer::new(Duration::from_millis(16), |patch| sent.push(patch));
+///     coalescer.push(json!({ "x": 1 }));
+///     coalescer.push(json!({ "x": 2, "y": 1 }));
+///     coalescer.flush();
+/// }
+/// assert_eq!(sent, vec![json!({ "x": 2, "y": 1 })]);
+/// ```
+pub struct Coalescer<F>
+where
+    F: FnMut(Value),
+{
+    window: Duration,
+    sink: F,
+    pending: Option<(Instant, Value)>,
+}
+
+impl<F> Coalescer<F>
+where
+    F: FnMut(Value),
+{
+    /// Creates a coalescer delivering composed patches to `sink`.
+    pub fn new(window: Duration, sink: F) -> Self {
+        Self {
+            window,
+            sink,
+            pending: None,
+        }
+    }
+
+    /// Composes `patch` into the pending patch, delivering it if the window has elapsed.
+    pub fn push(&mut self, patch: Value) {
+        self.pending = Some(match self.pending.take() {
+            Some((started, pending)) => (started, compose_owned(pending, patch)),
+            None => (Instant::now(), patch),
+        });
+        self.poll();
+    }
+
+    /// Delivers the pending patch if the window has elapsed; returns `true` if it did.
+    pub fn poll(&mut self) -> bool {
+        match &self.pending {
+            Some((started, _)) if started.elapsed() >= self.window => self.flush(),
+            _ => false,
+        }
+    }
+
+    /// Delivers the pending patch now; returns `true` if there was one.
+    pub fn flush(&mut self) -> bool {
+        match self.pending.take() {
+            Some((_, patch)) => {
+                (self.sink)(patch);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` if patches are waiting to be delivered.
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+impl<F> Drop for Coalescer<F>
+where
+    F: FnMut(Value),
+{
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl<F> fmt::Debug for Coalescer<F>
+where
+    F: FnMut(Value),
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coalescer")
+            .field("window", &self.window)
+            .field("pending", &self.pending.as_ref().map(|(_, patch)| patch))
+            .finish_non_exhaustive()
+    }
+}
+
+/// Composes two merge patches into one with the effect of applying `first`, then `second`.
+///
+/// Useful to squash queued patches (e.g. from an offline client) before
+/// sending them. Follows RFC 7396: a `null` in `second` removes the field
+/// whatever `first` did with it, and a field `first` removed stays removed
+/// unless `second` sets it again.
+///
+/// One case has no exact merge patch equivalent: when `first` sets a field to
+/// a non-object and `second` patches it as an object, the result only
+/// matches for targets whose field isn't an object.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let first = json!({ "name": "bob", "profile": { "bio": "hi", "avatar_url": null } });
+/// let second = json!({ "name": null, "profile": { "bio": "hello" } });
+///
+/// let composed = serde_patch::compose(&first, &second);
+/// assert_eq!(
+///     composed,
+///     json!({ "name": null, "profile": { "bio": "hello", "avatar_url": null } })
+/// );
+/// ```
+pub fn compose(first: &Value, second: &Value) -> Value {
+    compose_owned(first.clone(), second.clone())
+}
+
+/// Compacts a chain of merge patches into one with the effect of applying
+/// them in order, composing them as [`compose`] does.
+///
+/// An empty chain compacts to `{}`, which changes nothing.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let chain = [
+///     json!({ "status": "paid", "note": "rush" }),
+///     json!({ "note": null }),
+///     json!({ "status": "shipped", "tracking": { "carrier": "ups" } }),
+/// ];
+///
+/// assert_eq!(
+///     serde_patch::compact(&chain),
+///     json!({ "status": "shipped", "note": null, "tracking": { "carrier": "ups" } })
+/// );
+/// ```
+pub fn compact(patches: &[Value]) -> Value {
+    compact_all(patches)
+}
+
+/// [`compact`] over any sequence of patches (internal).
+pub(crate) fn compact_all<'a>(patches: impl IntoIterator<Item = &'a Value>) -> Value {
+    patches
+        .into_iter()
+        .fold(Value::Object(Map::new()), |compacted, patch| {
+            compose_owned(compacted, patch.clone())
+        })
+}
+
+fn compose_owned(first: Value, second: Value) -> Value {
+    let Value::Object(second_map) = second else {
+        return second;
+    };
+    let Value::Object(mut first_map) = first else {
+        // `first` replaced the target with a non-object, so `second` merges into an empty object.
+        return strip_nulls(Value::Object(second_map));
+    };
+
+    for (key, value) in second_map {
+        let composed = match (first_map.remove(&key), value) {
+            (_, Value::Null) => Value::Null,
+            (Some(previous), value) => compose_owned(previous, value),
+            (None, value) => value,
+        };
+        first_map.insert(key, composed);
+    }
+    Value::Object(first_map)
+}
+
+/// Drops `null` members recursively, as merging into an empty object would (internal).
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, strip_nulls(value)))
+                .collect::<Map<_, _>>(),
+        ),
+        other => other,
+    }
+}
@@ -0,0 +1,239 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Decides whether two values at a path are equivalent for diffing purposes.
+///
+/// Registered per dotted path in [`DiffOptions::comparators`](crate::DiffOptions::comparators).
+/// When it reports the old and new values as equivalent, the field is left out
+/// of the patch (unless forced); otherwise it is diffed as usual.
+pub trait Comparator: fmt::Debug + Send + Sync {
+    /// Returns `true` if `old` and `new` represent the same value.
+    fn equivalent(&self, old: &Value, new: &Value) -> bool;
+}
+
+/// Comparators registered by dotted path.
+pub type Comparators = HashMap<String, Arc<dyn Comparator>>;
+
+/// Compares durations written as human-readable strings or numeric seconds.
+///
+/// Accepts numbers (`90`, `1.5`) as seconds and strings made of one or more
+/// `<number><unit>` components (`"90s"`, `"1m30s"`, `"1h 15m"`, `"250ms"`).
+/// Supported units: `ns`, `us`/`µs`, `ms`, `s`, `m`, `h`, `d`, `w`, plus
+/// their long forms (`sec`, `minutes`, `hours`, ...). Values that don't parse
+/// are compared as plain JSON.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{DiffOptions, DurationComparator};
+/// use std::sync::Arc;
+///
+/// #[derive(serde::Serialize)]
+/// struct Config { timeout: String, retries: u8 }
+///
+/// let old = Config { timeout: "90s".into(), retries: 3 };
+/// let new = Config { timeout: "1m30s".into(), retries: 5 };
+///
+/// let options = DiffOptions {
+///     comparators: [("timeout".to_string(), Arc::new(DurationComparator) as _)].into(),
+///     ..Default::default()
+/// };
+/// let patch = serde_patch::diff_with(&old, &new, &options).unwrap();
+/// assert_eq!(patch, json!({ "retries": 5 }));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DurationComparator;
+
+impl Comparator for DurationComparator {
+    fn equivalent(&self, old: &Value, new: &Value) -> bool {
+        match (parse_duration(old), parse_duration(new)) {
+            (Some(old), Some(new)) => old == new,
+            _ => old == new,
+        }
+    }
+}
+
+/// Compares IP addresses by value rather than spelling.
+///
+/// `"::1"` equals `"0:0:0:0:0:0:0:1"`, and IPv4-mapped IPv6 addresses
+/// (`"::ffff:10.0.0.1"`) equal their IPv4 form. Arrays are compared
+/// element-wise. Values that don't parse are compared as plain JSON.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::{Comparator, IpAddrComparator};
+/// use serde_json::json;
+///
+/// assert!(IpAddrComparator.equivalent(&json!("::1"), &json!("0:0:0:0:0:0:0:1")));
+/// assert!(!IpAddrComparator.equivalent(&json!("10.0.0.1"), &json!("10.0.0.2")));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IpAddrComparator;
+
+impl Comparator for IpAddrComparator {
+    fn equivalent(&self, old: &Value, new: &Value) -> bool {
+        equivalent_by(old, new, parse_ip)
+    }
+}
+
+/// Compares CIDR blocks by the network they denote.
+///
+/// Host bits are masked off (`"10.1.2.3/8"` equals `"10.0.0.0/8"`), IPv6
+/// spellings are normalized, and a bare address is treated as a `/32` or
+/// `/128` block. Arrays are compared element-wise. Values that don't parse
+/// are compared as plain JSON.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::{CidrComparator, Comparator};
+/// use serde_json::json;
+///
+/// assert!(CidrComparator.equivalent(&json!("10.1.2.3/8"), &json!("10.0.0.0/8")));
+/// assert!(CidrComparator.equivalent(&json!("2001:db8::1/32"), &json!("2001:0db8::/32")));
+/// assert!(!CidrComparator.equivalent(&json!("10.0.0.0/8"), &json!("10.0.0.0/16")));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CidrComparator;
+
+impl Comparator for CidrComparator {
+    fn equivalent(&self, old: &Value, new: &Value) -> bool {
+        equivalent_by(old, new, parse_cidr)
+    }
+}
+
+/// Compares parsed forms, element-wise for arrays, falling back to JSON equality.
+fn equivalent_by<T: PartialEq>(old: &Value, new: &Value, parse: fn(&str) -> Option<T>) -> bool {
+    match (old, new) {
+        (Value::String(old_s), Value::String(new_s)) => match (parse(old_s), parse(new_s)) {
+            (Some(old), Some(new)) => old == new,
+            _ => old_s == new_s,
+        },
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            old_items.len() == new_items.len()
+                && old_items
+                    .iter()
+                    .zip(new_items)
+                    .all(|(old, new)| equivalent_by(old, new, parse))
+        }
+        _ => old == new,
+    }
+}
+
+fn parse_ip(s: &str) -> Option<IpAddr> {
+    s.trim().parse::<IpAddr>().ok().map(|ip| ip.to_canonical())
+}
+
+fn parse_cidr(s: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match s.trim().split_once('/') {
+        Some((addr, prefix)) => (parse_ip(addr)?, Some(prefix.parse::<u8>().ok()?)),
+        None => (parse_ip(s)?, None),
+    };
+
+    match addr {
+        IpAddr::V4(v4) => {
+            let prefix = prefix.unwrap_or(32);
+            if prefix > 32 {
+                return None;
+            }
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            Some((IpAddr::V4((u32::from(v4) & mask).into()), prefix))
+        }
+        IpAddr::V6(v6) => {
+            let prefix = prefix.unwrap_or(128);
+            if prefix > 128 {
+                return None;
+            }
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            Some((IpAddr::V6((u128::from(v6) & mask).into()), prefix))
+        }
+    }
+}
+
+/// Parses a JSON number (seconds) or a duration string (internal).
+pub fn parse_duration(value: &Value) -> Option<Duration> {
+    match value {
+        Value::Number(n) => parse_decimal(&n.to_string(), 1_000_000_000),
+        Value::String(s) => parse_duration_str(s),
+        _ => None,
+    }
+}
+
+fn parse_duration_str(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    if let Some(seconds) = parse_decimal(s, 1_000_000_000) {
+        return Some(seconds);
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(number_len);
+        let tail = tail.trim_start();
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+
+        total = total.checked_add(parse_decimal(number, unit_nanos(unit)?)?)?;
+        rest = tail.trim_start();
+    }
+    Some(total)
+}
+
+fn unit_nanos(unit: &str) -> Option<u64> {
+    Some(match unit {
+        "ns" | "nsec" | "nanos" => 1,
+        "us" | "µs" | "usec" | "micros" => 1_000,
+        "ms" | "msec" | "millis" => 1_000_000,
+        "s" | "sec" | "secs" | "second" | "seconds" => 1_000_000_000,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60_000_000_000,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3_600_000_000_000,
+        "d" | "day" | "days" => 86_400_000_000_000,
+        "w" | "week" | "weeks" => 604_800_000_000_000,
+        _ => return None,
+    })
+}
+
+/// Parses a non-negative decimal number of `unit_nanos` units exactly.
+fn parse_decimal(number: &str, unit_nanos: u64) -> Option<Duration> {
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    if !whole
+        .bytes()
+        .chain(fraction.bytes())
+        .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let whole: u128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let mut nanos = whole.checked_mul(unit_nanos as u128)?;
+
+    let mut scale = unit_nanos as u128;
+    for digit in fraction.bytes() {
+        scale /= 10;
+        nanos += (digit - b'0') as u128 * scale;
+    }
+
+    let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+    Some(Duration::new(secs, (nanos % 1_000_000_000) as u32))
+}
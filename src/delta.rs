@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Before and after values of one changed leaf; `None` means absent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeafChange {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// Changed leaves with their before and after values, keyed by dotted path.
+///
+/// Unlike a merge patch, which only keeps new values, a delta also records
+/// what each field changed from. Produced by [`diff_delta`]; serializes as
+/// `{ "<path>": { "old": ..., "new": ... } }`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Delta(BTreeMap<String, LeafChange>);
+
+impl Delta {
+    /// Returns `true` if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The change at a dotted `path`, if that leaf changed.
+    pub fn get(&self, path: &str) -> Option<&LeafChange> {
+        self.0.get(path)
+    }
+
+    /// Changed leaves in path order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &LeafChange)> {
+        self.0.iter().map(|(path, change)| (path.as_str(), change))
+    }
+
+    /// The merge patch with the same effect, turning the old value into the new one.
+    pub fn to_patch(&self) -> Value {
+        let mut patch = Value::Object(Default::default());
+        for (path, change) in &self.0 {
+            let mut target = &mut patch;
+            for segment in crate::paths::split(path) {
+                if !target.is_object() {
+                    *target = Value::Object(Default::default());
+                }
+                target = match target {
+                    Value::Object(map) => map.entry(segment).or_insert(Value::Null),
+                    _ => return patch,
+                };
+            }
+            *target = change.new.clone().unwrap_or(Value::Null);
+        }
+        patch
+    }
+}
+
+/// Computes the changed leaves between `old` and `new` with both their values.
+///
+/// Objects present on both sides are compared per key; any other differing
+/// value (a scalar, an array, a field that became an object) is one leaf.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(serde::Serialize)]
+/// struct User { name: String, age: u8, email: Option<String> }
+///
+/// let old = User { name: "alice".into(), age: 30, email: Some("a@example.com".into()) };
+/// let new = User { name: "alice".into(), age: 31, email: None };
+///
+/// let delta = serde_patch::diff_delta(&old, &new).unwrap();
+/// assert_eq!(
+///     serde_json::to_value(&delta).unwrap(),
+///     json!({
+///         "age": { "old": 30, "new": 31 },
+///         "email": { "old": "a@example.com", "new": null }
+///     })
+/// );
+/// assert_eq!(delta.to_patch(), serde_patch::diff(&old, &new).unwrap());
+/// ```
+pub fn diff_delta<T: Serialize>(old: &T, new: &T) -> Result<Delta, serde_json::Error> {
+    let (old_val, new_val) = crate::diff_patch::to_views(old, new, &crate::DiffOptions::default())?;
+    let mut changes = BTreeMap::new();
+    collect_changes(Some(&old_val), Some(&new_val), "", &mut changes);
+    Ok(Delta(changes))
+}
+
+fn collect_changes(
+    old: Option<&Value>,
+    new: Option<&Value>,
+    path: &str,
+    changes: &mut BTreeMap<String, LeafChange>,
+) {
+    match (old, new) {
+        (Some(Value::Object(old_map)), Some(Value::Object(new_map))) => {
+            for (key, old_value) in old_map {
+                let child = crate::paths::join(path, key);
+                collect_changes(Some(old_value), new_map.get(key), &child, changes);
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    let child = crate::paths::join(path, key);
+                    collect_changes(None, Some(new_value), &child, changes);
+                }
+            }
+        }
+        _ if old == new => {}
+        _ => {
+            let change = LeafChange {
+                old: old.cloned(),
+                new: new.cloned(),
+            };
+            changes.insert(path.to_string(), change);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::ApplyOptions;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use serde_json::error::Category;
+use std::fmt;
+
+/// Longest part of a source line shown in a [`ParseError`] snippet, in bytes.
+const SNIPPET_WIDTH: usize = 72;
+
+/// A patch error located in the patch text.
+///
+/// Syntax errors carry the position of the problem. Semantic errors (the
+/// patched value doesn't fit the target type) carry the path of the offending
+/// field and, when that field appears in the patch, its position too. The
+/// `Display` output includes a snippet of the offending line with a caret,
+/// ready to be returned to an API client.
+#[derive(Debug)]
+pub struct ParseError {
+    /// Message without location information.
+    pub message: String,
+    /// Position in the patch text, if known.
+    pub location: Option<Location>,
+    /// Dotted path of the field, for semantic errors.
+    pub path: Option<String>,
+    source: serde_json::Error,
+}
+
+/// Position of a [`ParseError`] in the patch text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    /// 1-based line.
+    pub line: usize,
+    /// 1-based byte column.
+    pub column: usize,
+    /// Byte offset from the start of the patch.
+    pub offset: usize,
+    /// The offending line with a caret under the column.
+    pub snippet: String,
+}
+
+impl ParseError {
+    /// Returns `true` for malformed JSON (a 400), `false` for semantic errors (a 422).
+    pub fn is_syntax(&self) -> bool {
+        matches!(self.source.classify(), Category::Syntax | Category::Eof)
+    }
+
+    fn syntax(text: &[u8], source: serde_json::Error) -> Self {
+        let text = String::from_utf8_lossy(text);
+        let offset = offset_of(&text, source.line(), source.column());
+        let mut error = Self {
+            message: message_of(&source),
+            location: None,
+            path: None,
+            source,
+        };
+        if let Some(offset) = offset {
+            error.locate(&text, offset);
+        }
+        error
+    }
+
+    fn locate(&mut self, text: &str, offset: usize) {
+        let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+        let line = text[line_start..line_end].trim_end_matches('\r');
+
+        let column = offset - line_start;
+        let mut from = column.saturating_sub(SNIPPET_WIDTH / 2);
+        while !line.is_char_boundary(from) {
+            from -= 1;
+        }
+        let mut to = (from + SNIPPET_WIDTH).min(line.len());
+        while !line.is_char_boundary(to) {
+            to -= 1;
+        }
+        let caret = line[from..column.min(line.len())].chars().count();
+
+        self.location = Some(Location {
+            line: text[..offset].matches('\n').count() + 1,
+            column: column + 1,
+            offset,
+            snippet: format!("{}\n{}^", &line[from..to], " ".repeat(caret)),
+        });
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(path) = &self.path {
+            write!(f, " at `{}`", path)?;
+        }
+        if let Some(location) = &self.location {
+            write!(
+                f,
+                " (line {} column {})\n{}",
+                location.line, location.column, location.snippet
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<ParseError> for serde_json::Error {
+    fn from(error: ParseError) -> Self {
+        error.source
+    }
+}
+
+/// Parses patch JSON, reporting syntax errors with their location and a snippet.
+///
+/// # Example
+///
+/// ```
+/// let err = serde_patch::parse_patch("{\n  \"age\": 31,,\n}").unwrap_err();
+///
+/// let location = err.location.as_ref().unwrap();
+///
+/// assert!(err.is_syntax());
+/// assert_eq!((location.line, location.column), (2, 13));
+/// assert_eq!(location.snippet, "  \"age\": 31,,\n            ^");
+/// ```
+pub fn parse_patch<P: AsRef<[u8]>>(patch: P) -> Result<Value, ParseError> {
+    crate::backend::from_slice(patch.as_ref())
+        .map_err(|err| ParseError::syntax(patch.as_ref(), err))
+}
+
+/// Applies a JSON Merge Patch (RFC 7396), reporting errors located in the patch text.
+///
+/// Like [`apply_with`](crate::apply_with), but malformed JSON is reported with
+/// its line, column and a snippet, and a patched value that doesn't fit `T`
+/// is reported with the path of the offending field (and its position, when
+/// the patch sets that field).
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::ApplyOptions;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// let current = User { name: "alice".into(), age: 30 };
+/// let patch = "{\n  \"age\": \"thirty-one\"\n}";
+///
+/// let err = serde_patch::apply_with_diagnostics(current, patch, &ApplyOptions::default())
+///     .err()
+///     .unwrap();
+/// assert!(!err.is_syntax());
+/// assert_eq!(err.path.as_deref(), Some("age"));
+/// assert_eq!(err.location.unwrap().line, 2);
+/// ```
+pub fn apply_with_diagnostics<T, P>(
+    current: T,
+    patch: P,
+    options: &ApplyOptions,
+) -> Result<T, ParseError>
+where
+    T: Serialize + DeserializeOwned,
+    P: AsRef<[u8]>,
+{
+    let patch_val = parse_patch(patch.as_ref())?;
+    let (updated, resolved) = crate::apply_patch::apply_to_value(&current, patch_val, options)
+        .map_err(|err| ParseError::syntax(patch.as_ref(), err.into()))?;
+
+    let updated: T = serde_path_to_error::deserialize(updated).map_err(|err| {
+        let segments: Vec<&str> = err
+            .path()
+            .iter()
+            .map_while(|segment| match segment {
+                serde_path_to_error::Segment::Map { key } => Some(key.as_str()),
+                _ => None,
+            })
+            .collect();
+        let path = err.path().to_string();
+        let text = String::from_utf8_lossy(patch.as_ref());
+        let offset = crate::jsonc::locate(&text, &segments);
+
+        let source = err.into_inner();
+        let mut error = ParseError {
+            message: message_of(&source),
+            location: None,
+            path: (path != ".").then_some(path),
+            source,
+        };
+        if let Some(offset) = offset {
+            error.locate(&text, offset);
+        }
+        error
+    })?;
+    crate::apply_patch::check_unknown_fields(&updated, &resolved, options)
+        .map_err(|err| ParseError::syntax(patch.as_ref(), err))?;
+    Ok(updated)
+}
+
+/// The error message without serde_json's " at line X column Y" suffix.
+fn message_of(err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    match message.rfind(" at line ") {
+        Some(i) if err.line() > 0 => message[..i].to_string(),
+        _ => message,
+    }
+}
+
+/// Byte offset of a 1-based line and column, clamped to the text.
+fn offset_of(text: &str, line: usize, column: usize) -> Option<usize> {
+    if line == 0 {
+        return None;
+    }
+    let line_start = if line == 1 {
+        0
+    } else {
+        text.match_indices('\n').nth(line - 2)?.0 + 1
+    };
+    let line_end = text[line_start..]
+        .find('\n')
+        .map_or(text.len(), |i| line_start + i);
+    let mut offset = (line_start + column.saturating_sub(1)).min(line_end);
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    Some(offset)
+}
@@ -1,5 +1,5 @@
+use crate::DiffOptions;
 use serde_json::{Map, Value};
-use std::collections::HashSet;
 
 /// Recursively computes a JSON diff between two values (internal).
 ///
@@ -10,31 +10,42 @@ use std::collections::HashSet;
 pub fn compute_diff(
     old: Option<&Value>,
     new: &Value,
-    forced: &HashSet<String>,
+    options: &DiffOptions,
     current_path: &str,
 ) -> Option<Value> {
+    if let (Some(old), Some(comparator)) = (old, options.comparators.get(current_path))
+        && comparator.equivalent(old, new)
+    {
+        return crate::paths::matches_any(&options.including, current_path).then(|| new.clone());
+    }
+
     if let (Some(old_obj), Value::Object(new_map)) = (old.and_then(|v| v.as_object()), new) {
         let old_map = old_obj;
         let mut diff_map: Map<String, Value> = Map::new();
 
         for (key, new_value) in new_map {
-            let full_path = if current_path.is_empty() {
-                key.clone()
-            } else {
-                format!("{}.{}", current_path, key)
-            };
+            let full_path = crate::paths::join(current_path, key);
+
+            if crate::paths::matches_any(&options.ignoring, &full_path) {
+                continue;
+            }
 
             let old_value = old_map.get(key);
 
-            if let Some(diff_value) = compute_diff(old_value, new_value, forced, &full_path) {
+            if let Some(diff_value) = compute_diff(old_value, new_value, options, &full_path) {
                 diff_map.insert(key.clone(), diff_value);
-            } else if forced.contains(&full_path) {
+            } else if crate::paths::matches_any(&options.including, &full_path) {
                 diff_map.insert(key.clone(), new_value.clone());
             }
         }
 
         for key in old_map.keys() {
-            if !new_map.contains_key(key) {
+            if !new_map.contains_key(key)
+                && !crate::paths::matches_any(
+                    &options.ignoring,
+                    &crate::paths::join(current_path, key),
+                )
+            {
                 diff_map.insert(key.clone(), Value::Null);
             }
         }
@@ -46,9 +57,24 @@ pub fn compute_diff(
         }
     } else {
         let equal = old == Some(new);
-        if equal && !forced.contains(current_path) {
+        if equal && !crate::paths::matches_any(&options.including, current_path) {
             None
         } else {
+            if let (false, Some(Value::Array(old_items)), Value::Array(new_items)) =
+                (equal, old, new)
+                && let Some(patch) =
+                    crate::array_diff::array_patch(old_items, new_items, options, current_path)
+            {
+                return Some(patch);
+            }
+
+            #[cfg(feature = "binary-delta")]
+            if let (false, Some(old)) = (equal, old)
+                && crate::paths::matches_any(&options.binary_delta, current_path)
+                && let Some(delta) = crate::binary_delta::delta_value(old, new)
+            {
+                return Some(delta);
+            }
             Some(new.clone())
         }
     }
@@ -76,16 +102,19 @@ pub fn compute_diff(
 /// assert_eq!(patch, json!({ "name": "new" }));
 /// ```
 pub fn diff<T: serde::Serialize>(old: &T, new: &T) -> Result<serde_json::Value, serde_json::Error> {
-    let old_val = serde_json::to_value(old)?;
-    let new_val = serde_json::to_value(new)?;
-    let diff_opt = compute_diff(Some(&old_val), &new_val, &HashSet::new(), "");
+    let options = DiffOptions::default();
+    let (old_val, new_val) = to_views(old, new, &options)?;
+    let diff_opt = compute_diff(Some(&old_val), &new_val, &options, "");
     Ok(diff_opt.unwrap_or(serde_json::Value::Object(serde_json::Map::new())))
 }
 
 /// Computes a JSON diff, forcing specific fields to be included even if unchanged.
 ///
 /// This is useful when you need to provide context (like an ID) in the patch,
-/// regardless of whether that field has changed.
+/// regardless of whether that field has changed. Paths may use globs: `*`
+/// matches one segment and `**` any number (e.g. `"items.*.id"`, `"profile.**"`).
+/// Paths starting with `/` are JSON Pointers (RFC 6901), e.g. `"/hosts/example.com"`
+/// for a key containing a dot.
 ///
 /// # Example
 ///
@@ -107,9 +136,232 @@ pub fn diff_including<T: serde::Serialize>(
     new: &T,
     including: &[&str],
 ) -> Result<serde_json::Value, serde_json::Error> {
-    let old_val = serde_json::to_value(old)?;
-    let new_val = serde_json::to_value(new)?;
-    let including_set: HashSet<String> = including.iter().map(|s| s.to_string()).collect();
-    let diff_opt = compute_diff(Some(&old_val), &new_val, &including_set, "");
+    let options = DiffOptions {
+        including: including.iter().map(|s| s.to_string()).collect(),
+        ..Default::default()
+    };
+    let (old_val, new_val) = to_views(old, new, &options)?;
+    let diff_opt = compute_diff(Some(&old_val), &new_val, &options, "");
+    Ok(diff_opt.unwrap_or(serde_json::Value::Object(serde_json::Map::new())))
+}
+
+/// Computes a JSON diff, leaving out specific fields even if changed or removed.
+///
+/// Useful for fields that change on every save (timestamps, counters) and
+/// would otherwise make every patch non-empty.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(serde::Serialize)]
+/// struct User { name: String, updated_at: u64 }
+///
+/// let old = User { name: "old".to_string(), updated_at: 1 };
+/// let new = User { name: "new".to_string(), updated_at: 2 };
+///
+/// let patch = serde_patch::diff_excluding(&old, &new, &["updated_at"]).unwrap();
+/// assert_eq!(patch, json!({ "name": "new" }));
+/// ```
+pub fn diff_excluding<T: serde::Serialize>(
+    old: &T,
+    new: &T,
+    excluding: &[&str],
+) -> Result<serde_json::Value, serde_json::Error> {
+    let options = DiffOptions {
+        ignoring: excluding.iter().map(|s| s.to_string()).collect(),
+        ..Default::default()
+    };
+    let (old_val, new_val) = to_views(old, new, &options)?;
+    let diff_opt = compute_diff(Some(&old_val), &new_val, &options, "");
     Ok(diff_opt.unwrap_or(serde_json::Value::Object(serde_json::Map::new())))
 }
+
+/// Computes the patch that undoes the change from `old` to `new`.
+///
+/// Applying it to `new` gives back `old`: changed fields get their old
+/// values and removed fields are restored. Fields `old` held as `null` (e.g.
+/// `None`) are removed, which deserializes back to the same value for
+/// `Option` fields.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, nickname: Option<String> }
+///
+/// let old = User { name: "alice".into(), nickname: Some("al".into()) };
+/// let new = User { name: "bob".into(), nickname: None };
+///
+/// let undo = serde_patch::diff_inverse(&old, &new).unwrap();
+/// assert_eq!(undo, json!({ "name": "alice", "nickname": "al" }));
+///
+/// let restored = serde_patch::apply(new, &undo.to_string()).unwrap();
+/// assert_eq!(restored, old);
+/// ```
+pub fn diff_inverse<T: serde::Serialize>(old: &T, new: &T) -> Result<Value, serde_json::Error> {
+    diff(new, old)
+}
+
+/// Lists the dotted paths of every field that differs between `old` and `new`.
+///
+/// Paths are sorted and point at the deepest changed value: a changed nested
+/// field is listed as `"profile.bio"`, not `"profile"`. Arrays are compared as
+/// a whole. Keys containing `.` or `\` are escaped with a backslash.
+///
+/// # Example
+///
+/// ```
+/// #[derive(serde::Serialize)]
+/// struct Profile { bio: String, avatar_url: Option<String> }
+///
+/// #[derive(serde::Serialize)]
+/// struct User { name: String, tags: Vec<String>, profile: Profile }
+///
+/// let old = User {
+///     name: "alice".into(),
+///     tags: vec!["a".into()],
+///     profile: Profile { bio: "hi".into(), avatar_url: Some("a.png".into()) },
+/// };
+/// let new = User {
+///     name: "alice".into(),
+///     tags: vec!["a".into(), "b".into()],
+///     profile: Profile { bio: "hello".into(), avatar_url: Some("a.png".into()) },
+/// };
+///
+/// let paths = serde_patch::changed_paths(&old, &new).unwrap();
+/// assert_eq!(paths, ["profile.bio", "tags"]);
+/// ```
+pub fn changed_paths<T: serde::Serialize>(
+    old: &T,
+    new: &T,
+) -> Result<Vec<String>, serde_json::Error> {
+    let patch = diff(old, new)?;
+    let mut paths = Vec::new();
+    crate::paths::collect_leaves(&patch, "", &mut paths);
+    paths.sort();
+    Ok(paths)
+}
+
+/// Computes a JSON diff of everything in `value` that differs from `T::default()`.
+///
+/// Useful for persisting only the settings a user overrode: applying the
+/// result to `T::default()` gives back `value`.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(Default, serde::Serialize)]
+/// struct Settings { theme: String, font_size: u8, telemetry: bool }
+///
+/// let settings = Settings { font_size: 14, ..Default::default() };
+///
+/// let overrides = serde_patch::diff_from_default(&settings).unwrap();
+/// assert_eq!(overrides, json!({ "font_size": 14 }));
+/// ```
+pub fn diff_from_default<T>(value: &T) -> Result<serde_json::Value, serde_json::Error>
+where
+    T: serde::Serialize + Default,
+{
+    diff(&T::default(), value)
+}
+
+/// Computes a JSON diff using the given [`DiffOptions`].
+///
+/// [`diff`] and [`diff_including`] are shorthands for common configurations.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::DiffOptions;
+///
+/// #[derive(serde::Serialize)]
+/// struct User { id: u32, name: String }
+///
+/// let old = User { id: 1, name: "old".to_string() };
+/// let new = User { id: 1, name: "new".to_string() };
+///
+/// let options = DiffOptions {
+///     including: ["id".to_string()].into(),
+///     ..Default::default()
+/// };
+/// let patch = serde_patch::diff_with(&old, &new, &options).unwrap();
+/// assert_eq!(patch, json!({ "id": 1, "name": "new" }));
+/// ```
+pub fn diff_with<T: serde::Serialize>(
+    old: &T,
+    new: &T,
+    options: &DiffOptions,
+) -> Result<serde_json::Value, serde_json::Error> {
+    let (old_val, new_val) = to_views(old, new, options)?;
+    diff_views(&old_val, &new_val, options)
+}
+
+/// Computes a JSON diff between two already-serialized values using the given [`DiffOptions`].
+///
+/// Same result as [`diff_with`] for values that serialize to `old` and `new`,
+/// without needing a `Serialize` type, e.g. for documents received over the wire.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::DiffOptions;
+///
+/// let old = json!({ "name": "old", "tags": ["a"] });
+/// let new = json!({ "name": "new" });
+///
+/// let patch = serde_patch::diff_values(&old, &new, &DiffOptions::default()).unwrap();
+/// assert_eq!(patch, json!({ "name": "new", "tags": null }));
+/// ```
+pub fn diff_values(
+    old: &Value,
+    new: &Value,
+    options: &DiffOptions,
+) -> Result<Value, serde_json::Error> {
+    if options.representations.is_empty() {
+        return diff_views(old, new, options);
+    }
+    let mut old_val = old.clone();
+    let mut new_val = new.clone();
+    crate::representation::to_views(&mut old_val, &options.representations);
+    crate::representation::to_views(&mut new_val, &options.representations);
+    diff_views(&old_val, &new_val, options)
+}
+
+/// Diffs values whose fields are already converted into their views (internal).
+fn diff_views(old: &Value, new: &Value, options: &DiffOptions) -> Result<Value, serde_json::Error> {
+    crate::apply_patch::check_depth(old, options.max_depth, "document")?;
+    crate::apply_patch::check_depth(new, options.max_depth, "document")?;
+    let patch = compute_diff(Some(old), new, options, "")
+        .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
+    #[cfg(feature = "external-refs")]
+    let patch = crate::external_ref::externalized(patch, options)?;
+    Ok(patch)
+}
+
+/// Serializes both sides, handling non-finite floats, and converts registered fields into their views (internal).
+pub(crate) fn to_views<T: serde::Serialize>(
+    old: &T,
+    new: &T,
+    options: &DiffOptions,
+) -> Result<(Value, Value), serde_json::Error> {
+    let (mut old_val, old_found) = crate::non_finite::to_value(old)?;
+    let (mut new_val, new_found) = crate::non_finite::to_value(new)?;
+    crate::non_finite::resolve(
+        &mut old_val,
+        &old_found,
+        &mut new_val,
+        &new_found,
+        options.non_finite,
+    )?;
+    crate::representation::to_views(&mut old_val, &options.representations);
+    crate::representation::to_views(&mut new_val, &options.representations);
+    Ok((old_val, new_val))
+}
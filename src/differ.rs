@@ -0,0 +1,121 @@
+#[cfg(feature = "external-refs")]
+use crate::ExternalStore;
+use crate::{ArrayStrategy, Comparator, DiffOptions, NonFinite, Representation};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Reusable diff configuration, built step by step.
+///
+/// Collects everything [`DiffOptions`] can express behind chained calls and
+/// keeps it for any number of [`diff`](Differ::diff) calls, so a server can
+/// configure it once and share it between threads.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{ArrayStrategy, Differ};
+///
+/// #[derive(serde::Serialize)]
+/// struct Doc { id: u32, title: String, updated_at: u64, tags: Vec<String> }
+///
+/// let differ = Differ::new()
+///     .force("id")
+///     .ignore("updated_at")
+///     .array_strategy(ArrayStrategy::ByIndex);
+///
+/// let old = Doc { id: 7, title: "a".into(), updated_at: 1, tags: vec!["x".into()] };
+/// let new = Doc { id: 7, title: "b".into(), updated_at: 2, tags: vec!["y".into()] };
+///
+/// let patch = differ.diff(&old, &new).unwrap();
+/// assert_eq!(patch, json!({ "id": 7, "title": "b", "tags": { "$items": { "0": "y" } } }));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Differ {
+    options: DiffOptions,
+}
+
+impl Differ {
+    /// Creates a differ producing plain merge patches, like [`diff`](crate::diff).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Includes a field in every patch, even if unchanged.
+    pub fn force(mut self, path: impl Into<String>) -> Self {
+        self.options.including.insert(path.into());
+        self
+    }
+
+    /// Leaves a field out of every patch, even if changed.
+    pub fn ignore(mut self, path: impl Into<String>) -> Self {
+        self.options.ignoring.insert(path.into());
+        self
+    }
+
+    /// Sets how changed arrays are encoded.
+    pub fn array_strategy(mut self, strategy: ArrayStrategy) -> Self {
+        self.options.array_strategy = strategy;
+        self
+    }
+
+    /// Matches the elements of the array at `path` by their `key` field.
+    pub fn array_key(mut self, path: impl Into<String>, key: impl Into<String>) -> Self {
+        self.options.array_keys.insert(path.into(), key.into());
+        self
+    }
+
+    /// Registers an equivalence check for a field.
+    pub fn comparator(
+        mut self,
+        path: impl Into<String>,
+        comparator: impl Comparator + 'static,
+    ) -> Self {
+        self.options
+            .comparators
+            .insert(path.into(), Arc::new(comparator));
+        self
+    }
+
+    /// Registers a view for a field whose serde adapter hides its structure.
+    pub fn representation(
+        mut self,
+        path: impl Into<String>,
+        representation: impl Representation + 'static,
+    ) -> Self {
+        self.options
+            .representations
+            .insert(path.into(), Arc::new(representation));
+        self
+    }
+
+    /// Sets what to do with `NaN` and infinite floats.
+    pub fn non_finite(mut self, policy: NonFinite) -> Self {
+        self.options.non_finite = policy;
+        self
+    }
+
+    /// Moves large values out of patches into external storage.
+    #[cfg(feature = "external-refs")]
+    pub fn external(mut self, store: ExternalStore) -> Self {
+        self.options.external = Some(store);
+        self
+    }
+
+    /// The accumulated options.
+    pub fn options(&self) -> &DiffOptions {
+        &self.options
+    }
+
+    /// Computes the patch turning `old` into `new`.
+    pub fn diff<T: Serialize>(&self, old: &T, new: &T) -> Result<Value, serde_json::Error> {
+        crate::diff_with(old, new, &self.options)
+    }
+}
+
+impl From<DiffOptions> for Differ {
+    fn from(options: DiffOptions) -> Self {
+        Self { options }
+    }
+}
@@ -0,0 +1,91 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Result of [`diff_documents`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentsDiff<Id: Ord> {
+    /// Patch per document that was created or changed.
+    ///
+    /// Created documents map to their full value; unchanged documents are omitted.
+    pub patches: BTreeMap<Id, Value>,
+    /// Ids present only in `new`.
+    pub created: BTreeSet<Id>,
+    /// Ids present only in `old`.
+    pub deleted: BTreeSet<Id>,
+}
+
+impl<Id: Ord> DocumentsDiff<Id> {
+    /// Returns `true` if no document was created, changed, or deleted.
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// Diffs two collections of documents keyed by id.
+///
+/// Documents present on both sides are diffed with [`diff`](crate::diff);
+/// created documents are reported in full and deleted ones by id only.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use std::collections::BTreeMap;
+///
+/// #[derive(serde::Serialize)]
+/// struct Tenant { plan: String, seats: u32 }
+///
+/// let old = BTreeMap::from([
+///     ("acme", Tenant { plan: "pro".into(), seats: 10 }),
+///     ("globex", Tenant { plan: "free".into(), seats: 1 }),
+/// ]);
+/// let new = BTreeMap::from([
+///     ("acme", Tenant { plan: "pro".into(), seats: 12 }),
+///     ("initech", Tenant { plan: "free".into(), seats: 1 }),
+/// ]);
+///
+/// let diff = serde_patch::diff_documents(&old, &new).unwrap();
+/// assert_eq!(diff.patches["acme"], json!({ "seats": 12 }));
+/// assert_eq!(diff.patches["initech"], json!({ "plan": "free", "seats": 1 }));
+/// assert!(diff.created.contains("initech"));
+/// assert!(diff.deleted.contains("globex"));
+/// ```
+pub fn diff_documents<Id, T>(
+    old: &BTreeMap<Id, T>,
+    new: &BTreeMap<Id, T>,
+) -> Result<DocumentsDiff<Id>, serde_json::Error>
+where
+    Id: Ord + Clone,
+    T: Serialize,
+{
+    let mut patches = BTreeMap::new();
+    let mut created = BTreeSet::new();
+
+    for (id, new_doc) in new {
+        match old.get(id) {
+            Some(old_doc) => {
+                let patch = crate::diff(old_doc, new_doc)?;
+                if patch.as_object().is_none_or(|map| !map.is_empty()) {
+                    patches.insert(id.clone(), patch);
+                }
+            }
+            None => {
+                patches.insert(id.clone(), crate::value::to_value(new_doc)?);
+                created.insert(id.clone());
+            }
+        }
+    }
+
+    let deleted = old
+        .keys()
+        .filter(|id| !new.contains_key(*id))
+        .cloned()
+        .collect();
+
+    Ok(DocumentsDiff {
+        patches,
+        created,
+        deleted,
+    })
+}
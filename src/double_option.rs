@@ -0,0 +1,49 @@
+//! Serde helpers for `Option<Option<T>>` patch fields.
+//!
+//! In a merge patch, a missing field means "leave unchanged" and `null`
+//! means "clear". Serde maps both to `None` for a plain `Option<T>`; with
+//! `#[serde(default, with = "serde_patch::double_option")]` an
+//! `Option<Option<T>>` field keeps them apart: absent is `None`, `null` is
+//! `Some(None)` and a value is `Some(Some(value))`.
+//!
+//! `#[derive(Patch)]` uses it for every `Option` field.
+//!
+//! # Example
+//!
+//! ```
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct ProfileChanges {
+//!     #[serde(default, skip_serializing_if = "Option::is_none", with = "serde_patch::double_option")]
+//!     nickname: Option<Option<String>>,
+//! }
+//!
+//! let absent: ProfileChanges = serde_json::from_str("{}").unwrap();
+//! let cleared: ProfileChanges = serde_json::from_str(r#"{ "nickname": null }"#).unwrap();
+//! assert_eq!(absent.nickname, None);
+//! assert_eq!(cleared.nickname, Some(None));
+//! assert_eq!(serde_json::to_string(&cleared).unwrap(), r#"{"nickname":null}"#);
+//! ```
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializes `Some(None)` as `null`; `None` should be skipped with
+/// `skip_serializing_if = "Option::is_none"`.
+pub fn serialize<T, S>(value: &Option<Option<T>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    match value {
+        Some(inner) => inner.serialize(serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Deserializes a present field, `null` included, as `Some`.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
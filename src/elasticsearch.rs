@@ -0,0 +1,136 @@
+//! Elasticsearch `_update` request bodies generated from merge patches.
+//!
+//! A plain merge patch is sent as a partial document, which Elasticsearch
+//! merges into `_source` the same way. Element-level array patches (see
+//! [`ArrayStrategy`](crate::ArrayStrategy)) have no partial document form, so
+//! patches containing them are sent as a Painless script that applies the
+//! patch, passed as a parameter, to `_source`.
+
+use crate::DiffOptions;
+use serde::Serialize;
+use serde::de::Error as _;
+use serde_json::{Value, json};
+
+/// Painless source applying `params.patch` to `ctx._source`, array markers
+/// included (internal).
+const SCRIPT: &str = r#"
+def patchValue(def current, def patch) {
+  if (!(patch instanceof Map)) { return patch; }
+  if (current instanceof List && patch.containsKey('$items')) {
+    return patch.containsKey('$key') ? keyed(current, patch) : byIndex(current, patch);
+  }
+  Map target = current instanceof Map ? current : new HashMap();
+  for (def entry : patch.entrySet()) {
+    if (entry.getValue() == null) { target.remove(entry.getKey()); }
+    else { target.put(entry.getKey(), patchValue(target.get(entry.getKey()), entry.getValue())); }
+  }
+  return target;
+}
+List byIndex(List items, Map marker) {
+  List out = new ArrayList(items);
+  if (marker.containsKey('$len')) {
+    int len = ((Number) marker.get('$len')).intValue();
+    while (out.size() > len) { out.remove(out.size() - 1); }
+    while (out.size() < len) { out.add(null); }
+  }
+  for (def entry : marker.get('$items').entrySet()) {
+    int index = Integer.parseInt(entry.getKey());
+    out.set(index, patchValue(out.get(index), entry.getValue()));
+  }
+  return out;
+}
+List keyed(List items, Map marker) {
+  String key = marker.get('$key');
+  Map byKey = new HashMap();
+  List order = new ArrayList();
+  for (def item : items) {
+    String id = String.valueOf(item.get(key));
+    byKey.put(id, item);
+    order.add(id);
+  }
+  List added = new ArrayList();
+  for (def entry : marker.get('$items').entrySet()) {
+    String id = entry.getKey();
+    if (entry.getValue() == null) { byKey.remove(id); order.removeIf(other -> other == id); }
+    else if (byKey.containsKey(id)) { byKey.put(id, patchValue(byKey.get(id), entry.getValue())); }
+    else { byKey.put(id, patchValue(null, entry.getValue())); added.add(id); }
+  }
+  if (marker.containsKey('$order')) { order = marker.get('$order'); }
+  else { Collections.sort(added); order.addAll(added); }
+  List out = new ArrayList();
+  for (def id : order) { out.add(byKey.get(id)); }
+  return out;
+}
+patchValue(ctx._source, params.patch);
+"#;
+
+/// Builds the body of an `_update` request applying a merge patch.
+///
+/// Plain patches become `{ "doc": patch, "detect_noop": true }`, so updates
+/// that change nothing aren't written. As with any partial document, a
+/// `null` is stored as `null` rather than removing the field; Elasticsearch
+/// indexes both alike. Patches with element-level array changes become
+/// `{ "script": ... }`, with the patch in `params.patch`; scripted updates
+/// remove fields set to `null` and are always written. Fails if the patch
+/// isn't an object.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let body = serde_patch::elasticsearch::update_body(&json!({ "price": 9 })).unwrap();
+/// assert_eq!(body, json!({ "doc": { "price": 9 }, "detect_noop": true }));
+///
+/// let patch = json!({ "lines": { "$key": "sku", "$items": { "a": { "qty": 2 } } } });
+/// let body = serde_patch::elasticsearch::update_body(&patch).unwrap();
+/// assert_eq!(body["script"]["lang"], "painless");
+/// assert_eq!(body["script"]["params"]["patch"], patch);
+/// ```
+pub fn update_body(patch: &Value) -> Result<Value, serde_json::Error> {
+    if !patch.is_object() {
+        return Err(serde_json::Error::custom(
+            "an _update body can only be built from an object patch",
+        ));
+    }
+    if crate::array_diff::has_markers(patch) {
+        Ok(json!({
+            "script": {
+                "source": SCRIPT.trim(),
+                "lang": "painless",
+                "params": { "patch": patch },
+            }
+        }))
+    } else {
+        Ok(json!({ "doc": patch, "detect_noop": true }))
+    }
+}
+
+/// Diffs two documents into the `_update` body turning `old` into `new`.
+///
+/// `options` are passed to [`diff_with`](crate::diff_with); with a keyed
+/// [`ArrayStrategy`](crate::ArrayStrategy), changes inside arrays of objects
+/// are sent as a script instead of rewriting the whole array.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::DiffOptions;
+///
+/// #[derive(serde::Serialize)]
+/// struct Product { name: String, price: u32 }
+///
+/// let old = Product { name: "mug".into(), price: 8 };
+/// let new = Product { name: "mug".into(), price: 9 };
+///
+/// let body = serde_patch::elasticsearch::diff(&old, &new, &DiffOptions::default()).unwrap();
+/// assert_eq!(body, json!({ "doc": { "price": 9 }, "detect_noop": true }));
+/// ```
+pub fn diff<T: Serialize>(
+    old: &T,
+    new: &T,
+    options: &DiffOptions,
+) -> Result<Value, serde_json::Error> {
+    update_body(&crate::diff_with(old, new, options)?)
+}
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::time::SystemTime;
+
+/// A merge patch with the metadata usually sent along with it.
+///
+/// Serialized as `{ "patch": { ... }, "schema_version": ..., "author": ...,
+/// "timestamp": ..., "revision": ..., "comment": ..., "metadata": { ... } }`,
+/// leaving out unset metadata. It is the one layout the crate uses for
+/// patches with metadata: [`diff_versioned`](crate::diff_versioned) produces
+/// it, [`apply_versioned`](crate::apply_versioned) consumes it and a
+/// [`PatchLog`](crate::PatchLog) stores its entries as envelopes. The timestamp
+/// is written as integer milliseconds since the Unix epoch (negative before
+/// it), so sub-millisecond precision is dropped. Only the inner patch is ever
+/// applied.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::PatchEnvelope;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { id: u32, name: String }
+///
+/// let envelope = PatchEnvelope {
+///     author: Some("alice".into()),
+///     revision: Some(7),
+///     ..PatchEnvelope::new(json!({ "name": "new" }))
+/// };
+/// assert_eq!(
+///     serde_json::to_value(&envelope).unwrap(),
+///     json!({ "patch": { "name": "new" }, "author": "alice", "revision": 7 })
+/// );
+///
+/// let user = User { id: 1, name: "old".into() };
+/// let user = envelope.apply_to(user).unwrap();
+/// assert_eq!(user.name, "new");
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PatchEnvelope {
+    /// The merge patch itself.
+    pub patch: Value,
+    /// Schema version of the type the patch was made against (see
+    /// [`Migrate`](crate::Migrate)).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    /// Who made the patch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// When the patch was made, as Unix milliseconds on the wire.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "unix_millis")]
+    pub timestamp: Option<SystemTime>,
+    /// Revision the patch produces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u64>,
+    /// Free-form description of the change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Caller-defined metadata (request id, client, ...).
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub metadata: Map<String, Value>,
+    /// Hex-encoded HMAC-SHA256 of the other fields, set by `sign_patch`
+    /// (feature `signing`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl PatchEnvelope {
+    /// Wraps a patch with no metadata.
+    pub fn new(patch: Value) -> Self {
+        Self {
+            patch,
+            schema_version: None,
+            author: None,
+            timestamp: None,
+            revision: None,
+            comment: None,
+            metadata: Map::new(),
+            signature: None,
+        }
+    }
+
+    /// Applies the inner patch, as [`apply_value`](crate::apply_value) does.
+    pub fn apply_to<T>(&self, current: T) -> Result<T, crate::Error>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        crate::apply_value(current, &self.patch)
+    }
+}
+
+impl From<Value> for PatchEnvelope {
+    fn from(patch: Value) -> Self {
+        Self::new(patch)
+    }
+}
+
+/// Applies the patch inside a serialized [`PatchEnvelope`], ignoring its
+/// metadata.
+///
+/// An envelope that can't be parsed is reported as
+/// [`Error::ParsePatch`](crate::Error::ParsePatch).
+///
+/// # Example
+///
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { id: u32, name: String }
+///
+/// let user = User { id: 1, name: "old".into() };
+/// let envelope = r#"{ "patch": { "name": "new" }, "author": "alice", "comment": "typo" }"#;
+///
+/// let user = serde_patch::apply_envelope(user, envelope).unwrap();
+/// assert_eq!(user.name, "new");
+/// ```
+pub fn apply_envelope<T, P>(current: T, envelope: P) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+    P: AsRef<[u8]>,
+{
+    let envelope: PatchEnvelope =
+        crate::backend::from_slice(envelope.as_ref()).map_err(crate::Error::ParsePatch)?;
+    envelope.apply_to(current)
+}
+
+/// Serde helpers writing an optional `SystemTime` as Unix milliseconds (internal).
+mod unix_millis {
+    use serde::{Deserialize, Deserializer, Serializer, de, ser};
+    use std::time::{Duration, SystemTime};
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let Some(time) = time else {
+            return serializer.serialize_none();
+        };
+        let millis = match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(after) => i64::try_from(after.as_millis()).ok(),
+            Err(before) => i64::try_from(before.duration().as_millis())
+                .ok()
+                .map(|millis| -millis),
+        };
+        match millis {
+            Some(millis) => serializer.serialize_i64(millis),
+            None => Err(ser::Error::custom("timestamp out of range")),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SystemTime>, D::Error> {
+        let Some(millis) = Option::<i64>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        let offset = Duration::from_millis(millis.unsigned_abs());
+        let time = if millis < 0 {
+            SystemTime::UNIX_EPOCH.checked_sub(offset)
+        } else {
+            SystemTime::UNIX_EPOCH.checked_add(offset)
+        };
+        time.map(Some)
+            .ok_or_else(|| de::Error::custom("timestamp out of range"))
+    }
+}
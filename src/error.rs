@@ -0,0 +1,247 @@
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::fmt;
+
+/// Error returned by [`apply`](crate::apply), [`apply_mut`](crate::apply_mut)
+/// and the other typed apply functions.
+///
+/// Field-level variants carry the JSON Pointer (RFC 6901) of the field that
+/// failed, e.g. `/profile/bio`.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::Error;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Profile { bio: String }
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, profile: Profile }
+///
+/// let user = || User { name: "alice".into(), profile: Profile { bio: "hi".into() } };
+///
+/// let err = serde_patch::apply(user(), r#"{ "profile": { "bio": 7 } }"#).err().unwrap();
+/// assert!(matches!(err, Error::Deserialize { .. }));
+/// assert_eq!(err.pointer(), Some("/profile/bio"));
+///
+/// let err = serde_patch::apply(user(), r#"{ "profile": { "bio": null } }"#).err().unwrap();
+/// assert!(matches!(err, Error::InvalidRemoval { .. }));
+/// assert_eq!(err.to_string(), "cannot remove required field `/profile/bio`");
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The patch could not be parsed.
+    ParsePatch(serde_json::Error),
+    /// The current value, or a typed patch, could not be serialized.
+    Serialize {
+        /// Pointer of the failing field; empty for the whole value.
+        pointer: String,
+        /// Underlying error.
+        source: serde_json::Error,
+    },
+    /// The patched value does not fit the target type.
+    Deserialize {
+        /// Pointer of the failing field; empty for the whole value.
+        pointer: String,
+        /// Underlying error.
+        source: serde_json::Error,
+    },
+    /// The patch sets a required field to `null`.
+    InvalidRemoval {
+        /// Pointer of the removed field.
+        pointer: String,
+    },
+    /// The document no longer matches the ETag the patch was made against,
+    /// see [`http::apply_if_match`](crate::http::apply_if_match).
+    PreconditionFailed {
+        /// The `If-Match` value the caller sent.
+        expected: String,
+        /// The ETag of the current document.
+        actual: String,
+    },
+    /// The patch exceeds one of the [`ApplyLimits`](crate::ApplyLimits).
+    LimitExceeded {
+        /// The limit exceeded.
+        limit: crate::Limit,
+        /// Its configured value.
+        max: usize,
+    },
+    /// A signed [`PatchEnvelope`](crate::PatchEnvelope) has no signature, or
+    /// one that doesn't match its contents and key.
+    InvalidSignature,
+    /// The patch parsed but can't be applied as asked, e.g. it sets an
+    /// unknown or protected field, fails a validator, or holds an unresolved
+    /// external reference.
+    Rejected(serde_json::Error),
+}
+
+impl Error {
+    /// JSON Pointer of the field the error is about, if any.
+    pub fn pointer(&self) -> Option<&str> {
+        match self {
+            Error::ParsePatch(_)
+            | Error::PreconditionFailed { .. }
+            | Error::LimitExceeded { .. }
+            | Error::InvalidSignature
+            | Error::Rejected(_) => None,
+            Error::Serialize { pointer, .. }
+            | Error::Deserialize { pointer, .. }
+            | Error::InvalidRemoval { pointer } => Some(pointer),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParsePatch(source) => write!(f, "invalid patch: {}", source),
+            Error::Serialize { pointer, source } | Error::Deserialize { pointer, source }
+                if pointer.is_empty() =>
+            {
+                fmt::Display::fmt(source, f)
+            }
+            Error::Serialize { pointer, source } | Error::Deserialize { pointer, source } => {
+                write!(f, "{} at `{}`", source, pointer)
+            }
+            Error::InvalidRemoval { pointer } => {
+                write!(f, "cannot remove required field `{}`", pointer)
+            }
+            Error::PreconditionFailed { expected, actual } => write!(
+                f,
+                "document has changed: ETag is {}, request expected {}",
+                actual, expected
+            ),
+            Error::LimitExceeded { limit, max } => {
+                write!(f, "patch exceeds the limit of {} {}", max, limit)
+            }
+            Error::InvalidSignature => f.write_str("patch signature is missing or invalid"),
+            Error::Rejected(source) => fmt::Display::fmt(source, f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ParsePatch(source)
+            | Error::Serialize { source, .. }
+            | Error::Deserialize { source, .. }
+            | Error::Rejected(source) => Some(source),
+            Error::InvalidRemoval { .. }
+            | Error::PreconditionFailed { .. }
+            | Error::LimitExceeded { .. }
+            | Error::InvalidSignature => None,
+        }
+    }
+}
+
+impl From<Error> for serde_json::Error {
+    fn from(error: Error) -> Self {
+        use serde::de::Error as _;
+        match error {
+            Error::ParsePatch(source) | Error::Rejected(source) => source,
+            error => serde_json::Error::custom(error),
+        }
+    }
+}
+
+/// Serializes the current value, reporting the pointer of a failing field (internal).
+///
+/// Paths are only tracked to report a failure, on a second pass.
+pub(crate) fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<Value, Error> {
+    if let Ok(value) = serde_json::to_value(value) {
+        return Ok(value);
+    }
+    serde_path_to_error::serialize(value, serde_json::value::Serializer).map_err(|err| {
+        Error::Serialize {
+            pointer: pointer_of(err.path()),
+            source: err.into_inner(),
+        }
+    })
+}
+
+/// Deserializes `current` with `patch` merged in, telling removed required
+/// fields apart (internal).
+///
+/// The patch is merged on the fly as `current` is read, so no merged copy of
+/// it is built; `current` itself is an already serialized document. As in
+/// [`serialize`], paths are only tracked on failure.
+pub(crate) fn deserialize_patched<T: DeserializeOwned>(
+    current: &Value,
+    patch: &Value,
+) -> Result<T, Error> {
+    if let Ok(value) = T::deserialize(crate::overlay::Patched::new(current, patch)) {
+        return Ok(value);
+    }
+    serde_path_to_error::deserialize(crate::overlay::Patched::new(current, patch))
+        .map_err(|err| deserialize_error(err, patch))
+}
+
+/// Deserializes a document `patch` was already merged into, as
+/// [`deserialize_patched`] does for an unmerged one (internal).
+pub(crate) fn deserialize_merged<T: DeserializeOwned>(
+    merged: &Value,
+    patch: &Value,
+) -> Result<T, Error> {
+    if let Ok(value) = T::deserialize(merged) {
+        return Ok(value);
+    }
+    serde_path_to_error::deserialize(merged).map_err(|err| deserialize_error(err, patch))
+}
+
+fn deserialize_error(err: serde_path_to_error::Error<serde_json::Error>, patch: &Value) -> Error {
+    let (pointer, _) = field_of(&err);
+    if patch.pointer(&pointer).is_some_and(Value::is_null) {
+        Error::InvalidRemoval { pointer }
+    } else {
+        Error::Deserialize {
+            pointer,
+            source: err.into_inner(),
+        }
+    }
+}
+
+/// JSON Pointer and dotted path of the field a deserialization error is about (internal).
+///
+/// "missing field `x`" errors are reported by serde at the containing object;
+/// both paths then point at the missing field itself.
+pub(crate) fn field_of(err: &serde_path_to_error::Error<serde_json::Error>) -> (String, String) {
+    let mut pointer = pointer_of(err.path());
+    let mut path = err.path().to_string();
+
+    let message = err.inner().to_string();
+    if let Some(field) = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.split('`').next())
+    {
+        push_token(&mut pointer, field);
+        path = if path == "." {
+            field.to_string()
+        } else {
+            format!("{}.{}", path, field)
+        };
+    }
+    (pointer, path)
+}
+
+/// Converts a serde path into a JSON Pointer, stopping at enum variants (internal).
+fn pointer_of(path: &serde_path_to_error::Path) -> String {
+    let mut pointer = String::new();
+    for segment in path.iter() {
+        match segment {
+            serde_path_to_error::Segment::Map { key } => push_token(&mut pointer, key),
+            serde_path_to_error::Segment::Seq { index } => {
+                push_token(&mut pointer, &index.to_string())
+            }
+            _ => break,
+        }
+    }
+    pointer
+}
+
+fn push_token(pointer: &mut String, token: &str) {
+    pointer.push('/');
+    pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+}
@@ -0,0 +1,172 @@
+use crate::DiffOptions;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Why a field appears in (or is missing from) an explained diff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffReason {
+    /// The value differs from the old one.
+    Changed,
+    /// The field did not exist in the old value.
+    Added,
+    /// The field no longer exists and is emitted as `null`.
+    Removed,
+    /// The value is unchanged but the path is listed in `including`.
+    Forced,
+    /// A registered comparator judged the values different.
+    ComparatorChanged,
+    /// A registered comparator judged differing values equivalent, so the
+    /// field is left out of the patch.
+    ComparatorEquivalent,
+}
+
+/// One explained path of an [`ExplainedDiff`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DiffEntry {
+    /// Dotted path of the field.
+    pub path: String,
+    /// Why the field was emitted or left out.
+    pub reason: DiffReason,
+}
+
+/// A patch together with the reason behind each of its entries.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ExplainedDiff {
+    /// The same patch [`diff_with`](crate::diff_with) returns.
+    pub patch: Value,
+    /// One entry per emitted value, plus fields a comparator left out.
+    pub entries: Vec<DiffEntry>,
+}
+
+/// Computes a JSON diff and explains why every entry was included.
+///
+/// Entries are reported at the level a value is emitted: a nested partial
+/// diff yields entries for its changed leaves, a wholesale replacement yields
+/// a single entry. Fields that differ as JSON but were judged equivalent by a
+/// comparator are listed as [`DiffReason::ComparatorEquivalent`] even though
+/// they are not in the patch.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{DiffEntry, DiffOptions, DiffReason};
+///
+/// #[derive(serde::Serialize)]
+/// struct User { id: u32, name: String, email: Option<String> }
+///
+/// let old = User { id: 1, name: "old".into(), email: Some("a@b.c".into()) };
+/// let new = User { id: 1, name: "new".into(), email: None };
+///
+/// let options = DiffOptions {
+///     including: ["id".to_string()].into(),
+///     ..Default::default()
+/// };
+/// let explained = serde_patch::diff_explained(&old, &new, &options).unwrap();
+/// assert_eq!(explained.patch, json!({ "id": 1, "name": "new", "email": null }));
+/// assert_eq!(
+///     explained.entries,
+///     [
+///         DiffEntry { path: "email".into(), reason: DiffReason::Changed },
+///         DiffEntry { path: "id".into(), reason: DiffReason::Forced },
+///         DiffEntry { path: "name".into(), reason: DiffReason::Changed },
+///     ]
+/// );
+/// ```
+pub fn diff_explained<T: Serialize>(
+    old: &T,
+    new: &T,
+    options: &DiffOptions,
+) -> Result<ExplainedDiff, serde_json::Error> {
+    let (old_val, new_val) = crate::diff_patch::to_views(old, new, options)?;
+
+    let patch = crate::diff_patch::compute_diff(Some(&old_val), &new_val, options, "")
+        .unwrap_or(Value::Object(serde_json::Map::new()));
+
+    let mut entries = Vec::new();
+    explain(
+        Some(&old_val),
+        Some(&new_val),
+        &patch,
+        options,
+        "",
+        &mut entries,
+    );
+    explain_suppressed(&old_val, &new_val, options, &mut entries);
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    #[cfg(feature = "external-refs")]
+    let patch = crate::external_ref::externalized(patch, options)?;
+    Ok(ExplainedDiff { patch, entries })
+}
+
+/// Walks the patch the way `compute_diff` built it, classifying emitted values.
+fn explain(
+    old: Option<&Value>,
+    new: Option<&Value>,
+    patch: &Value,
+    options: &DiffOptions,
+    current_path: &str,
+    out: &mut Vec<DiffEntry>,
+) {
+    let forced = crate::paths::matches_any(&options.including, current_path);
+    let comparator = options.comparators.get(current_path);
+
+    if let (Some(Value::Object(old_map)), Some(Value::Object(new_map)), Value::Object(patch_map)) =
+        (old, new, patch)
+        && !(forced && old == new)
+    {
+        for (key, patch_value) in patch_map {
+            let full_path = crate::paths::join(current_path, key);
+            explain(
+                old_map.get(key),
+                new_map.get(key),
+                patch_value,
+                options,
+                &full_path,
+                out,
+            );
+        }
+        return;
+    }
+
+    let reason = match (old, new) {
+        (_, None) => DiffReason::Removed,
+        (None, Some(_)) => DiffReason::Added,
+        (Some(old), Some(new)) if old == new => DiffReason::Forced,
+        (Some(old), Some(new)) => match comparator {
+            Some(comparator) if comparator.equivalent(old, new) => DiffReason::Forced,
+            Some(_) => DiffReason::ComparatorChanged,
+            None => DiffReason::Changed,
+        },
+    };
+    out.push(DiffEntry {
+        path: current_path.to_string(),
+        reason,
+    });
+}
+
+/// Lists comparator paths whose differing values were judged equivalent.
+fn explain_suppressed(old: &Value, new: &Value, options: &DiffOptions, out: &mut Vec<DiffEntry>) {
+    for (path, comparator) in &options.comparators {
+        if out.iter().any(|entry| entry.path == *path) {
+            continue;
+        }
+        let lookup = |root: &'_ Value| -> Option<Value> {
+            crate::paths::split(path)
+                .iter()
+                .try_fold(root, |value, key| value.get(key))
+                .cloned()
+        };
+        if let (Some(old), Some(new)) = (lookup(old), lookup(new))
+            && old != new
+            && comparator.equivalent(&old, &new)
+        {
+            out.push(DiffEntry {
+                path: path.clone(),
+                reason: DiffReason::ComparatorEquivalent,
+            });
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::Arc;
+
+const REF_KEY: &str = "$ref";
+const SIZE_KEY: &str = "$size";
+
+/// Error type returned by [`ExternalStore`] callbacks.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+type StoreFn = dyn Fn(&str, &Value) -> Result<(), BoxError> + Send + Sync;
+type FetchFn = dyn Fn(&str) -> Option<Value> + Send + Sync;
+
+/// Moves values larger than `threshold` bytes out of generated patches.
+///
+/// Each such value is handed to the store callback together with its content
+/// hash and replaced in the patch by `{ "$ref": <hash>, "$size": <bytes> }`.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{DiffOptions, ExternalStore};
+/// use std::sync::{Arc, Mutex};
+///
+/// #[derive(serde::Serialize)]
+/// struct Doc { title: String, body: String }
+///
+/// let old = Doc { title: "a".into(), body: "short".into() };
+/// let new = Doc { title: "a".into(), body: "x".repeat(1000) };
+///
+/// let blobs = Arc::new(Mutex::new(Vec::new()));
+/// let sink = blobs.clone();
+/// let options = DiffOptions {
+///     external: Some(ExternalStore::new(256, move |hash, value| {
+///         sink.lock().unwrap().push((hash.to_string(), value.clone()));
+///         Ok(())
+///     })),
+///     ..Default::default()
+/// };
+///
+/// let patch = serde_patch::diff_with(&old, &new, &options).unwrap();
+/// assert_eq!(patch["body"]["$size"], json!(1002));
+/// assert_eq!(blobs.lock().unwrap().len(), 1);
+/// ```
+#[derive(Clone)]
+pub struct ExternalStore {
+    /// Serialized size in bytes above which a value is externalized.
+    pub threshold: usize,
+    store: Arc<StoreFn>,
+}
+
+impl ExternalStore {
+    /// Creates a store externalizing values larger than `threshold` bytes.
+    pub fn new<F>(threshold: usize, store: F) -> Self
+    where
+        F: Fn(&str, &Value) -> Result<(), BoxError> + Send + Sync + 'static,
+    {
+        Self {
+            threshold,
+            store: Arc::new(store),
+        }
+    }
+}
+
+impl fmt::Debug for ExternalStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalStore")
+            .field("threshold", &self.threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Resolves `{ "$ref": ..., "$size": ... }` entries when applying a patch.
+#[derive(Clone)]
+pub struct ExternalFetch {
+    fetch: Arc<FetchFn>,
+}
+
+impl ExternalFetch {
+    /// Creates a resolver that looks up content by hash.
+    pub fn new<F>(fetch: F) -> Self
+    where
+        F: Fn(&str) -> Option<Value> + Send + Sync + 'static,
+    {
+        Self {
+            fetch: Arc::new(fetch),
+        }
+    }
+}
+
+impl fmt::Debug for ExternalFetch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalFetch").finish_non_exhaustive()
+    }
+}
+
+/// Returns the content hash of a JSON value (internal).
+///
+/// The hex SHA-256 of the value's canonical form (see
+/// [`fingerprint`](crate::fingerprint)), so equal values hash identically and
+/// fetched content can be checked against its reference.
+pub fn content_hash(value: &Value) -> String {
+    let mut hasher = Sha256::new();
+    crate::fingerprint::write_canonical(value, &mut |bytes| hasher.update(bytes));
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Serialized sizes of a value and, for objects, of each member (internal).
+struct Sizes {
+    len: usize,
+    members: Vec<Sizes>,
+}
+
+/// Measures the compact JSON length of every object member bottom-up, in one
+/// pass (internal).
+fn measure(value: &Value) -> Sizes {
+    match value {
+        Value::Object(map) => {
+            let members: Vec<Sizes> = map.values().map(measure).collect();
+            let len = map
+                .keys()
+                .zip(&members)
+                .map(|(key, member)| string_len(key) + 1 + member.len)
+                .sum::<usize>()
+                + map.len().saturating_sub(1)
+                + 2;
+            Sizes { len, members }
+        }
+        value => Sizes {
+            len: leaf_len(value),
+            members: Vec::new(),
+        },
+    }
+}
+
+fn leaf_len(value: &Value) -> usize {
+    match value {
+        Value::Null => 4,
+        Value::Bool(true) => 4,
+        Value::Bool(false) => 5,
+        Value::Number(number) => number.to_string().len(),
+        Value::String(string) => string_len(string),
+        Value::Array(items) => {
+            items.iter().map(|item| measure(item).len).sum::<usize>()
+                + items.len().saturating_sub(1)
+                + 2
+        }
+        Value::Object(_) => measure(value).len,
+    }
+}
+
+/// Length of a string once quoted and escaped (internal).
+fn string_len(string: &str) -> usize {
+    serde_json::to_string(string).map_or(string.len() + 2, |quoted| quoted.len())
+}
+
+/// Externalizes `patch` if `options` has an external store (internal).
+pub(crate) fn externalized(
+    mut patch: Value,
+    options: &crate::DiffOptions,
+) -> Result<Value, serde_json::Error> {
+    if let Some(external) = &options.external {
+        externalize(&mut patch, external)?;
+    }
+    Ok(patch)
+}
+
+/// Replaces oversized subtrees of a patch with external references (internal).
+///
+/// Works top-down so the largest subtrees are moved out first. The root
+/// itself is never replaced. Sizes are measured once, bottom-up.
+fn externalize(patch: &mut Value, external: &ExternalStore) -> Result<(), serde_json::Error> {
+    let sizes = measure(patch);
+    externalize_measured(patch, &sizes, external)
+}
+
+fn externalize_measured(
+    patch: &mut Value,
+    sizes: &Sizes,
+    external: &ExternalStore,
+) -> Result<(), serde_json::Error> {
+    if let Value::Object(map) = patch {
+        for (value, size) in map.values_mut().zip(&sizes.members) {
+            if size.len > external.threshold {
+                let hash = content_hash(value);
+                (external.store)(&hash, value).map_err(serde_json::Error::custom)?;
+
+                let mut reference = Map::new();
+                reference.insert(REF_KEY.to_string(), Value::String(hash));
+                reference.insert(SIZE_KEY.to_string(), Value::from(size.len));
+                *value = Value::Object(reference);
+            } else {
+                externalize_measured(value, size, external)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replaces external references in a patch with the fetched content (internal).
+///
+/// Fetched content must hash to its reference; anything else is rejected
+/// rather than merged.
+pub fn resolve_refs(patch: &mut Value, external: &ExternalFetch) -> Result<(), serde_json::Error> {
+    if let Some((hash, size)) = as_ref(patch) {
+        let fetched = (external.fetch)(hash).ok_or_else(|| {
+            serde_json::Error::custom(format!("unresolved external reference `{}`", hash))
+        })?;
+        if content_hash(&fetched) != hash {
+            return Err(serde_json::Error::custom(format!(
+                "content mismatch for external reference `{}`",
+                hash
+            )));
+        }
+        if measure(&fetched).len as u64 != size {
+            return Err(serde_json::Error::custom(format!(
+                "size mismatch for external reference `{}`",
+                hash
+            )));
+        }
+        *patch = fetched;
+        return Ok(());
+    }
+
+    if let Value::Object(map) = patch {
+        for value in map.values_mut() {
+            resolve_refs(value, external)?;
+        }
+    }
+    Ok(())
+}
+
+fn as_ref(value: &Value) -> Option<(&str, u64)> {
+    match value.as_object() {
+        Some(map) if map.len() == 2 => {
+            Some((map.get(REF_KEY)?.as_str()?, map.get(SIZE_KEY)?.as_u64()?))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,182 @@
+//! C-compatible functions for computing and applying patches from other
+//! languages.
+//!
+//! Documents and patches are passed as NUL-terminated UTF-8 JSON strings.
+//! Every call returns one of the `SERDE_PATCH_*` codes and stores a string
+//! in `*out`: the compact JSON result on success, the error message
+//! otherwise. Strings stored in `*out` must be released with
+//! [`serde_patch_free`]. Patches are byte-identical to those the Rust API
+//! serializes with `serde_json::to_string`.
+//!
+//! Build a shared library with
+//! `cargo rustc --release --features ffi --crate-type cdylib` and declare:
+//!
+//! ```c
+//! int serde_patch_diff(const char *old_json, const char *new_json, char **out);
+//! int serde_patch_apply(const char *document_json, const char *patch_json, char **out);
+//! void serde_patch_free(char *string);
+//! ```
+
+use serde_json::Value;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+/// The call succeeded; `*out` holds the result.
+pub const SERDE_PATCH_OK: c_int = 0;
+/// An argument was a null pointer or not valid UTF-8.
+pub const SERDE_PATCH_INVALID_ARGUMENT: c_int = 1;
+/// A document was not valid JSON.
+pub const SERDE_PATCH_INVALID_DOCUMENT: c_int = 2;
+/// The patch was not valid JSON.
+pub const SERDE_PATCH_INVALID_PATCH: c_int = 3;
+/// The call failed unexpectedly.
+pub const SERDE_PATCH_INTERNAL_ERROR: c_int = 4;
+
+/// A failed call: its code and message (internal).
+type Failure = (c_int, String);
+
+/// Computes the merge patch turning `old_json` into `new_json`, as
+/// [`diff`](crate::diff) does, and stores it in `*out`.
+///
+/// # Safety
+///
+/// `old_json` and `new_json` must be null or point to NUL-terminated strings,
+/// and `out` must be valid for writing a pointer; calls with a null `out`
+/// fail with [`SERDE_PATCH_INVALID_ARGUMENT`].
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::ffi::{SERDE_PATCH_OK, serde_patch_diff, serde_patch_free};
+/// use std::ffi::{CStr, CString};
+///
+/// let old = CString::new(r#"{"id":1,"name":"old"}"#).unwrap();
+/// let new = CString::new(r#"{"id":1,"name":"new"}"#).unwrap();
+/// let mut out = std::ptr::null_mut();
+///
+/// unsafe {
+///     assert_eq!(serde_patch_diff(old.as_ptr(), new.as_ptr(), &mut out), SERDE_PATCH_OK);
+///     assert_eq!(CStr::from_ptr(out).to_str().unwrap(), r#"{"name":"new"}"#);
+///     serde_patch_free(out);
+/// }
+/// ```
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn serde_patch_diff(
+    old_json: *const c_char,
+    new_json: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let old = parse(unsafe { read(old_json) }?, SERDE_PATCH_INVALID_DOCUMENT)?;
+        let new = parse(unsafe { read(new_json) }?, SERDE_PATCH_INVALID_DOCUMENT)?;
+        crate::diff(&old, &new)
+            .and_then(|patch| serde_json::to_string(&patch))
+            .map_err(|err| (SERDE_PATCH_INTERNAL_ERROR, err.to_string()))
+    }));
+    unsafe { finish(result, out) }
+}
+
+/// Applies the merge patch `patch_json` to `document_json`, as
+/// [`apply`](crate::apply) does, and stores the patched document in `*out`.
+///
+/// # Safety
+///
+/// `document_json` and `patch_json` must be null or point to NUL-terminated
+/// strings, and `out` must be valid for writing a pointer; calls with a null
+/// `out` fail with [`SERDE_PATCH_INVALID_ARGUMENT`].
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::ffi::{SERDE_PATCH_INVALID_PATCH, SERDE_PATCH_OK, serde_patch_apply, serde_patch_free};
+/// use std::ffi::{CStr, CString};
+///
+/// let document = CString::new(r#"{"id":1,"name":"old"}"#).unwrap();
+/// let patch = CString::new(r#"{"name":"new"}"#).unwrap();
+/// let mut out = std::ptr::null_mut();
+///
+/// unsafe {
+///     assert_eq!(serde_patch_apply(document.as_ptr(), patch.as_ptr(), &mut out), SERDE_PATCH_OK);
+///     assert_eq!(CStr::from_ptr(out).to_str().unwrap(), r#"{"id":1,"name":"new"}"#);
+///     serde_patch_free(out);
+///
+///     let bad = CString::new("{ nope").unwrap();
+///     assert_eq!(serde_patch_apply(document.as_ptr(), bad.as_ptr(), &mut out), SERDE_PATCH_INVALID_PATCH);
+///     serde_patch_free(out);
+/// }
+/// ```
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn serde_patch_apply(
+    document_json: *const c_char,
+    patch_json: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let document = parse(
+            unsafe { read(document_json) }?,
+            SERDE_PATCH_INVALID_DOCUMENT,
+        )?;
+        let patch = unsafe { read(patch_json) }?;
+        let patched: Value = crate::apply(document, patch).map_err(|err| match err {
+            crate::Error::ParsePatch(_) => (SERDE_PATCH_INVALID_PATCH, err.to_string()),
+            _ => (SERDE_PATCH_INTERNAL_ERROR, err.to_string()),
+        })?;
+        serde_json::to_string(&patched).map_err(|err| (SERDE_PATCH_INTERNAL_ERROR, err.to_string()))
+    }));
+    unsafe { finish(result, out) }
+}
+
+/// Releases a string stored in `*out` by this module. Null is ignored.
+///
+/// # Safety
+///
+/// `string` must be null or a pointer stored by a `serde_patch_*` call, not
+/// yet released.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn serde_patch_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Borrows a C string argument (internal).
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL-terminated string that outlives `'a`.
+unsafe fn read<'a>(ptr: *const c_char) -> Result<&'a str, Failure> {
+    if ptr.is_null() {
+        return Err((SERDE_PATCH_INVALID_ARGUMENT, "null pointer".to_string()));
+    }
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|err| (SERDE_PATCH_INVALID_ARGUMENT, err.to_string()))
+}
+
+fn parse(json: &str, code: c_int) -> Result<Value, Failure> {
+    serde_json::from_str(json).map_err(|err| (code, err.to_string()))
+}
+
+/// Stores the result or error message in `*out` and returns the code
+/// (internal).
+///
+/// # Safety
+///
+/// `out` must be null or valid for writing a pointer.
+unsafe fn finish(
+    result: std::thread::Result<Result<String, Failure>>,
+    out: *mut *mut c_char,
+) -> c_int {
+    let (code, text) = match result {
+        Ok(Ok(json)) => (SERDE_PATCH_OK, json),
+        Ok(Err(failure)) => failure,
+        Err(_) => (SERDE_PATCH_INTERNAL_ERROR, "internal error".to_string()),
+    };
+    if out.is_null() {
+        return SERDE_PATCH_INVALID_ARGUMENT;
+    }
+    // JSON output escapes NUL, so only an error message could hold one.
+    let text = CString::new(text.replace('\0', "")).unwrap_or_default();
+    unsafe { *out = text.into_raw() };
+    code
+}
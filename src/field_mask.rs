@@ -0,0 +1,187 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// A set of field paths, as in Protocol Buffers' `google.protobuf.FieldMask`.
+///
+/// Paths are dotted, like everywhere else in this crate. Serializes as the
+/// proto3 JSON form, a single comma-separated string; with the `prost`
+/// feature it converts to and from `prost_types::FieldMask`.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::FieldMask;
+///
+/// let mask: FieldMask = "age,profile.bio".parse().unwrap();
+/// assert_eq!(mask.paths, ["age", "profile.bio"]);
+/// assert_eq!(serde_json::to_string(&mask).unwrap(), r#""age,profile.bio""#);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FieldMask {
+    /// The masked paths.
+    pub paths: Vec<String>,
+}
+
+impl FieldMask {
+    /// Creates a mask from paths.
+    pub fn new<I, S>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            paths: paths.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns `true` if the mask has no paths.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+}
+
+impl fmt::Display for FieldMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.paths.join(","))
+    }
+}
+
+impl FromStr for FieldMask {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+        let paths: Vec<String> = s.split(',').map(|path| path.trim().to_string()).collect();
+        if paths.iter().any(String::is_empty) {
+            return Err(serde_json::Error::custom(format_args!(
+                "empty path in field mask `{s}`"
+            )));
+        }
+        Ok(Self { paths })
+    }
+}
+
+impl Serialize for FieldMask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldMask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "prost")]
+impl From<prost_types::FieldMask> for FieldMask {
+    fn from(mask: prost_types::FieldMask) -> Self {
+        Self { paths: mask.paths }
+    }
+}
+
+#[cfg(feature = "prost")]
+impl From<FieldMask> for prost_types::FieldMask {
+    fn from(mask: FieldMask) -> Self {
+        Self { paths: mask.paths }
+    }
+}
+
+/// The field mask of a JSON Merge Patch (RFC 7396).
+///
+/// One path per value the patch sets or removes, in sorted order, so a
+/// Merge Patch received over REST can be forwarded as a masked gRPC update.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let patch = json!({ "age": 31, "profile": { "bio": "hi", "avatar_url": null } });
+/// let mask = serde_patch::to_field_mask(&patch);
+/// assert_eq!(mask.paths, ["age", "profile.avatar_url", "profile.bio"]);
+/// ```
+pub fn to_field_mask(patch: &Value) -> FieldMask {
+    let mut paths = Vec::new();
+    crate::paths::collect_leaves(patch, "", &mut paths);
+    paths.sort();
+    FieldMask { paths }
+}
+
+/// Copies the fields listed in `mask` from `source` into `target`.
+///
+/// Follows `FieldMask` update semantics: each masked field is replaced as a
+/// whole, and cleared if `source` doesn't have it. Fields outside the mask
+/// keep their `target` value. A path naming a field neither value has is
+/// reported as [`Error::Rejected`](crate::Error::Rejected).
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::FieldMask;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8, email: Option<String> }
+///
+/// let target = User { name: "alice".into(), age: 30, email: Some("a@example.com".into()) };
+/// let source = User { name: "ignored".into(), age: 31, email: None };
+///
+/// let updated = serde_patch::apply_with_mask(target, &source, &FieldMask::new(["age", "email"])).unwrap();
+/// assert_eq!((updated.name.as_str(), updated.age, updated.email), ("alice", 31, None));
+/// ```
+pub fn apply_with_mask<T>(target: T, source: &T, mask: &FieldMask) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut target_val = crate::error::serialize(&target)?;
+    let source_val = crate::error::serialize(source)?;
+
+    for path in &mask.paths {
+        let keys = crate::paths::split(path);
+        let lookup = |root: &Value| {
+            keys.iter()
+                .try_fold(root, |value, key| value.get(key))
+                .cloned()
+        };
+        let value = lookup(&source_val);
+        if value.is_none() && lookup(&target_val).is_none() {
+            return Err(crate::Error::Rejected(serde_json::Error::custom(
+                format_args!("unknown field `{path}` in field mask"),
+            )));
+        }
+        set(&mut target_val, &keys, value);
+    }
+    crate::error::deserialize_merged(&target_val, &Value::Object(Map::new()))
+}
+
+/// Sets or removes the value at `keys`, creating parent objects (internal).
+fn set(root: &mut Value, keys: &[String], value: Option<Value>) {
+    let Some((last, parents)) = keys.split_last() else {
+        return;
+    };
+    let mut current = root;
+    for key in parents {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        let Value::Object(map) = current else {
+            return;
+        };
+        current = map.entry(key.clone()).or_insert(Value::Null);
+    }
+    if !current.is_object() {
+        *current = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = current {
+        match value {
+            Some(value) => map.insert(last.clone(), value),
+            None => map.remove(last),
+        };
+    }
+}
@@ -0,0 +1,101 @@
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+/// Computes a stable 64-bit hash of a value's JSON form.
+///
+/// The value is hashed in a canonical encoding: object keys in sorted order
+/// and floats without a fractional part written as integers, so `1.0`, `1`
+/// and `-0.0`/`0` hash alike. Two values with equal fingerprints are almost
+/// certainly equal, which makes comparing fingerprints a cheap way to skip a
+/// diff. The hash is 64-bit FNV-1a: stable across runs and platforms, but not
+/// meant to resist deliberate collisions.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(serde::Serialize)]
+/// struct Reading { sensor: String, celsius: f64 }
+///
+/// let reading = Reading { sensor: "t1".into(), celsius: 21.0 };
+/// let stored = json!({ "celsius": 21, "sensor": "t1" });
+///
+/// assert_eq!(
+///     serde_patch::fingerprint(&reading).unwrap(),
+///     serde_patch::fingerprint(&stored).unwrap()
+/// );
+/// ```
+pub fn fingerprint<T: Serialize + ?Sized>(value: &T) -> Result<u64, serde_json::Error> {
+    Ok(hash_value(&crate::value::to_value(value)?))
+}
+
+/// The fingerprint of a JSON value (internal).
+pub(crate) fn hash_value(value: &Value) -> u64 {
+    let mut hasher = Fnv1a::default();
+    write_canonical(value, &mut |bytes| hasher.write(bytes));
+    hasher.0
+}
+
+/// 64-bit FNV-1a (internal).
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Feeds the compact JSON encoding of `value` to `hasher`, with sorted keys
+/// and normalized numbers (internal).
+pub(crate) fn write_canonical(value: &Value, hasher: &mut impl FnMut(&[u8])) {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => hasher(value.to_string().as_bytes()),
+        Value::Number(number) => hasher(normalize(number).as_bytes()),
+        Value::Array(items) => {
+            hasher(b"[");
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    hasher(b",");
+                }
+                write_canonical(item, hasher);
+            }
+            hasher(b"]");
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            hasher(b"{");
+            for (index, (key, item)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    hasher(b",");
+                }
+                hasher(Value::String(key.clone()).to_string().as_bytes());
+                hasher(b":");
+                write_canonical(item, hasher);
+            }
+            hasher(b"}");
+        }
+    }
+}
+
+/// Writes integral floats that fit an `i64` exactly as integers (internal).
+fn normalize(number: &Number) -> String {
+    match number.as_f64() {
+        Some(float)
+            if number.is_f64() && float.fract() == 0.0 && float.abs() < (1u64 << 53) as f64 =>
+        {
+            (float as i64).to_string()
+        }
+        _ => number.to_string(),
+    }
+}
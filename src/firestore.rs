@@ -0,0 +1,213 @@
+//! Firestore field updates generated from merge patches.
+//!
+//! Firestore updates name the fields they touch by field path (`profile.bio`)
+//! and remove fields with a delete sentinel (`FieldValue.delete()` in the
+//! client SDKs). [`from_merge_patch`] and [`diff`] produce an [`Update`] in
+//! that shape, with the update mask and `fields` body the REST and gRPC
+//! `UpdateDocument` calls expect.
+
+use serde::Serialize;
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// What an [`Update`] does to one field path.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldUpdate {
+    /// Sets the field to the value.
+    Set(Value),
+    /// Removes the field, like `FieldValue.delete()`.
+    Delete,
+}
+
+/// A Firestore update: field paths and what to do with them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Update {
+    /// Changes by field path, sorted. Path segments that aren't plain
+    /// identifiers are quoted as [`field_path`] does.
+    pub fields: BTreeMap<String, FieldUpdate>,
+}
+
+impl Update {
+    /// Returns `true` if no field changes, in which case no write should be
+    /// issued.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The update mask: every changed field path, sorted.
+    ///
+    /// Paths in the mask but missing from [`document`](Self::document) are
+    /// deleted by Firestore.
+    pub fn update_mask(&self) -> Vec<String> {
+        self.fields.keys().cloned().collect()
+    }
+
+    /// The fields to write, nested into a document: the request body to send
+    /// along with [`update_mask`](Self::update_mask). Deleted fields are left
+    /// out.
+    pub fn document(&self) -> Value {
+        let mut document = Map::new();
+        'fields: for (path, update) in &self.fields {
+            let FieldUpdate::Set(value) = update else {
+                continue;
+            };
+            let segments = split_path(path);
+            let Some((last, parents)) = segments.split_last() else {
+                continue;
+            };
+            let mut target = &mut document;
+            for segment in parents {
+                let entry = target
+                    .entry(segment.clone())
+                    .or_insert_with(|| Value::Object(Map::new()));
+                // A field set whole and below is only possible in a hand-built update.
+                let Value::Object(next) = entry else {
+                    continue 'fields;
+                };
+                target = next;
+            }
+            target.insert(last.clone(), value.clone());
+        }
+        Value::Object(document)
+    }
+}
+
+/// Translates a merge patch into a Firestore update.
+///
+/// Leaves of the patch become field paths: values are set, `null` is
+/// deleted. Arrays and empty objects are leaves too, since Firestore can't
+/// address array elements. Fails if the patch isn't an object.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::firestore::FieldUpdate;
+///
+/// let patch = json!({ "age": 31, "profile": { "bio": "hello", "avatar-url": null } });
+/// let update = serde_patch::firestore::from_merge_patch(&patch).unwrap();
+///
+/// assert_eq!(update.update_mask(), ["age", "profile.`avatar-url`", "profile.bio"]);
+/// assert_eq!(update.fields["profile.`avatar-url`"], FieldUpdate::Delete);
+/// assert_eq!(update.document(), json!({ "age": 31, "profile": { "bio": "hello" } }));
+/// ```
+pub fn from_merge_patch(patch: &Value) -> Result<Update, serde_json::Error> {
+    let Value::Object(map) = patch else {
+        return Err(serde_json::Error::custom(
+            "a Firestore update can only be built from an object patch",
+        ));
+    };
+    let mut update = Update::default();
+    flatten(map, "", &mut update.fields);
+    Ok(update)
+}
+
+/// Diffs two documents into the minimal Firestore update turning `old` into
+/// `new`.
+///
+/// As in a merge patch, fields that become `null` are deleted rather than set
+/// to `null`. Fails if either document doesn't serialize to an object.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(serde::Serialize)]
+/// struct User { name: String, age: u8, nickname: Option<String> }
+///
+/// let old = User { name: "alice".into(), age: 30, nickname: Some("al".into()) };
+/// let new = User { name: "alice".into(), age: 31, nickname: None };
+///
+/// let update = serde_patch::firestore::diff(&old, &new).unwrap();
+/// assert_eq!(update.update_mask(), ["age", "nickname"]);
+/// assert_eq!(update.document(), json!({ "age": 31 }));
+/// ```
+pub fn diff<T: Serialize>(old: &T, new: &T) -> Result<Update, serde_json::Error> {
+    let old = crate::value::to_value(old)?;
+    let new = crate::value::to_value(new)?;
+    if !old.is_object() || !new.is_object() {
+        return Err(serde_json::Error::custom(
+            "Firestore documents must serialize to objects",
+        ));
+    }
+    from_merge_patch(&crate::diff_values(
+        &old,
+        &new,
+        &crate::DiffOptions::default(),
+    )?)
+}
+
+/// Quotes one field name for use in a Firestore field path.
+///
+/// Names made of letters, digits and `_` not starting with a digit are
+/// written as they are; others are wrapped in backticks, with `` ` `` and `\`
+/// escaped.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::firestore::field_path;
+///
+/// assert_eq!(field_path("bio"), "bio");
+/// assert_eq!(field_path("avatar.url"), "`avatar.url`");
+/// assert_eq!(field_path("a`b"), r"`a\`b`");
+/// ```
+pub fn field_path(name: &str) -> String {
+    let simple = name
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if simple {
+        return name.to_string();
+    }
+    let mut quoted = String::with_capacity(name.len() + 2);
+    quoted.push('`');
+    for c in name.chars() {
+        if c == '`' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('`');
+    quoted
+}
+
+fn flatten(patch: &Map<String, Value>, prefix: &str, out: &mut BTreeMap<String, FieldUpdate>) {
+    for (key, value) in patch {
+        let path = if prefix.is_empty() {
+            field_path(key)
+        } else {
+            format!("{prefix}.{}", field_path(key))
+        };
+        match value {
+            Value::Null => {
+                out.insert(path, FieldUpdate::Delete);
+            }
+            Value::Object(nested) if !nested.is_empty() => flatten(nested, &path, out),
+            _ => {
+                out.insert(path, FieldUpdate::Set(value.clone()));
+            }
+        }
+    }
+}
+
+/// Splits a field path built by [`field_path`] back into names (internal).
+fn split_path(path: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut chars = path.chars();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '`' => quoted = !quoted,
+            '\\' if quoted => current.extend(chars.next()),
+            '.' if !quoted => segments.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    segments.push(current);
+    segments
+}
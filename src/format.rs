@@ -0,0 +1,111 @@
+use crate::DiffOptions;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+/// A serialization format patches and documents can be exchanged in.
+///
+/// Diffing and merging always work on the serde data model as held by
+/// `serde_json::Value`; a format only supplies the hooks to [`parse`] bytes
+/// into it and [`serialize`] it back, and gets the diff and apply entry points
+/// for free. [`Json`] is the format every other function in this crate
+/// speaks; [`Yaml`](crate::Yaml), [`Cbor`](crate::Cbor) and
+/// [`MsgPack`](crate::MsgPack) come with their Cargo features.
+///
+/// [`parse`]: Format::parse
+/// [`serialize`]: Format::serialize
+///
+/// # Example
+///
+/// ```
+/// use serde_json::Value;
+/// use serde_patch::Format;
+///
+/// /// Pretty-printed JSON, e.g. for patches stored in version control.
+/// struct PrettyJson;
+///
+/// impl Format for PrettyJson {
+///     fn parse(bytes: &[u8]) -> Result<Value, serde_json::Error> {
+///         serde_json::from_slice(bytes)
+///     }
+///
+///     fn serialize(value: &Value) -> Result<Vec<u8>, serde_json::Error> {
+///         serde_json::to_vec_pretty(value)
+///     }
+/// }
+///
+/// let patch = PrettyJson::diff_documents(br#"{"a":1,"b":2}"#, br#"{"a":1,"b":3}"#).unwrap();
+/// assert_eq!(patch, b"{\n  \"b\": 3\n}");
+/// ```
+pub trait Format {
+    /// Parses a document or patch into the serde data model.
+    fn parse(bytes: &[u8]) -> Result<Value, serde_json::Error>;
+
+    /// Serializes a document or patch.
+    fn serialize(value: &Value) -> Result<Vec<u8>, serde_json::Error>;
+
+    /// Computes the merge patch turning `old` into `new`, encoded in this
+    /// format.
+    fn diff<T: Serialize>(old: &T, new: &T) -> Result<Vec<u8>, serde_json::Error> {
+        Self::serialize(&crate::diff(old, new)?)
+    }
+
+    /// Applies a merge patch encoded in this format.
+    ///
+    /// Like [`apply_value`](crate::apply_value); a patch that can't be parsed
+    /// is reported as [`Error::ParsePatch`](crate::Error::ParsePatch).
+    fn apply<T>(current: T, patch: &[u8]) -> Result<T, crate::Error>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let patch = Self::parse(patch).map_err(crate::Error::ParsePatch)?;
+        crate::apply_value(current, &patch)
+    }
+
+    /// Computes the merge patch between two documents in this format.
+    fn diff_documents(old: &[u8], new: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+        let patch = crate::diff_values(
+            &Self::parse(old)?,
+            &Self::parse(new)?,
+            &DiffOptions::default(),
+        )?;
+        Self::serialize(&patch)
+    }
+
+    /// Merges a patch into a document, both in this format.
+    fn apply_document(document: &[u8], patch: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+        let mut value = Self::parse(document)?;
+        crate::merge_into(&mut value, &Self::parse(patch)?);
+        Self::serialize(&value)
+    }
+}
+
+/// JSON, parsed with the backend selected by the `simd-json` / `sonic-rs`
+/// features.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::{Format, Json};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// let old = User { name: "alice".into(), age: 30 };
+/// let new = User { name: "alice".into(), age: 31 };
+///
+/// let patch = Json::diff(&old, &new).unwrap();
+/// assert_eq!(patch, br#"{"age":31}"#);
+/// assert_eq!(Json::apply(old, &patch).unwrap().age, 31);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
+
+impl Format for Json {
+    fn parse(bytes: &[u8]) -> Result<Value, serde_json::Error> {
+        crate::backend::from_slice(bytes)
+    }
+
+    fn serialize(value: &Value) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(value)
+    }
+}
@@ -0,0 +1,97 @@
+//! Conditional requests (RFC 9110) for merge patches.
+//!
+//! A merge patch says nothing about the state it was made against, so two
+//! clients patching the same document can silently overwrite each other.
+//! Serving documents with an [`etag`] and applying patches with
+//! [`apply_if_match`] turns such lost updates into `412 Precondition Failed`.
+
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+/// Computes a strong ETag for a document.
+///
+/// The tag is a hash of the document's JSON form, quoted as the `ETag`
+/// header requires. Object keys are hashed in sorted order, so equal documents
+/// have equal tags however they were built.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let etag = serde_patch::http::etag(&json!({ "name": "alice", "age": 30 })).unwrap();
+/// assert!(etag.starts_with('"') && etag.ends_with('"'));
+/// assert_eq!(etag, serde_patch::http::etag(&json!({ "age": 30, "name": "alice" })).unwrap());
+/// ```
+pub fn etag<T: Serialize + ?Sized>(document: &T) -> Result<String, crate::Error> {
+    Ok(etag_of(&crate::error::serialize(document)?))
+}
+
+/// Returns `true` if an `If-Match` header value is satisfied by `etag`.
+///
+/// Accepts `*` and comma-separated lists of entity tags, compared strongly:
+/// weak tags (`W/"..."`) never match.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::http::if_match;
+///
+/// assert!(if_match(r#""a1", "b2""#, r#""b2""#));
+/// assert!(if_match("*", r#""b2""#));
+/// assert!(!if_match(r#"W/"b2""#, r#""b2""#));
+/// ```
+pub fn if_match(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || (candidate == etag && !candidate.starts_with("W/")))
+}
+
+/// Applies a merge patch only if the document still has the ETag the client
+/// saw.
+///
+/// `if_match` is the request's `If-Match` header value. When no tag matches
+/// the current document, nothing is applied and
+/// [`Error::PreconditionFailed`](crate::Error::PreconditionFailed) is
+/// returned, which should become a `412` response. Otherwise behaves like
+/// [`apply_value`](crate::apply_value); the new ETag is
+/// [`etag`] of the result.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{Error, http};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// let user = User { name: "alice".into(), age: 30 };
+/// let seen = http::etag(&user).unwrap();
+///
+/// let user = http::apply_if_match(user, &json!({ "age": 31 }), &seen).unwrap();
+/// assert_eq!(user.age, 31);
+///
+/// // A second client still holding the old tag is refused.
+/// let err = http::apply_if_match(user, &json!({ "age": 40 }), &seen).err().unwrap();
+/// assert!(matches!(err, Error::PreconditionFailed { .. }));
+/// ```
+pub fn apply_if_match<T>(current: T, patch: &Value, if_match: &str) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let value = crate::error::serialize(&current)?;
+    let actual = etag_of(&value);
+    if !self::if_match(if_match, &actual) {
+        return Err(crate::Error::PreconditionFailed {
+            expected: if_match.to_string(),
+            actual,
+        });
+    }
+    crate::error::deserialize_patched(&value, patch)
+}
+
+fn etag_of(value: &Value) -> String {
+    format!("\"{:016x}\"", crate::fingerprint::hash_value(value))
+}
@@ -0,0 +1,54 @@
+use crate::ApplyOptions;
+use serde::de::Error as _;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+/// Parses a patch written in JSON5 (a superset of JSONC).
+///
+/// Accepts comments, trailing commas, unquoted keys and single-quoted strings,
+/// which makes hand-authored patch files easier to maintain. Strict JSON stays
+/// the default everywhere else.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let patch = serde_patch::parse_json5(r#"{
+///     // bump the replica count for the sale
+///     replicas: 5,
+///     image: 'app:1.4.2',
+/// }"#).unwrap();
+/// assert_eq!(patch, json!({ "replicas": 5, "image": "app:1.4.2" }));
+/// ```
+pub fn parse_json5(patch: &str) -> Result<Value, serde_json::Error> {
+    json5::from_str(patch).map_err(serde_json::Error::custom)
+}
+
+/// Applies a JSON Merge Patch (RFC 7396) written in JSON5 or JSONC.
+///
+/// Consumes the current value and returns the updated value. A patch that
+/// can't be parsed is reported as [`Error::ParsePatch`](crate::Error::ParsePatch).
+///
+/// # Example
+///
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Deployment { replicas: u32, image: String }
+///
+/// let current = Deployment { replicas: 2, image: "app:1.4.1".into() };
+/// let patch = r#"{
+///     /* scale out */
+///     "replicas": 5,
+/// }"#;
+///
+/// let updated = serde_patch::apply_json5(current, patch).unwrap();
+/// assert_eq!(updated.replicas, 5);
+/// ```
+pub fn apply_json5<T>(current: T, patch: &str) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let patch_val = parse_json5(patch).map_err(crate::Error::ParsePatch)?;
+    crate::apply_patch::apply_parsed(current, patch_val, &ApplyOptions::default())
+}
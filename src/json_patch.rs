@@ -0,0 +1,483 @@
+//! JSON Patch (RFC 6902) operation lists.
+//!
+//! Unlike merge patches, operation lists can address array elements, set
+//! values to `null`, and assert values with `test`. They are the body format
+//! of `application/json-patch+json` requests.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+
+/// One RFC 6902 operation. Paths are JSON Pointers (RFC 6901).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Op {
+    /// Inserts `value` at `path` (`-` appends to an array).
+    Add { path: String, value: Value },
+    /// Removes the value at `path`.
+    Remove { path: String },
+    /// Replaces the existing value at `path`.
+    Replace { path: String, value: Value },
+    /// Removes the value at `from` and adds it at `path`.
+    Move { from: String, path: String },
+    /// Adds a copy of the value at `from` at `path`.
+    Copy { from: String, path: String },
+    /// Fails unless the value at `path` equals `value`.
+    Test { path: String, value: Value },
+}
+
+/// Computes the RFC 6902 operations turning `old` into `new`.
+///
+/// Objects are compared per key and arrays per element after skipping their
+/// common prefix and suffix, so inserting or removing elements yields `add`
+/// and `remove` operations instead of replacing the whole array. Only `add`,
+/// `remove` and `replace` are emitted.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::json_patch::{Op, diff_ops};
+///
+/// #[derive(serde::Serialize)]
+/// struct Post { title: String, tags: Vec<String> }
+///
+/// let old = Post { title: "Hello".into(), tags: vec!["a".into(), "c".into()] };
+/// let new = Post { title: "Hello".into(), tags: vec!["a".into(), "b".into(), "c".into()] };
+///
+/// let ops = diff_ops(&old, &new).unwrap();
+/// assert_eq!(ops, [Op::Add { path: "/tags/1".into(), value: json!("b") }]);
+/// ```
+pub fn diff_ops<T: Serialize>(old: &T, new: &T) -> Result<Vec<Op>, serde_json::Error> {
+    let old = crate::value::to_value(old)?;
+    let new = crate::value::to_value(new)?;
+    let mut ops = Vec::new();
+    diff_values(&old, &new, &mut String::new(), &mut ops);
+    Ok(ops)
+}
+
+/// Applies RFC 6902 operations in order.
+///
+/// The operations are atomic: if one fails (missing path, failed `test`,
+/// out-of-range index), an [`Error::Rejected`](crate::Error::Rejected)
+/// naming it is returned and nothing is applied.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::json_patch::{Op, apply_ops};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Post { title: String, tags: Vec<String> }
+///
+/// let post = Post { title: "Hello".into(), tags: vec!["a".into(), "b".into()] };
+/// let ops: Vec<Op> = serde_json::from_str(r#"[
+///     { "op": "test", "path": "/title", "value": "Hello" },
+///     { "op": "remove", "path": "/tags/0" },
+///     { "op": "add", "path": "/tags/-", "value": "c" }
+/// ]"#).unwrap();
+///
+/// let post = apply_ops(post, &ops).unwrap();
+/// assert_eq!(post.tags, ["b", "c"]);
+/// ```
+pub fn apply_ops<T>(current: T, ops: &[Op]) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut value = crate::error::serialize(&current)?;
+    for (index, op) in ops.iter().enumerate() {
+        apply_op(&mut value, op).map_err(|message| {
+            crate::Error::Rejected(serde_json::Error::custom(format!(
+                "{} (operation {})",
+                message, index
+            )))
+        })?;
+    }
+    crate::error::deserialize_merged(&value, &Value::Object(Map::new()))
+}
+
+fn diff_values(old: &Value, new: &Value, path: &mut String, ops: &mut Vec<Op>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_value) in old_map {
+                let len = path.len();
+                push_token(path, key);
+                match new_map.get(key) {
+                    Some(new_value) => diff_values(old_value, new_value, path, ops),
+                    None => ops.push(Op::Remove { path: path.clone() }),
+                }
+                path.truncate(len);
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    let len = path.len();
+                    push_token(path, key);
+                    ops.push(Op::Add {
+                        path: path.clone(),
+                        value: new_value.clone(),
+                    });
+                    path.truncate(len);
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            diff_arrays(old_items, new_items, path, ops)
+        }
+        _ if old == new => {}
+        _ => ops.push(Op::Replace {
+            path: path.clone(),
+            value: new.clone(),
+        }),
+    }
+}
+
+fn diff_arrays(old: &[Value], new: &[Value], path: &mut String, ops: &mut Vec<Op>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    let common = old_mid.len().min(new_mid.len());
+
+    let element = |path: &String, index: usize| format!("{}/{}", path, index);
+    for i in 0..common {
+        let mut element_path = element(path, prefix + i);
+        diff_values(&old_mid[i], &new_mid[i], &mut element_path, ops);
+    }
+    // Remove from the back so earlier indices stay valid.
+    for i in (common..old_mid.len()).rev() {
+        ops.push(Op::Remove {
+            path: element(path, prefix + i),
+        });
+    }
+    for (i, value) in new_mid.iter().enumerate().skip(common) {
+        ops.push(Op::Add {
+            path: element(path, prefix + i),
+            value: value.clone(),
+        });
+    }
+}
+
+fn push_token(path: &mut String, key: &str) {
+    path.push('/');
+    path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+}
+
+fn apply_op(value: &mut Value, op: &Op) -> Result<(), String> {
+    match op {
+        Op::Add { path, value: new } => add(value, path, new.clone()),
+        Op::Remove { path } => remove(value, path).map(drop),
+        Op::Replace { path, value: new } => {
+            let target = value
+                .pointer_mut(path)
+                .ok_or_else(|| format!("path `{}` does not exist", path))?;
+            *target = new.clone();
+            Ok(())
+        }
+        Op::Move { from, path } => {
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(format!(
+                    "cannot move `{}` into its own child `{}`",
+                    from, path
+                ));
+            }
+            let moved = remove(value, from)?;
+            add(value, path, moved)
+        }
+        Op::Copy { from, path } => {
+            let copied = value
+                .pointer(from)
+                .cloned()
+                .ok_or_else(|| format!("path `{}` does not exist", from))?;
+            add(value, path, copied)
+        }
+        Op::Test {
+            path,
+            value: expected,
+        } => match value.pointer(path) {
+            Some(actual) if crate::report::values_equal(actual, expected) => Ok(()),
+            Some(_) => Err(format!("test failed at `{}`", path)),
+            None => Err(format!("path `{}` does not exist", path)),
+        },
+    }
+}
+
+/// Splits a pointer into its parent pointer and unescaped last token.
+fn split_pointer(path: &str) -> Result<(&str, String), String> {
+    let (parent, token) = path
+        .rsplit_once('/')
+        .filter(|(parent, _)| parent.is_empty() || parent.starts_with('/'))
+        .ok_or_else(|| format!("invalid JSON pointer `{}`", path))?;
+    Ok((parent, token.replace("~1", "/").replace("~0", "~")))
+}
+
+fn add(value: &mut Value, path: &str, new: Value) -> Result<(), String> {
+    if path.is_empty() {
+        *value = new;
+        return Ok(());
+    }
+    let (parent, token) = split_pointer(path)?;
+    match value.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(token, new);
+            Ok(())
+        }
+        Some(Value::Array(items)) => {
+            let index = if token == "-" {
+                items.len()
+            } else {
+                array_index(&token, items.len() + 1, path)?
+            };
+            items.insert(index, new);
+            Ok(())
+        }
+        Some(_) => Err(format!("parent of `{}` is not a container", path)),
+        None => Err(format!("path `{}` does not exist", parent)),
+    }
+}
+
+fn remove(value: &mut Value, path: &str) -> Result<Value, String> {
+    if path.is_empty() {
+        return Ok(std::mem::take(value));
+    }
+    let (parent, token) = split_pointer(path)?;
+    let removed = match value.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&token),
+        Some(Value::Array(items)) => {
+            let index = array_index(&token, items.len(), path)?;
+            Some(items.remove(index))
+        }
+        _ => None,
+    };
+    removed.ok_or_else(|| format!("path `{}` does not exist", path))
+}
+
+/// Parses an array index token, which must be below `bound`.
+fn array_index(token: &str, bound: usize, path: &str) -> Result<usize, String> {
+    let valid = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    match token.parse::<usize>() {
+        Ok(index) if valid && index < bound => Ok(index),
+        _ => Err(format!("invalid array index in `{}`", path)),
+    }
+}
+
+/// Converts to the `json-patch` crate's operation type.
+///
+/// Fails if a path or `from` isn't a valid JSON Pointer.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::json_patch::Op;
+///
+/// let op = Op::Replace { path: "/title".into(), value: json!("Hi") };
+/// let converted = json_patch::PatchOperation::try_from(op.clone()).unwrap();
+/// assert_eq!(converted.path().as_str(), "/title");
+/// assert_eq!(Op::from(converted), op);
+/// ```
+#[cfg(feature = "json-patch")]
+impl TryFrom<Op> for ::json_patch::PatchOperation {
+    type Error = serde_json::Error;
+
+    fn try_from(op: Op) -> Result<Self, Self::Error> {
+        use ::json_patch::{
+            AddOperation, CopyOperation, MoveOperation, PatchOperation, RemoveOperation,
+            ReplaceOperation, TestOperation,
+        };
+
+        Ok(match op {
+            Op::Add { path, value } => PatchOperation::Add(AddOperation {
+                path: pointer(path)?,
+                value,
+            }),
+            Op::Remove { path } => PatchOperation::Remove(RemoveOperation {
+                path: pointer(path)?,
+            }),
+            Op::Replace { path, value } => PatchOperation::Replace(ReplaceOperation {
+                path: pointer(path)?,
+                value,
+            }),
+            Op::Move { from, path } => PatchOperation::Move(MoveOperation {
+                from: pointer(from)?,
+                path: pointer(path)?,
+            }),
+            Op::Copy { from, path } => PatchOperation::Copy(CopyOperation {
+                from: pointer(from)?,
+                path: pointer(path)?,
+            }),
+            Op::Test { path, value } => PatchOperation::Test(TestOperation {
+                path: pointer(path)?,
+                value,
+            }),
+        })
+    }
+}
+
+#[cfg(feature = "json-patch")]
+impl From<::json_patch::PatchOperation> for Op {
+    fn from(op: ::json_patch::PatchOperation) -> Self {
+        use ::json_patch::PatchOperation;
+
+        match op {
+            PatchOperation::Add(op) => Op::Add {
+                path: op.path.to_string(),
+                value: op.value,
+            },
+            PatchOperation::Remove(op) => Op::Remove {
+                path: op.path.to_string(),
+            },
+            PatchOperation::Replace(op) => Op::Replace {
+                path: op.path.to_string(),
+                value: op.value,
+            },
+            PatchOperation::Move(op) => Op::Move {
+                from: op.from.to_string(),
+                path: op.path.to_string(),
+            },
+            PatchOperation::Copy(op) => Op::Copy {
+                from: op.from.to_string(),
+                path: op.path.to_string(),
+            },
+            PatchOperation::Test(op) => Op::Test {
+                path: op.path.to_string(),
+                value: op.value,
+            },
+        }
+    }
+}
+
+/// Converts an operation list to the `json-patch` crate's `Patch`.
+///
+/// Fails on the first path that isn't a valid JSON Pointer.
+#[cfg(feature = "json-patch")]
+pub fn to_json_patch<I>(ops: I) -> Result<::json_patch::Patch, serde_json::Error>
+where
+    I: IntoIterator<Item = Op>,
+{
+    ops.into_iter()
+        .map(::json_patch::PatchOperation::try_from)
+        .collect::<Result<_, _>>()
+        .map(::json_patch::Patch)
+}
+
+/// Converts the `json-patch` crate's `Patch` to an operation list.
+#[cfg(feature = "json-patch")]
+pub fn from_json_patch(patch: ::json_patch::Patch) -> Vec<Op> {
+    patch.0.into_iter().map(Op::from).collect()
+}
+
+/// Converts an RFC 6902 patch to the equivalent merge patch.
+///
+/// Only lists of `add`, `replace` and `remove` operations on object members
+/// have one: `move`, `copy` and `test`, the root path, the `-` array token
+/// and values holding `null` inside objects are errors. Tokens are read as
+/// member names, so operations addressing array elements by index can't be
+/// told apart from ones on members named like numbers. Unlike the operation
+/// list, the merge patch doesn't fail on missing members.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::Patch;
+///
+/// let ops: json_patch::Patch = serde_json::from_value(json!([
+///     { "op": "replace", "path": "/age", "value": 31 },
+///     { "op": "remove", "path": "/profile/bio" },
+/// ])).unwrap();
+///
+/// let patch = Patch::try_from(ops).unwrap();
+/// assert_eq!(patch.as_value(), &json!({ "age": 31, "profile": { "bio": null } }));
+/// ```
+#[cfg(feature = "json-patch")]
+impl TryFrom<::json_patch::Patch> for crate::Patch {
+    type Error = serde_json::Error;
+
+    fn try_from(patch: ::json_patch::Patch) -> Result<Self, Self::Error> {
+        use ::json_patch::PatchOperation;
+
+        let mut merge_patch = Value::Object(serde_json::Map::new());
+        for op in patch.0 {
+            let (path, value) = match op {
+                PatchOperation::Add(op) => (op.path, op.value),
+                PatchOperation::Replace(op) => (op.path, op.value),
+                PatchOperation::Remove(op) => (op.path, Value::Null),
+                PatchOperation::Move(_) | PatchOperation::Copy(_) | PatchOperation::Test(_) => {
+                    return Err(serde_json::Error::custom(format_args!(
+                        "`{}` at `{}` has no merge patch equivalent",
+                        op_name(&op),
+                        op.path()
+                    )));
+                }
+            };
+            let tokens: Vec<String> = path
+                .tokens()
+                .map(|token| token.decoded().into_owned())
+                .collect();
+            let Some((last, parents)) = tokens.split_last() else {
+                return Err(serde_json::Error::custom(
+                    "a merge patch cannot replace the whole document",
+                ));
+            };
+            if last == "-" || has_null_member(&value) {
+                return Err(serde_json::Error::custom(format_args!(
+                    "`{path}` has no merge patch equivalent"
+                )));
+            }
+            let mut target = &mut merge_patch;
+            for token in parents {
+                let Value::Object(map) = target else {
+                    return Err(serde_json::Error::custom(format_args!(
+                        "`{path}` is below a value set by an earlier operation"
+                    )));
+                };
+                target = map
+                    .entry(token.clone())
+                    .or_insert_with(|| Value::Object(serde_json::Map::new()));
+            }
+            let Value::Object(map) = target else {
+                return Err(serde_json::Error::custom(format_args!(
+                    "`{path}` is below a value set by an earlier operation"
+                )));
+            };
+            map.insert(last.clone(), value);
+        }
+        Ok(crate::Patch::new(merge_patch))
+    }
+}
+
+#[cfg(feature = "json-patch")]
+fn pointer(path: String) -> Result<::json_patch::jsonptr::PointerBuf, serde_json::Error> {
+    ::json_patch::jsonptr::PointerBuf::parse(path).map_err(serde_json::Error::custom)
+}
+
+/// Whether a value has `null` object members, which a merge patch would read
+/// as removals (internal).
+#[cfg(feature = "json-patch")]
+fn has_null_member(value: &Value) -> bool {
+    value.as_object().is_some_and(|map| {
+        map.values()
+            .any(|member| member.is_null() || has_null_member(member))
+    })
+}
+
+#[cfg(feature = "json-patch")]
+fn op_name(op: &::json_patch::PatchOperation) -> &'static str {
+    use ::json_patch::PatchOperation;
+
+    match op {
+        PatchOperation::Add(_) => "add",
+        PatchOperation::Remove(_) => "remove",
+        PatchOperation::Replace(_) => "replace",
+        PatchOperation::Move(_) => "move",
+        PatchOperation::Copy(_) => "copy",
+        PatchOperation::Test(_) => "test",
+    }
+}
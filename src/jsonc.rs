@@ -0,0 +1,209 @@
+//! Minimal JSONC parser recording where each object member sits in the text
+//! (internal), shared by the `preserve` module and the diagnostics.
+
+use serde::de::Error as _;
+
+/// Byte offset of the deepest member of `path` present in a JSON(C) document (internal).
+///
+/// Points at the member's key; `None` if the document doesn't parse or
+/// doesn't contain the first segment.
+pub(crate) fn locate(text: &str, path: &[&str]) -> Option<usize> {
+    let mut parser = JsoncParser { src: text, pos: 0 };
+    parser.skip_trivia().ok()?;
+    let JsonNode::Object(mut object) = parser.parse_value().ok()? else {
+        return None;
+    };
+
+    let mut found = None;
+    for segment in path {
+        let Some(member) = object
+            .members
+            .into_iter()
+            .rev()
+            .find(|member| member.key == *segment)
+        else {
+            break;
+        };
+        found = Some(member.key_start);
+        let JsonNode::Object(child) = member.value else {
+            break;
+        };
+        object = child;
+    }
+    found
+}
+
+pub(crate) enum JsonNode {
+    Object(JsonObject),
+    Other,
+}
+
+#[cfg_attr(not(feature = "preserve"), allow(dead_code))]
+pub(crate) struct JsonObject {
+    pub(crate) open: usize,
+    pub(crate) close: usize,
+    pub(crate) members: Vec<JsonMember>,
+}
+
+#[cfg_attr(not(feature = "preserve"), allow(dead_code))]
+pub(crate) struct JsonMember {
+    pub(crate) key: String,
+    pub(crate) key_start: usize,
+    pub(crate) value_start: usize,
+    pub(crate) value_end: usize,
+    pub(crate) value: JsonNode,
+    pub(crate) comma: Option<usize>,
+}
+
+pub(crate) struct JsoncParser<'a> {
+    pub(crate) src: &'a str,
+    pub(crate) pos: usize,
+}
+
+impl JsoncParser<'_> {
+    pub(crate) fn error(&self, msg: &str) -> serde_json::Error {
+        let (line, column) = line_col(self.src, self.pos);
+        serde_json::Error::custom(format!("{} at line {} column {}", msg, line, column))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    /// Skips whitespace and comments.
+    pub(crate) fn skip_trivia(&mut self) -> Result<(), serde_json::Error> {
+        loop {
+            let rest = &self.src[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                let Some(end) = comment.find("*/") else {
+                    return Err(self.error("unterminated comment"));
+                };
+                self.pos += end + 4;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), serde_json::Error> {
+        if self.peek() != Some(byte) {
+            return Err(self.error(&format!("expected `{}`", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    pub(crate) fn parse_value(&mut self) -> Result<JsonNode, serde_json::Error> {
+        match self.peek() {
+            Some(b'{') => self.parse_object().map(JsonNode::Object),
+            Some(b'[') => self.parse_array().map(|()| JsonNode::Other),
+            Some(b'"') => self.parse_string().map(|_| JsonNode::Other),
+            _ => {
+                let start = self.pos;
+                let rest = &self.src[start..];
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || ",]}/".contains(c))
+                    .unwrap_or(rest.len());
+                if len == 0 {
+                    return Err(self.error("expected a value"));
+                }
+                self.pos += len;
+                Ok(JsonNode::Other)
+            }
+        }
+    }
+
+    /// Parses a string literal and returns its decoded value.
+    fn parse_string(&mut self) -> Result<String, serde_json::Error> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        let bytes = self.src.as_bytes();
+        while let Some(&byte) = bytes.get(self.pos) {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos += 1,
+                b'"' => return serde_json::from_str(&self.src[start..self.pos]),
+                _ => {}
+            }
+        }
+        self.pos = start;
+        Err(self.error("unterminated string"))
+    }
+
+    fn parse_array(&mut self) -> Result<(), serde_json::Error> {
+        self.expect(b'[')?;
+        loop {
+            self.skip_trivia()?;
+            if self.peek() == Some(b']') {
+                self.pos += 1;
+                return Ok(());
+            }
+            self.parse_value()?;
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {}
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonObject, serde_json::Error> {
+        let open = self.pos;
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            if self.peek() == Some(b'}') {
+                let close = self.pos;
+                self.pos += 1;
+                return Ok(JsonObject {
+                    open,
+                    close,
+                    members,
+                });
+            }
+
+            let key_start = self.pos;
+            let key = self.parse_string()?;
+            self.skip_trivia()?;
+            self.expect(b':')?;
+            self.skip_trivia()?;
+            let value_start = self.pos;
+            let value = self.parse_value()?;
+            let value_end = self.pos;
+            self.skip_trivia()?;
+            let comma = match self.peek() {
+                Some(b',') => {
+                    self.pos += 1;
+                    Some(self.pos - 1)
+                }
+                Some(b'}') => None,
+                _ => return Err(self.error("expected `,` or `}`")),
+            };
+            members.push(JsonMember {
+                key,
+                key_start,
+                value_start,
+                value_end,
+                value,
+                comma,
+            });
+        }
+    }
+}
+
+/// Start of the line containing `pos`.
+pub(crate) fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Returns `1-based (line, column)` of a byte offset.
+pub(crate) fn line_col(text: &str, pos: usize) -> (usize, usize) {
+    let line = text[..pos].matches('\n').count() + 1;
+    (line, pos - line_start(text, pos) + 1)
+}
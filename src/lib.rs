@@ -1,11 +1,212 @@
+// Malformed or adversarial input must surface as an error, never a panic.
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::unreachable
+    )
+)]
+
+// Lets `#[derive(Patch)]` output, which names `::serde_patch`, compile inside this crate.
+extern crate self as serde_patch;
+
+#[cfg(feature = "actix")]
+pub mod actix;
 mod apply_patch;
 mod apply_patch_mut;
+mod array_diff;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod audit;
+#[cfg(feature = "axum")]
+pub mod axum;
+mod backend;
+mod batch;
+#[cfg(feature = "binary-delta")]
+mod binary_delta;
+#[cfg(feature = "bson")]
+mod bson_patch;
+#[cfg(feature = "cbor")]
+mod cbor_patch;
+mod coalesce;
+mod comparator;
+mod delta;
+mod diagnostics;
 mod diff_patch;
+mod differ;
+mod documents;
+pub mod double_option;
+pub mod elasticsearch;
+mod envelope;
+mod error;
+mod explain;
+#[cfg(feature = "external-refs")]
+mod external_ref;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod field_mask;
+mod fingerprint;
+pub mod firestore;
+mod format;
+pub mod http;
+#[cfg(feature = "json5")]
+mod json5_patch;
+pub mod json_patch;
+mod jsonc;
+mod limits;
+pub mod lossy_path;
+mod maybe_absent;
+mod merge3;
+#[cfg(feature = "msgpack")]
+mod msgpack_patch;
+mod ndjson;
+mod non_finite;
+pub mod odata;
+mod options;
+#[cfg(feature = "otel")]
+mod otel;
+mod overlay;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod patch;
+mod patch_log;
+mod patch_ref;
+mod patcher;
+mod paths;
+mod pending;
+#[cfg(feature = "preserve")]
+pub mod preserve;
+mod raw_patch;
+mod report;
+mod representation;
+mod review;
+#[cfg(feature = "rocket")]
+pub mod rocket;
+pub mod scim;
+#[cfg(feature = "signing")]
+mod signing;
+pub mod sql;
+mod store;
+pub mod strategic;
+mod stream;
+#[cfg(feature = "toml")]
+mod toml_patch;
+mod validate;
+mod value;
+mod versioned;
+#[cfg(feature = "warp")]
+pub mod warp;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod wide_int;
+#[cfg(feature = "yaml")]
+mod yaml_patch;
 
+pub use apply_patch::NullSemantics;
 pub use apply_patch::apply;
+pub use apply_patch::apply_typed;
+pub use apply_patch::apply_value;
+pub use apply_patch::apply_with;
+pub use apply_patch::from_patch;
+pub use apply_patch::merge_into;
 pub use apply_patch_mut::apply_mut;
+pub use apply_patch_mut::apply_mut_value;
+pub use array_diff::ArrayStrategy;
+#[cfg(feature = "tokio")]
+pub use async_io::{apply_async, diff_async};
+pub use audit::{AuditChange, AuditRecord, FieldChange, REDACTED, Redact, audit_record};
+pub use batch::Batcher;
+#[cfg(feature = "bson")]
+pub use bson_patch::{apply_bson, diff_bson, from_bson, to_bson, to_mongo_update};
+#[cfg(feature = "cbor")]
+pub use cbor_patch::{Cbor, apply_cbor, diff_to_cbor, parse_cbor, to_cbor};
+pub use coalesce::{Coalescer, compact, compose};
+pub use comparator::{
+    CidrComparator, Comparator, Comparators, DurationComparator, IpAddrComparator,
+};
+pub use delta::{Delta, LeafChange, diff_delta};
+pub use diagnostics::{Location, ParseError, apply_with_diagnostics, parse_patch};
+pub use diff_patch::changed_paths;
 pub use diff_patch::diff;
+pub use diff_patch::diff_excluding;
+pub use diff_patch::diff_from_default;
 pub use diff_patch::diff_including;
+pub use diff_patch::diff_inverse;
+pub use diff_patch::diff_values;
+pub use diff_patch::diff_with;
+pub use differ::Differ;
+pub use documents::{DocumentsDiff, diff_documents};
+pub use envelope::{PatchEnvelope, apply_envelope};
+pub use error::Error;
+pub use explain::{DiffEntry, DiffReason, ExplainedDiff, diff_explained};
+#[cfg(feature = "external-refs")]
+pub use external_ref::{BoxError, ExternalFetch, ExternalStore};
+pub use field_mask::{FieldMask, apply_with_mask, to_field_mask};
+pub use fingerprint::fingerprint;
+pub use format::{Format, Json};
+#[cfg(feature = "json5")]
+pub use json5_patch::{apply_json5, parse_json5};
+pub use limits::{ApplyLimits, Limit, apply_limited};
+pub use maybe_absent::MaybeAbsent;
+pub use merge3::{Conflict, MergeOutcome, Resolution, merge3};
+#[cfg(feature = "msgpack")]
+pub use msgpack_patch::{MsgPack, apply_msgpack, diff_to_msgpack, parse_msgpack, to_msgpack};
+pub use ndjson::{ApplyStream, apply_stream};
+pub use non_finite::NonFinite;
+pub use options::{ApplyOptions, DiffOptions};
+#[cfg(feature = "otel")]
+pub use otel::otel_attributes;
+#[cfg(feature = "rayon")]
+pub use parallel::{apply_many, diff_many};
+pub use patch::{MERGE_PATCH_MEDIA_TYPE, Patch};
+pub use patch_log::{PatchLog, Snapshot, SnapshotPolicy, between, state_at};
+pub use patch_ref::{PatchRef, diff_borrowed};
+pub use patcher::Patcher;
+pub use pending::{ApprovalError, PatchState, PendingPatch};
+pub use raw_patch::{RawPatch, apply_raw};
+pub use report::{ApplyReport, LossyConversion, apply_with_report};
+pub use representation::{EmbeddedJson, PairsAsMap, Representation, Representations};
+pub use review::{Decision, Review, ReviewEntry};
+/// Derives a typed `<Name>Patch` struct with every field optional, plus an
+/// `apply_patch(&mut self, patch)` method.
+///
+/// `Option<T>` fields become `Option<Option<T>>` (see [`double_option`]), so a
+/// patch can tell "leave unchanged" (`None`) from "clear" (`Some(None)`).
+///
+/// ```
+/// use serde_patch::Patch;
+///
+/// #[derive(Patch, serde::Serialize, serde::Deserialize)]
+/// #[patch(derive(Debug))]
+/// struct User { name: String, age: u8 }
+///
+/// let mut user = User { name: "alice".into(), age: 30 };
+/// let patch: UserPatch = serde_json::from_str(r#"{ "age": 31 }"#).unwrap();
+/// user.apply_patch(patch);
+/// assert_eq!((user.name.as_str(), user.age), ("alice", 31));
+///
+/// let patch = UserPatch { name: Some("bob".into()), ..Default::default() };
+/// assert_eq!(serde_json::to_string(&patch).unwrap(), r#"{"name":"bob"}"#);
+/// ```
+#[cfg(feature = "derive")]
+pub use serde_patch_derive::Patch;
+#[cfg(feature = "signing")]
+pub use signing::{sign_patch, verify_and_apply};
+pub use store::Store;
+pub use stream::{apply_streaming, diff_readers};
+#[cfg(feature = "toml")]
+pub use toml_patch::{apply_toml, diff_toml};
+pub use validate::{FieldError, FieldErrorKind, Validator, Validators, apply_collecting};
+pub use versioned::{Migrate, apply_versioned, diff_versioned};
+#[cfg(feature = "yaml")]
+pub use yaml_patch::{Yaml, apply_yaml, diff_yaml, parse_yaml};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde;
+}
 
 #[cfg(test)]
 mod tests {
@@ -27,6 +228,18 @@ mod tests {
         profile: Option<Profile>,
     }
 
+    impl crate::Redact for User {
+        const REDACTED_PATHS: &'static [&'static str] = &["profile.avatar_url"];
+    }
+
+    impl crate::Migrate for User {
+        const SCHEMA_VERSION: u32 = 2;
+
+        fn migrate(_: &mut serde_json::Value, _: u32, _: u32) -> Result<(), serde_json::Error> {
+            Ok(())
+        }
+    }
+
     #[test]
     fn test_diff() {
         let old = User {
@@ -51,11 +264,15 @@ mod tests {
             }),
         };
 
-        let patch = serde_json::to_string(&crate::diff(&old, &new).unwrap()).unwrap();
+        let patch = crate::diff(&old, &new).unwrap();
 
         assert_eq!(
             patch,
-            r#"{"active":false,"age":31,"profile":{"avatar_url":null,"bio":"Senior software engineer"}}"#
+            json!({
+                "active": false,
+                "age": 31,
+                "profile": { "avatar_url": null, "bio": "Senior software engineer" }
+            })
         );
     }
 
@@ -177,4 +394,5225 @@ mod tests {
             }
         );
     }
+
+    #[cfg(feature = "binary-delta")]
+    #[test]
+    fn test_binary_delta_roundtrip() {
+        use base64::{Engine, engine::general_purpose::STANDARD};
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+        struct Asset {
+            name: String,
+            data: String,
+        }
+
+        let mut bytes: Vec<u8> = (0..4096u32).map(|i| (i * 31 % 251) as u8).collect();
+        let old = Asset {
+            name: "thumb.png".to_string(),
+            data: STANDARD.encode(&bytes),
+        };
+        bytes[2000] ^= 0xff;
+        bytes.extend_from_slice(b"trailer");
+        let new = Asset {
+            name: "thumb.png".to_string(),
+            data: STANDARD.encode(&bytes),
+        };
+
+        let options = crate::DiffOptions {
+            binary_delta: ["data".to_string()].into(),
+            ..Default::default()
+        };
+        let patch = crate::diff_with(&old, &new, &options).unwrap();
+        let delta = patch["data"]["$delta"].as_str().unwrap();
+        assert!(delta.len() < new.data.len() / 10);
+
+        let apply_options = crate::ApplyOptions {
+            binary_delta: ["data".to_string()].into(),
+            ..Default::default()
+        };
+        let updated: Asset = crate::apply_with(old, patch.to_string(), &apply_options).unwrap();
+        assert_eq!(updated, new);
+
+        // Paths are matched like `including`: globs cover every asset.
+        #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+        struct Gallery {
+            assets: std::collections::BTreeMap<String, Asset>,
+        }
+
+        let old = Gallery {
+            assets: [("thumb".to_string(), updated)].into(),
+        };
+        bytes[100] ^= 0xff;
+        let new = Gallery {
+            assets: [(
+                "thumb".to_string(),
+                Asset {
+                    name: "thumb.png".to_string(),
+                    data: STANDARD.encode(&bytes),
+                },
+            )]
+            .into(),
+        };
+        let options = crate::DiffOptions {
+            binary_delta: ["assets.*.data".to_string()].into(),
+            ..Default::default()
+        };
+        let patch = crate::diff_with(&old, &new, &options).unwrap();
+        assert!(patch["assets"]["thumb"]["data"]["$delta"].is_string());
+        let apply_options = crate::ApplyOptions {
+            binary_delta: ["assets.*.data".to_string()].into(),
+            ..Default::default()
+        };
+        let updated: Gallery = crate::apply_with(old, patch.to_string(), &apply_options).unwrap();
+        assert_eq!(updated, new);
+    }
+
+    #[cfg(feature = "external-refs")]
+    #[test]
+    fn test_external_refs_roundtrip() {
+        use crate::{ApplyOptions, DiffOptions, ExternalFetch, ExternalStore};
+        use std::collections::HashMap;
+        use std::sync::{Arc, Mutex};
+
+        let old = User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let new = User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 31,
+            active: true,
+            profile: Some(Profile {
+                bio: "x".repeat(500),
+                avatar_url: None,
+            }),
+        };
+
+        let blobs = Arc::new(Mutex::new(HashMap::new()));
+        let sink = blobs.clone();
+        let diff_options = DiffOptions {
+            external: Some(ExternalStore::new(128, move |hash, value| {
+                sink.lock().unwrap().insert(hash.to_string(), value.clone());
+                Ok(())
+            })),
+            ..Default::default()
+        };
+
+        let patch = crate::diff_with(&old, &new, &diff_options).unwrap();
+        assert_eq!(patch["age"], json!(31));
+        let hash = patch["profile"]["$ref"].as_str().unwrap().to_string();
+        assert!(blobs.lock().unwrap().contains_key(&hash));
+
+        let source = blobs.clone();
+        let apply_options = ApplyOptions {
+            external: Some(ExternalFetch::new(move |hash| {
+                source.lock().unwrap().get(hash).cloned()
+            })),
+            ..Default::default()
+        };
+        let updated: User = crate::apply_with(old, patch.to_string(), &apply_options).unwrap();
+        assert_eq!(updated, new);
+
+        let missing = ApplyOptions {
+            external: Some(ExternalFetch::new(|_| None)),
+            ..Default::default()
+        };
+        assert!(crate::apply_with(updated, patch.to_string(), &missing).is_err());
+
+        // A store entry that doesn't hash to its reference is never merged.
+        let poisoned = ApplyOptions {
+            external: Some(ExternalFetch::new(|_| {
+                Some(json!({ "bio": "y".repeat(500), "avatar_url": null }))
+            })),
+            ..Default::default()
+        };
+        let err = crate::apply_with(new, patch.to_string(), &poisoned).unwrap_err();
+        assert!(err.to_string().contains("content mismatch"));
+    }
+
+    #[test]
+    fn test_apply_versioned_migrates_old_patch() {
+        use crate::Migrate;
+        use serde_json::Value;
+
+        // v3 moved `bio` out of `profile` into a top-level `about` field.
+        #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+        struct UserV3 {
+            id: u32,
+            username: String,
+            about: String,
+        }
+
+        impl Migrate for UserV3 {
+            const SCHEMA_VERSION: u32 = 3;
+
+            fn migrate(patch: &mut Value, from: u32, to: u32) -> Result<(), serde_json::Error> {
+                assert_eq!((from, to), (2, 3));
+                let map = patch.as_object_mut().unwrap();
+                if let Some(mut profile) = map.remove("profile")
+                    && let Some(bio) = profile.get_mut("bio").map(Value::take)
+                {
+                    map.insert("about".to_string(), bio);
+                }
+                Ok(())
+            }
+        }
+
+        let current = UserV3 {
+            id: 1001,
+            username: "alice".to_string(),
+            about: "Software engineer".to_string(),
+        };
+        let patch = json!({
+            "schema_version": 2,
+            "patch": { "profile": { "bio": "Senior software engineer" } }
+        });
+
+        let updated = crate::apply_versioned(current, patch.to_string()).unwrap();
+        assert_eq!(updated.about, "Senior software engineer");
+
+        let current_version =
+            json!({ "schema_version": 3, "patch": { "about": "Staff engineer" } });
+        let updated = crate::apply_versioned(updated, current_version.to_string()).unwrap();
+        assert_eq!(updated.about, "Staff engineer");
+    }
+
+    #[test]
+    fn test_versioned_patch_mismatch() {
+        use crate::PatchEnvelope;
+
+        let user = |age| User {
+            id: 1001,
+            username: "alice".to_string(),
+            age,
+            active: true,
+            profile: None,
+        };
+
+        let versioned = crate::diff_versioned(&user(30), &user(31)).unwrap();
+        assert_eq!(
+            versioned,
+            PatchEnvelope {
+                schema_version: Some(2),
+                ..PatchEnvelope::new(json!({ "age": 31 }))
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&versioned).unwrap(),
+            json!({ "patch": { "age": 31 }, "schema_version": 2 })
+        );
+
+        let unversioned = json!({ "patch": { "age": 31 } });
+        let err = crate::apply_versioned(user(30), unversioned.to_string()).unwrap_err();
+        assert!(err.to_string().contains("no schema version"));
+
+        let newer = json!({ "schema_version": 3, "patch": { "age": 31 } });
+        let err = crate::apply_versioned(user(30), newer.to_string()).unwrap_err();
+        assert!(err.to_string().contains("schema version 3"));
+
+        // v1 used `name`, and the no-op migration above leaves it untouched.
+        let stale = json!({ "schema_version": 1, "patch": { "name": "bob", "age": 31 } });
+        let err = crate::apply_versioned(user(30), stale.to_string()).unwrap_err();
+        assert!(err.to_string().contains("name"));
+    }
+
+    #[test]
+    fn test_serde_with_adapters() {
+        use crate::{ApplyOptions, DiffOptions, PairsAsMap, Representation};
+        use serde_with::{DisplayFromStr, DurationSeconds, serde_as};
+        use std::collections::HashMap;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        #[serde_as]
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Service {
+            #[serde_as(as = "DisplayFromStr")]
+            port: u16,
+            #[serde_as(as = "DurationSeconds<u64>")]
+            timeout: Duration,
+            #[serde_as(as = "Vec<(_, _)>")]
+            labels: HashMap<String, String>,
+        }
+
+        let labels = |env: &str| -> HashMap<String, String> {
+            (0..16)
+                .map(|i| (format!("label{}", i), i.to_string()))
+                .chain([("env".to_string(), env.to_string())])
+                .collect()
+        };
+        let old = Service {
+            port: 8080,
+            timeout: Duration::from_secs(30),
+            labels: labels("dev"),
+        };
+        let new = Service {
+            port: 8081,
+            timeout: Duration::from_secs(30),
+            labels: labels("prod"),
+        };
+
+        let representations: crate::Representations = [(
+            "labels".to_string(),
+            Arc::new(PairsAsMap) as Arc<dyn Representation>,
+        )]
+        .into();
+        let diff_options = DiffOptions {
+            including: ["timeout".to_string(), "labels.label3".to_string()].into(),
+            representations: representations.clone(),
+            ..Default::default()
+        };
+
+        let patch = crate::diff_with(&old, &new, &diff_options).unwrap();
+        assert_eq!(
+            patch,
+            json!({
+                "port": "8081",
+                "timeout": 30,
+                "labels": { "env": "prod", "label3": "3" }
+            })
+        );
+
+        let apply_options = ApplyOptions {
+            representations,
+            ..Default::default()
+        };
+        let updated: Service = crate::apply_with(old, patch.to_string(), &apply_options).unwrap();
+        assert_eq!(updated, new);
+    }
+
+    #[test]
+    fn test_duration_comparator() {
+        use crate::{Comparator, DiffOptions, DurationComparator};
+        use std::sync::Arc;
+
+        let equivalent = [
+            (json!("90s"), json!("1m30s")),
+            (json!("1m 30s"), json!(90)),
+            (json!("1.5s"), json!(1.5)),
+            (json!("1500ms"), json!("1.5 seconds")),
+            (json!("1h"), json!("60m")),
+            (json!("2d"), json!("48h")),
+        ];
+        for (old, new) in &equivalent {
+            assert!(
+                DurationComparator.equivalent(old, new),
+                "{} vs {}",
+                old,
+                new
+            );
+        }
+        assert!(!DurationComparator.equivalent(&json!("90s"), &json!("1m31s")));
+        assert!(!DurationComparator.equivalent(&json!("soon"), &json!("later")));
+
+        let options = DiffOptions {
+            comparators: [(
+                "timeouts.read".to_string(),
+                Arc::new(DurationComparator) as Arc<dyn Comparator>,
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let old = json!({ "timeouts": { "read": "90s", "write": "10s" } });
+        let new = json!({ "timeouts": { "read": "1m30s", "write": "15s" } });
+        let patch = crate::diff_with(&old, &new, &options).unwrap();
+        assert_eq!(patch, json!({ "timeouts": { "write": "15s" } }));
+    }
+
+    #[test]
+    fn test_network_comparators() {
+        use crate::{CidrComparator, Comparator, DiffOptions, IpAddrComparator};
+        use std::sync::Arc;
+
+        let options = DiffOptions {
+            comparators: [
+                (
+                    "listen".to_string(),
+                    Arc::new(IpAddrComparator) as Arc<dyn Comparator>,
+                ),
+                (
+                    "allow".to_string(),
+                    Arc::new(CidrComparator) as Arc<dyn Comparator>,
+                ),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        let old = json!({
+            "listen": "::ffff:10.0.0.1",
+            "allow": ["10.0.0.0/8", "fd00::/8", "192.168.1.7"],
+        });
+        let new = json!({
+            "listen": "10.0.0.1",
+            "allow": ["10.20.30.40/8", "fd00:0:0:0:0:0:0:0/8", "192.168.1.7/32"],
+        });
+        assert_eq!(crate::diff_with(&old, &new, &options).unwrap(), json!({}));
+
+        let new = json!({
+            "listen": "10.0.0.2",
+            "allow": ["10.0.0.0/16", "fd00::/8", "192.168.1.7"],
+        });
+        assert_eq!(crate::diff_with(&old, &new, &options).unwrap(), new);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path_fields() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        use std::path::PathBuf;
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Index {
+            root: PathBuf,
+            files: Vec<PathBuf>,
+        }
+
+        #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+        struct LossyIndex {
+            #[serde(with = "crate::lossy_path")]
+            root: PathBuf,
+            count: u32,
+        }
+
+        let invalid = PathBuf::from(OsStr::from_bytes(b"/data/caf\xe9.txt"));
+
+        let old = Index {
+            root: PathBuf::from("/data"),
+            files: vec![PathBuf::from("/data/a.txt")],
+        };
+        let new = Index {
+            root: PathBuf::from("/data"),
+            files: vec![PathBuf::from("/data/a.txt"), invalid.clone()],
+        };
+        let err = crate::diff(&old, &new).unwrap_err();
+        assert!(err.to_string().contains("at `files[1]`"), "{}", err);
+
+        let old = LossyIndex {
+            root: PathBuf::from("/data"),
+            count: 1,
+        };
+        let new = LossyIndex {
+            root: invalid.clone(),
+            count: 1,
+        };
+        let patch = crate::diff(&old, &new).unwrap();
+        assert_eq!(patch, json!({ "root": b"/data/caf\xe9.txt".to_vec() }));
+        assert_eq!(crate::apply(old, &patch.to_string()).unwrap(), new);
+
+        // A patch that doesn't touch the path keeps its exact bytes.
+        let patched = crate::apply(new.clone(), r#"{ "count": 2 }"#).unwrap();
+        assert_eq!(patched.root, invalid);
+        assert_eq!(patched.count, 2);
+        let patched = crate::apply(new, r#"{ "root": "/data/b.txt" }"#).unwrap();
+        assert_eq!(patched.root, PathBuf::from("/data/b.txt"));
+    }
+
+    #[test]
+    fn test_embedded_json_comparator() {
+        use crate::{Comparator, DiffOptions, EmbeddedJson};
+        use std::sync::Arc;
+
+        let options = DiffOptions {
+            comparators: [(
+                "metadata".to_string(),
+                Arc::new(EmbeddedJson) as Arc<dyn Comparator>,
+            )]
+            .into(),
+            ..Default::default()
+        };
+
+        let old = json!({ "metadata": r#"{"b":[1,2],"a":"x"}"# });
+        let reformatted = json!({ "metadata": "{ \"a\": \"x\", \"b\": [1, 2] }" });
+        assert_eq!(
+            crate::diff_with(&old, &reformatted, &options).unwrap(),
+            json!({})
+        );
+
+        let changed = json!({ "metadata": r#"{"a":"y","b":[1,2]}"# });
+        assert_eq!(crate::diff_with(&old, &changed, &options).unwrap(), changed);
+
+        let not_json = json!({ "metadata": "plain text" });
+        assert_eq!(
+            crate::diff_with(&old, &not_json, &options).unwrap(),
+            not_json
+        );
+    }
+
+    #[test]
+    fn test_apply_report_lossy_conversions() {
+        use crate::{ApplyOptions, LossyConversion};
+
+        fn truncate<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+            Ok(f64::deserialize(deserializer)? as u8)
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Settings {
+            #[serde(deserialize_with = "truncate")]
+            volume: u8,
+            gain: f32,
+            ratio: f64,
+            name: String,
+        }
+
+        let current = Settings {
+            volume: 1,
+            gain: 0.5,
+            ratio: 1.0,
+            name: "default".to_string(),
+        };
+        let patch =
+            json!({ "volume": 3.999, "gain": 0.1, "ratio": 2, "name": "loud", "legacy": true });
+
+        let (updated, report) =
+            crate::apply_with_report(current, patch.to_string(), &ApplyOptions::default()).unwrap();
+        assert_eq!(updated.volume, 3);
+        // Reported in patch order, which `preserve-order` keeps.
+        let mut lossy = report.lossy;
+        lossy.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut expected = Vec::new();
+        // With `arbitrary-precision`, an `f32` serializes as its shortest
+        // decimal, so `0.1` reads back exactly.
+        if cfg!(not(feature = "arbitrary-precision")) {
+            expected.push(LossyConversion {
+                path: "gain".to_string(),
+                requested: json!(0.1),
+                applied: Some(json!(0.1_f32 as f64)),
+            });
+        }
+        expected.extend([
+            LossyConversion {
+                path: "legacy".to_string(),
+                requested: json!(true),
+                applied: None,
+            },
+            LossyConversion {
+                path: "volume".to_string(),
+                requested: json!(3.999),
+                applied: Some(json!(3)),
+            },
+        ]);
+        assert_eq!(lossy, expected);
+
+        let (_, report) = crate::apply_with_report(
+            updated,
+            r#"{ "ratio": 3, "volume": 4 }"#,
+            &ApplyOptions::default(),
+        )
+        .unwrap();
+        assert!(report.is_lossless());
+    }
+
+    #[test]
+    fn test_diff_explained() {
+        use crate::{Comparator, DiffEntry, DiffOptions, DiffReason, DurationComparator};
+        use std::sync::Arc;
+
+        let old = json!({
+            "id": 7,
+            "name": "svc",
+            "timeouts": { "read": "90s", "write": "10s" },
+            "owner": null,
+            "legacy": true,
+            "limits": { "cpu": 1 }
+        });
+        let new = json!({
+            "id": 7,
+            "name": "svc",
+            "timeouts": { "read": "1m30s", "write": "20s" },
+            "owner": { "team": "core" },
+            "tags": ["a"],
+            "limits": { "cpu": 1 }
+        });
+
+        let comparator: Arc<dyn Comparator> = Arc::new(DurationComparator);
+        let options = DiffOptions {
+            including: ["id".to_string(), "limits".to_string()].into(),
+            comparators: [
+                ("timeouts.read".to_string(), comparator.clone()),
+                ("timeouts.write".to_string(), comparator),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        let explained = crate::diff_explained(&old, &new, &options).unwrap();
+        assert_eq!(
+            explained.patch,
+            crate::diff_with(&old, &new, &options).unwrap()
+        );
+
+        let entry = |path: &str, reason| DiffEntry {
+            path: path.to_string(),
+            reason,
+        };
+        assert_eq!(
+            explained.entries,
+            vec![
+                entry("id", DiffReason::Forced),
+                entry("legacy", DiffReason::Removed),
+                entry("limits", DiffReason::Forced),
+                entry("owner", DiffReason::Changed),
+                entry("tags", DiffReason::Added),
+                entry("timeouts.read", DiffReason::ComparatorEquivalent),
+                entry("timeouts.write", DiffReason::ComparatorChanged),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge3_resolver() {
+        use crate::{Conflict, Resolution};
+
+        let user = |username: &str, age: u8, bio: &str| User {
+            id: 1001,
+            username: username.to_string(),
+            age,
+            active: true,
+            profile: Some(Profile {
+                bio: bio.to_string(),
+                avatar_url: None,
+            }),
+        };
+
+        let base = user("alice", 30, "Engineer");
+        let ours = user("alice_a", 31, "Senior engineer");
+        let theirs = user("alice_b", 31, "Staff engineer");
+
+        let mut seen = Vec::new();
+        let outcome = crate::merge3(&base, &ours, &theirs, |conflict| {
+            seen.push(conflict.path.clone());
+            match conflict.path.as_str() {
+                "username" => Resolution::Value(json!("alice_c")),
+                _ => Resolution::Defer,
+            }
+        })
+        .unwrap();
+
+        assert_eq!(seen, ["profile.bio", "username"]);
+        assert_eq!(outcome.merged, user("alice_c", 31, "Engineer"));
+        assert_eq!(
+            outcome.deferred,
+            vec![Conflict {
+                path: "profile.bio".to_string(),
+                base: Some(json!("Engineer")),
+                ours: Some(json!("Senior engineer")),
+                theirs: Some(json!("Staff engineer")),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_review_filters_patch() {
+        use crate::{Decision, Review};
+
+        let current = User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let patch = json!({
+            "age": 31,
+            "profile": { "avatar_url": "https://example.com/a.jpg", "bio": "Hi!" },
+            "username": "alice_2"
+        });
+
+        let mut review = Review::with_old(&patch, &current).unwrap();
+        let paths: Vec<_> = review.entries().iter().map(|e| e.path.as_str()).collect();
+        // `profile` is currently null, so it is reviewed as one replacement.
+        assert_eq!(paths, ["age", "profile", "username"]);
+        assert_eq!(review.entries()[0].old, Some(json!(30)));
+        assert_eq!(review.entries()[1].old, None);
+
+        assert_eq!(review.accept("profile"), 1);
+        review.reject("username");
+        assert!(!review.is_complete());
+        assert_eq!(
+            review.to_patch(),
+            json!({ "profile": { "bio": "Hi!", "avatar_url": "https://example.com/a.jpg" } })
+        );
+
+        review.accept_pending();
+        assert!(review.is_complete());
+        assert_eq!(review.entries()[0].decision, Decision::Accepted);
+
+        let updated = crate::apply(current, &review.to_patch().to_string()).unwrap();
+        assert_eq!(updated.age, 31);
+        assert_eq!(updated.username, "alice");
+
+        let mut review = Review::new(&patch);
+        assert_eq!(review.entries().len(), 4);
+        review.accept("profile");
+        review.reject("profile.avatar_url");
+        assert_eq!(review.to_patch(), json!({ "profile": { "bio": "Hi!" } }));
+
+        // Keys containing dots are escaped and matched segment by segment.
+        let mut review = Review::new(&json!({ "a.b": 1, "a": { "b": 2 } }));
+        let mut paths: Vec<_> = review.entries().iter().map(|e| e.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, ["a.b", "a\\.b"]);
+        assert_eq!(review.accept("a"), 1);
+        assert_eq!(review.accept("a\\.b"), 1);
+        assert_eq!(review.to_patch(), json!({ "a.b": 1, "a": { "b": 2 } }));
+
+        // An empty nested object is a change of its own and survives review.
+        let patch = json!({ "age": 31, "settings": {}, "profile": { "tags": {} } });
+        let mut review = Review::new(&patch);
+        let mut paths: Vec<_> = review.entries().iter().map(|e| e.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, ["age", "profile.tags", "settings"]);
+        review.accept_pending();
+        assert_eq!(review.to_patch(), patch);
+        let mut review = Review::with_old(&patch, &json!({ "age": 30, "profile": null })).unwrap();
+        assert_eq!(review.accept("settings"), 1);
+        assert_eq!(review.to_patch(), json!({ "settings": {} }));
+        assert!(Review::new(&json!({})).entries().is_empty());
+    }
+
+    #[test]
+    fn test_pending_patch_workflow() {
+        use crate::{ApprovalError, PatchState, PendingPatch};
+
+        let user = |age| User {
+            id: 1001,
+            username: "alice".to_string(),
+            age,
+            active: true,
+            profile: None,
+        };
+
+        let mut pending: PendingPatch<User> =
+            PendingPatch::new(json!({ "active": false }), &user(30), "user:1001").unwrap();
+        assert_eq!(
+            pending.base_version,
+            format!("{:016x}", crate::fingerprint(&user(30)).unwrap())
+        );
+        assert!(matches!(
+            pending.apply_approved(&user(30)),
+            Err(ApprovalError::InvalidState(PatchState::Pending))
+        ));
+
+        pending.approve("moderator:7").unwrap();
+        assert!(pending.reject("moderator:8", None).is_err());
+
+        // The document moved on after the patch was requested.
+        assert!(matches!(
+            pending.apply_approved(&user(31)),
+            Err(ApprovalError::VersionMismatch { .. })
+        ));
+
+        let updated = pending.apply_approved(&user(30)).unwrap();
+        assert!(!updated.active);
+        assert_eq!(
+            pending.state(),
+            &PatchState::Applied {
+                approved_by: "moderator:7".to_string()
+            }
+        );
+        assert!(pending.apply_approved(&user(30)).is_err());
+
+        let stored = serde_json::to_value(&pending).unwrap();
+        assert_eq!(
+            stored["state"],
+            json!({ "state": "applied", "approved_by": "moderator:7" })
+        );
+        let restored: PendingPatch<User> = serde_json::from_value(stored).unwrap();
+        assert_eq!(restored, pending);
+
+        // A version the caller tracks replaces the fingerprint.
+        let mut pending: PendingPatch<User, u64> =
+            PendingPatch::with_version(json!({ "age": 32 }), 4, "user:1001");
+        pending.approve("moderator:7").unwrap();
+        let err = pending
+            .apply_approved_with_version(&user(31), 5)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "document changed since the patch was requested (expected version 4, found 5)"
+        );
+        let updated = pending.apply_approved_with_version(&user(31), 4).unwrap();
+        assert_eq!(updated.age, 32);
+        let stored = serde_json::to_value(&pending).unwrap();
+        assert_eq!(stored["base_version"], json!(4));
+        let restored: PendingPatch<User, u64> = serde_json::from_value(stored).unwrap();
+        assert_eq!(restored, pending);
+    }
+
+    #[test]
+    fn test_audit_record_redacts() {
+        use crate::{AuditChange, FieldChange, REDACTED};
+        use std::time::{Duration, SystemTime};
+
+        let old = User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let new = User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 31,
+            active: true,
+            profile: Some(Profile {
+                bio: "Engineer".to_string(),
+                avatar_url: Some("https://example.com/a.jpg".to_string()),
+            }),
+        };
+
+        let at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let record = crate::audit_record(&old, AuditChange::New(&new), "admin", at).unwrap();
+        assert_eq!(record.actor, "admin");
+        assert_eq!(
+            record.changes,
+            vec![
+                FieldChange {
+                    path: "age".to_string(),
+                    before: Some(json!(30)),
+                    after: Some(json!(31)),
+                },
+                FieldChange {
+                    path: "profile".to_string(),
+                    before: None,
+                    after: Some(json!({ "bio": "Engineer", "avatar_url": REDACTED })),
+                },
+            ]
+        );
+
+        let patch = json!({ "profile": { "avatar_url": null } });
+        let record = crate::audit_record(&new, AuditChange::Patch(&patch), "admin", at).unwrap();
+        assert_eq!(
+            record.changes,
+            vec![FieldChange {
+                path: "profile.avatar_url".to_string(),
+                before: Some(json!(REDACTED)),
+                after: None,
+            }]
+        );
+
+        #[derive(Serialize, Deserialize)]
+        struct Hosts {
+            tokens: std::collections::BTreeMap<String, String>,
+        }
+        impl crate::Redact for Hosts {
+            const REDACTED_PATHS: &'static [&'static str] = &["tokens.example\\.com"];
+        }
+        let hosts = |token: &str| Hosts {
+            tokens: [
+                ("example.com".to_string(), token.to_string()),
+                ("example".to_string(), token.to_string()),
+            ]
+            .into(),
+        };
+        let record =
+            crate::audit_record(&hosts("a"), AuditChange::New(&hosts("b")), "admin", at).unwrap();
+        let afters: Vec<_> = record
+            .changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.after.clone()))
+            .collect();
+        assert_eq!(
+            afters,
+            [
+                ("tokens.example", Some(json!("b"))),
+                ("tokens.example\\.com", Some(json!(REDACTED))),
+            ]
+        );
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn test_otel_attributes() {
+        use opentelemetry::KeyValue;
+
+        let patch = json!({ "age": 31, "profile": { "avatar_url": null } });
+        assert_eq!(
+            crate::otel_attributes::<User>(&patch),
+            vec![
+                KeyValue::new("serde_patch.entity_type", "User"),
+                KeyValue::new("serde_patch.changed_paths", 2),
+                KeyValue::new("serde_patch.removed_paths", 1),
+                KeyValue::new("serde_patch.patch_bytes", patch.to_string().len() as i64),
+            ]
+        );
+        assert_eq!(
+            crate::otel_attributes::<Vec<User>>(&json!({}))[0],
+            KeyValue::new("serde_patch.entity_type", "Vec<serde_patch::tests::User>")
+        );
+    }
+
+    #[test]
+    fn test_coalescer_composes_patches() {
+        use crate::Coalescer;
+        use std::time::Duration;
+
+        let patches = [
+            json!({ "age": 31, "profile": { "bio": "Engineer", "avatar_url": "a.jpg" } }),
+            json!({ "profile": { "avatar_url": null } }),
+            json!({ "age": 32, "active": false }),
+        ];
+
+        let mut sent = Vec::new();
+        let mut coalescer = Coalescer::new(Duration::from_secs(3600), |patch| sent.push(patch));
+        for patch in &patches {
+            coalescer.push(patch.clone());
+        }
+        assert!(coalescer.has_pending());
+        assert!(!coalescer.poll());
+        assert!(coalescer.flush());
+        assert!(!coalescer.flush());
+        drop(coalescer);
+
+        let composed = json!({
+            "age": 32,
+            "active": false,
+            "profile": { "bio": "Engineer", "avatar_url": null }
+        });
+        assert_eq!(sent, vec![composed.clone()]);
+
+        let old = || User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let stepwise = patches.iter().fold(old(), |user, patch| {
+            crate::apply(user, &patch.to_string()).unwrap()
+        });
+        let at_once = crate::apply(old(), &composed.to_string()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&stepwise).unwrap(),
+            serde_json::to_value(&at_once).unwrap()
+        );
+
+        let mut sent = Vec::new();
+        {
+            let mut coalescer = Coalescer::new(Duration::ZERO, |patch| sent.push(patch));
+            coalescer.push(json!({ "age": 31 }));
+            coalescer.push(json!({ "age": 32 }));
+            assert!(!coalescer.has_pending());
+        }
+        assert_eq!(sent, vec![json!({ "age": 31 }), json!({ "age": 32 })]);
+    }
+
+    #[test]
+    fn test_batcher_bounds() {
+        use crate::Batcher;
+
+        let small = json!({ "age": 31 }); // 10 bytes
+        let large = json!({ "profile": { "bio": "x".repeat(64) } });
+
+        let mut batches = Vec::new();
+        {
+            let mut batcher = Batcher::new(3, 25, |batch| batches.push(batch));
+            batcher.push(small.clone());
+            batcher.push(small.clone());
+            assert_eq!((batcher.len(), batcher.bytes()), (2, 20));
+            batcher.push(small.clone()); // would exceed 25 bytes
+            assert_eq!(batcher.len(), 1);
+            batcher.push(large.clone()); // oversized, delivered alone
+            assert!(batcher.is_empty());
+            batcher.push(small.clone());
+            assert!(batcher.flush());
+            assert!(!batcher.flush());
+        }
+        assert_eq!(
+            batches,
+            vec![
+                vec![small.clone(), small.clone()],
+                vec![small.clone()],
+                vec![large],
+                vec![small.clone()],
+            ]
+        );
+
+        let mut batches = Vec::new();
+        {
+            let mut batcher = Batcher::new(2, usize::MAX, |batch| batches.push(batch));
+            for _ in 0..5 {
+                batcher.push(small.clone());
+            }
+        }
+        assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), [2, 2, 1]);
+    }
+
+    #[test]
+    fn test_diff_documents() {
+        use std::collections::BTreeMap;
+
+        let user = |id, age| User {
+            id,
+            username: format!("user{}", id),
+            age,
+            active: true,
+            profile: None,
+        };
+        let old = BTreeMap::from([(1, user(1, 30)), (2, user(2, 40)), (3, user(3, 50))]);
+        let new = BTreeMap::from([(1, user(1, 30)), (2, user(2, 41)), (4, user(4, 20))]);
+
+        let diff = crate::diff_documents(&old, &new).unwrap();
+        assert_eq!(diff.patches.keys().copied().collect::<Vec<_>>(), [2, 4]);
+        assert_eq!(diff.patches[&2], json!({ "age": 41 }));
+        assert_eq!(diff.patches[&4], serde_json::to_value(user(4, 20)).unwrap());
+        assert_eq!(diff.created.into_iter().collect::<Vec<_>>(), [4]);
+        assert_eq!(diff.deleted.into_iter().collect::<Vec<_>>(), [3]);
+
+        assert!(crate::diff_documents(&old, &old).unwrap().is_empty());
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn test_apply_json5_patch() {
+        let user = User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let patch = r#"{
+            // birthday
+            age: 31,
+            /* new profile */
+            profile: { bio: 'Engineer', avatar_url: null, },
+        }"#;
+
+        let updated = crate::apply_json5(user, patch).unwrap();
+        assert_eq!(updated.age, 31);
+        assert_eq!(updated.profile.unwrap().bio, "Engineer");
+
+        let err = crate::parse_json5("{ age: 31,, }").unwrap_err();
+        assert!(err.to_string().contains("1:"), "{}", err);
+    }
+
+    #[cfg(feature = "preserve")]
+    #[test]
+    fn test_preserve_formatting() {
+        let jsonc = r#"{
+  "id": 1001, // primary key
+  /* profile block */
+  "profile": { "bio": "Engineer", "avatar_url": "a.jpg" },
+  "age": 30
+}"#;
+        let patch = json!({
+            "age": null,
+            "active": true,
+            "profile": { "avatar_url": null, "bio": "Senior engineer" },
+            "tags": ["x"]
+        });
+        assert_eq!(
+            crate::preserve::apply_jsonc_preserving(jsonc, &patch).unwrap(),
+            r#"{
+  "id": 1001, // primary key
+  /* profile block */
+  "profile": { "bio": "Senior engineer" },
+  "active": true,
+  "tags": [
+    "x"
+  ]
+}"#
+        );
+
+        let yaml = "\
+id: 1001
+profile:
+  bio: Engineer   # shown publicly
+  avatar_url: 'a.jpg'
+limits: {\"burst\": 5, \"rpm\": 60}
+";
+        let patch = json!({
+            "profile": { "avatar_url": null, "links": { "site": "https://example.com" } },
+            "limits": { "burst": 10 },
+            "username": "true"
+        });
+        assert_eq!(
+            crate::preserve::apply_yaml_preserving(yaml, &patch).unwrap(),
+            "\
+id: 1001
+profile:
+  bio: Engineer   # shown publicly
+  links:
+    site: \"https://example.com\"
+limits: {\"burst\":10,\"rpm\":60}
+username: \"true\"
+"
+        );
+
+        // Values added inside inline objects stay inline.
+        assert_eq!(
+            crate::preserve::apply_jsonc_preserving(
+                "{\n  \"limits\": { \"rpm\": 60 }\n}",
+                &json!({ "limits": { "tiers": [1, 2] } })
+            )
+            .unwrap(),
+            "{\n  \"limits\": { \"rpm\": 60, \"tiers\": [1,2] }\n}"
+        );
+
+        // Mappings left without entries become `{}` rather than `null`.
+        assert_eq!(
+            crate::preserve::apply_yaml_preserving("# empty\nid: 1\n", &json!({ "id": null }))
+                .unwrap(),
+            "# empty\n{}\n"
+        );
+        assert_eq!(
+            crate::preserve::apply_yaml_preserving(
+                "id: 1\nprofile: # public\n  bio: x\n",
+                &json!({ "profile": { "bio": null } })
+            )
+            .unwrap(),
+            "id: 1\nprofile: {} # public\n"
+        );
+
+        // Comments above a member stay with it when a neighbour is removed,
+        // and go with it when it is removed itself.
+        let jsonc = "{\n  // first\n  \"a\": 1,\n  // second\n  \"b\": 2,\n  \"c\": 3 // third\n}";
+        assert_eq!(
+            crate::preserve::apply_jsonc_preserving(jsonc, &json!({ "b": null })).unwrap(),
+            "{\n  // first\n  \"a\": 1,\n  \"c\": 3 // third\n}"
+        );
+        assert_eq!(
+            crate::preserve::apply_jsonc_preserving(jsonc, &json!({ "a": null, "c": 4 })).unwrap(),
+            "{\n  // second\n  \"b\": 2,\n  \"c\": 4 // third\n}"
+        );
+        let yaml = "# config\n\n# first\na: 1\n# second\nb: 2\nc: 3 # third\nd:\n  e: 1\n  # about f\n  f: 2\n";
+        assert_eq!(
+            crate::preserve::apply_yaml_preserving(yaml, &json!({ "b": null, "d": { "e": null } }))
+                .unwrap(),
+            "# config\n\n# first\na: 1\nc: 3 # third\nd:\n  # about f\n  f: 2\n"
+        );
+        assert_eq!(
+            crate::preserve::apply_yaml_preserving(yaml, &json!({ "a": null, "d": { "f": null } }))
+                .unwrap(),
+            "# config\n\n# second\nb: 2\nc: 3 # third\nd:\n  e: 1\n"
+        );
+
+        assert!(crate::preserve::apply_jsonc_preserving("{ \"a\": 1,, }", &json!({})).is_err());
+        assert!(crate::preserve::apply_yaml_preserving("- a\n- b\n", &json!({ "a": 1 })).is_err());
+    }
+
+    #[cfg(feature = "toml-edit")]
+    #[test]
+    fn test_preserve_toml() {
+        let toml = r#"# user record
+id = 1001
+age = 30 # years
+
+[profile]
+bio = "Engineer"
+links = { site = "https://a.example" }
+"#;
+        let patch = json!({
+            "age": 31,
+            "active": true,
+            "profile": { "links": { "site": null, "blog": "https://b.example" } },
+            "limits": { "rpm": 60 }
+        });
+        assert_eq!(
+            crate::preserve::apply_toml_preserving(toml, &patch).unwrap(),
+            r#"# user record
+id = 1001
+age = 31 # years
+active = true
+
+[profile]
+bio = "Engineer"
+links = { blog = "https://b.example" }
+
+[limits]
+rpm = 60
+"#
+        );
+
+        assert!(crate::preserve::apply_toml_preserving(toml, &json!({ "tags": [null] })).is_err());
+    }
+
+    #[test]
+    fn test_raw_patch_forwarding() {
+        use crate::RawPatch;
+
+        let body = r#"{"age":31,  "profile": {"bio": "Engineer", "avatar_url": null}}"#;
+        let raw: RawPatch = serde_json::from_str(body).unwrap();
+
+        assert_eq!(
+            raw.paths().unwrap(),
+            ["age", "profile.avatar_url", "profile.bio"]
+        );
+        assert!(raw.touches("profile").unwrap());
+        assert!(!raw.touches("age.years").unwrap());
+        assert_eq!(
+            raw.at("profile.bio").unwrap().unwrap().get(),
+            r#""Engineer""#
+        );
+
+        // Forwarding keeps the original text.
+        assert_eq!(serde_json::to_string(&raw).unwrap(), body);
+
+        let user = User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let updated = crate::apply_with(user, &raw, &Default::default()).unwrap();
+        assert_eq!(updated.age, 31);
+        assert_eq!(raw.to_value().unwrap()["profile"]["bio"], "Engineer");
+        let patch = raw.to_patch().unwrap();
+        assert_eq!(patch.paths(), raw.paths().unwrap());
+        assert_eq!(crate::Patch::try_from(&raw).unwrap(), patch);
+
+        // Nested empty objects are leaves, as in `Patch::paths`.
+        let nested = r#"{"profile": {}, "settings": {"theme": {}}}"#;
+        let raw = RawPatch::from_string(nested.to_string()).unwrap();
+        assert_eq!(raw.paths().unwrap(), ["profile", "settings.theme"]);
+        assert_eq!(raw.paths().unwrap(), raw.to_patch().unwrap().paths());
+        assert!(
+            RawPatch::from_string("{}".to_string())
+                .unwrap()
+                .paths()
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(RawPatch::from_string("{ nope".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_store_revisions_and_listeners() {
+        use crate::Store;
+        use std::sync::{Arc, Mutex};
+
+        let store = Arc::new(Store::new(User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        }));
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let reader = store.clone();
+        let id = store.subscribe(move |revision, patch| {
+            // Listeners may read the store.
+            assert!(reader.revision() >= revision);
+            sink.lock().unwrap().push((revision, patch.clone()));
+        });
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let store = store.clone();
+                std::thread::spawn(move || store.apply(r#"{ "active": false }"#).unwrap())
+            })
+            .collect();
+        for worker in workers {
+            assert!(worker.join().unwrap().is_lossless());
+        }
+        assert_eq!(store.revision(), 4);
+
+        let report = store.apply(r#"{ "age": 300 }"#);
+        assert!(report.is_err());
+        assert_eq!(store.revision(), 4);
+        assert_eq!(store.read(|user| user.age), 30);
+
+        assert!(store.unsubscribe(id));
+        assert!(!store.unsubscribe(id));
+        store.apply(r#"{ "age": 31 }"#).unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(
+            seen.iter()
+                .map(|(revision, _)| *revision)
+                .collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+        assert!(
+            seen.iter()
+                .all(|(_, patch)| patch == &json!({ "active": false }))
+        );
+    }
+
+    #[test]
+    fn test_store_listener_reads_while_another_thread_applies() {
+        use crate::Store;
+        use std::sync::mpsc;
+        use std::sync::{Arc, Mutex};
+        use std::time::Duration;
+
+        let store = Arc::new(Store::new(User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        }));
+
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (go_tx, go_rx) = mpsc::channel::<()>();
+        let go_rx = Mutex::new(go_rx);
+        let reader = store.clone();
+        let ages = Arc::new(Mutex::new(Vec::new()));
+        let sink = ages.clone();
+        store.subscribe(move |revision, _| {
+            if revision == 1 {
+                entered_tx.send(()).unwrap();
+                go_rx.lock().unwrap().recv().unwrap();
+            }
+            // Reading must not wait on a writer that is queued behind us.
+            sink.lock()
+                .unwrap()
+                .push((revision, reader.read(|user| user.age)));
+        });
+
+        let first = {
+            let store = store.clone();
+            std::thread::spawn(move || store.apply(r#"{ "age": 31 }"#).unwrap())
+        };
+        entered_rx.recv().unwrap();
+        let second = {
+            let store = store.clone();
+            std::thread::spawn(move || store.apply(r#"{ "age": 32 }"#).unwrap())
+        };
+        // Give the second writer time to update the value and queue its notification.
+        std::thread::sleep(Duration::from_millis(50));
+        go_tx.send(()).unwrap();
+
+        first.join().unwrap();
+        second.join().unwrap();
+        let ages = ages.lock().unwrap();
+        assert_eq!(
+            ages.iter()
+                .map(|(revision, _)| *revision)
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(ages[1], (2, 32));
+        assert_eq!(store.revision(), 2);
+    }
+
+    #[test]
+    fn test_diff_from_default() {
+        #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+        struct Settings {
+            theme: Option<String>,
+            font_size: u8,
+            profile: Option<Profile>,
+        }
+
+        let settings = Settings {
+            theme: Some("dark".to_string()),
+            font_size: 0,
+            profile: Some(Profile {
+                bio: String::new(),
+                avatar_url: None,
+            }),
+        };
+        let overrides = crate::diff_from_default(&settings).unwrap();
+        assert_eq!(
+            overrides,
+            json!({ "theme": "dark", "profile": { "bio": "", "avatar_url": null } })
+        );
+        assert_eq!(
+            crate::diff_from_default(&Settings::default()).unwrap(),
+            json!({})
+        );
+
+        let restored = crate::apply(Settings::default(), &overrides.to_string()).unwrap();
+        assert_eq!(restored, settings);
+    }
+
+    #[test]
+    fn test_from_patch_over_default() {
+        #[derive(Debug, Default, Serialize, Deserialize)]
+        struct NewUser {
+            username: String,
+            age: u8,
+            profile: Option<Profile>,
+        }
+
+        let user: NewUser =
+            crate::from_patch(r#"{ "username": "alice", "profile": { "bio": "Engineer" } }"#)
+                .unwrap();
+        assert_eq!(user.username, "alice");
+        assert_eq!(user.age, 0);
+        assert_eq!(user.profile.unwrap().bio, "Engineer");
+
+        let err = crate::from_patch::<NewUser, _>(r#"{ "profile": { "avatar_url": "a.jpg" } }"#)
+            .err()
+            .unwrap();
+        assert!(matches!(err, crate::Error::Deserialize { .. }));
+        assert_eq!(err.pointer(), Some("/profile/bio"));
+        assert!(
+            err.to_string().starts_with("missing field `bio`"),
+            "{}",
+            err
+        );
+
+        let err = crate::from_patch::<NewUser, _>(r#"{ "username": null }"#)
+            .err()
+            .unwrap();
+        assert!(matches!(err, crate::Error::InvalidRemoval { .. }));
+        assert_eq!(err.pointer(), Some("/username"));
+
+        let err = crate::from_patch::<NewUser, _>("{ nope").err().unwrap();
+        assert!(matches!(err, crate::Error::ParsePatch(_)));
+    }
+
+    #[test]
+    fn test_patch_error_locations() {
+        use crate::ApplyOptions;
+
+        let user = || User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+
+        let err = crate::parse_patch("{\n  \"age\": 31\n  \"active\": false\n}").unwrap_err();
+        assert!(err.is_syntax());
+        let location = err.location.as_ref().unwrap();
+        assert_eq!(
+            (location.line, location.column, location.offset),
+            (3, 3, 16)
+        );
+        assert_eq!(err.path, None);
+        assert_eq!(
+            err.to_string(),
+            "expected `,` or `}` (line 3 column 3)\n  \"active\": false\n  ^"
+        );
+
+        let patch = r#"{ "profile": { "bio": 42 } }"#;
+        let err = crate::apply_with_diagnostics(user(), patch, &ApplyOptions::default())
+            .err()
+            .unwrap();
+        assert!(!err.is_syntax());
+        assert_eq!(err.path.as_deref(), Some("profile.bio"));
+        assert_eq!(err.location.unwrap().offset, 15);
+
+        let patch = r#"{ "profile": { "avatar_url": "a.jpg" } }"#;
+        let err = crate::apply_with_diagnostics(user(), patch, &ApplyOptions::default())
+            .err()
+            .unwrap();
+        assert!(err.message.starts_with("missing field `bio`"));
+        assert_eq!(err.path.as_deref(), Some("profile"));
+        assert_eq!(err.location.unwrap().offset, 2);
+
+        let updated =
+            crate::apply_with_diagnostics(user(), r#"{ "age": 31 }"#, &ApplyOptions::default())
+                .unwrap();
+        assert_eq!(updated.age, 31);
+    }
+
+    #[test]
+    fn test_apply_collecting_field_errors() {
+        use crate::{ApplyOptions, FieldErrorKind, Validator};
+        use std::sync::Arc;
+
+        #[derive(Debug)]
+        struct NonEmpty;
+
+        impl Validator for NonEmpty {
+            fn validate(&self, value: &serde_json::Value) -> Result<(), String> {
+                match value.as_str() {
+                    Some("") | None => Err("must not be empty".to_string()),
+                    Some(_) => Ok(()),
+                }
+            }
+        }
+
+        let user = || User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: None,
+            }),
+        };
+        let options = ApplyOptions {
+            protected: ["id".to_string()].into(),
+            validators: [("username".to_string(), Arc::new(NonEmpty) as _)].into(),
+            ..Default::default()
+        };
+
+        let patch = r#"{ "id": 7, "username": "", "age": "old", "profile": { "bio": null } }"#;
+        let errors = crate::apply_collecting(user(), patch, &options).unwrap_err();
+        let found: Vec<_> = errors.iter().map(|e| (e.path.as_str(), e.kind)).collect();
+        assert_eq!(
+            found,
+            [
+                ("age", FieldErrorKind::Type),
+                ("id", FieldErrorKind::Protected),
+                ("profile.bio", FieldErrorKind::Type),
+                ("username", FieldErrorKind::Invalid),
+            ]
+        );
+        assert_eq!(errors[3].to_string(), "must not be empty at `username`");
+
+        // Protected ancestors count too, and apply_with stops at the first failure.
+        let err = crate::apply_with(user(), r#"{ "id": 7 }"#, &options).unwrap_err();
+        assert_eq!(err.to_string(), "field is protected at `id`");
+
+        let errors = crate::apply_collecting(user(), "{ oops", &options).unwrap_err();
+        assert_eq!(errors[0].kind, FieldErrorKind::Patch);
+
+        let updated = crate::apply_collecting(user(), r#"{ "age": 31 }"#, &options).unwrap();
+        assert_eq!(updated.age, 31);
+    }
+
+    #[test]
+    fn test_adversarial_patches_return_errors() {
+        use crate::ApplyOptions;
+
+        let user = || User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+
+        // Nesting beyond the parser's recursion limit.
+        let deep = format!("{}{}", "{\"a\":".repeat(10_000), "}".repeat(10_000));
+        assert!(crate::apply(user(), &deep).is_err());
+        assert!(crate::apply_with(user(), &deep, &ApplyOptions::default()).is_err());
+
+        // Patches of the wrong shape at the root or below.
+        for patch in [
+            "null",
+            "[]",
+            "42",
+            r#"{ "profile": 7 }"#,
+            r#"{ "profile": [] }"#,
+        ] {
+            assert!(crate::apply(user(), patch).is_err(), "{}", patch);
+            let mut target = user();
+            assert!(crate::apply_mut(&mut target, patch).is_err(), "{}", patch);
+            assert_eq!(target, user());
+        }
+
+        // Object patches over non-object fields replace them.
+        let err = crate::apply(user(), r#"{ "age": { "years": 31 } }"#).unwrap_err();
+        assert!(matches!(err, crate::Error::Deserialize { .. }));
+    }
+
+    #[test]
+    fn test_backend_parses_like_serde_json() {
+        let user = || User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+
+        let patch = r#"{ "username": "al\"ice é😀", "profile": { "bio": "line\nbreak", "avatar_url": null } }"#;
+        let expected: serde_json::Value = serde_json::from_str(patch).unwrap();
+        let parsed: serde_json::Value = crate::backend::from_slice(patch.as_bytes()).unwrap();
+        assert_eq!(parsed, expected);
+
+        let updated = crate::apply(user(), patch).unwrap();
+        assert_eq!(updated.username, "al\"ice é😀");
+        assert_eq!(updated.profile.unwrap().bio, "line\nbreak");
+
+        // Errors are serde_json's, whatever the backend.
+        let bad = "{\n  \"age\": 31,,\n}";
+        let expected = serde_json::from_str::<serde_json::Value>(bad).unwrap_err();
+        let Err(crate::Error::ParsePatch(err)) = crate::apply(user(), bad) else {
+            panic!("expected a parse error");
+        };
+        assert_eq!(err.to_string(), expected.to_string());
+        assert_eq!((err.line(), err.column()), (2, 13));
+    }
+
+    #[test]
+    fn test_json_patch_ops() {
+        use crate::json_patch::{Op, apply_ops, diff_ops};
+
+        let old = User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+        let new = || User {
+            id: 1001,
+            username: "alice/a~b".to_string(),
+            age: 31,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: None,
+            }),
+        };
+
+        // Sorted by path, since `preserve-order` emits them in field order.
+        let mut ops = diff_ops(&old, &new()).unwrap();
+        ops.sort_by_key(|op| serde_json::to_string(op).unwrap());
+        assert_eq!(
+            ops,
+            [
+                Op::Replace {
+                    path: "/age".into(),
+                    value: json!(31)
+                },
+                Op::Replace {
+                    path: "/profile/avatar_url".into(),
+                    value: json!(null)
+                },
+                Op::Replace {
+                    path: "/username".into(),
+                    value: json!("alice/a~b")
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&ops[0]).unwrap(),
+            json!({ "op": "replace", "path": "/age", "value": 31 })
+        );
+        assert_eq!(apply_ops(old, &ops).unwrap(), new());
+
+        // Array elements, move/copy, and atomic failure.
+        let list = |items: &[i32]| json!({ "items": items, "keep": 1 });
+        let ops = diff_ops(&list(&[1, 2, 3, 4]), &list(&[1, 9, 4, 5])).unwrap();
+        let patched = apply_ops(list(&[1, 2, 3, 4]), &ops).unwrap();
+        assert_eq!(patched, list(&[1, 9, 4, 5]));
+
+        let ops: Vec<Op> = serde_json::from_value(json!([
+            { "op": "copy", "from": "/items/0", "path": "/first" },
+            { "op": "move", "from": "/keep", "path": "/items/-" },
+            { "op": "test", "path": "/first", "value": 1.0 }
+        ]))
+        .unwrap();
+        let patched = apply_ops(list(&[1, 2]), &ops).unwrap();
+        assert_eq!(patched, json!({ "items": [1, 2, 1], "first": 1 }));
+
+        let failing = [
+            Op::Remove {
+                path: "/keep".into(),
+            },
+            Op::Test {
+                path: "/items/0".into(),
+                value: json!(7),
+            },
+        ];
+        let err = apply_ops(list(&[1]), &failing).unwrap_err();
+        assert_eq!(err.to_string(), "test failed at `/items/0` (operation 1)");
+        let err = apply_ops(
+            list(&[1]),
+            &[Op::Remove {
+                path: "/items/01".into(),
+            }],
+        )
+        .unwrap_err();
+        assert!(err.to_string().starts_with("invalid array index"));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_patch() {
+        use crate::Patch;
+
+        #[derive(Debug, PartialEq, Patch, Serialize, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        #[patch(name = "AccountChanges", derive(Debug, PartialEq))]
+        struct Account {
+            display_name: String,
+            #[serde(rename = "mail")]
+            email: String,
+            tags: Vec<String>,
+        }
+
+        let mut account = Account {
+            display_name: "Alice".to_string(),
+            email: "a@example.com".to_string(),
+            tags: vec![],
+        };
+        let changes: AccountChanges =
+            serde_json::from_str(r#"{ "displayName": "Al", "mail": "al@example.com" }"#).unwrap();
+        assert_eq!(changes.tags, None);
+        account.apply_patch(changes);
+        assert_eq!(account.display_name, "Al");
+        assert_eq!(account.email, "al@example.com");
+
+        // Serialized patches are merge patches.
+        let changes = AccountChanges {
+            tags: Some(vec!["admin".to_string()]),
+            ..Default::default()
+        };
+        let patch = serde_json::to_string(&changes).unwrap();
+        assert_eq!(patch, r#"{"tags":["admin"]}"#);
+        let account = crate::apply(account, &patch).unwrap();
+        assert_eq!(account.tags, ["admin"]);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_patch_double_option() {
+        use crate::Patch;
+
+        #[derive(Debug, Default, PartialEq, Patch, Serialize, Deserialize)]
+        struct Contact {
+            name: String,
+            phone: Option<String>,
+        }
+
+        let contact = || Contact {
+            name: "alice".to_string(),
+            phone: Some("555".to_string()),
+        };
+
+        let untouched: ContactPatch = serde_json::from_str(r#"{ "name": "al" }"#).unwrap();
+        assert_eq!(untouched.phone, None);
+        let cleared: ContactPatch = serde_json::from_str(r#"{ "phone": null }"#).unwrap();
+        assert_eq!(cleared.phone, Some(None));
+
+        let mut target = contact();
+        target.apply_patch(untouched);
+        assert_eq!(target.phone.as_deref(), Some("555"));
+        target.apply_patch(cleared);
+        assert_eq!(target.phone, None);
+
+        // Typed patches serialize to the merge patch `diff` produces.
+        let patch = ContactPatch {
+            phone: Some(None),
+            ..Default::default()
+        };
+        let expected = crate::diff(
+            &contact(),
+            &Contact {
+                phone: None,
+                ..contact()
+            },
+        )
+        .unwrap();
+        assert_eq!(serde_json::to_value(&patch).unwrap(), expected);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_patch_serde_options() {
+        use crate::Patch;
+
+        #[derive(Debug, PartialEq, Patch, Serialize, Deserialize)]
+        #[patch(derive(Debug, PartialEq))]
+        struct Ledger {
+            #[serde(with = "crate::wide_int")]
+            balance: u128,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            note: Option<String>,
+            #[serde(skip)]
+            cache: u32,
+        }
+
+        // `with` applies to set values, unset fields are still skipped.
+        let patch = LedgerPatch {
+            balance: Some(u128::MAX),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&patch).unwrap();
+        assert_eq!(json, json!({ "balance": u128::MAX.to_string() }));
+        assert_eq!(serde_json::from_value::<LedgerPatch>(json).unwrap(), patch);
+        let patch: LedgerPatch = serde_json::from_str(r#"{ "balance": 7, "note": null }"#).unwrap();
+        assert_eq!(
+            patch,
+            LedgerPatch {
+                balance: Some(7),
+                note: Some(None),
+            }
+        );
+
+        let mut ledger = Ledger {
+            balance: 1,
+            note: Some("n".to_string()),
+            cache: 3,
+        };
+        ledger.apply_patch(patch);
+        assert_eq!((ledger.balance, ledger.note, ledger.cache), (7, None, 3));
+
+        // Generic patch structs don't require `T: Default`.
+        struct NoDefault;
+        #[derive(Patch, Serialize, Deserialize)]
+        struct Wrapper<T> {
+            value: T,
+        }
+        let mut wrapper = Wrapper { value: NoDefault };
+        let patch = WrapperPatch::<NoDefault>::default();
+        assert!(patch.is_empty());
+        wrapper.apply_patch(patch);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_patch_redact() {
+        use crate::{AuditChange, Patch, REDACTED, Redact};
+        use std::time::SystemTime;
+
+        #[derive(Patch, Serialize, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Account {
+            email: String,
+            #[patch(redact)]
+            password_hash: String,
+            #[patch(redact)]
+            #[serde(rename = "token")]
+            api_token: Option<String>,
+        }
+
+        assert_eq!(Account::REDACTED_PATHS, ["passwordHash", "token"]);
+
+        let old = Account {
+            email: "a@example.com".to_string(),
+            password_hash: "old".to_string(),
+            api_token: None,
+        };
+        let patch = json!({ "email": "b@example.com", "passwordHash": "new", "token": "t" });
+        let record =
+            crate::audit_record(&old, AuditChange::Patch(&patch), "admin", SystemTime::now())
+                .unwrap();
+        let values: Vec<_> = record
+            .changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.after.clone()))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("email", Some(json!("b@example.com"))),
+                ("passwordHash", Some(json!(REDACTED))),
+                ("token", Some(json!(REDACTED))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_error_pointers() {
+        use crate::Error;
+
+        let user = || User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: None,
+            }),
+        };
+
+        let err = crate::apply(user(), r#"{ "profile": { "bio": [] } }"#).unwrap_err();
+        assert!(matches!(err, Error::Deserialize { .. }));
+        assert_eq!(err.pointer(), Some("/profile/bio"));
+        assert!(err.to_string().ends_with(" at `/profile/bio`"));
+
+        let err = crate::apply(user(), r#"{ "username": null }"#).unwrap_err();
+        assert!(matches!(&err, Error::InvalidRemoval { pointer } if pointer == "/username"));
+
+        let mut target = user();
+        let err = crate::apply_mut(&mut target, r#"{ "age": 300 }"#).unwrap_err();
+        assert_eq!(err.pointer(), Some("/age"));
+        assert_eq!(target, user());
+
+        let err = crate::apply_mut(&mut target, "{").unwrap_err();
+        assert!(matches!(err, Error::ParsePatch(_)));
+        assert_eq!(err.pointer(), None);
+        assert!(std::error::Error::source(&err).is_some());
+
+        // Still usable where a serde_json::Error is expected.
+        let converted: serde_json::Error = crate::apply(user(), "[").unwrap_err().into();
+        assert!(converted.is_eof());
+
+        // Option-driven entry points report the same errors.
+        let options = crate::ApplyOptions {
+            deny_unknown_fields: true,
+            ..Default::default()
+        };
+        let err = crate::apply_with(user(), r#"{ "age": "old" }"#, &options).unwrap_err();
+        assert_eq!(err.pointer(), Some("/age"));
+        let err = crate::apply_with(user(), r#"{ "nickname": "al" }"#, &options).unwrap_err();
+        assert!(matches!(err, Error::Rejected(_)));
+        assert_eq!(err.to_string(), "unknown field `nickname`");
+
+        let mut target = user();
+        let err = crate::Patcher::new()
+            .apply_mut(&mut target, r#"{ "profile": { "bio": null } }"#)
+            .unwrap_err();
+        assert!(matches!(&err, Error::InvalidRemoval { pointer } if pointer == "/profile/bio"));
+
+        let store = crate::Store::new(user());
+        let err = store.apply(r#"{ "age": 300 }"#).unwrap_err();
+        assert_eq!(err.pointer(), Some("/age"));
+    }
+
+    #[test]
+    fn test_array_strategies() {
+        use crate::{ApplyOptions, ArrayStrategy, DiffOptions};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Item {
+            id: String,
+            qty: u32,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Order {
+            items: Vec<Item>,
+            tags: Vec<String>,
+        }
+
+        let item = |id: &str, qty| Item {
+            id: id.to_string(),
+            qty,
+        };
+        let old = || Order {
+            items: vec![item("a", 1), item("b", 2), item("c", 3)],
+            tags: vec!["x".to_string(), "y".to_string()],
+        };
+        let new = || Order {
+            items: vec![item("c", 3), item("d", 4), item("a", 5)],
+            tags: vec!["x".to_string(), "z".to_string(), "w".to_string()],
+        };
+        let roundtrip = |strategy: ArrayStrategy| {
+            let diff_options = DiffOptions {
+                array_strategy: strategy.clone(),
+                ..Default::default()
+            };
+            let patch = crate::diff_with(&old(), &new(), &diff_options).unwrap();
+            let apply_options = ApplyOptions {
+                array_strategy: strategy,
+                ..Default::default()
+            };
+            let applied = crate::apply_with(old(), patch.to_string(), &apply_options).unwrap();
+            assert_eq!(applied, new());
+            patch
+        };
+
+        let patch = roundtrip(ArrayStrategy::ByIndex);
+        assert_eq!(
+            patch["tags"],
+            json!({ "$items": { "1": "z", "2": "w" }, "$len": 3 })
+        );
+        assert_eq!(
+            patch["items"]["$items"]["0"],
+            json!({ "id": "c", "qty": 3 })
+        );
+
+        let patch = roundtrip(ArrayStrategy::Keyed("id".to_string()));
+        assert_eq!(
+            patch["items"],
+            json!({
+                "$key": "id",
+                "$items": { "a": { "qty": 5 }, "b": null, "d": { "id": "d", "qty": 4 } },
+                "$added": ["d"],
+                "$order": ["c", "d", "a"]
+            })
+        );
+        // Strings have no key field: the array is replaced.
+        assert_eq!(patch["tags"], json!(["x", "z", "w"]));
+
+        let patch = roundtrip(ArrayStrategy::Replace);
+        assert!(patch["items"].is_array());
+
+        // Markers that don't fit the current array are rejected.
+        let options = ApplyOptions {
+            array_strategy: ArrayStrategy::ByIndex,
+            ..Default::default()
+        };
+        let bad = r#"{ "tags": { "$items": { "5": "q" } } }"#;
+        let err = crate::apply_with(old(), bad, &options).unwrap_err();
+        assert_eq!(err.to_string(), "invalid array patch at `tags`");
+    }
+
+    #[test]
+    fn test_keyed_array_diff() {
+        use crate::{ApplyOptions, DiffOptions};
+        use std::collections::HashMap;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Member {
+            id: u32,
+            role: String,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Label {
+            name: String,
+            color: String,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Team {
+            members: Vec<Member>,
+            labels: Vec<Label>,
+        }
+
+        let member = |id, role: &str| Member {
+            id,
+            role: role.to_string(),
+        };
+        let label = |name: &str, color: &str| Label {
+            name: name.to_string(),
+            color: color.to_string(),
+        };
+        let old = || Team {
+            members: vec![member(1, "owner"), member(2, "dev"), member(3, "dev")],
+            labels: vec![label("bug", "red")],
+        };
+        // A member inserted in the middle and another one promoted.
+        let new = || Team {
+            members: vec![
+                member(1, "owner"),
+                member(4, "dev"),
+                member(2, "lead"),
+                member(3, "dev"),
+            ],
+            labels: vec![label("bug", "orange"), label("docs", "blue")],
+        };
+
+        let keys: HashMap<String, String> = [
+            ("members".to_string(), "id".to_string()),
+            ("labels".to_string(), "name".to_string()),
+        ]
+        .into();
+        let diff_options = DiffOptions {
+            array_keys: keys.clone(),
+            ..Default::default()
+        };
+        let patch = crate::diff_with(&old(), &new(), &diff_options).unwrap();
+        assert_eq!(
+            patch,
+            json!({
+                "members": {
+                    "$key": "id",
+                    "$items": { "2": { "role": "lead" }, "4": { "id": 4, "role": "dev" } },
+                    "$added": ["4"],
+                    "$order": ["1", "4", "2", "3"]
+                },
+                "labels": {
+                    "$key": "name",
+                    "$items": { "bug": { "color": "orange" }, "docs": { "name": "docs", "color": "blue" } },
+                    "$added": ["docs"]
+                }
+            })
+        );
+
+        let apply_options = ApplyOptions {
+            array_keys: keys,
+            ..Default::default()
+        };
+        let applied = crate::apply_with(old(), patch.to_string(), &apply_options).unwrap();
+        assert_eq!(applied, new());
+
+        // Member 2 left in the meantime: the promotion can't be applied.
+        let mut concurrent = old();
+        concurrent.members.remove(1);
+        let err = crate::apply_with(concurrent, patch.to_string(), &apply_options).unwrap_err();
+        assert_eq!(err.to_string(), "element `2` no longer exists at `members`");
+
+        // The marker's key field must match the configured one.
+        let mut wrong_key = patch.clone();
+        wrong_key["labels"]["$key"] = json!("color");
+        assert!(crate::apply_with(old(), wrong_key.to_string(), &apply_options).is_err());
+    }
+
+    #[test]
+    fn test_differ_builder() {
+        let old = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+        let new = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 31,
+            active: false,
+            profile: Some(Profile {
+                bio: "hello".to_string(),
+                avatar_url: None,
+            }),
+        };
+
+        let differ = crate::Differ::new()
+            .force("id")
+            .ignore("active")
+            .ignore("profile.avatar_url");
+
+        // Ignored fields are left out whether changed or removed.
+        let patch = differ.diff(&old, &new).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "id": 1, "age": 31, "profile": { "bio": "hello" } })
+        );
+
+        // The same differ can be reused, and matches the equivalent options.
+        assert_eq!(differ.diff(&new, &old).unwrap()["age"], json!(30));
+        assert_eq!(
+            crate::diff_with(&old, &new, differ.options()).unwrap(),
+            patch
+        );
+    }
+
+    #[test]
+    fn test_patcher_builder() {
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+
+        let strict = crate::Patcher::new().deny_unknown_fields().deny("id");
+        let err = strict
+            .apply(
+                user(),
+                r#"{ "nick": "al", "profile": { "banner": "b.png" } }"#,
+            )
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "unknown fields `nick`, `profile.banner`");
+        assert!(strict.apply(user(), r#"{ "id": 2 }"#).is_err());
+
+        // Nulls left alone instead of clearing the avatar.
+        let lenient = crate::Patcher::new().nulls(crate::NullSemantics::Ignore);
+        let mut current = user();
+        lenient
+            .apply_mut(
+                &mut current,
+                r#"{ "age": 31, "profile": { "avatar_url": null } }"#,
+            )
+            .unwrap();
+        assert_eq!(current.age, 31);
+        assert_eq!(
+            current.profile.unwrap().avatar_url.as_deref(),
+            Some("a.png")
+        );
+
+        let shallow = crate::Patcher::new().max_depth(1);
+        assert!(shallow.apply(user(), r#"{ "age": 31 }"#).is_ok());
+        let err = shallow
+            .apply(user(), r#"{ "profile": { "bio": "x" } }"#)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "patch nesting exceeds the maximum depth of 1"
+        );
+    }
+
+    #[test]
+    fn test_diff_excluding() {
+        let old = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+        let new = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 31,
+            active: true,
+            profile: Some(Profile {
+                bio: "hello".to_string(),
+                avatar_url: None,
+            }),
+        };
+
+        let patch = crate::diff_excluding(&old, &new, &["age", "profile.avatar_url"]).unwrap();
+        assert_eq!(patch, json!({ "profile": { "bio": "hello" } }));
+
+        // Excluding every change yields an empty patch.
+        let patch = crate::diff_excluding(&old, &new, &["age", "profile"]).unwrap();
+        assert_eq!(patch, json!({}));
+    }
+
+    #[test]
+    fn test_glob_paths() {
+        let old = json!({
+            "items": [{ "id": 1, "name": "a" }, { "id": 2, "name": "b" }],
+            "meta": { "a": { "ts": 1, "v": 1 }, "b": { "ts": 1, "v": 2 } },
+            "profile": { "bio": "hi", "links": { "web": "x" } }
+        });
+        let mut new = old.clone();
+        new["items"][1]["name"] = json!("c");
+        new["meta"]["a"]["ts"] = json!(2);
+        new["meta"]["b"]["ts"] = json!(2);
+        new["meta"]["b"]["v"] = json!(3);
+
+        let options = crate::DiffOptions {
+            including: ["items.*.id".to_string(), "profile.**".to_string()].into(),
+            ignoring: ["meta.*.ts".to_string()].into(),
+            array_strategy: crate::ArrayStrategy::ByIndex,
+            ..Default::default()
+        };
+        let patch = crate::diff_with(&old, &new, &options).unwrap();
+        assert_eq!(
+            patch,
+            json!({
+                "items": { "$items": { "0": { "id": 1 }, "1": { "id": 2, "name": "c" } } },
+                "meta": { "b": { "v": 3 } },
+                "profile": { "bio": "hi", "links": { "web": "x" } }
+            })
+        );
+
+        // `*` matches exactly one segment.
+        let patch = crate::diff_excluding(&old, &new, &["*.ts"]).unwrap();
+        assert_eq!(patch["meta"]["a"], json!({ "ts": 2 }));
+    }
+
+    #[test]
+    fn test_json_pointer_paths() {
+        let old = json!({
+            "hosts": {
+                "example.com": { "port": 80, "tls": false },
+                "a/b": { "port": 1 },
+                "~x": { "port": 2 }
+            },
+            "rev": 1
+        });
+        let mut new = old.clone();
+        new["rev"] = json!(2);
+        new["hosts"]["example.com"]["tls"] = json!(true);
+
+        let patch = crate::diff_including(
+            &old,
+            &new,
+            &[
+                "/hosts/example.com/port",
+                "/hosts/a~1b/port",
+                "/hosts/~0x/port",
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            patch,
+            json!({
+                "rev": 2,
+                "hosts": {
+                    "example.com": { "port": 80, "tls": true },
+                    "a/b": { "port": 1 },
+                    "~x": { "port": 2 }
+                }
+            })
+        );
+
+        // The dotted form can't address a key containing a dot.
+        let patch = crate::diff_excluding(&old, &new, &["hosts.example.com.tls"]).unwrap();
+        assert_eq!(patch["hosts"]["example.com"]["tls"], json!(true));
+        let patch = crate::diff_excluding(&old, &new, &["/hosts/example.com/tls", "/rev"]).unwrap();
+        assert_eq!(patch, json!({}));
+    }
+
+    #[test]
+    fn test_escaped_dotted_paths() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Host {
+            port: u16,
+            tls: bool,
+        }
+
+        let hosts = |port| {
+            BTreeMap::from([
+                (
+                    "example".to_string(),
+                    Host {
+                        port: 1,
+                        tls: false,
+                    },
+                ),
+                ("example.com".to_string(), Host { port, tls: false }),
+            ])
+        };
+
+        let patch = crate::diff_including(&hosts(80), &hosts(443), &["example\\.com.tls"]).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "example.com": { "port": 443, "tls": false } })
+        );
+        let raw = crate::RawPatch::from_string(patch.to_string()).unwrap();
+        assert_eq!(
+            raw.paths().unwrap(),
+            ["example\\.com.port", "example\\.com.tls"]
+        );
+        assert!(raw.touches("example\\.com.port").unwrap());
+        assert!(!raw.touches("example.com.port").unwrap());
+
+        let options = crate::ApplyOptions {
+            protected: ["example\\.com".to_string()].into(),
+            ..Default::default()
+        };
+        let err = crate::apply_with(hosts(80), patch.to_string(), &options).unwrap_err();
+        assert_eq!(err.to_string(), "field is protected at `example\\.com`");
+        let sibling = json!({ "example": { "port": 2 } }).to_string();
+        assert_eq!(
+            crate::apply_with(hosts(80), sibling, &options).unwrap()["example"].port,
+            2
+        );
+    }
+
+    #[test]
+    fn test_diff_values() {
+        let old = json!({ "id": 1, "profile": { "bio": "hi", "links": ["a"] }, "tmp": true });
+        let new = json!({ "id": 1, "profile": { "bio": "hello", "links": ["a"] } });
+
+        let options = crate::DiffOptions {
+            including: ["id".to_string()].into(),
+            ..Default::default()
+        };
+        let patch = crate::diff_values(&old, &new, &options).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "id": 1, "profile": { "bio": "hello" }, "tmp": null })
+        );
+        assert_eq!(patch, crate::diff_with(&old, &new, &options).unwrap());
+
+        // Non-object roots are replaced.
+        assert_eq!(
+            crate::diff_values(&json!([1]), &json!(2), &Default::default()).unwrap(),
+            json!(2)
+        );
+    }
+
+    #[test]
+    fn test_apply_value_patch() {
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+        let patch = json!({ "age": 31, "profile": { "avatar_url": null } });
+
+        let updated = crate::apply_value(user(), &patch).unwrap();
+        assert_eq!(updated.age, 31);
+        assert_eq!(updated.profile.unwrap().avatar_url, None);
+
+        let mut current = user();
+        crate::apply_mut_value(&mut current, &patch).unwrap();
+        assert_eq!(current.age, 31);
+
+        // Same errors as with a textual patch, and the value is left unchanged.
+        let err = crate::apply_mut_value(&mut current, &json!({ "username": null })).unwrap_err();
+        assert_eq!(err.pointer(), Some("/username"));
+        assert_eq!(current.username, "alice");
+    }
+
+    #[test]
+    fn test_merge_into_value() {
+        let mut document = json!({ "a": { "b": 1, "c": [1, 2] }, "d": "x" });
+        crate::merge_into(
+            &mut document,
+            &json!({ "a": { "b": null, "c": [3], "e": { "f": true } } }),
+        );
+        assert_eq!(
+            document,
+            json!({ "a": { "c": [3], "e": { "f": true } }, "d": "x" })
+        );
+
+        // RFC 7396: a non-object patch replaces the document, an object patch
+        // turns a non-object document into an object.
+        crate::merge_into(&mut document, &json!("scalar"));
+        assert_eq!(document, json!("scalar"));
+        crate::merge_into(&mut document, &json!({ "k": null, "v": 1 }));
+        assert_eq!(document, json!({ "v": 1 }));
+    }
+
+    #[test]
+    fn test_changed_paths() {
+        let old = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+        let new = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 31,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: None,
+            }),
+        };
+
+        assert_eq!(
+            crate::changed_paths(&old, &new).unwrap(),
+            ["age", "profile.avatar_url"]
+        );
+        assert!(crate::changed_paths(&old, &old).unwrap().is_empty());
+
+        // A value turning into an empty object is a change too.
+        let paths =
+            crate::changed_paths(&json!({ "a": 1, "b.c": 1 }), &json!({ "a": {}, "b.c": 2 }));
+        assert_eq!(paths.unwrap(), ["a", "b\\.c"]);
+    }
+
+    #[test]
+    fn test_patch_wrapper() {
+        let patch: crate::Patch = serde_json::from_str(
+            r#"{ "age": 31, "profile": { "bio": "new", "avatar_url": null } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(patch.paths(), ["age", "profile.avatar_url", "profile.bio"]);
+        assert_eq!(
+            patch.get("profile.avatar_url"),
+            Some(&serde_json::Value::Null)
+        );
+        assert!(patch.touches("profile"));
+        assert!(!patch.touches("profile.bio.len"));
+        assert!(!patch.touches("username"));
+        assert_eq!(
+            serde_json::to_value(&patch).unwrap(),
+            json!({ "age": 31, "profile": { "bio": "new", "avatar_url": null } })
+        );
+
+        assert!(crate::Patch::default().is_empty());
+        assert_eq!(crate::Patch::default().to_string(), "{}");
+        assert!(!crate::Patch::new(json!(null)).is_empty());
+    }
+
+    #[test]
+    fn test_compose_patches() {
+        let target = json!({ "a": 1, "b": { "c": 2, "d": 3 }, "e": [1] });
+        let patches = [
+            json!({ "a": 2, "b": { "c": null } }),
+            json!({ "b": { "c": 5, "f": 6 }, "e": null }),
+            json!({ "a": null, "e": [2], "g": { "h": null, "i": 1 } }),
+        ];
+
+        let mut expected = target.clone();
+        for patch in &patches {
+            crate::merge_into(&mut expected, patch);
+        }
+        let squashed = patches.iter().fold(json!({}), |composed, patch| {
+            crate::compose(&composed, patch)
+        });
+        let mut actual = target;
+        crate::merge_into(&mut actual, &squashed);
+        assert_eq!(actual, expected);
+        assert_eq!(
+            squashed,
+            json!({ "a": null, "b": { "c": 5, "f": 6 }, "e": [2], "g": { "h": null, "i": 1 } })
+        );
+    }
+
+    #[test]
+    fn test_diff_inverse() {
+        let old = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+        let new = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 31,
+            active: true,
+            profile: None,
+        };
+
+        let forward = crate::diff(&old, &new).unwrap();
+        let undo = crate::diff_inverse(&old, &new).unwrap();
+        assert_eq!(
+            undo,
+            json!({ "age": 30, "profile": { "bio": "hi", "avatar_url": "a.png" } })
+        );
+
+        let updated = crate::apply_value(old, &forward).unwrap();
+        let restored = crate::apply_value(updated, &undo).unwrap();
+        assert_eq!(restored.age, 30);
+        assert_eq!(
+            restored.profile.unwrap().avatar_url.as_deref(),
+            Some("a.png")
+        );
+    }
+
+    #[test]
+    fn test_diff_delta() {
+        let old = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let new = User {
+            id: 1,
+            username: "alicia".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: None,
+            }),
+        };
+
+        let delta = crate::diff_delta(&old, &new).unwrap();
+        assert_eq!(
+            serde_json::to_value(&delta).unwrap(),
+            json!({
+                "profile": { "old": null, "new": { "bio": "hi", "avatar_url": null } },
+                "username": { "old": "alice", "new": "alicia" }
+            })
+        );
+        assert_eq!(delta.get("username").unwrap().old, Some(json!("alice")));
+        assert_eq!(delta.to_patch(), crate::diff(&old, &new).unwrap());
+
+        // Removed keys have no new value.
+        let delta = crate::diff_delta(&json!({ "a": { "b": 1 } }), &json!({ "a": {} })).unwrap();
+        let paths: Vec<_> = delta.iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, ["a.b"]);
+        assert_eq!(delta.get("a.b").unwrap().new, None);
+        assert_eq!(delta.to_patch(), json!({ "a": { "b": null } }));
+        assert!(crate::diff_delta(&old, &old).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_deny_unknown_fields() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Settings {
+            profile: Option<Profile>,
+            labels: BTreeMap<String, String>,
+        }
+        let settings = || Settings {
+            profile: None,
+            labels: BTreeMap::new(),
+        };
+        let options = crate::ApplyOptions {
+            deny_unknown_fields: true,
+            ..Default::default()
+        };
+
+        // Fields under a `None` option and new map entries are checked against the type.
+        let patch = r#"{ "profile": { "bio": "hi", "banner": "b.png" }, "labels": { "env": "prod" }, "theme": "dark" }"#;
+        let err = crate::apply_with(settings(), patch, &options).unwrap_err();
+        assert_eq!(err.to_string(), "unknown fields `profile.banner`, `theme`");
+
+        let updated =
+            crate::apply_with(settings(), r#"{ "labels": { "env": "prod" } }"#, &options).unwrap();
+        assert_eq!(updated.labels["env"], "prod");
+
+        // Without the option, unknown fields are dropped.
+        assert!(crate::apply_with(settings(), patch, &Default::default()).is_ok());
+
+        let errors = crate::apply_collecting(settings(), patch, &options).unwrap_err();
+        let kinds: Vec<_> = errors.iter().map(|e| (e.path.as_str(), e.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("profile.banner", crate::FieldErrorKind::Unknown),
+                ("theme", crate::FieldErrorKind::Unknown),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_maybe_absent_fields() {
+        use crate::MaybeAbsent;
+
+        #[derive(Debug, Default, Serialize, Deserialize)]
+        struct ProfileChanges {
+            #[serde(default, skip_serializing_if = "MaybeAbsent::is_absent")]
+            bio: MaybeAbsent<String>,
+            #[serde(default, skip_serializing_if = "MaybeAbsent::is_absent")]
+            avatar_url: MaybeAbsent<String>,
+        }
+
+        let changes: ProfileChanges = serde_json::from_str(r#"{ "avatar_url": null }"#).unwrap();
+        assert_eq!(changes.bio, MaybeAbsent::Absent);
+        assert_eq!(changes.avatar_url, MaybeAbsent::Null);
+        assert_eq!(
+            serde_json::to_value(&changes).unwrap(),
+            json!({ "avatar_url": null })
+        );
+
+        let profile = Profile {
+            bio: "hi".to_string(),
+            avatar_url: Some("a.png".to_string()),
+        };
+        let profile = crate::apply_typed(profile, &changes).unwrap();
+        assert_eq!(profile.bio, "hi");
+        assert_eq!(profile.avatar_url, None);
+
+        let mut field = None;
+        MaybeAbsent::Value("b.png".to_string()).apply_to(&mut field);
+        MaybeAbsent::Absent.apply_to(&mut field);
+        assert_eq!(field.as_deref(), Some("b.png"));
+        assert_eq!(MaybeAbsent::from(Some(None::<u8>)), MaybeAbsent::Null);
+
+        // `Null` on a required field is a removal error.
+        let changes = ProfileChanges {
+            bio: MaybeAbsent::Null,
+            ..Default::default()
+        };
+        let err = crate::apply_typed(profile, &changes).unwrap_err();
+        assert!(matches!(err, crate::Error::InvalidRemoval { .. }));
+    }
+
+    #[test]
+    fn test_non_finite_floats() {
+        use crate::{DiffOptions, NonFinite};
+        use std::collections::BTreeMap;
+
+        #[derive(Serialize)]
+        enum Reading {
+            Scalar(f64),
+        }
+
+        #[derive(Serialize)]
+        struct Sensor {
+            name: String,
+            samples: Vec<f32>,
+            limits: BTreeMap<u8, Option<f64>>,
+            last: Reading,
+        }
+        let sensor = |sample: f32, limit: f64, last: f64| Sensor {
+            name: "t1".to_string(),
+            samples: vec![1.0, sample],
+            limits: BTreeMap::from([(1, Some(limit))]),
+            last: Reading::Scalar(last),
+        };
+
+        let old = sensor(2.0, 10.0, 1.0);
+        let err = crate::diff(&old, &sensor(f32::NAN, 10.0, 1.0)).unwrap_err();
+        assert_eq!(err.to_string(), "non-finite float NaN at `samples.1`");
+        let err = crate::diff(&old, &sensor(2.0, f64::NEG_INFINITY, 1.0)).unwrap_err();
+        assert_eq!(err.to_string(), "non-finite float -Infinity at `limits.1`");
+        let err = crate::diff(&old, &sensor(2.0, 10.0, f64::INFINITY)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "non-finite float Infinity at `last.Scalar`"
+        );
+
+        // Only floats that would be written to the patch are errors.
+        let nan = sensor(f32::NAN, 10.0, 1.0);
+        assert_eq!(crate::diff(&nan, &nan).unwrap(), json!({}));
+        let mut renamed = sensor(f32::NAN, 10.0, 1.0);
+        renamed.name = "t2".to_string();
+        assert_eq!(
+            crate::diff(&nan, &renamed).unwrap(),
+            json!({ "name": "t2" })
+        );
+        assert_eq!(
+            crate::diff(&nan, &old).unwrap(),
+            json!({ "samples": [1.0, 2.0] })
+        );
+        let err = crate::diff(&nan, &sensor(f32::INFINITY, 10.0, 1.0)).unwrap_err();
+        assert!(err.is_data());
+        assert_eq!(err.to_string(), "non-finite float Infinity at `samples.1`");
+        // Arrays are replaced whole, so an unchanged element is still written.
+        let mut grown = sensor(f32::NAN, 10.0, 1.0);
+        grown.samples.push(3.0);
+        assert!(crate::diff(&nan, &grown).is_err());
+
+        let new = sensor(f32::NAN, f64::INFINITY, 1.0);
+        let with = |non_finite| {
+            let options = DiffOptions {
+                non_finite,
+                ..Default::default()
+            };
+            crate::diff_with(&old, &new, &options).unwrap()
+        };
+        assert_eq!(
+            with(NonFinite::Null),
+            json!({ "samples": [1.0, null], "limits": { "1": null } })
+        );
+        assert_eq!(
+            with(NonFinite::String),
+            json!({ "samples": [1.0, "NaN"], "limits": { "1": "Infinity" } })
+        );
+        assert_eq!(with(NonFinite::Skip), json!({}));
+
+        // Values without non-finite floats are unaffected, nulls included.
+        let profile = Profile {
+            bio: "hi".to_string(),
+            avatar_url: None,
+        };
+        assert_eq!(crate::diff(&profile, &profile).unwrap(), json!({}));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_documents() {
+        let old = "\
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+  labels: &labels
+    app: web
+spec:
+  replicas: 2
+  selector:
+    matchLabels: *labels
+";
+        let new = "\
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 3
+  selector:
+    matchLabels:
+      app: web
+";
+
+        let patch = crate::diff_yaml(old, new).unwrap();
+        assert_eq!(
+            crate::parse_yaml(&patch).unwrap(),
+            json!({ "metadata": { "labels": null }, "spec": { "replicas": 3 } })
+        );
+        assert_eq!(
+            crate::parse_yaml(&crate::apply_yaml(old, &patch).unwrap()).unwrap(),
+            crate::parse_yaml(new).unwrap()
+        );
+        assert_eq!(crate::diff_yaml(new, new).unwrap(), "{}\n");
+
+        let err = crate::apply_yaml(old, "spec: [unclosed").unwrap_err();
+        assert!(err.to_string().contains("line 1"), "{err}");
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml_documents() {
+        let old = r#"
+title = "app"
+ratio = 0.5
+
+[release]
+date = 2024-01-15
+at = 09:30:00
+tags = ["a", "b"]
+"#;
+        let new = r#"
+title = "app"
+
+[release]
+date = 2024-02-01
+at = 09:30:00
+tags = ["a", "b", "c"]
+"#;
+
+        let patch = crate::diff_toml(old, new).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "ratio": null, "release": { "date": "2024-02-01", "tags": ["a", "b", "c"] } })
+        );
+
+        // Dates stay TOML dates rather than turning into strings.
+        let updated = crate::apply_toml(old, &patch).unwrap();
+        let updated: toml::Table = toml::from_str(&updated).unwrap();
+        assert_eq!(updated, toml::from_str::<toml::Table>(new).unwrap());
+        assert!(updated["release"]["date"].is_datetime());
+
+        // A new string field is not mistaken for a datetime.
+        let updated = crate::apply_toml(old, &json!({ "note": "2024-02-01" })).unwrap();
+        assert!(updated.contains(r#"note = "2024-02-01""#));
+
+        let err = crate::apply_toml(old, &json!({ "release": { "tags": [1, null] } })).unwrap_err();
+        assert!(err.to_string().ends_with("at `release.tags`"), "{err}");
+        assert!(crate::apply_toml(old, &json!([1])).is_err());
+        assert!(crate::diff_toml("x = nan", "x = 1").is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_patches() {
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: None,
+            }),
+        };
+        let mut new = user();
+        new.age = 31;
+        new.profile = None;
+
+        let bytes = crate::diff_to_cbor(&user(), &new).unwrap();
+        assert_eq!(
+            crate::parse_cbor(&bytes).unwrap(),
+            json!({ "age": 31, "profile": null })
+        );
+        assert!(
+            bytes.len()
+                < serde_json::to_vec(&json!({ "age": 31, "profile": null }))
+                    .unwrap()
+                    .len()
+        );
+
+        let updated = crate::apply_cbor(user(), &bytes).unwrap();
+        assert_eq!(updated.age, 31);
+        assert!(updated.profile.is_none());
+
+        let err = crate::apply_cbor(user(), &[0xff, 0x00]).err().unwrap();
+        assert!(matches!(err, crate::Error::ParsePatch(_)));
+        let err = crate::apply_cbor(user(), &crate::to_cbor(&json!({ "age": "old" })).unwrap())
+            .err()
+            .unwrap();
+        assert_eq!(err.pointer(), Some("/age"));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_patches() {
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: None,
+            }),
+        };
+        let mut new = user();
+        new.active = false;
+        new.profile = Some(Profile {
+            bio: "hi".to_string(),
+            avatar_url: Some("a.png".to_string()),
+        });
+
+        let bytes = crate::diff_to_msgpack(&user(), &new).unwrap();
+        assert_eq!(
+            crate::parse_msgpack(&bytes).unwrap(),
+            json!({ "active": false, "profile": { "avatar_url": "a.png" } })
+        );
+
+        let updated = crate::apply_msgpack(user(), &bytes).unwrap();
+        assert!(!updated.active);
+        assert_eq!(
+            updated.profile.unwrap().avatar_url.as_deref(),
+            Some("a.png")
+        );
+
+        // Removals survive the encoding as `nil`.
+        let bytes = crate::to_msgpack(&json!({ "profile": null })).unwrap();
+        assert!(
+            crate::apply_msgpack(user(), &bytes)
+                .unwrap()
+                .profile
+                .is_none()
+        );
+
+        let err = crate::apply_msgpack(user(), &[0xc1]).err().unwrap();
+        assert!(matches!(err, crate::Error::ParsePatch(_)));
+    }
+
+    #[cfg(feature = "bson")]
+    #[test]
+    fn test_bson_documents() {
+        use bson::{Bson, DateTime, Decimal128, doc, oid::ObjectId};
+
+        let id = ObjectId::new();
+        let price = |text: &str| text.parse::<Decimal128>().unwrap();
+        let old = doc! {
+            "_id": id,
+            "count": 1_i64,
+            "price": price("9.99"),
+            "meta": { "created": DateTime::from_millis(0), "tags": ["a"] },
+        };
+        let new = doc! {
+            "_id": id,
+            "count": 2_i64,
+            "price": price("9.99"),
+            "meta": { "created": DateTime::from_millis(0), "tags": ["a", "b"], "owner": id },
+        };
+
+        let patch = crate::diff_bson(&old, &new);
+        assert_eq!(
+            patch,
+            doc! { "count": 2_i64, "meta": { "tags": ["a", "b"], "owner": id } }
+        );
+        assert_eq!(crate::apply_bson(old.clone(), &patch), new);
+        assert!(crate::diff_bson(&new, &new).is_empty());
+
+        // Round-tripping through JSON keeps Int64, ObjectId, dates and decimals.
+        let full = crate::diff_bson(&doc! {}, &old);
+        let json = crate::from_bson(&full);
+        assert_eq!(json["count"], json!({ "$numberLong": "1" }));
+        assert_eq!(crate::to_bson(&json).unwrap(), old);
+
+        let removal = crate::to_bson(&json!({ "meta": { "created": null } })).unwrap();
+        let updated = crate::apply_bson(old, &removal);
+        assert_eq!(updated.get_document("meta").unwrap().get("created"), None);
+        assert_eq!(updated.get("count"), Some(&Bson::Int64(1)));
+
+        assert!(crate::to_bson(&json!({ "_id": { "$oid": "nope" } })).is_err());
+        assert!(crate::to_bson(&json!([1])).is_err());
+    }
+
+    #[cfg(feature = "bson")]
+    #[test]
+    fn test_to_mongo_update() {
+        use bson::doc;
+
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+        let mut new = user();
+        new.age = 31;
+        new.profile = Some(Profile {
+            bio: "hello".to_string(),
+            avatar_url: None,
+        });
+
+        let patch = crate::to_bson(&crate::diff(&user(), &new).unwrap()).unwrap();
+        assert_eq!(
+            crate::to_mongo_update(&patch),
+            doc! {
+                "$set": { "age": 31, "profile.bio": "hello" },
+                "$unset": { "profile.avatar_url": "" },
+            }
+        );
+
+        assert_eq!(
+            crate::to_mongo_update(&doc! { "a": { "b": {} }, "c": [1, 2] }),
+            doc! { "$set": { "a.b": {}, "c": [1, 2] } }
+        );
+        assert!(crate::to_mongo_update(&doc! {}).is_empty());
+    }
+
+    #[test]
+    fn test_sql_set_clause() {
+        use crate::sql::{Dialect, SqlOptions, set_clause};
+
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+        let mut new = user();
+        new.age = 31;
+        new.profile = Some(Profile {
+            bio: "hello".to_string(),
+            avatar_url: None,
+        });
+        let patch = crate::diff(&user(), &new).unwrap();
+
+        // Unmapped fields are rejected by default.
+        let err = set_clause(&patch, &SqlOptions::default()).unwrap_err();
+        assert_eq!(err.to_string(), "no column for `age`");
+
+        // Without mappings the nested object would overwrite the whole JSON
+        // column, losing the members the patch doesn't mention, unless the
+        // caller opts in to replacing it.
+        let open = SqlOptions {
+            deny_unmapped: false,
+            ..Default::default()
+        };
+        let err = set_clause(&patch, &open).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("`profile` patches part of a JSON column")
+        );
+        let mapped = SqlOptions {
+            columns: [("profile".to_string(), "profile_json".to_string())].into(),
+            ..open.clone()
+        };
+        let err = set_clause(&json!({ "profile": { "bio": "x" } }), &mapped).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("`profile` patches part of a JSON column")
+        );
+        let clause = set_clause(&json!({ "profile": null }), &mapped).unwrap();
+        assert_eq!(clause.sql, r#"SET "profile_json" = NULL"#);
+        let open = SqlOptions {
+            replace_objects: true,
+            ..open
+        };
+        let clause = set_clause(&patch, &open).unwrap();
+        assert_eq!(clause.sql, r#"SET "age" = $1, "profile" = $2"#);
+        assert_eq!(
+            clause.params[1],
+            json!({ "bio": "hello", "avatar_url": null })
+        );
+
+        let options = SqlOptions {
+            dialect: Dialect::Sqlite,
+            columns: [
+                ("age".to_string(), "user_age".to_string()),
+                ("profile.bio".to_string(), "bio".to_string()),
+                ("profile.avatar_url".to_string(), "avatar".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let clause = set_clause(&patch, &options).unwrap();
+        assert_eq!(
+            clause.sql,
+            r#"SET "avatar" = NULL, "bio" = ?, "user_age" = ?"#
+        );
+        assert_eq!(clause.params, [json!("hello"), json!(31)]);
+        assert_eq!(clause.columns, ["avatar", "bio", "user_age"]);
+
+        let clause = set_clause(
+            &patch,
+            &SqlOptions {
+                first_param: 3,
+                ..open.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(clause.sql, r#"SET "age" = $3, "profile" = $4"#);
+
+        // Client-supplied keys are quoted, or rejected with `deny_unmapped`.
+        let hostile = json!({ "name\" = 'x'; --": 1 });
+        let clause = set_clause(&hostile, &open).unwrap();
+        assert_eq!(clause.sql, r#"SET "name"" = 'x'; --" = $1"#);
+        let err = set_clause(&hostile, &options).unwrap_err();
+        assert_eq!(err.to_string(), r#"no column for `name" = 'x'; --`"#);
+        let clause = set_clause(&json!({ "profile": null }), &options).unwrap();
+        assert_eq!(clause.sql, r#"SET "avatar" = NULL, "bio" = NULL"#);
+        let err = set_clause(&json!({ "profile": "x" }), &options).unwrap_err();
+        assert!(
+            err.to_string().contains("mapped to several columns"),
+            "{err}"
+        );
+
+        // Two fields assigning one column are rejected.
+        let overlapping = SqlOptions {
+            columns: [
+                ("bio".to_string(), "bio".to_string()),
+                ("profile.bio".to_string(), "bio".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let err = set_clause(
+            &json!({ "bio": "a", "profile": { "bio": "b" } }),
+            &overlapping,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "column `bio` is assigned by several patch fields"
+        );
+        assert!(set_clause(&json!({ "bio": "a" }), &overlapping).is_ok());
+
+        assert!(set_clause(&json!({}), &options).unwrap().is_empty());
+        assert!(set_clause(&json!([1]), &options).is_err());
+    }
+
+    #[cfg(feature = "sqlx-postgres")]
+    #[test]
+    fn test_sqlx_push_set() {
+        use crate::sql::{Dialect, SqlOptions, push_set};
+        use sqlx::{Postgres, QueryBuilder};
+
+        #[derive(Serialize)]
+        struct UserPatch {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            username: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            age: Option<u32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            profile: Option<Option<Profile>>,
+        }
+
+        let patch = UserPatch {
+            username: None,
+            age: Some(31),
+            profile: Some(Some(Profile {
+                bio: "hello".to_string(),
+                avatar_url: None,
+            })),
+        };
+        let mut query = QueryBuilder::<Postgres>::new("UPDATE users ");
+        let open = SqlOptions {
+            deny_unmapped: false,
+            replace_objects: true,
+            ..Default::default()
+        };
+        assert!(push_set(&mut query, &patch, &open).unwrap());
+        query.push(" WHERE id = ").push_bind(1_i64);
+        assert_eq!(
+            query.sql().as_str(),
+            r#"UPDATE users SET "age" = $1, "profile" = $2 WHERE id = $3"#
+        );
+
+        let options = SqlOptions {
+            dialect: Dialect::Postgres,
+            columns: [("profile.bio".to_string(), "bio".to_string())].into(),
+            ..open
+        };
+        let mut query = QueryBuilder::<Postgres>::new("UPDATE users ");
+        push_set(
+            &mut query,
+            &json!({ "profile": { "bio": "hi" }, "active": false }),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            query.sql().as_str(),
+            r#"UPDATE users SET "active" = $1, "bio" = $2"#
+        );
+
+        let empty = UserPatch {
+            username: None,
+            age: None,
+            profile: None,
+        };
+        let mut query = QueryBuilder::<Postgres>::new("UPDATE users ");
+        assert!(!push_set(&mut query, &empty, &options).unwrap());
+        assert_eq!(query.sql().as_str(), "UPDATE users ");
+
+        let err = push_set(&mut query, &json!({ "big": u64::MAX }), &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "18446744073709551615 does not fit a 64-bit column at `big`"
+        );
+    }
+
+    #[cfg(feature = "diesel")]
+    #[test]
+    fn test_derive_patch_diesel() {
+        use crate::Patch;
+        use diesel::pg::Pg;
+        use diesel::prelude::*;
+
+        diesel::table! {
+            users (id) {
+                id -> Int4,
+                name -> Text,
+                email_address -> Nullable<Text>,
+            }
+        }
+
+        #[derive(Queryable, Patch, Serialize, Deserialize)]
+        #[patch(diesel(table_name = users))]
+        struct Account {
+            id: i32,
+            name: String,
+            #[diesel(column_name = email_address)]
+            email: Option<String>,
+        }
+
+        let sql = |patch: AccountPatch| {
+            let query = diesel::update(users::table.find(7)).set(patch);
+            diesel::debug_query::<Pg, _>(&query).to_string()
+        };
+
+        let patch: AccountPatch = serde_json::from_str(r#"{ "name": "al" }"#).unwrap();
+        assert_eq!(
+            sql(patch),
+            r#"UPDATE "users" SET "name" = $1 WHERE ("users"."id" = $2) -- binds: ["al", 7]"#
+        );
+
+        let patch: AccountPatch = serde_json::from_str(r#"{ "email": null }"#).unwrap();
+        assert_eq!(
+            sql(patch),
+            r#"UPDATE "users" SET "email_address" = $1 WHERE ("users"."id" = $2) -- binds: [None, 7]"#
+        );
+
+        // The same patch still updates an in-memory value.
+        let mut account = Account {
+            id: 7,
+            name: "alice".to_string(),
+            email: Some("a@example.com".to_string()),
+        };
+        account.apply_patch(serde_json::from_str(r#"{ "email": null }"#).unwrap());
+        assert_eq!(
+            (account.id, account.name.as_str(), account.email),
+            (7, "alice", None)
+        );
+    }
+
+    #[cfg(feature = "sea-orm")]
+    #[test]
+    fn test_derive_patch_sea_orm() {
+        use crate::Patch;
+        use sea_orm::ActiveValue::{NotSet, Set};
+
+        mod account {
+            use sea_orm::entity::prelude::*;
+
+            #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+            #[sea_orm(table_name = "accounts")]
+            pub struct Model {
+                #[sea_orm(primary_key)]
+                pub id: i32,
+                pub name: String,
+                pub email: Option<String>,
+            }
+
+            #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+            pub enum Relation {}
+
+            impl ActiveModelBehavior for ActiveModel {}
+        }
+
+        #[derive(Patch, Serialize, Deserialize)]
+        #[patch(sea_orm(active_model = "account::ActiveModel"))]
+        struct Account {
+            name: String,
+            email: Option<String>,
+        }
+
+        let patch: AccountPatch = serde_json::from_str(r#"{ "name": "al" }"#).unwrap();
+        let model = account::ActiveModel::from(patch);
+        assert_eq!(model.id, NotSet);
+        assert_eq!(model.name, Set("al".to_string()));
+        assert_eq!(model.email, NotSet);
+
+        let patch: AccountPatch = serde_json::from_str(r#"{ "email": null }"#).unwrap();
+        let model: account::ActiveModel = patch.into();
+        assert_eq!(model.name, NotSet);
+        assert_eq!(model.email, Set(None));
+
+        let mut account = Account {
+            name: "alice".to_string(),
+            email: None,
+        };
+        account.apply_patch(serde_json::from_str(r#"{ "email": "a@example.com" }"#).unwrap());
+        assert_eq!(
+            (account.name.as_str(), account.email.as_deref()),
+            ("alice", Some("a@example.com"))
+        );
+    }
+
+    #[test]
+    fn test_field_mask() {
+        use crate::FieldMask;
+
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: Some("a.png".to_string()),
+            }),
+        };
+        let mut new = user();
+        new.age = 31;
+        new.profile = Some(Profile {
+            bio: "hello".to_string(),
+            avatar_url: None,
+        });
+
+        let mask = crate::to_field_mask(&crate::diff(&user(), &new).unwrap());
+        assert_eq!(mask.to_string(), "age,profile.avatar_url,profile.bio");
+        assert!(crate::to_field_mask(&json!({})).is_empty());
+
+        // Applying the mask of a diff reproduces the new value.
+        let updated = crate::apply_with_mask(user(), &new, &mask).unwrap();
+        assert_eq!(updated.age, 31);
+        assert_eq!(updated.profile.as_ref().unwrap().bio, "hello");
+        assert_eq!(updated.profile.unwrap().avatar_url, None);
+
+        // A masked message field is replaced as a whole, or cleared.
+        let mut source = user();
+        source.username = "bob".to_string();
+        source.profile = None;
+        let updated =
+            crate::apply_with_mask(user(), &source, &FieldMask::new(["profile"])).unwrap();
+        assert_eq!(updated.username, "alice");
+        assert!(updated.profile.is_none());
+
+        let err = crate::apply_with_mask(user(), &new, &FieldMask::new(["nickname"]))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "unknown field `nickname` in field mask");
+
+        let mask: FieldMask = serde_json::from_str(r#""username, age""#).unwrap();
+        assert_eq!(mask.paths, ["username", "age"]);
+        assert!("age,,username".parse::<FieldMask>().is_err());
+
+        #[cfg(feature = "prost")]
+        {
+            let proto = prost_types::FieldMask::from(mask.clone());
+            assert_eq!(proto.paths, ["username", "age"]);
+            assert_eq!(FieldMask::from(proto), mask);
+        }
+    }
+
+    #[test]
+    fn test_strategic_merge_patch() {
+        use crate::strategic::{self, StrategicOptions};
+
+        let options = StrategicOptions::default()
+            .merge_key("spec.containers", "name")
+            .merge_key("spec.containers.ports", "containerPort");
+        let old = json!({
+            "metadata": { "labels": { "app": "web", "tier": "front" } },
+            "spec": { "containers": [
+                { "name": "app", "image": "app:1", "ports": [{ "containerPort": 80 }] },
+                { "name": "proxy", "image": "envoy:1" },
+                { "name": "log", "image": "fluent:1" },
+            ] }
+        });
+        let new = json!({
+            "metadata": { "labels": { "app": "web" } },
+            "spec": { "containers": [
+                { "name": "app", "image": "app:2", "ports": [{ "containerPort": 80 }, { "containerPort": 443 }] },
+                { "name": "metrics", "image": "prom:1" },
+                { "name": "log", "image": "fluent:1" },
+            ] }
+        });
+
+        let patch = strategic::diff(&old, &new, &options).unwrap();
+        assert_eq!(
+            patch,
+            json!({
+                "metadata": { "labels": { "tier": null } },
+                "spec": {
+                    "containers": [
+                        { "name": "app", "image": "app:2", "ports": [{ "containerPort": 443 }] },
+                        { "name": "metrics", "image": "prom:1" },
+                        { "name": "proxy", "$patch": "delete" },
+                    ],
+                    "$setElementOrder/containers": [{ "name": "app" }, { "name": "metrics" }, { "name": "log" }],
+                }
+            })
+        );
+        assert_eq!(
+            strategic::apply(old.clone(), &patch, &options).unwrap(),
+            new
+        );
+        assert_eq!(strategic::diff(&new, &new, &options).unwrap(), json!({}));
+
+        // Unkeyed lists are replaced, as in a merge patch.
+        let patch = strategic::diff(&old, &new, &StrategicOptions::default()).unwrap();
+        assert_eq!(patch["spec"]["containers"], new["spec"]["containers"]);
+
+        // Hand-written directives.
+        let strategy = json!({ "type": "RollingUpdate", "rollingUpdate": { "maxSurge": 1 } });
+        let patch = json!({ "strategy": { "type": "Recreate", "$retainKeys": ["type"] } });
+        assert_eq!(
+            strategic::apply(json!({ "strategy": strategy }), &patch, &options).unwrap(),
+            json!({ "strategy": { "type": "Recreate" } })
+        );
+        let patch =
+            json!({ "spec": { "containers": [{ "$patch": "replace" }, { "name": "only" }] } });
+        assert_eq!(
+            strategic::apply(old.clone(), &patch, &options).unwrap()["spec"]["containers"],
+            json!([{ "name": "only" }])
+        );
+        let patch = json!({ "metadata": { "labels": { "$patch": "replace", "app": "api" } } });
+        assert_eq!(
+            strategic::apply(old.clone(), &patch, &options).unwrap()["metadata"],
+            json!({ "labels": { "app": "api" } })
+        );
+
+        let patch = json!({ "spec": { "containers": [{ "image": "x" }] } });
+        let err = strategic::apply(old.clone(), &patch, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "list element without merge key `name` at `spec.containers`"
+        );
+        let patch = json!({ "spec": { "$patch": "explode" } });
+        assert!(strategic::apply(old, &patch, &options).is_err());
+    }
+
+    #[test]
+    fn test_scim_patch_op() {
+        use crate::scim::{OpKind, ScimOptions};
+
+        // Keys in sorted order, so `preserve-order` emits the same operations.
+        let old = json!({
+            "emails": [
+                { "type": "work", "value": "alice@corp.example", "primary": true },
+                { "type": "home", "value": "alice@home.example" },
+            ],
+            "name": { "givenName": "Alice", "middleName": "J" },
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User": { "department": "R&D" },
+            "userName": "alice",
+        });
+        let new = json!({
+            "emails": [
+                { "type": "work", "value": "alice@corp.example" },
+                { "type": "other", "value": "a@other.example" },
+            ],
+            "name": { "givenName": "Alicia" },
+            "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User": { "department": "Sales" },
+            "userName": "alice",
+        });
+
+        let options = ScimOptions::default().filter("emails", "type");
+        let patch = crate::scim::diff(&old, &new, &options).unwrap();
+        assert_eq!(
+            serde_json::to_value(&patch.operations).unwrap(),
+            json!([
+                { "op": "remove", "path": "emails[type eq \"work\"].primary" },
+                { "op": "remove", "path": "emails[type eq \"home\"]" },
+                { "op": "add", "path": "emails", "value": [{ "type": "other", "value": "a@other.example" }] },
+                { "op": "remove", "path": "name.middleName" },
+                { "op": "replace", "path": "name.givenName", "value": "Alicia" },
+                {
+                    "op": "replace",
+                    "path": "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:department",
+                    "value": "Sales",
+                },
+            ])
+        );
+
+        // Without a filter the list is replaced whole.
+        let patch = crate::scim::diff(&old, &new, &ScimOptions::default()).unwrap();
+        assert!(
+            patch
+                .operations
+                .iter()
+                .any(|operation| operation.op == OpKind::Replace
+                    && operation.path.as_deref() == Some("emails"))
+        );
+
+        assert!(crate::scim::diff(&old, &old, &options).unwrap().is_empty());
+        assert_eq!(
+            serde_json::to_value(
+                crate::scim::from_merge_patch(&json!({
+                    "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User": {
+                        "manager": { "value": "26118915-6090-4610-87e4-49d8ca9f808d" }
+                    }
+                }))
+                .operations
+            )
+            .unwrap(),
+            json!([{
+                "op": "replace",
+                "path": "urn:ietf:params:scim:schemas:extension:enterprise:2.0:User:manager.value",
+                "value": "26118915-6090-4610-87e4-49d8ca9f808d",
+            }])
+        );
+        let parsed: crate::scim::PatchOp =
+            serde_json::from_value(serde_json::to_value(&patch).unwrap()).unwrap();
+        assert_eq!(parsed, patch);
+    }
+
+    #[test]
+    fn test_odata_delta() {
+        use crate::odata::{EntitySet, ODataOptions};
+
+        let old = json!({
+            "accountid": 7,
+            "name": "Contoso",
+            "contacts": [
+                { "contactid": "a1", "fullname": "Ann" },
+                { "contactid": "b2", "fullname": "Bob" },
+            ],
+            "owner": { "userid": 3, "fullname": "Olga", "title": "Lead" },
+        });
+        let new = json!({
+            "accountid": 7,
+            "contacts": [
+                { "contactid": "a1", "fullname": "Ann Lee" },
+                { "contactid": "c3", "fullname": "Cat" },
+            ],
+            "owner": { "userid": 3, "fullname": "Olga" },
+        });
+
+        let options = ODataOptions::default()
+            .navigation("contacts", EntitySet::new("contacts", "contactid"))
+            .navigation("owner", EntitySet::new("systemusers", "userid"));
+        let payload = crate::odata::delta(&old, &new, "accounts(7)", &options).unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "@odata.id": "accounts(7)",
+                "name": null,
+                "contacts@delta": [
+                    { "@odata.id": "contacts('a1')", "fullname": "Ann Lee" },
+                    { "@odata.id": "contacts('c3')", "contactid": "c3", "fullname": "Cat" },
+                    { "@odata.id": "contacts('b2')", "@odata.removed": { "reason": "changed" } },
+                ],
+                "owner": { "@odata.id": "systemusers(3)", "title": null },
+            })
+        );
+
+        assert_eq!(
+            crate::odata::delta(&old, &old, "accounts(7)", &options).unwrap(),
+            json!({ "@odata.id": "accounts(7)" })
+        );
+
+        let keyless = json!({ "accountid": 7, "owner": { "fullname": "Olga" } });
+        let err = crate::odata::delta(&old, &keyless, "accounts(7)", &options).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("entity without key `userid` at `owner`")
+        );
+    }
+
+    #[cfg(feature = "json-patch")]
+    #[test]
+    fn test_json_patch_crate_bridge() {
+        use crate::json_patch::{Op, from_json_patch, to_json_patch};
+
+        let old = json!({ "title": "Hello", "tags": ["a", "c"], "a/b": 1 });
+        let new = json!({ "title": "Hi", "tags": ["a", "b", "c"] });
+        let ops = crate::json_patch::diff_ops(&old, &new).unwrap();
+
+        let external = to_json_patch(ops.clone()).unwrap();
+        let mut document = old.clone();
+        ::json_patch::patch(&mut document, &external).unwrap();
+        assert_eq!(document, new);
+        assert_eq!(from_json_patch(external), ops);
+
+        let invalid = Op::Remove {
+            path: "title".into(),
+        };
+        assert!(::json_patch::PatchOperation::try_from(invalid).is_err());
+
+        let merge = |ops: serde_json::Value| {
+            crate::Patch::try_from(serde_json::from_value::<::json_patch::Patch>(ops).unwrap())
+        };
+        assert_eq!(
+            merge(json!([
+                { "op": "add", "path": "/profile", "value": { "bio": "hi" } },
+                { "op": "replace", "path": "/profile/avatar_url", "value": "a.png" },
+                { "op": "remove", "path": "/a~1b" },
+            ]))
+            .unwrap()
+            .into_inner(),
+            json!({ "profile": { "bio": "hi", "avatar_url": "a.png" }, "a/b": null })
+        );
+        let err = merge(json!([{ "op": "move", "from": "/a", "path": "/b" }])).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("`move` at `/b` has no merge patch equivalent")
+        );
+        assert!(merge(json!([{ "op": "add", "path": "/x", "value": { "y": null } }])).is_err());
+        assert!(merge(json!([{ "op": "add", "path": "/tags/-", "value": "d" }])).is_err());
+        assert!(merge(json!([{ "op": "add", "path": "", "value": {} }])).is_err());
+    }
+
+    #[test]
+    fn test_format_trait() {
+        use crate::{Format, Json};
+
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let old = user();
+        let new = User {
+            age: 31,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: None,
+            }),
+            ..user()
+        };
+
+        let patch = Json::diff(&old, &new).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&patch).unwrap(),
+            crate::diff(&old, &new).unwrap()
+        );
+        let updated = Json::apply(old, &patch).unwrap();
+        assert_eq!(
+            (updated.age, updated.profile.unwrap().bio),
+            (31, "hi".to_string())
+        );
+
+        let document = Json::apply_document(br#"{"a":{"b":1,"c":2}}"#, br#"{"a":{"c":null}}"#);
+        assert_eq!(document.unwrap(), br#"{"a":{"b":1}}"#);
+        assert!(matches!(
+            Json::apply(new, b"{not json"),
+            Err(crate::Error::ParsePatch(_))
+        ));
+
+        #[cfg(feature = "cbor")]
+        {
+            use crate::Cbor;
+
+            let old = Cbor::serialize(&json!({ "a": 1, "b": [1, 2] })).unwrap();
+            let new = Cbor::serialize(&json!({ "a": 1, "b": [3] })).unwrap();
+            let patch = Cbor::diff_documents(&old, &new).unwrap();
+            assert_eq!(Cbor::parse(&patch).unwrap(), json!({ "b": [3] }));
+            assert_eq!(Cbor::apply_document(&old, &patch).unwrap(), new);
+        }
+    }
+
+    #[cfg(feature = "axum")]
+    #[tokio::test]
+    async fn test_axum_merge_patch_extractor() {
+        use crate::axum::{MergePatch, MergePatchRejection};
+        use ::axum::body::Body;
+        use ::axum::extract::FromRequest;
+        use ::axum::http::{Request, StatusCode, header};
+        use ::axum::response::IntoResponse;
+
+        let request = |content_type: &str, body: Body| {
+            Request::builder()
+                .method("PATCH")
+                .header(header::CONTENT_TYPE, content_type)
+                .body(body)
+                .unwrap()
+        };
+        let patch_body = r#"{ "age": 31, "profile": null }"#;
+
+        let MergePatch(patch) = MergePatch::<crate::Patch>::from_request(
+            request(
+                "application/merge-patch+json; charset=utf-8",
+                patch_body.into(),
+            ),
+            &(),
+        )
+        .await
+        .unwrap();
+        let user = patch
+            .apply_to(User {
+                id: 1,
+                username: "alice".to_string(),
+                age: 30,
+                active: true,
+                profile: None,
+            })
+            .unwrap();
+        assert_eq!(user.age, 31);
+
+        let rejection = MergePatch::<crate::Patch>::from_request(
+            request("application/json", patch_body.into()),
+            &(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            rejection,
+            MergePatchRejection::UnsupportedMediaType
+        ));
+        assert_eq!(
+            rejection.into_response().status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let rejection = MergePatch::<crate::Patch>::from_request(
+            request(crate::MERGE_PATCH_MEDIA_TYPE, "{ not json".into()),
+            &(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+
+        #[derive(Debug, Deserialize)]
+        struct AgePatch {
+            #[allow(dead_code)]
+            age: Option<u8>,
+        }
+        let rejection = MergePatch::<AgePatch>::from_request(
+            request(crate::MERGE_PATCH_MEDIA_TYPE, r#"{ "age": "old" }"#.into()),
+            &(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(rejection.to_string().contains("age: invalid type"));
+
+        // Bodies above the default 2 MB limit are refused.
+        let huge = format!(r#"{{ "bio": "{}" }}"#, "x".repeat(3 * 1024 * 1024));
+        let rejection = MergePatch::<crate::Patch>::from_request(
+            request(crate::MERGE_PATCH_MEDIA_TYPE, huge.into()),
+            &(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = MergePatch(json!({ "age": 31 })).into_response();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            crate::MERGE_PATCH_MEDIA_TYPE
+        );
+    }
+
+    #[cfg(feature = "actix")]
+    #[tokio::test]
+    async fn test_actix_patch_extractor() {
+        use crate::actix::{Patch, PatchConfig, PatchPayloadError};
+        use ::actix_web::http::StatusCode;
+        use ::actix_web::test::TestRequest;
+        use ::actix_web::{FromRequest, HttpResponse, Responder};
+
+        let extract = |content_type: &str, body: &str, config: Option<PatchConfig>| {
+            let mut request = TestRequest::patch()
+                .insert_header(("content-type", content_type))
+                .set_payload(body.to_string());
+            if let Some(config) = config {
+                request = request.app_data(config);
+            }
+            let (req, mut payload) = request.to_http_parts();
+            Patch::<crate::Patch>::from_request(&req, &mut payload)
+        };
+        let body = r#"{ "age": 31 }"#;
+
+        let patch = extract(crate::MERGE_PATCH_MEDIA_TYPE, body, None)
+            .await
+            .unwrap();
+        assert_eq!(patch.get("age"), Some(&json!(31)));
+
+        let status = |err: ::actix_web::Error| err.as_response_error().status_code();
+        let err = extract("application/json", body, None).await.unwrap_err();
+        assert_eq!(status(err), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let err = extract(crate::MERGE_PATCH_MEDIA_TYPE, "{", None)
+            .await
+            .unwrap_err();
+        assert_eq!(status(err), StatusCode::BAD_REQUEST);
+
+        let config = PatchConfig::default().limit(4);
+        let err = extract(crate::MERGE_PATCH_MEDIA_TYPE, body, Some(config))
+            .await
+            .unwrap_err();
+        assert_eq!(status(err), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let config = PatchConfig::default().limit(4).error_handler(|err, _req| {
+            assert!(matches!(err, PatchPayloadError::Overflow { limit: 4 }));
+            ::actix_web::error::InternalError::from_response(
+                err,
+                HttpResponse::BadRequest().body("too big"),
+            )
+            .into()
+        });
+        let err = extract(crate::MERGE_PATCH_MEDIA_TYPE, body, Some(config))
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_response().status(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::default().to_http_request();
+        let response = Patch(json!({ "age": 31 })).respond_to(&req);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            crate::MERGE_PATCH_MEDIA_TYPE
+        );
+    }
+
+    #[test]
+    fn test_http_if_match() {
+        use crate::http::{apply_if_match, etag, if_match};
+
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let tag = etag(&user()).unwrap();
+        assert_eq!(tag, etag(&user()).unwrap());
+        assert_ne!(tag, etag(&User { age: 31, ..user() }).unwrap());
+
+        let updated = apply_if_match(user(), &json!({ "age": 31 }), &tag).unwrap();
+        assert_eq!(updated.age, 31);
+        let updated = apply_if_match(user(), &json!({ "age": 32 }), "*").unwrap();
+        assert_eq!(updated.age, 32);
+        let list = format!(r#""0000000000000000", {tag}"#);
+        assert!(apply_if_match(user(), &json!({ "age": 33 }), &list).is_ok());
+
+        let err = apply_if_match(updated, &json!({ "age": 40 }), &tag).unwrap_err();
+        assert!(matches!(
+            &err,
+            crate::Error::PreconditionFailed { expected, .. } if *expected == tag
+        ));
+        assert_eq!(err.pointer(), None);
+        assert!(!if_match(&format!("W/{tag}"), &tag));
+
+        // The precondition is checked before the patch is validated.
+        let err = apply_if_match(user(), &json!({ "username": null }), &tag).unwrap_err();
+        assert!(matches!(err, crate::Error::InvalidRemoval { .. }));
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        use crate::fingerprint;
+
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".to_string(),
+                avatar_url: None,
+            }),
+        };
+        let as_json = json!({
+            "profile": { "avatar_url": null, "bio": "hi" },
+            "active": true,
+            "age": 30.0,
+            "username": "alice",
+            "id": 1,
+        });
+        assert_eq!(
+            fingerprint(&user()).unwrap(),
+            fingerprint(&as_json).unwrap()
+        );
+        assert_ne!(
+            fingerprint(&user()).unwrap(),
+            fingerprint(&User { age: 31, ..user() }).unwrap()
+        );
+
+        assert_eq!(
+            fingerprint(&json!(-0.0)).unwrap(),
+            fingerprint(&json!(0)).unwrap()
+        );
+        assert_ne!(
+            fingerprint(&json!(1.5)).unwrap(),
+            fingerprint(&json!(1)).unwrap()
+        );
+        assert_ne!(
+            fingerprint(&json!("1")).unwrap(),
+            fingerprint(&json!(1)).unwrap()
+        );
+        assert_ne!(
+            fingerprint(&json!({ "a": [1, 2] })).unwrap(),
+            fingerprint(&json!({ "a": [2, 1] })).unwrap()
+        );
+
+        // Values without floats hash like their compact serialization did
+        // before, so stored hashes stay valid.
+        let value = json!({ "b": [true, null, "x\"y"], "a": { "c": -5 } });
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in r#"{"a":{"c":-5},"b":[true,null,"x\"y"]}"#.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        assert_eq!(fingerprint(&value).unwrap(), hash);
+    }
+
+    #[cfg(feature = "warp-test")]
+    #[tokio::test]
+    async fn test_warp_merge_patch_filter() {
+        use crate::warp::{PatchRejection, merge_patch};
+        use ::warp::http::StatusCode;
+
+        let filter = merge_patch::<crate::Patch>(16);
+        let request = |content_type: &str, body: &str| {
+            ::warp::test::request()
+                .method("PATCH")
+                .header("content-type", content_type)
+                .body(body)
+        };
+
+        let patch = request(crate::MERGE_PATCH_MEDIA_TYPE, r#"{ "age": 31 }"#)
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(patch.get("age"), Some(&json!(31)));
+
+        let status =
+            |rejection: ::warp::Rejection| rejection.find::<PatchRejection>().unwrap().status();
+        let rejection = request("application/json", r#"{ "age": 31 }"#)
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert_eq!(status(rejection), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let rejection = request(crate::MERGE_PATCH_MEDIA_TYPE, "{")
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert_eq!(status(rejection), StatusCode::BAD_REQUEST);
+
+        let rejection = request(
+            crate::MERGE_PATCH_MEDIA_TYPE,
+            r#"{ "profile": { "bio": null } }"#,
+        )
+        .filter(&filter)
+        .await
+        .unwrap_err();
+        assert!(
+            rejection
+                .find::<::warp::reject::PayloadTooLarge>()
+                .is_some()
+        );
+
+        let typed = merge_patch::<User>(1024);
+        let rejection = request(crate::MERGE_PATCH_MEDIA_TYPE, r#"{ "id": "one" }"#)
+            .filter(&typed)
+            .await
+            .unwrap_err();
+        let rejection = rejection.find::<PatchRejection>().unwrap();
+        assert_eq!(rejection.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(rejection.to_string().contains("id"));
+    }
+
+    #[cfg(feature = "rocket")]
+    #[::rocket::patch("/", data = "<patch>")]
+    fn rocket_update(
+        patch: Result<crate::rocket::MergePatch, crate::rocket::MergePatchError>,
+    ) -> Result<crate::rocket::MergePatch, crate::rocket::MergePatchError> {
+        patch
+    }
+
+    #[cfg(feature = "rocket")]
+    #[::rocket::patch("/user", data = "<patch>")]
+    fn rocket_update_user(
+        patch: Result<crate::rocket::MergePatch<User>, crate::rocket::MergePatchError>,
+    ) -> Result<String, crate::rocket::MergePatchError> {
+        patch.map(|crate::rocket::MergePatch(user)| user.username)
+    }
+
+    #[cfg(feature = "rocket")]
+    #[tokio::test]
+    async fn test_rocket_merge_patch_guard() {
+        use ::rocket::http::{ContentType, Status};
+        use ::rocket::local::asynchronous::Client;
+
+        let figment = ::rocket::Config::figment()
+            .merge(("log_level", "off"))
+            .merge(("limits.merge-patch", 24));
+        let rocket = ::rocket::custom(figment)
+            .mount("/", ::rocket::routes![rocket_update, rocket_update_user]);
+        let client = Client::untracked(rocket).await.unwrap();
+        let merge_patch = ContentType::new("application", "merge-patch+json");
+        let send = |path: &'static str, content_type: ContentType, body: &'static str| {
+            client
+                .patch(path)
+                .header(content_type)
+                .body(body)
+                .dispatch()
+        };
+
+        let response = send("/", merge_patch.clone(), r#"{ "age": 31 }"#).await;
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(merge_patch.clone()));
+        assert_eq!(response.into_string().await.unwrap(), r#"{"age":31}"#);
+
+        let response = send("/", ContentType::JSON, r#"{ "age": 31 }"#).await;
+        assert_eq!(response.status(), Status::UnsupportedMediaType);
+        assert_eq!(response.content_type(), Some(ContentType::JSON));
+
+        let response = send("/", merge_patch.clone(), "{").await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = send(
+            "/",
+            merge_patch.clone(),
+            r#"{ "profile": { "bio": null } }"#,
+        )
+        .await;
+        assert_eq!(response.status(), Status::PayloadTooLarge);
+        let body: serde_json::Value =
+            serde_json::from_str(&response.into_string().await.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({ "error": "merge patch is larger than the 24 byte limit" })
+        );
+
+        let response = send("/user", merge_patch, r#"{ "id": "one" }"#).await;
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        assert!(response.into_string().await.unwrap().contains("id"));
+    }
+
+    #[test]
+    fn test_firestore_update() {
+        use crate::firestore::{FieldUpdate, Update};
+
+        let old = User {
+            id: 1,
+            username: "alice".into(),
+            age: 30,
+            active: true,
+            profile: Some(Profile {
+                bio: "hi".into(),
+                avatar_url: Some("a.png".into()),
+            }),
+        };
+        let new = User {
+            id: 1,
+            username: "alice".into(),
+            age: 31,
+            active: true,
+            profile: Some(Profile {
+                bio: "hello".into(),
+                avatar_url: None,
+            }),
+        };
+        let update = crate::firestore::diff(&old, &new).unwrap();
+        assert_eq!(
+            update.fields,
+            [
+                ("age".to_string(), FieldUpdate::Set(json!(31))),
+                ("profile.avatar_url".to_string(), FieldUpdate::Delete),
+                ("profile.bio".to_string(), FieldUpdate::Set(json!("hello"))),
+            ]
+            .into()
+        );
+        assert_eq!(
+            update.document(),
+            json!({ "age": 31, "profile": { "bio": "hello" } })
+        );
+        assert!(crate::firestore::diff(&old, &old).unwrap().is_empty());
+
+        // Quoted segments survive the round trip into the document.
+        let patch = json!({ "tags": ["a"], "meta": { "a.b": { "c`d": 1 }, "empty": {} } });
+        let update = crate::firestore::from_merge_patch(&patch).unwrap();
+        assert_eq!(
+            update.update_mask(),
+            ["meta.`a.b`.`c\\`d`", "meta.empty", "tags"]
+        );
+        assert_eq!(update.document(), patch);
+
+        assert!(crate::firestore::from_merge_patch(&json!([1])).is_err());
+        assert_eq!(Update::default().document(), json!({}));
+    }
+
+    #[test]
+    fn test_elasticsearch_update_body() {
+        use crate::{ArrayStrategy, DiffOptions};
+
+        let old = json!({ "name": "order", "lines": [{ "sku": "a", "qty": 1 }, { "sku": "b", "qty": 1 }] });
+        let new = json!({ "name": "order", "lines": [{ "sku": "a", "qty": 2 }, { "sku": "b", "qty": 1 }] });
+
+        let body = crate::elasticsearch::diff(&old, &new, &DiffOptions::default()).unwrap();
+        assert_eq!(
+            body,
+            json!({ "doc": { "lines": new["lines"] }, "detect_noop": true })
+        );
+
+        let options = DiffOptions {
+            array_strategy: ArrayStrategy::Keyed("sku".into()),
+            ..Default::default()
+        };
+        let body = crate::elasticsearch::diff(&old, &new, &options).unwrap();
+        let script = &body["script"];
+        assert_eq!(script["lang"], "painless");
+        assert_eq!(
+            script["params"]["patch"],
+            json!({ "lines": { "$key": "sku", "$items": { "a": { "qty": 2 } } } })
+        );
+        assert!(
+            script["source"]
+                .as_str()
+                .unwrap()
+                .ends_with("patchValue(ctx._source, params.patch);")
+        );
+        assert!(body.get("doc").is_none());
+
+        assert_eq!(
+            crate::elasticsearch::diff(&old, &old, &options).unwrap(),
+            json!({ "doc": {}, "detect_noop": true })
+        );
+        assert!(crate::elasticsearch::update_body(&json!(null)).is_err());
+    }
+
+    #[test]
+    fn test_apply_value_matches_merged_document() {
+        use std::collections::HashMap;
+
+        #[derive(Debug, Serialize, Deserialize)]
+        enum Shape {
+            Circle { radius: u32 },
+            Square(u32),
+            Empty,
+        }
+
+        #[derive(Debug, Serialize, Deserialize)]
+        #[serde(tag = "kind")]
+        enum Event {
+            Created { by: String },
+            Deleted { by: String, reason: Option<String> },
+        }
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Meters(f64);
+
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Doc {
+            scores: HashMap<u32, String>,
+            shape: Shape,
+            event: Event,
+            profile: Option<Profile>,
+            pair: (u8, bool),
+            length: Meters,
+            raw: Box<serde_json::value::RawValue>,
+        }
+
+        let doc = || Doc {
+            scores: HashMap::from([(1, "a".to_string()), (2, "b".to_string())]),
+            shape: Shape::Circle { radius: 1 },
+            event: Event::Created { by: "alice".into() },
+            profile: None,
+            pair: (1, true),
+            length: Meters(2.5),
+            raw: serde_json::value::RawValue::from_string(r#"{"a":1}"#.into()).unwrap(),
+        };
+        let patches = [
+            json!({ "scores": { "1": null, "3": "c" } }),
+            json!({ "shape": { "Circle": { "radius": 2 } } }),
+            json!({ "shape": { "Circle": null, "Square": 3 } }),
+            json!({ "shape": "Empty" }),
+            json!({ "event": { "kind": "Deleted", "reason": "spam" } }),
+            json!({ "profile": { "bio": "hi", "avatar_url": null } }),
+            json!({ "pair": [2, false], "length": 3.0 }),
+            json!({ "raw": { "a": null, "b": [2] } }),
+        ];
+        for patch in &patches {
+            let mut merged = serde_json::to_value(doc()).unwrap();
+            crate::merge_into(&mut merged, patch);
+            let expected = serde_json::from_value::<Doc>(merged).unwrap();
+            let applied = crate::apply_value(doc(), patch).unwrap();
+            assert_eq!(
+                serde_json::to_value(&applied).unwrap(),
+                serde_json::to_value(&expected).unwrap(),
+                "{patch}"
+            );
+        }
+
+        // Two variants left after merging are still an error, at the field.
+        let err = crate::apply_value(doc(), &json!({ "shape": { "Square": 3 } }))
+            .err()
+            .unwrap();
+        assert_eq!(err.pointer(), Some("/shape"));
+        let err = crate::apply_value(doc(), &json!({ "profile": { "avatar_url": "a.png" } }))
+            .err()
+            .unwrap();
+        assert_eq!(err.pointer(), Some("/profile/bio"));
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_patch_native_diff() {
+        use crate::Patch;
+
+        #[derive(Clone, Debug, PartialEq, Patch, Serialize, Deserialize)]
+        #[patch(diff, derive(Debug, PartialEq))]
+        struct Contact {
+            name: String,
+            phone: Option<String>,
+            tags: Vec<String>,
+        }
+
+        let old = Contact {
+            name: "alice".to_string(),
+            phone: Some("555".to_string()),
+            tags: vec!["a".to_string()],
+        };
+        let new = Contact {
+            name: "alice".to_string(),
+            phone: None,
+            tags: vec!["a".to_string(), "b".to_string()],
+        };
+
+        let patch = old.diff(&new);
+        assert_eq!(patch.name, None);
+        assert_eq!(patch.phone, Some(None));
+        assert!(!patch.is_empty());
+        assert!(old.diff(&old).is_empty());
+
+        // Same merge patch as the serializing diff, and applying it gives `new`.
+        assert_eq!(
+            serde_json::to_value(&patch).unwrap(),
+            crate::diff(&old, &new).unwrap()
+        );
+        let mut target = old.clone();
+        target.apply_patch(patch);
+        assert_eq!(target, new);
+
+        #[derive(Patch, Serialize, Deserialize)]
+        #[patch(diff)]
+        struct Tagged<T> {
+            value: T,
+        }
+        let patch = Tagged { value: 1 }.diff(&Tagged { value: 2 });
+        assert_eq!(patch.value, Some(2));
+    }
+
+    #[test]
+    fn test_apply_raw_matches_merge() {
+        use serde_json::value::RawValue;
+
+        let text = r#"{ "a\"b": 1, "n": 1.50, "list": [1, 2], "obj": { "x": { "deep": true }, "y": "z" }, "s": "t" }"#;
+        let document = RawValue::from_string(text.to_string()).unwrap();
+        let patches = [
+            json!({}),
+            json!({ "obj": { "x": { "deep": null, "new": { "k": 1, "gone": null } } } }),
+            json!({ "s": { "now": "object" }, "a\"b": null, "extra": [null] }),
+            json!({ "list": 3 }),
+            json!([1]),
+        ];
+        for patch in &patches {
+            let patched = crate::apply_raw(&document, patch).unwrap();
+            let mut expected: serde_json::Value = serde_json::from_str(text).unwrap();
+            crate::merge_into(&mut expected, patch);
+            let actual: serde_json::Value = serde_json::from_str(patched.get()).unwrap();
+            assert_eq!(actual, expected, "{patch}");
+        }
+
+        // Untouched members keep their exact text, number formatting included.
+        let patched = crate::apply_raw(&document, &json!({ "s": "u" })).unwrap();
+        assert!(patched.get().contains(r#""n":1.50"#));
+        assert!(patched.get().contains(r#""list":[1, 2]"#));
+    }
+
+    #[test]
+    fn test_diff_borrowed() {
+        use crate::{DiffOptions, PatchRef};
+
+        let old = json!({
+            "id": 1,
+            "profile": { "bio": "hi", "avatar_url": "a.png", "links": ["x"] },
+            "tags": ["a"],
+            "scalar": 1
+        });
+        let new = json!({
+            "id": 1,
+            "profile": { "bio": "hello", "links": ["x"], "extra": { "n": null } },
+            "scalar": { "now": "object" },
+            "added": null
+        });
+
+        let patch = crate::diff_borrowed(&old, &new);
+        let expected = crate::diff_values(&old, &new, &DiffOptions::default()).unwrap();
+        assert_eq!(patch.to_value(), expected);
+        assert_eq!(serde_json::to_value(&patch).unwrap(), expected);
+
+        // Changed values point into `new` rather than being copied.
+        let PatchRef::Merge(members) = &patch else {
+            panic!("expected an object patch");
+        };
+        assert!(
+            matches!(members["scalar"], PatchRef::Set(value) if std::ptr::eq(value, &new["scalar"]))
+        );
+        assert_eq!(members["tags"], PatchRef::Remove);
+
+        assert!(crate::diff_borrowed(&old, &old).is_empty());
+        assert_eq!(
+            crate::diff_borrowed(&json!(1), &json!(2)),
+            PatchRef::Set(&json!(2))
+        );
+    }
+
+    #[test]
+    fn test_deep_patches_do_not_overflow() {
+        use crate::DiffOptions;
+
+        // Nested far beyond what recursion on a test thread's stack survives.
+        let nested = |depth: usize, leaf: serde_json::Value| {
+            (0..depth).fold(leaf, |inner, _| {
+                serde_json::Value::Object([("a".to_string(), inner)].into_iter().collect())
+            })
+        };
+        let patch = nested(200_000, json!(1));
+        let mut target = json!({});
+        crate::merge_into(&mut target, &patch);
+        assert_eq!(crate::apply_patch::depth(&target), 200_000);
+
+        // As done for `NullSemantics::Ignore`.
+        let mut with_nulls = nested(200_000, json!({ "gone": null, "kept": 1 }));
+        crate::apply_patch::strip_nulls(&mut with_nulls);
+        let mut innermost = &with_nulls;
+        while let Some(inner) = innermost.get("a") {
+            innermost = inner;
+        }
+        assert_eq!(innermost, &json!({ "kept": 1 }));
+
+        let options = DiffOptions {
+            max_depth: Some(64),
+            ..Default::default()
+        };
+        let err = crate::diff_values(&json!({}), &patch, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "document nesting exceeds the maximum depth of 64"
+        );
+        let shallow = nested(64, json!(2));
+        assert!(crate::diff_values(&nested(64, json!(1)), &shallow, &options).is_ok());
+
+        // Dropping is recursive in `serde_json`, so the deep values are leaked.
+        std::mem::forget(patch);
+        std::mem::forget(target);
+        std::mem::forget(with_nulls);
+    }
+
+    #[cfg(feature = "preserve-order")]
+    #[test]
+    fn test_preserve_order_keeps_document_order() {
+        #[derive(Serialize)]
+        struct Server {
+            name: String,
+            host: String,
+            port: u16,
+            alias: Option<String>,
+        }
+
+        let old = Server {
+            name: "api".to_string(),
+            host: "old.example.com".to_string(),
+            port: 80,
+            alias: Some("a".to_string()),
+        };
+        let new = Server {
+            name: "web".to_string(),
+            host: "new.example.com".to_string(),
+            port: 8080,
+            alias: None,
+        };
+        let patch = crate::diff(&old, &new).unwrap();
+        assert_eq!(
+            serde_json::to_string(&patch).unwrap(),
+            r#"{"name":"web","host":"new.example.com","port":8080,"alias":null}"#
+        );
+
+        let mut document = json!({ "z": 1, "b": 2, "m": 3, "a": 4 });
+        crate::merge_into(&mut document, &json!({ "b": null, "c": 5 }));
+        assert_eq!(
+            serde_json::to_string(&document).unwrap(),
+            r#"{"z":1,"m":3,"a":4,"c":5}"#
+        );
+    }
+
+    #[test]
+    fn test_backends_parse_like_serde_json() {
+        use crate::backend::Backend;
+        use serde_json::Value;
+
+        type Parse = fn(&[u8]) -> Result<Value, serde_json::Error>;
+        let backends: &[(&str, Parse)] = &[
+            ("selected", crate::backend::from_slice),
+            #[cfg(not(any(feature = "simd-json", feature = "sonic-rs")))]
+            ("serde_json", crate::backend::SerdeJson::from_slice),
+            #[cfg(feature = "simd-json")]
+            ("simd-json", crate::backend::SimdJson::from_slice),
+            #[cfg(feature = "sonic-rs")]
+            ("sonic-rs", crate::backend::SonicRs::from_slice),
+        ];
+
+        let inputs = [
+            // Escapes, valid and invalid.
+            r#"{ "s": "tab\there \u00e9 \ud83d\ude00" }"#,
+            r#"{ "s": "\x" }"#,
+            r#"{ "s": "\u12" }"#,
+            r#"{ "s": "\ud800" }"#,
+            r#"{ "s": "\udc00 low first" }"#,
+            "{ \"s\": \"raw\u{1}control\" }",
+            // Signed zeros and numbers at the edges of the integer types.
+            r#"{ "a": -0, "b": -0.0, "c": 0, "d": -0e0 }"#,
+            r#"{ "max": 18446744073709551615, "min": -9223372036854775808 }"#,
+            r#"{ "over": 18446744073709551616, "under": -9223372036854775809 }"#,
+            r#"{ "huge": 1e400 }"#,
+            r#"{ "digits": 0.1000000000000000000001 }"#,
+            // Duplicate keys keep the last value.
+            r#"{ "k": 1, "k": { "x": 2 } }"#,
+            // Malformed documents.
+            r#"{ "a": 1, }"#,
+            r#"{ "a": 01 }"#,
+            r#"[1, 2"#,
+            "",
+        ];
+        for (name, parse) in backends {
+            for input in inputs {
+                match (
+                    parse(input.as_bytes()),
+                    serde_json::from_str::<Value>(input),
+                ) {
+                    (Ok(parsed), Ok(expected)) => {
+                        assert_eq!(parsed.to_string(), expected.to_string(), "{name}: {input}")
+                    }
+                    (Err(parsed), Err(expected)) => {
+                        assert_eq!(parsed.to_string(), expected.to_string(), "{name}: {input}")
+                    }
+                    (parsed, expected) => panic!("{name}: {input}: {parsed:?} vs {expected:?}"),
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn test_simd_json_reuses_buffers() {
+        use serde_json::Value;
+
+        // Alternating sizes, failures and oversized input leave nothing
+        // behind in the reused buffers.
+        let large = format!(r#"{{ "blob": "{}" }}"#, "x".repeat(2 << 20));
+        let inputs = [
+            r#"{ "name": "a much longer name than the next one", "tags": ["x", "y"] }"#,
+            r#"{ "n": 1 }"#,
+            r#"{ "n": 1,, }"#,
+            large.as_str(),
+            r#"{ "escaped": "q\"uote" }"#,
+            r#"{ "n": 2 }"#,
+            // Read differently by simd-json itself, so left to serde_json.
+            r#"{ "lone": "\ud800" }"#,
+            r#"{ "pair": "\ud83d\ude00", "zero": -0, "neg": -0.5 }"#,
+        ];
+        for _ in 0..2 {
+            for input in inputs {
+                let parsed = crate::backend::from_slice::<Value>(input.as_bytes());
+                let expected = serde_json::from_str::<Value>(input);
+                match (parsed, expected) {
+                    (Ok(parsed), Ok(expected)) => assert_eq!(parsed, expected),
+                    (Err(parsed), Err(expected)) => {
+                        assert_eq!(parsed.to_string(), expected.to_string())
+                    }
+                    (parsed, expected) => panic!("{input}: {parsed:?} vs {expected:?}"),
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_bulk_operations() {
+        let user = |id: u32, age: u8| User {
+            id,
+            username: format!("user{id}"),
+            age,
+            active: true,
+            profile: None,
+        };
+
+        let pairs: Vec<(User, User)> = (0..1000)
+            .map(|id| (user(id, 30), user(id, if id % 2 == 0 { 31 } else { 30 })))
+            .collect();
+        let patches = crate::diff_many(&pairs).unwrap();
+        assert_eq!(patches.len(), 1000);
+        for (id, patch) in patches.iter().enumerate() {
+            let expected = if id % 2 == 0 {
+                json!({ "age": 31 })
+            } else {
+                json!({})
+            };
+            assert_eq!(patch, &expected);
+        }
+
+        let mut users: Vec<User> = (0..1000).map(|id| user(id, 30)).collect();
+        crate::apply_many(&mut users, r#"{ "active": false }"#).unwrap();
+        assert!(users.iter().all(|user| !user.active && user.age == 30));
+
+        // A parse error leaves every document untouched.
+        let err = crate::apply_many(&mut users, "{ nope").unwrap_err();
+        assert!(matches!(err, crate::Error::ParsePatch(_)));
+        assert!(users.iter().all(|user| !user.active));
+
+        let err = crate::apply_many(&mut users, r#"{ "age": "old" }"#).unwrap_err();
+        assert!(matches!(err, crate::Error::Deserialize { .. }));
+        assert!(users.iter().all(|user| user.age == 30));
+    }
+
+    #[test]
+    fn test_diff_readers_matches_diff_values() {
+        use crate::DiffOptions;
+        use std::io::{Cursor, Read, Seek, SeekFrom};
+
+        let old = json!({
+            "users": { "alice": { "age": 30, "tags": ["a"] }, "bob": { "age": 40 } },
+            "version": 1,
+            "settings": { "theme": "dark" },
+            "legacy": true,
+            "unchanged": [1, 2, 3],
+        });
+        let new = json!({
+            "users": { "alice": { "age": 31, "tags": ["a"] }, "carol": { "age": 20 } },
+            "version": 1,
+            "settings": { "theme": "dark", "font": "mono" },
+            "unchanged": [1, 2, 3],
+            "added": null,
+        });
+        let diff_readers = |old: &serde_json::Value, new: &serde_json::Value, options| {
+            crate::diff_readers(
+                Cursor::new(old.to_string()),
+                Cursor::new(new.to_string()),
+                options,
+            )
+        };
+
+        let options = DiffOptions::default();
+        let patch = diff_readers(&old, &new, &options).unwrap();
+        assert_eq!(patch, crate::diff_values(&old, &new, &options).unwrap());
+        assert_eq!(diff_readers(&new, &new, &options).unwrap(), json!({}));
+
+        let options = DiffOptions {
+            including: ["version".to_string(), "users.*.age".to_string()].into(),
+            ignoring: ["settings".to_string()].into(),
+            ..Default::default()
+        };
+        let patch = diff_readers(&old, &new, &options).unwrap();
+        assert_eq!(patch, crate::diff_values(&old, &new, &options).unwrap());
+        assert_eq!(patch["version"], 1);
+        assert!(patch.get("settings").is_none());
+
+        // `old` is read again from where it started, not from the beginning.
+        let mut old_reader = Cursor::new(format!("garbage{old}"));
+        old_reader.seek(SeekFrom::Start(7)).unwrap();
+        let patch = crate::diff_readers(
+            old_reader.by_ref(),
+            Cursor::new(new.to_string()),
+            &DiffOptions::default(),
+        );
+        assert_eq!(
+            patch.unwrap(),
+            crate::diff_values(&old, &new, &DiffOptions::default()).unwrap()
+        );
+
+        let err = diff_readers(&json!([1]), &new, &DiffOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("expected a JSON object"), "{err}");
+
+        // A document that is one big member is diffed as a whole: changing one
+        // record puts the entire array in the patch, as `diff_values` does.
+        let records = |changed: u64| {
+            json!({
+                "records": (0..1000)
+                    .map(|id| json!({ "id": id, "v": if id == 500 { changed } else { 0 } }))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let patch = crate::diff_readers(
+            Cursor::new(records(0).to_string()),
+            Cursor::new(records(1).to_string()),
+            &DiffOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            patch,
+            crate::diff_values(&records(0), &records(1), &DiffOptions::default()).unwrap()
+        );
+        assert_eq!(patch["records"].as_array().unwrap().len(), 1000);
+    }
+
+    #[test]
+    fn test_apply_streaming_matches_merge() {
+        let document = json!({
+            "name": "old",
+            "items": [1, { "a": [true, null] }, "x"],
+            "meta": { "a": 1, "b": { "c": 2.5 }, "list": [] },
+            "scalar": 7,
+            "gone": { "deep": [1] },
+        });
+        let patches = [
+            json!({ "name": "new", "meta": { "a": null, "b": { "d": "e" } } }),
+            json!({ "items": [3], "scalar": { "now": "object", "skip": null } }),
+            json!({ "gone": null, "added": { "x": 1, "y": null }, "missing": null }),
+            json!({}),
+            json!(["not", "an", "object"]),
+            json!({ "meta": "replaced" }),
+        ];
+        for patch in &patches {
+            let mut out = Vec::new();
+            crate::apply_streaming(document.to_string().as_bytes(), patch, &mut out).unwrap();
+            let streamed: serde_json::Value = serde_json::from_slice(&out).unwrap();
+            let mut expected = document.clone();
+            crate::merge_into(&mut expected, patch);
+            assert_eq!(streamed, expected, "{patch}");
+        }
+
+        // An object patch over a non-object document builds the object.
+        let mut out = Vec::new();
+        crate::apply_streaming(&b"[1, 2]"[..], &json!({ "a": 1, "b": null }), &mut out).unwrap();
+        assert_eq!(out, br#"{"a":1}"#);
+
+        let err = crate::apply_streaming(&b"{ \"a\": 1"[..], &json!({}), Vec::new()).unwrap_err();
+        assert!(err.is_eof());
+        let err = crate::apply_streaming(&b"{} {}"[..], &json!({}), Vec::new()).unwrap_err();
+        assert!(err.is_syntax());
+    }
+
+    #[cfg(feature = "arbitrary-precision")]
+    #[test]
+    fn test_arbitrary_precision_numbers() {
+        use serde_json::Number;
+
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Invoice {
+            id: u32,
+            total: Number,
+        }
+
+        let number = |digits: &str| digits.parse::<Number>().unwrap();
+        let old = Invoice {
+            id: 1,
+            total: number("12345678901234567890.123456789"),
+        };
+        let new = Invoice {
+            id: 1,
+            total: number("12345678901234567890.123456790"),
+        };
+
+        let patch = crate::diff(&old, &new).unwrap();
+        assert_eq!(
+            patch.to_string(),
+            r#"{"total":12345678901234567890.123456790}"#
+        );
+        let applied = crate::apply(old, &patch.to_string()).unwrap();
+        assert_eq!(applied, new);
+
+        let mut document = json!({ "id": 1, "total": number("0.1") });
+        crate::apply_mut(&mut document, patch.to_string()).unwrap();
+        assert_eq!(document["total"], json!(new.total));
+
+        let source = r#"{"rate":0.100000000000000000001,"lines":[1e+400,2.50],"id":1}"#;
+        let mut out = Vec::new();
+        crate::apply_streaming(source.as_bytes(), &json!({ "id": 2 }), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"rate":0.100000000000000000001,"lines":[1e+400,2.50],"id":2}"#
+        );
+
+        let patch = crate::diff_readers(
+            std::io::Cursor::new(source),
+            std::io::Cursor::new(
+                source.replace("0.100000000000000000001", "0.100000000000000000002"),
+            ),
+            &crate::DiffOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(patch.to_string(), r#"{"rate":0.100000000000000000002}"#);
+
+        // Reports see digits an `f64` field can't hold.
+        #[derive(Serialize, Deserialize)]
+        struct Rate {
+            rate: f64,
+        }
+        let (applied, report) = crate::apply_with_report(
+            Rate { rate: 1.0 },
+            r#"{ "rate": 0.100000000000000000001 }"#,
+            &crate::ApplyOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(applied.rate, 0.1);
+        assert_eq!(report.lossy.len(), 1);
+        assert_eq!(report.lossy[0].applied, Some(json!(number("0.1"))));
+        let (_, report) = crate::apply_with_report(
+            Rate { rate: 1.0 },
+            r#"{ "rate": 2.50 }"#,
+            &crate::ApplyOptions::default(),
+        )
+        .unwrap();
+        assert!(report.is_lossless());
+
+        // 128-bit integers are plain numbers.
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Wide {
+            id: u128,
+            delta: i128,
+        }
+        let old = Wide {
+            id: u128::MAX,
+            delta: i128::MIN,
+        };
+        let new = Wide {
+            id: u128::MAX - 1,
+            delta: i128::MIN,
+        };
+        let patch = crate::diff(&old, &new).unwrap();
+        assert_eq!(patch.to_string(), format!(r#"{{"id":{}}}"#, u128::MAX - 1));
+        assert_eq!(crate::apply(old, &patch.to_string()).unwrap(), new);
+    }
+
+    #[test]
+    fn test_wide_int_fields() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Ledger {
+            #[serde(with = "crate::wide_int")]
+            balance: u128,
+            #[serde(with = "crate::wide_int")]
+            adjustment: i128,
+        }
+
+        let old = Ledger {
+            balance: u128::MAX,
+            adjustment: i128::MIN,
+        };
+        let new = Ledger {
+            balance: u128::MAX - 1,
+            adjustment: i128::MIN,
+        };
+        let patch = crate::diff(&old, &new).unwrap();
+        assert_eq!(patch, json!({ "balance": (u128::MAX - 1).to_string() }));
+        let applied = crate::apply(old, &patch.to_string()).unwrap();
+        assert_eq!(applied, new);
+
+        let applied = crate::apply(applied, r#"{ "adjustment": -5, "balance": "7" }"#).unwrap();
+        assert_eq!((applied.balance, applied.adjustment), (7, -5));
+
+        let err = crate::apply(applied, r#"{ "balance": -1 }"#).unwrap_err();
+        assert!(matches!(err, crate::Error::Deserialize { .. }));
+    }
+
+    #[test]
+    fn test_apply_limits() {
+        use crate::{ApplyLimits, Limit};
+
+        let user = || User {
+            id: 1001,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let limits = ApplyLimits {
+            max_bytes: Some(128),
+            max_depth: Some(2),
+            max_keys: Some(4),
+        };
+        let exceeded = |patch: &str| match crate::apply_limited(user(), patch, &limits) {
+            Err(crate::Error::LimitExceeded { limit, max }) => Some((limit, max)),
+            _ => None,
+        };
+
+        let updated = crate::apply_limited(
+            user(),
+            r#"{ "age": 31, "profile": { "bio": "hi", "avatar_url": null } }"#,
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(updated.age, 31);
+
+        assert_eq!(
+            exceeded(&format!(r#"{{ "username": "{}" }}"#, "a".repeat(200))),
+            Some((Limit::Bytes, 128))
+        );
+        assert_eq!(
+            exceeded(r#"{ "profile": { "bio": ["deep"] } }"#),
+            Some((Limit::Depth, 2))
+        );
+        assert_eq!(
+            exceeded(r#"{ "a": 1, "b": 2, "c": 3, "d": 4, "e": 5 }"#),
+            Some((Limit::Keys, 4))
+        );
+        assert_eq!(
+            exceeded(r#"{ "a": { "b": 1, "c": 2 }, "d": { "e": 3 } }"#),
+            Some((Limit::Keys, 4))
+        );
+
+        let err = crate::apply_limited(user(), r#"{ "a": [[1]] }"#, &limits)
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "patch exceeds the limit of 2 levels of nesting"
+        );
+
+        // Syntax errors stay parse errors, and no limits means none are checked.
+        let err = crate::apply_limited(user(), "{ \"age\": }", &limits)
+            .err()
+            .unwrap();
+        assert!(matches!(err, crate::Error::ParsePatch(_)));
+        let deep = r#"{ "profile": { "bio": "x", "extra": [[[{}]]] } }"#;
+        assert!(ApplyLimits::default().parse(deep.as_bytes()).is_ok());
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_bindings_match_library() {
+        use crate::wasm::{apply_json, diff_json};
+
+        let old = r#"{"id":7,"tags":["a"],"profile":{"bio":"x","links":{"site":"s"}}}"#;
+        let new = r#"{"id":7,"tags":["a","b"],"profile":{"bio":"x"}}"#;
+        let patch = diff_json(old, new, &[]).unwrap();
+        assert_eq!(patch, r#"{"profile":{"links":null},"tags":["a","b"]}"#);
+        let with_id = diff_json(old, new, &["id".to_string()]).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&with_id).unwrap()["id"],
+            7
+        );
+
+        let applied = apply_json(old, &patch).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&applied).unwrap(),
+            serde_json::from_str::<serde_json::Value>(new).unwrap()
+        );
+
+        assert!(apply_json(old, "{ nope").unwrap_err().is_syntax());
+        assert!(diff_json("[1", new, &[]).is_err());
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_ffi_error_codes() {
+        use crate::ffi::*;
+        use std::ffi::{CStr, CString, c_char, c_int};
+
+        type Call = unsafe extern "C" fn(*const c_char, *const c_char, *mut *mut c_char) -> c_int;
+        let call = |f: Call, a: &[u8], b: &[u8]| {
+            let a = CString::new(a).unwrap();
+            let b = CString::new(b).unwrap();
+            let mut out = std::ptr::null_mut();
+            unsafe {
+                let code = f(a.as_ptr(), b.as_ptr(), &mut out);
+                let text = CStr::from_ptr(out).to_string_lossy().into_owned();
+                serde_patch_free(out);
+                (code, text)
+            }
+        };
+
+        let old = br#"{"id":1,"tags":["a"],"nested":{"x":1}}"#;
+        let new = br#"{"id":1,"nested":{},"tags":["a","b"]}"#;
+        let (code, patch) = call(serde_patch_diff, old, new);
+        assert_eq!(
+            (code, patch.as_str()),
+            (SERDE_PATCH_OK, r#"{"nested":{"x":null},"tags":["a","b"]}"#)
+        );
+        let (code, applied) = call(serde_patch_apply, old, patch.as_bytes());
+        assert_eq!((code, applied.as_bytes()), (SERDE_PATCH_OK, &new[..]));
+
+        let (code, message) = call(serde_patch_diff, b"{", new);
+        assert_eq!(code, SERDE_PATCH_INVALID_DOCUMENT);
+        assert!(message.contains("EOF"), "{message}");
+        assert_eq!(
+            call(serde_patch_apply, b"[", b"{}").0,
+            SERDE_PATCH_INVALID_DOCUMENT
+        );
+        assert_eq!(
+            call(serde_patch_apply, old, b"{,}").0,
+            SERDE_PATCH_INVALID_PATCH
+        );
+        assert_eq!(
+            call(serde_patch_diff, b"\"\xff\"", new).0,
+            SERDE_PATCH_INVALID_ARGUMENT
+        );
+
+        unsafe {
+            let mut out = std::ptr::null_mut();
+            let doc = CString::new("{}").unwrap();
+            assert_eq!(
+                serde_patch_apply(std::ptr::null(), doc.as_ptr(), &mut out),
+                SERDE_PATCH_INVALID_ARGUMENT
+            );
+            serde_patch_free(out);
+            assert_eq!(
+                serde_patch_diff(doc.as_ptr(), doc.as_ptr(), std::ptr::null_mut()),
+                SERDE_PATCH_INVALID_ARGUMENT
+            );
+            serde_patch_free(std::ptr::null_mut());
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_apply_and_diff() {
+        // Bodies larger than one chunk, delivered in small reads.
+        let bio = "x".repeat(20_000);
+        let old =
+            serde_json::to_vec(&json!({ "id": 1, "username": "alice", "age": 30, "active": true }))
+                .unwrap();
+        let new = serde_json::to_vec(&json!({
+            "id": 1, "username": "alice", "age": 31, "active": true,
+            "profile": { "bio": bio, "avatar_url": null }
+        }))
+        .unwrap();
+        let slow =
+            |bytes: Vec<u8>| tokio::io::BufReader::with_capacity(1000, std::io::Cursor::new(bytes));
+
+        let patch = crate::diff_async(slow(old.clone()), slow(new))
+            .await
+            .unwrap();
+        assert_eq!(
+            patch,
+            json!({ "age": 31, "profile": { "bio": bio, "avatar_url": null } })
+        );
+
+        let user: User =
+            crate::apply_async(slow(old.clone()), slow(serde_json::to_vec(&patch).unwrap()))
+                .await
+                .unwrap();
+        assert_eq!(user.age, 31);
+        assert_eq!(user.profile.unwrap().bio, bio);
+
+        let err = crate::apply_async::<User, _, _>(&b"{ nope"[..], &b"{}"[..])
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, crate::Error::Deserialize { .. }));
+        let err = crate::apply_async::<User, _, _>(&old[..], &b"{ nope"[..])
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, crate::Error::ParsePatch(_)));
+    }
+
+    #[test]
+    fn test_apply_stream_ndjson() {
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let changes = "{\"age\":31}\r\n\n{\"profile\":{\"bio\":\"hi\",\"avatar_url\":null}}\n{\"active\":false}\n";
+
+        let states: Vec<User> = crate::apply_stream(user(), changes.lines())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(states.len(), 3);
+        assert_eq!((states[0].age, states[0].active), (31, true));
+        assert_eq!(states[1].profile.as_ref().unwrap().bio, "hi");
+        assert!(!states[2].active);
+
+        let last = crate::apply_stream(user(), changes.lines())
+            .finish()
+            .unwrap();
+        assert_eq!((last.age, last.active), (31, false));
+
+        // Owned lines work too; the stream stops at the first bad patch.
+        let lines = vec![
+            "{\"age\":32}".to_string(),
+            "{ nope".to_string(),
+            "{\"age\":33}".to_string(),
+        ];
+        let mut stream = crate::apply_stream(user(), lines);
+        assert_eq!(stream.next().unwrap().unwrap().age, 32);
+        assert!(matches!(
+            stream.next(),
+            Some(Err(crate::Error::ParsePatch(_)))
+        ));
+        assert_eq!(stream.line(), 2);
+        assert!(stream.next().is_none());
+
+        let mut stream = crate::apply_stream(user(), ["{\"username\":null}"]);
+        assert!(matches!(
+            stream.next(),
+            Some(Err(crate::Error::InvalidRemoval { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_patch_log_replay() {
+        let user = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let mut log = crate::PatchLog::new(&user).unwrap();
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("request".to_string(), json!("r-1"));
+        let envelope = crate::PatchEnvelope {
+            author: Some("bob".to_string()),
+            metadata,
+            ..crate::PatchEnvelope::new(json!({ "age": 31 }))
+        };
+        assert_eq!(log.append(envelope).unwrap(), 1);
+        assert_eq!(
+            log.append(json!({ "profile": { "bio": "hi", "avatar_url": null } }))
+                .unwrap(),
+            2
+        );
+        assert!(matches!(
+            log.append(json!({ "age": "old" })),
+            Err(crate::Error::Deserialize { .. })
+        ));
+        assert_eq!(log.append(json!({ "profile": null })).unwrap(), 3);
+
+        let replayed = log.replay().unwrap();
+        assert_eq!((replayed.age, replayed.profile.is_none()), (31, true));
+        let revisions: Vec<_> = log.iter().map(|entry| entry.revision).collect();
+        assert_eq!(revisions, [Some(1), Some(2), Some(3)]);
+
+        // Entries are stored as envelopes, and reloaded the log keeps its
+        // history and head.
+        let stored = serde_json::to_value(&log).unwrap();
+        assert_eq!(
+            stored["entries"][0],
+            json!({
+                "patch": { "age": 31 },
+                "author": "bob",
+                "revision": 1,
+                "metadata": { "request": "r-1" }
+            })
+        );
+        assert_eq!(
+            stored["entries"][2],
+            json!({ "patch": { "profile": null }, "revision": 3 })
+        );
+        let mut reloaded: crate::PatchLog<User> = serde_json::from_value(stored).unwrap();
+        assert_eq!(reloaded.base(), log.base());
+        assert!(reloaded.append(json!({ "username": null })).is_err());
+        assert_eq!(reloaded.append(json!({ "active": false })).unwrap(), 4);
+        let legacy: crate::PatchLog<User> = serde_json::from_value(json!({
+            "base": log.base(),
+            "entries": [{ "patch": { "age": 40 } }, { "patch": { "active": false } }]
+        }))
+        .unwrap();
+        assert_eq!(legacy.revision(), 2);
+        assert_eq!(legacy.state_at(1).unwrap().age, 40);
+        assert!(!reloaded.replay().unwrap().active);
+    }
+
+    #[test]
+    fn test_compaction_and_snapshots() {
+        let chain = [
+            json!({ "age": 31, "profile": { "bio": "hi", "avatar_url": "a.png" } }),
+            json!({ "profile": { "avatar_url": null }, "active": false }),
+            json!({ "age": 32, "profile": null }),
+            json!({ "profile": { "bio": "back" } }),
+        ];
+        let start = json!({ "id": 1, "username": "alice", "age": 30, "active": true });
+        let mut applied = start.clone();
+        for patch in &chain {
+            crate::merge_into(&mut applied, patch);
+        }
+        let mut compacted = start.clone();
+        crate::merge_into(&mut compacted, &crate::compact(&chain));
+        assert_eq!(compacted, applied);
+        assert_eq!(crate::compact(&[]), json!({}));
+        let expected =
+            serde_json::to_value(serde_json::from_value::<User>(applied).unwrap()).unwrap();
+
+        let user: User = serde_json::from_value(start).unwrap();
+        let policy = crate::SnapshotPolicy {
+            every_patches: None,
+            every_bytes: Some(60),
+        };
+        let mut log = crate::PatchLog::with_snapshot_policy(&user, policy).unwrap();
+        for patch in &chain {
+            log.append(patch.clone()).unwrap();
+        }
+        // The first two patches add up to 60 bytes or more; the last two don't.
+        let snapshot = log.latest_snapshot().unwrap();
+        assert_eq!(snapshot.revision, 2);
+        assert_eq!(snapshot.state["profile"], json!({ "bio": "hi" }));
+        let replayed = serde_json::to_value(log.replay().unwrap()).unwrap();
+        assert_eq!(replayed, expected);
+
+        let stored = serde_json::to_value(&log).unwrap();
+        assert_eq!(stored["snapshot"]["revision"], 2);
+        let mut reloaded: crate::PatchLog<User> = serde_json::from_value(stored).unwrap();
+        assert_eq!(
+            serde_json::to_value(reloaded.replay().unwrap()).unwrap(),
+            expected
+        );
+        reloaded.take_snapshot();
+        assert_eq!(reloaded.latest_snapshot().unwrap().revision, 4);
+        assert_eq!(reloaded.iter().count(), 4);
+    }
+
+    #[test]
+    fn test_time_travel() {
+        let user = User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let patches = [
+            json!({ "age": 31 }),
+            json!({ "profile": { "bio": "hi", "avatar_url": null } }),
+            json!({ "age": 32, "profile": { "bio": "hello" } }),
+            json!({ "profile": null }),
+        ];
+        assert_eq!(crate::state_at(&user, &patches, 0).unwrap().age, 30);
+        let at_2 = crate::state_at(&user, &patches, 2).unwrap();
+        assert_eq!((at_2.age, at_2.profile.unwrap().bio.as_str()), (31, "hi"));
+        assert!(
+            crate::state_at(&user, &patches, 99)
+                .unwrap()
+                .profile
+                .is_none()
+        );
+
+        assert_eq!(
+            crate::between(&patches, 1, 3),
+            json!({ "age": 32, "profile": { "bio": "hello", "avatar_url": null } })
+        );
+        assert_eq!(
+            crate::between(&patches, 2, 4),
+            json!({ "age": 32, "profile": null })
+        );
+        assert_eq!(crate::between(&patches, 3, 3), json!({}));
+        assert_eq!(crate::between(&patches, 3, 1), json!({}));
+
+        // Applying the effective patch to state i gives state j.
+        let at_1 = crate::state_at(&user, &patches, 1).unwrap();
+        let at_3 = crate::apply_value(at_1, &crate::between(&patches, 1, 3)).unwrap();
+        assert_eq!(at_3.profile.unwrap().bio, "hello");
+
+        let policy = crate::SnapshotPolicy {
+            every_patches: Some(2),
+            every_bytes: None,
+        };
+        let mut log = crate::PatchLog::with_snapshot_policy(&user, policy).unwrap();
+        for patch in &patches {
+            log.append(patch.clone()).unwrap();
+        }
+        assert_eq!(log.latest_snapshot().unwrap().revision, 4);
+        for revision in 0..=4 {
+            assert_eq!(
+                serde_json::to_value(log.state_at(revision).unwrap()).unwrap(),
+                serde_json::to_value(crate::state_at(&user, &patches, revision as usize).unwrap())
+                    .unwrap()
+            );
+            assert_eq!(
+                log.between(revision, 4),
+                crate::between(&patches, revision as usize, 4)
+            );
+        }
+    }
+
+    #[test]
+    fn test_patch_envelope() {
+        use std::time::{Duration, SystemTime};
+
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let envelope = crate::PatchEnvelope {
+            author: Some("bob".to_string()),
+            timestamp: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            revision: Some(3),
+            comment: Some("birthday".to_string()),
+            ..crate::PatchEnvelope::new(json!({ "age": 31 }))
+        };
+        let json = serde_json::to_string(&envelope).unwrap();
+        let parsed: crate::PatchEnvelope = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, envelope);
+        assert_eq!(
+            serde_json::to_value(&envelope).unwrap()["timestamp"],
+            json!(1_700_000_000_000_i64)
+        );
+
+        // Timestamps are Unix milliseconds, negative before the epoch.
+        let before = crate::PatchEnvelope {
+            timestamp: Some(SystemTime::UNIX_EPOCH - Duration::from_millis(1_500)),
+            ..crate::PatchEnvelope::new(json!({}))
+        };
+        let wire = serde_json::to_value(&before).unwrap();
+        assert_eq!(wire, json!({ "patch": {}, "timestamp": -1_500 }));
+        assert_eq!(
+            serde_json::from_value::<crate::PatchEnvelope>(wire).unwrap(),
+            before
+        );
+        let parsed: crate::PatchEnvelope =
+            serde_json::from_str(r#"{ "patch": {}, "timestamp": 1700000000123 }"#).unwrap();
+        assert_eq!(
+            parsed.timestamp,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123))
+        );
+
+        assert_eq!(envelope.apply_to(user()).unwrap().age, 31);
+        assert_eq!(crate::apply_envelope(user(), &json).unwrap().age, 31);
+
+        // Metadata is optional; the patch is not, and metadata is never applied.
+        let bare: crate::PatchEnvelope =
+            serde_json::from_value(json!({ "patch": { "active": false } })).unwrap();
+        assert_eq!(bare, crate::PatchEnvelope::new(json!({ "active": false })));
+        assert!(matches!(
+            crate::apply_envelope(user(), r#"{ "age": 31 }"#),
+            Err(crate::Error::ParsePatch(_))
+        ));
+        let updated = crate::apply_envelope(user(), r#"{ "patch": {}, "author": "eve" }"#).unwrap();
+        assert_eq!(updated.username, "alice");
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_signed_envelopes() {
+        let user = || User {
+            id: 1,
+            username: "alice".to_string(),
+            age: 30,
+            active: true,
+            profile: None,
+        };
+        let key = b"webhook secret";
+        let envelope = crate::PatchEnvelope {
+            author: Some("bob".to_string()),
+            revision: Some(2),
+            ..crate::PatchEnvelope::new(
+                json!({ "age": 31, "profile": { "bio": "hi", "avatar_url": null } }),
+            )
+        };
+        let signed = crate::sign_patch(envelope, key).unwrap();
+        let signature = signed.signature.clone().unwrap();
+        assert_eq!(signature.len(), 64);
+        // Signing is deterministic and ignores any existing signature.
+        assert_eq!(crate::sign_patch(signed.clone(), key).unwrap(), signed);
+
+        // Key order and whitespace on the wire don't matter.
+        let relayed: crate::PatchEnvelope = serde_json::from_str(&format!(
+            r#"{{ "revision": 2, "signature": "{signature}", "patch": {{ "profile": {{ "avatar_url": null, "bio": "hi" }}, "age": 31 }}, "author": "bob" }}"#
+        ))
+        .unwrap();
+        let updated = crate::verify_and_apply(&relayed, key, user()).unwrap();
+        assert_eq!(
+            (updated.age, updated.profile.unwrap().bio.as_str()),
+            (31, "hi")
+        );
+
+        let invalid = |envelope: &crate::PatchEnvelope, key: &[u8]| {
+            matches!(
+                crate::verify_and_apply(envelope, key, user()),
+                Err(crate::Error::InvalidSignature)
+            )
+        };
+        assert!(invalid(&signed, b"other secret"));
+        let mut tampered = signed.clone();
+        tampered.author = Some("eve".to_string());
+        assert!(invalid(&tampered, key));
+        let mut tampered = signed.clone();
+        tampered.signature = Some(signature.to_uppercase().replace('A', "B"));
+        assert!(invalid(&tampered, key));
+        tampered.signature = Some("zz".repeat(32));
+        assert!(invalid(&tampered, key));
+        assert!(invalid(
+            &crate::PatchEnvelope::new(json!({ "age": 99 })),
+            key
+        ));
+    }
 }
@@ -0,0 +1,227 @@
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// Resource limits for patches from untrusted sources.
+///
+/// Checked while the patch is parsed, so an oversized patch is rejected
+/// before it is built, let alone merged: the size up front, nesting and
+/// member count as each object or array is read. Violations are reported as
+/// [`Error::LimitExceeded`](crate::Error::LimitExceeded). Unset limits aren't
+/// checked, though `serde_json` still stops nesting at 128 levels.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::{ApplyLimits, Error, Limit};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// let limits = ApplyLimits { max_bytes: Some(64), max_depth: Some(2), max_keys: Some(8) };
+/// let user = User { name: "alice".into(), age: 30 };
+///
+/// let user = serde_patch::apply_limited(user, r#"{ "age": 31 }"#, &limits).unwrap();
+/// assert_eq!(user.age, 31);
+///
+/// let err = serde_patch::apply_limited(user, r#"{ "name": { "a": { "b": 1 } } }"#, &limits)
+///     .err()
+///     .unwrap();
+/// assert!(matches!(err, Error::LimitExceeded { limit: Limit::Depth, max: 2 }));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ApplyLimits {
+    /// Largest accepted patch, in bytes.
+    pub max_bytes: Option<usize>,
+    /// Deepest accepted nesting of objects and arrays; `{}` has depth 1.
+    pub max_depth: Option<usize>,
+    /// Most object members accepted, counted over all levels.
+    pub max_keys: Option<usize>,
+}
+
+/// Which of the [`ApplyLimits`] a patch exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Limit {
+    /// [`ApplyLimits::max_bytes`].
+    Bytes,
+    /// [`ApplyLimits::max_depth`].
+    Depth,
+    /// [`ApplyLimits::max_keys`].
+    Keys,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Bytes => "bytes",
+            Limit::Depth => "levels of nesting",
+            Limit::Keys => "keys",
+        })
+    }
+}
+
+impl ApplyLimits {
+    /// Parses a patch, failing as soon as it exceeds a limit.
+    ///
+    /// Syntax errors are reported as [`Error::ParsePatch`](crate::Error::ParsePatch).
+    pub fn parse(&self, patch: &[u8]) -> Result<Value, crate::Error> {
+        if let Some(max) = self.max_bytes
+            && patch.len() > max
+        {
+            return Err(crate::Error::LimitExceeded {
+                limit: Limit::Bytes,
+                max,
+            });
+        }
+        let mut budget = Budget {
+            limits: self,
+            keys: 0,
+            exceeded: None,
+        };
+        let mut deserializer = serde_json::Deserializer::from_slice(patch);
+        let parsed = Limited {
+            budget: &mut budget,
+            depth: 0,
+        }
+        .deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|()| value));
+        match (parsed, budget.exceeded) {
+            (_, Some((limit, max))) => Err(crate::Error::LimitExceeded { limit, max }),
+            (parsed, None) => parsed.map_err(crate::Error::ParsePatch),
+        }
+    }
+}
+
+/// Applies a JSON Merge Patch (RFC 7396) from an untrusted source.
+///
+/// Like [`apply`](crate::apply), with the patch parsed under `limits`; see
+/// [`ApplyLimits`].
+pub fn apply_limited<T, P>(current: T, patch: P, limits: &ApplyLimits) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+    P: AsRef<[u8]>,
+{
+    let patch_val = limits.parse(patch.as_ref())?;
+    let current_val = crate::error::serialize(&current)?;
+    crate::error::deserialize_patched(&current_val, &patch_val)
+}
+
+/// What parsing has used up so far, and the first limit exceeded (internal).
+struct Budget<'l> {
+    limits: &'l ApplyLimits,
+    keys: usize,
+    exceeded: Option<(Limit, usize)>,
+}
+
+impl Budget<'_> {
+    fn exceed<E: de::Error>(&mut self, limit: Limit, max: usize) -> E {
+        self.exceeded = Some((limit, max));
+        E::custom(format_args!("patch exceeds the limit of {max} {limit}"))
+    }
+
+    fn enter<E: de::Error>(&mut self, depth: usize) -> Result<(), E> {
+        match self.limits.max_depth {
+            Some(max) if depth > max => Err(self.exceed(Limit::Depth, max)),
+            _ => Ok(()),
+        }
+    }
+
+    fn add_key<E: de::Error>(&mut self) -> Result<(), E> {
+        self.keys += 1;
+        match self.limits.max_keys {
+            Some(max) if self.keys > max => Err(self.exceed(Limit::Keys, max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parses one value within the budget (internal).
+struct Limited<'b, 'l> {
+    budget: &'b mut Budget<'l>,
+    /// Nesting of the value's container.
+    depth: usize,
+}
+
+impl<'de> DeserializeSeed<'de> for Limited<'_, '_> {
+    type Value = Value;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Limited<'_, '_> {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::from(value))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let depth = self.depth + 1;
+        self.budget.enter(depth)?;
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element_seed(Limited {
+            budget: &mut *self.budget,
+            depth,
+        })? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut pending = map.next_key::<String>()?;
+        // With `arbitrary-precision`, numbers arrive as a single-member map.
+        #[cfg(feature = "arbitrary-precision")]
+        if pending.as_deref() == Some(crate::value::NUMBER_TOKEN) {
+            let digits = map.next_value::<String>()?;
+            return digits.parse().map(Value::Number).map_err(de::Error::custom);
+        }
+        let depth = self.depth + 1;
+        self.budget.enter(depth)?;
+        let mut members = Map::new();
+        while let Some(key) = match pending.take() {
+            Some(key) => Some(key),
+            None => map.next_key::<String>()?,
+        } {
+            self.budget.add_key()?;
+            let value = map.next_value_seed(Limited {
+                budget: &mut *self.budget,
+                depth,
+            })?;
+            members.insert(key, value);
+        }
+        Ok(Value::Object(members))
+    }
+}
@@ -0,0 +1,103 @@
+//! Serde helpers for `PathBuf` fields that may hold non-UTF-8 paths.
+//!
+//! By default such paths make [`diff`](crate::diff) and [`apply`](crate::apply)
+//! fail with an error naming the field. Annotating the field with
+//! `#[serde(with = "serde_patch::lossy_path")]` writes UTF-8 paths as strings
+//! and other paths as an array of their raw bytes, so such paths diff and
+//! apply without losing any byte. Reading accepts either form.
+//! Raw bytes are only available on Unix; elsewhere a non-UTF-8 path is still
+//! an error naming the field.
+//!
+//! ```
+//! use std::path::PathBuf;
+//!
+//! #[derive(serde::Serialize, serde::Deserialize)]
+//! struct Entry {
+//!     #[serde(with = "serde_patch::lossy_path")]
+//!     path: PathBuf,
+//! }
+//! ```
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserializer, Serializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Serializes a path as a string, or as its raw bytes if it isn't UTF-8.
+pub fn serialize<P, S>(path: &P, serializer: S) -> Result<S::Ok, S::Error>
+where
+    P: AsRef<Path>,
+    S: Serializer,
+{
+    let path = path.as_ref();
+    if let Some(text) = path.to_str() {
+        return serializer.serialize_str(text);
+    }
+    #[cfg(unix)]
+    {
+        use serde::ser::SerializeSeq;
+        use std::os::unix::ffi::OsStrExt;
+
+        let bytes = path.as_os_str().as_bytes();
+        let mut seq = serializer.serialize_seq(Some(bytes.len()))?;
+        for byte in bytes {
+            seq.serialize_element(byte)?;
+        }
+        seq.end()
+    }
+    #[cfg(not(unix))]
+    {
+        Err(serde::ser::Error::custom(
+            "path contains invalid UTF-8 characters",
+        ))
+    }
+}
+
+/// Deserializes a path from a string or an array of raw bytes.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<PathBuf, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(PathVisitor)
+}
+
+struct PathVisitor;
+
+impl<'de> Visitor<'de> for PathVisitor {
+    type Value = PathBuf;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a path string or an array of bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<PathBuf, E> {
+        Ok(PathBuf::from(text))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<PathBuf, E> {
+        from_bytes(bytes.to_vec())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PathBuf, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            bytes.push(byte);
+        }
+        from_bytes(bytes)
+    }
+}
+
+fn from_bytes<E: de::Error>(bytes: Vec<u8>) -> Result<PathBuf, E> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+
+        Ok(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+    }
+    #[cfg(not(unix))]
+    {
+        String::from_utf8(bytes)
+            .map(PathBuf::from)
+            .map_err(|_| E::custom("path contains invalid UTF-8 characters"))
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A patch field that is either absent, explicitly `null`, or set.
+///
+/// For hand-written patch structs: with `#[serde(default, skip_serializing_if
+/// = "MaybeAbsent::is_absent")]`, a missing field deserializes to `Absent`,
+/// `null` to `Null` and anything else to `Value`, and the struct serializes
+/// back to the merge patch it was read from, so it can be passed to
+/// [`apply_typed`](crate::apply_typed).
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::MaybeAbsent;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, nickname: Option<String> }
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct UserChanges {
+///     #[serde(default, skip_serializing_if = "MaybeAbsent::is_absent")]
+///     name: MaybeAbsent<String>,
+///     #[serde(default, skip_serializing_if = "MaybeAbsent::is_absent")]
+///     nickname: MaybeAbsent<String>,
+/// }
+///
+/// let changes: UserChanges = serde_json::from_str(r#"{ "nickname": null }"#).unwrap();
+/// assert!(changes.name.is_absent());
+/// assert!(changes.nickname.is_null());
+///
+/// let user = User { name: "alice".into(), nickname: Some("al".into()) };
+/// let user = serde_patch::apply_typed(user, &changes).unwrap();
+/// assert_eq!((user.name.as_str(), user.nickname), ("alice", None));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MaybeAbsent<T> {
+    /// Not in the patch: leave the field unchanged.
+    #[default]
+    Absent,
+    /// `null` in the patch: clear the field.
+    Null,
+    /// Set the field to this value.
+    Value(T),
+}
+
+impl<T> MaybeAbsent<T> {
+    /// Returns `true` for [`MaybeAbsent::Absent`].
+    pub fn is_absent(&self) -> bool {
+        matches!(self, MaybeAbsent::Absent)
+    }
+
+    /// Returns `true` for [`MaybeAbsent::Null`].
+    pub fn is_null(&self) -> bool {
+        matches!(self, MaybeAbsent::Null)
+    }
+
+    /// The set value, if any.
+    pub fn as_value(&self) -> Option<&T> {
+        match self {
+            MaybeAbsent::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Converts to the double-option form: `None`, `Some(None)` or `Some(Some(value))`.
+    pub fn into_option(self) -> Option<Option<T>> {
+        match self {
+            MaybeAbsent::Absent => None,
+            MaybeAbsent::Null => Some(None),
+            MaybeAbsent::Value(value) => Some(Some(value)),
+        }
+    }
+
+    /// Applies the change to an optional field: clears it on `Null`, sets it on `Value`.
+    pub fn apply_to(self, field: &mut Option<T>) {
+        if let Some(value) = self.into_option() {
+            *field = value;
+        }
+    }
+}
+
+impl<T> From<Option<Option<T>>> for MaybeAbsent<T> {
+    fn from(value: Option<Option<T>>) -> Self {
+        match value {
+            None => MaybeAbsent::Absent,
+            Some(None) => MaybeAbsent::Null,
+            Some(Some(value)) => MaybeAbsent::Value(value),
+        }
+    }
+}
+
+/// `Absent` and `Null` both serialize as `null`; skip `Absent` fields with
+/// `skip_serializing_if = "MaybeAbsent::is_absent"`.
+impl<T: Serialize> Serialize for MaybeAbsent<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MaybeAbsent::Value(value) => serializer.serialize_some(value),
+            _ => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Only called for present fields; `#[serde(default)]` yields `Absent` for missing ones.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for MaybeAbsent<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            None => MaybeAbsent::Null,
+            Some(value) => MaybeAbsent::Value(value),
+        })
+    }
+}
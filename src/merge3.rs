@@ -0,0 +1,161 @@
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// A path changed differently on both sides of a three-way merge.
+///
+/// `None` means the field is absent on that side.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Conflict {
+    /// Dotted path of the field (empty for the root).
+    pub path: String,
+    /// Value in the common base.
+    pub base: Option<Value>,
+    /// Value on our side.
+    pub ours: Option<Value>,
+    /// Value on their side.
+    pub theirs: Option<Value>,
+}
+
+/// How a [`Conflict`] is resolved by the resolver passed to [`merge3`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resolution {
+    /// Keep our side (removing the field if we removed it).
+    Ours,
+    /// Keep their side (removing the field if they removed it).
+    Theirs,
+    /// Use the given value.
+    Value(Value),
+    /// Leave the base value in place and report the conflict in [`MergeOutcome::deferred`].
+    Defer,
+}
+
+/// Result of [`merge3`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MergeOutcome<T> {
+    /// The merged value, deferred conflicts left at their base value.
+    pub merged: T,
+    /// Conflicts the resolver deferred, in path order.
+    pub deferred: Vec<Conflict>,
+}
+
+/// Three-way merges two edits of a common base value.
+///
+/// Changes made on only one side are taken as-is; objects changed on both
+/// sides are merged key by key. Where both sides changed the same field to
+/// different values, `resolve` is called with the conflict and decides the
+/// outcome, which lets UI-driven flows plug straight into the merge.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::Resolution;
+///
+/// #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+/// struct Doc { title: String, body: String, tags: Vec<String> }
+///
+/// let base = Doc { title: "t".into(), body: "b".into(), tags: vec![] };
+/// let ours = Doc { title: "ours".into(), body: "b".into(), tags: vec!["x".into()] };
+/// let theirs = Doc { title: "theirs".into(), body: "b2".into(), tags: vec![] };
+///
+/// let outcome = serde_patch::merge3(&base, &ours, &theirs, |conflict| {
+///     assert_eq!(conflict.path, "title");
+///     Resolution::Theirs
+/// })
+/// .unwrap();
+///
+/// assert_eq!(outcome.merged, Doc { title: "theirs".into(), body: "b2".into(), tags: vec!["x".into()] });
+/// assert!(outcome.deferred.is_empty());
+/// ```
+pub fn merge3<T, F>(
+    base: &T,
+    ours: &T,
+    theirs: &T,
+    mut resolve: F,
+) -> Result<MergeOutcome<T>, serde_json::Error>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnMut(&Conflict) -> Resolution,
+{
+    let base_val = crate::value::to_value(base)?;
+    let ours_val = crate::value::to_value(ours)?;
+    let theirs_val = crate::value::to_value(theirs)?;
+
+    let mut deferred = Vec::new();
+    let merged = merge_values(
+        Some(&base_val),
+        Some(&ours_val),
+        Some(&theirs_val),
+        "",
+        &mut resolve,
+        &mut deferred,
+    );
+
+    Ok(MergeOutcome {
+        merged: serde_json::from_value(merged.unwrap_or(Value::Null))?,
+        deferred,
+    })
+}
+
+/// Recursively merges three versions of a value; `None` means absent (internal).
+fn merge_values<F>(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    current_path: &str,
+    resolve: &mut F,
+    deferred: &mut Vec<Conflict>,
+) -> Option<Value>
+where
+    F: FnMut(&Conflict) -> Resolution,
+{
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+
+    if let (Some(Value::Object(ours_map)), Some(Value::Object(theirs_map))) = (ours, theirs) {
+        let empty = Map::new();
+        let base_map = base.and_then(Value::as_object).unwrap_or(&empty);
+
+        let keys: BTreeSet<&String> = base_map
+            .keys()
+            .chain(ours_map.keys())
+            .chain(theirs_map.keys())
+            .collect();
+        let mut merged = Map::new();
+        for key in keys {
+            let full_path = crate::paths::join(current_path, key);
+            if let Some(value) = merge_values(
+                base_map.get(key),
+                ours_map.get(key),
+                theirs_map.get(key),
+                &full_path,
+                resolve,
+                deferred,
+            ) {
+                merged.insert(key.clone(), value);
+            }
+        }
+        return Some(Value::Object(merged));
+    }
+
+    let conflict = Conflict {
+        path: current_path.to_string(),
+        base: base.cloned(),
+        ours: ours.cloned(),
+        theirs: theirs.cloned(),
+    };
+    match resolve(&conflict) {
+        Resolution::Ours => conflict.ours,
+        Resolution::Theirs => conflict.theirs,
+        Resolution::Value(value) => Some(value),
+        Resolution::Defer => {
+            let base = conflict.base.clone();
+            deferred.push(conflict);
+            base
+        }
+    }
+}
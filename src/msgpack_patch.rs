@@ -0,0 +1,66 @@
+use crate::Format;
+use serde::de::Error as _;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+/// MessagePack as a [`Format`].
+///
+/// Maps are written with field names; map keys must be strings, and binary
+/// and extension values are rejected.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MsgPack;
+
+impl Format for MsgPack {
+    fn parse(bytes: &[u8]) -> Result<Value, serde_json::Error> {
+        rmp_serde::from_slice(bytes).map_err(serde_json::Error::custom)
+    }
+
+    fn serialize(value: &Value) -> Result<Vec<u8>, serde_json::Error> {
+        rmp_serde::to_vec_named(value).map_err(serde_json::Error::custom)
+    }
+}
+
+/// Computes a JSON Merge Patch (RFC 7396) and encodes it as MessagePack.
+///
+/// The patch is the one [`diff`](crate::diff) returns; removed fields are
+/// encoded as MessagePack `null`.
+///
+/// # Example
+///
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Reading { sensor: String, celsius: f32 }
+///
+/// let old = Reading { sensor: "t1".into(), celsius: 20.5 };
+/// let new = Reading { sensor: "t1".into(), celsius: 21.0 };
+///
+/// let patch = serde_patch::diff_to_msgpack(&old, &new).unwrap();
+/// let updated = serde_patch::apply_msgpack(old, &patch).unwrap();
+/// assert_eq!(updated.celsius, 21.0);
+/// ```
+pub fn diff_to_msgpack<T: Serialize>(old: &T, new: &T) -> Result<Vec<u8>, serde_json::Error> {
+    MsgPack::diff(old, new)
+}
+
+/// Encodes a merge patch as MessagePack.
+pub fn to_msgpack(patch: &Value) -> Result<Vec<u8>, serde_json::Error> {
+    MsgPack::serialize(patch)
+}
+
+/// Decodes a MessagePack-encoded merge patch.
+///
+/// Accepts the same input as [`MsgPack`].
+pub fn parse_msgpack(patch: &[u8]) -> Result<Value, serde_json::Error> {
+    MsgPack::parse(patch)
+}
+
+/// Applies a MessagePack-encoded JSON Merge Patch (RFC 7396).
+///
+/// Like [`apply`](crate::apply); a patch that can't be decoded is reported as
+/// [`Error::ParsePatch`](crate::Error::ParsePatch).
+pub fn apply_msgpack<T>(current: T, patch: &[u8]) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    MsgPack::apply(current, patch)
+}
@@ -0,0 +1,137 @@
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::marker::PhantomData;
+
+/// Applies newline-delimited merge patches (NDJSON), one per line, to a
+/// document in order.
+///
+/// The returned [`ApplyStream`] yields the document after each patch; call
+/// [`finish`](ApplyStream::finish) for the final state only. Blank lines are
+/// skipped. The first failure ends the stream, and
+/// [`line`](ApplyStream::line) tells which line caused it.
+///
+/// # Example
+///
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize, Debug)]
+/// struct Order { id: u32, status: String, total: u32 }
+///
+/// let order = Order { id: 7, status: "new".into(), total: 10 };
+/// let changes = "{\"status\":\"paid\"}\n{\"total\":12}\n\n{\"status\":\"shipped\"}\n";
+///
+/// let states: Vec<Order> = serde_patch::apply_stream(order, changes.lines())
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(states.len(), 3);
+/// assert_eq!((states[1].status.as_str(), states[1].total), ("paid", 12));
+///
+/// let order = Order { id: 7, status: "new".into(), total: 10 };
+/// let last = serde_patch::apply_stream(order, changes.lines()).finish().unwrap();
+/// assert_eq!(last.status, "shipped");
+/// ```
+pub fn apply_stream<T, I>(document: T, lines: I) -> ApplyStream<T, I::IntoIter>
+where
+    T: Serialize + DeserializeOwned,
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let (current, error) = match crate::error::serialize(&document) {
+        Ok(current) => (Some(current), None),
+        Err(err) => (None, Some(err)),
+    };
+    ApplyStream {
+        current,
+        error,
+        lines: lines.into_iter(),
+        line: 0,
+        marker: PhantomData,
+    }
+}
+
+/// Iterator returned by [`apply_stream`].
+pub struct ApplyStream<T, I> {
+    /// The document so far; `None` once the stream has failed.
+    current: Option<Value>,
+    /// A failure to report on the next call.
+    error: Option<crate::Error>,
+    lines: I,
+    line: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T, I> ApplyStream<T, I>
+where
+    T: DeserializeOwned,
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    /// Number of lines read so far, blank ones included; after a failure, the
+    /// line of the offending patch.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Applies the remaining patches and returns the final document.
+    ///
+    /// Only the final document has to fit `T`, as if the patches were applied
+    /// as one.
+    pub fn finish(mut self) -> Result<T, crate::Error> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        let Some(mut current) = self.current.take() else {
+            return Err(crate::Error::ParsePatch(serde::de::Error::custom(
+                "patch stream has already failed",
+            )));
+        };
+        while let Some(patch) = self.next_patch() {
+            crate::apply_patch::merge_patch(&mut current, &patch?);
+        }
+        crate::error::deserialize_patched(&current, &Value::Object(Map::new()))
+    }
+
+    /// Parses the next non-blank line (internal).
+    fn next_patch(&mut self) -> Option<Result<Value, crate::Error>> {
+        loop {
+            let line = self.lines.next()?;
+            self.line += 1;
+            let line = line.as_ref().trim();
+            if !line.is_empty() {
+                return Some(
+                    crate::backend::from_slice(line.as_bytes()).map_err(crate::Error::ParsePatch),
+                );
+            }
+        }
+    }
+}
+
+impl<T, I> Iterator for ApplyStream<T, I>
+where
+    T: DeserializeOwned,
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    type Item = Result<T, crate::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+        self.current.as_ref()?;
+        let patch = match self.next_patch()? {
+            Ok(patch) => patch,
+            Err(err) => {
+                self.current = None;
+                return Some(Err(err));
+            }
+        };
+        let current = self.current.as_mut()?;
+        let result = crate::error::deserialize_patched(current, &patch);
+        if result.is_ok() {
+            crate::apply_patch::merge_patch(current, &patch);
+        } else {
+            self.current = None;
+        }
+        Some(result)
+    }
+}
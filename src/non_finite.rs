@@ -0,0 +1,501 @@
+use serde::ser::{self, Serialize};
+use serde_json::Value;
+
+/// How diffs handle `NaN` and infinite floats, which JSON can't represent.
+///
+/// `serde_json` turns them into `null`, which a merge patch reads as "remove
+/// the field", so by default diffing fails instead when one would end up in
+/// the patch, naming the field. Unchanged non-finite fields are fine.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{DiffOptions, NonFinite};
+///
+/// #[derive(serde::Serialize)]
+/// struct Stats { mean: f64, count: u32 }
+///
+/// let old = Stats { mean: 1.5, count: 2 };
+/// let new = Stats { mean: f64::NAN, count: 0 };
+///
+/// let err = serde_patch::diff(&old, &new).unwrap_err();
+/// assert_eq!(err.to_string(), "non-finite float NaN at `mean`");
+/// assert_eq!(serde_patch::diff(&new, &new).unwrap(), json!({}));
+///
+/// let options = DiffOptions { non_finite: NonFinite::String, ..Default::default() };
+/// let patch = serde_patch::diff_with(&old, &new, &options).unwrap();
+/// assert_eq!(patch, json!({ "mean": "NaN", "count": 0 }));
+///
+/// let options = DiffOptions { non_finite: NonFinite::Skip, ..Default::default() };
+/// let patch = serde_patch::diff_with(&old, &new, &options).unwrap();
+/// assert_eq!(patch, json!({ "count": 0 }));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFinite {
+    /// Fail if one would be written to the patch. The error is a data error
+    /// (`is_data()`) reading ``non-finite float <NaN|Infinity|-Infinity> at `<path>` ``,
+    /// with the dotted path of the field.
+    #[default]
+    Error,
+    /// Serialize them as `null`, like `serde_json` (the field is removed on apply).
+    Null,
+    /// Serialize them as the strings `"NaN"`, `"Infinity"` and `"-Infinity"`.
+    String,
+    /// Leave fields holding them on either side out of the patch.
+    Skip,
+}
+
+/// A non-finite float found while serializing (internal).
+pub(crate) struct Found {
+    tokens: Vec<String>,
+    value: f64,
+}
+
+impl Found {
+    fn pointer(&self) -> String {
+        let mut pointer = String::new();
+        for token in &self.tokens {
+            pointer.push('/');
+            pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+        }
+        pointer
+    }
+
+    fn dotted(&self) -> String {
+        self.tokens.iter().fold(String::new(), |path, token| {
+            crate::paths::join(&path, token)
+        })
+    }
+
+    fn sentinel(&self) -> &'static str {
+        if self.value.is_nan() {
+            "NaN"
+        } else if self.value > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        }
+    }
+}
+
+/// Serializes a value for diffing, locating the non-finite floats `serde_json` turned into `null` (internal).
+pub(crate) fn to_value<T: Serialize + ?Sized>(
+    value: &T,
+) -> Result<(Value, Vec<Found>), serde_json::Error> {
+    let json = crate::value::to_value(value)?;
+    if !contains_null(&json) {
+        return Ok((json, Vec::new()));
+    }
+    let mut finder = Finder::default();
+    value.serialize(&mut finder)?;
+    Ok((json, finder.found))
+}
+
+/// Applies `policy` to the non-finite floats found in both sides of a diff (internal).
+pub(crate) fn resolve(
+    old: &mut Value,
+    old_found: &[Found],
+    new: &mut Value,
+    new_found: &[Found],
+    policy: NonFinite,
+) -> Result<(), serde_json::Error> {
+    match policy {
+        NonFinite::Null => {}
+        NonFinite::Error if !new_found.is_empty() => {
+            // Compare with each float marked, so unchanged ones aren't reported.
+            let (mut old_marked, mut new_marked) = (old.clone(), new.clone());
+            substitute(&mut old_marked, old_found, "\0");
+            substitute(&mut new_marked, new_found, "\0");
+            if let Some(found) = new_found
+                .iter()
+                .find(|found| emitted(&old_marked, &new_marked, &found.tokens))
+            {
+                return Err(ser::Error::custom(format!(
+                    "non-finite float {} at `{}`",
+                    found.sentinel(),
+                    found.dotted()
+                )));
+            }
+        }
+        NonFinite::Error => {}
+        NonFinite::String => {
+            substitute(old, old_found, "");
+            substitute(new, new_found, "");
+        }
+        NonFinite::Skip => {
+            // Make both sides agree on every such field, so it produces no change.
+            for found in new_found {
+                let pointer = found.pointer();
+                match old.pointer(&pointer) {
+                    Some(previous) => set(new, &pointer, Some(previous.clone())),
+                    None => set(new, &pointer, None),
+                }
+            }
+            for found in old_found {
+                let pointer = found.pointer();
+                match new.pointer(&pointer) {
+                    Some(current) => set(old, &pointer, Some(current.clone())),
+                    None => set(old, &pointer, None),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Replaces each found float with its sentinel string, after `prefix` (internal).
+fn substitute(value: &mut Value, found: &[Found], prefix: &str) {
+    for found in found {
+        if let Some(target) = value.pointer_mut(&found.pointer()) {
+            *target = Value::String(format!("{prefix}{}", found.sentinel()));
+        }
+    }
+}
+
+/// Whether a merge patch from `old` to `new` contains the value at `tokens`:
+/// objects are diffed member by member, anything else is replaced whole (internal).
+fn emitted(old: &Value, new: &Value, tokens: &[String]) -> bool {
+    let (mut old, mut new) = (Some(old), new);
+    for token in tokens {
+        match (old, new) {
+            (Some(Value::Object(old_map)), Value::Object(new_map)) => {
+                let Some(next) = new_map.get(token) else {
+                    return false;
+                };
+                old = old_map.get(token);
+                new = next;
+            }
+            _ => break,
+        }
+    }
+    old != Some(new)
+}
+
+/// Sets or removes the value at `pointer`; array elements are set to `null` instead of removed.
+fn set(root: &mut Value, pointer: &str, value: Option<Value>) {
+    let Some((parent, token)) = pointer.rsplit_once('/') else {
+        return;
+    };
+    let token = token.replace("~1", "/").replace("~0", "~");
+    match (root.pointer_mut(parent), value) {
+        (Some(Value::Object(map)), Some(value)) => {
+            map.insert(token, value);
+        }
+        (Some(Value::Object(map)), None) => {
+            map.remove(&token);
+        }
+        (Some(target @ Value::Array(_)), value) => {
+            if let Some(element) = target.get_mut(token.parse::<usize>().unwrap_or(usize::MAX)) {
+                *element = value.unwrap_or(Value::Null);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn contains_null(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.iter().any(contains_null),
+        Value::Object(map) => map.values().any(contains_null),
+        _ => false,
+    }
+}
+
+/// Walks a value like `serde_json::value::Serializer`, recording where non-finite floats are.
+#[derive(Default)]
+struct Finder {
+    tokens: Vec<String>,
+    found: Vec<Found>,
+}
+
+impl Finder {
+    fn float(&mut self, value: f64) {
+        if !value.is_finite() {
+            self.found.push(Found {
+                tokens: self.tokens.clone(),
+                value,
+            });
+        }
+    }
+
+    fn nested<T: Serialize + ?Sized>(
+        &mut self,
+        token: String,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.tokens.push(token);
+        let result = value.serialize(&mut *self);
+        self.tokens.pop();
+        result
+    }
+
+    fn compound(&mut self, variant: Option<&str>) -> Compound<'_> {
+        if let Some(variant) = variant {
+            self.tokens.push(variant.to_string());
+        }
+        Compound {
+            finder: self,
+            index: 0,
+            key: None,
+            variant: variant.is_some(),
+        }
+    }
+}
+
+struct Compound<'a> {
+    finder: &'a mut Finder,
+    index: usize,
+    key: Option<String>,
+    variant: bool,
+}
+
+impl Compound<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), serde_json::Error> {
+        let index = self.index;
+        self.index += 1;
+        self.finder.nested(index.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        if self.variant {
+            self.finder.tokens.pop();
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Finder {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, _: bool) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_i8(self, _: i8) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_i16(self, _: i16) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_i32(self, _: i32) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_i64(self, _: i64) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_i128(self, _: i128) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_u8(self, _: u8) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_u16(self, _: u16) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_u32(self, _: u32) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_u64(self, _: u64) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_u128(self, _: u128) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_f32(self, value: f32) -> Result<(), serde_json::Error> {
+        self.float(f64::from(value));
+        Ok(())
+    }
+    fn serialize_f64(self, value: f64) -> Result<(), serde_json::Error> {
+        self.float(value);
+        Ok(())
+    }
+    fn serialize_char(self, _: char) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_str(self, _: &str) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), serde_json::Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        self.nested(variant.to_string(), value)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Compound<'a>, serde_json::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Compound<'a>, serde_json::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, serde_json::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, serde_json::Error> {
+        Ok(self.compound(Some(variant)))
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Compound<'a>, serde_json::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, serde_json::Error> {
+        Ok(self.compound(None))
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, serde_json::Error> {
+        Ok(self.compound(Some(variant)))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        // Keys are rendered the way `serde_json` renders them as object keys.
+        self.key = Some(match crate::value::to_value(key)? {
+            Value::String(key) => key,
+            other => other.to_string(),
+        });
+        Ok(())
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        let key = self.key.take().unwrap_or_default();
+        self.finder.nested(key, value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.finder.nested(key.to_string(), value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.finder.nested(key.to_string(), value)
+    }
+    fn end(self) -> Result<(), Self::Error> {
+        Compound::end(self)
+    }
+}
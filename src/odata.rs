@@ -0,0 +1,295 @@
+//! OData v4 delta payloads.
+//!
+//! Services such as Dynamics 365 and SharePoint take `PATCH` bodies holding
+//! only the changed properties of an entity, identified by an `@odata.id`
+//! annotation. Navigation properties pointing at other entities are updated
+//! through `@odata.bind` references, nested deltas and `@delta` collections.
+
+use serde::Serialize;
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// The entity set and key property of the entities a navigation property
+/// points at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntitySet {
+    /// Entity set name, e.g. `Contacts`.
+    pub name: String,
+    /// Key property, e.g. `contactid`.
+    pub key: String,
+}
+
+impl EntitySet {
+    /// Describes the entity set `name` keyed by `key`.
+    pub fn new(name: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            key: key.into(),
+        }
+    }
+
+    /// The entity id of the entity whose key is `key`, e.g. `Contacts(42)`.
+    pub fn id(&self, key: &Value) -> String {
+        entity_id(&self.name, key)
+    }
+}
+
+/// Options for [`delta`].
+#[derive(Clone, Debug, Default)]
+pub struct ODataOptions {
+    /// Navigation properties by dotted path. Properties not listed are
+    /// structural: complex values are diffed property by property and
+    /// collections replaced as a whole.
+    pub navigation: HashMap<String, EntitySet>,
+}
+
+impl ODataOptions {
+    /// Registers the navigation property at `path`.
+    pub fn navigation(mut self, path: impl Into<String>, entity_set: EntitySet) -> Self {
+        self.navigation.insert(path.into(), entity_set);
+        self
+    }
+}
+
+/// Formats an entity id from an entity set and a key value.
+///
+/// String keys are quoted, doubling embedded quotes, as OData URL literals
+/// require; other keys are written as they are.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// assert_eq!(serde_patch::odata::entity_id("Customers", &json!("O'Neil")), "Customers('O''Neil')");
+/// assert_eq!(serde_patch::odata::entity_id("Orders", &json!(10248)), "Orders(10248)");
+/// ```
+pub fn entity_id(entity_set: &str, key: &Value) -> String {
+    match key {
+        Value::String(text) => format!("{entity_set}('{}')", text.replace('\'', "''")),
+        other => format!("{entity_set}({other})"),
+    }
+}
+
+/// Computes the OData delta payload turning the entity `old` into `new`.
+///
+/// The payload carries `@odata.id` set to `id`, plus every changed property;
+/// removed properties are `null`. Navigation properties registered in
+/// [`ODataOptions::navigation`] become:
+///
+/// - `"<name>@odata.bind": "<id>"` when a single-valued property points at a
+///   different entity, or a nested delta with its own `@odata.id` when the
+///   same entity changed;
+/// - `"<name>@delta": [...]` for collections, listing added and changed
+///   entities and `{ "@odata.id": ..., "@odata.removed": { "reason": "changed" } }`
+///   for each entity no longer linked.
+///
+/// A navigation entity without its key property is an error naming its path.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::odata::{EntitySet, ODataOptions};
+///
+/// let old = json!({
+///     "accountid": 7,
+///     "name": "Contoso",
+///     "address": { "city": "Seattle", "line1": "1 Main St" },
+///     "primarycontact": { "contactid": 1, "fullname": "Ann" },
+/// });
+/// let new = json!({
+///     "accountid": 7,
+///     "name": "Contoso Ltd",
+///     "address": { "city": "Redmond", "line1": "1 Main St" },
+///     "primarycontact": { "contactid": 2, "fullname": "Bob" },
+/// });
+///
+/// let options = ODataOptions::default()
+///     .navigation("primarycontact", EntitySet::new("contacts", "contactid"));
+/// let payload = serde_patch::odata::delta(&old, &new, "accounts(7)", &options).unwrap();
+/// assert_eq!(payload, json!({
+///     "@odata.id": "accounts(7)",
+///     "address": { "city": "Redmond" },
+///     "name": "Contoso Ltd",
+///     "primarycontact@odata.bind": "contacts(2)",
+/// }));
+/// ```
+pub fn delta<T: Serialize>(
+    old: &T,
+    new: &T,
+    id: &str,
+    options: &ODataOptions,
+) -> Result<Value, serde_json::Error> {
+    let old = crate::value::to_value(old)?;
+    let new = crate::value::to_value(new)?;
+    let (Value::Object(old_map), Value::Object(new_map)) = (&old, &new) else {
+        return Err(serde_json::Error::custom(
+            "an OData delta can only be built from entities (objects)",
+        ));
+    };
+    let mut payload = Map::new();
+    payload.insert(ODATA_ID.to_string(), Value::String(id.to_string()));
+    payload.extend(diff_properties(old_map, new_map, options, "")?);
+    Ok(Value::Object(payload))
+}
+
+const ODATA_ID: &str = "@odata.id";
+
+fn diff_properties(
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    options: &ODataOptions,
+    current_path: &str,
+) -> Result<Map<String, Value>, serde_json::Error> {
+    let mut payload = Map::new();
+    for key in old.keys().filter(|key| !new.contains_key(*key)) {
+        payload.insert(key.clone(), Value::Null);
+    }
+    for (key, new_value) in new {
+        let full_path = crate::paths::join(current_path, key);
+        let old_value = old.get(key).unwrap_or(&Value::Null);
+        if old_value == new_value {
+            continue;
+        }
+        if let Some(entity_set) = options.navigation.get(&full_path) {
+            diff_navigation(
+                &mut payload,
+                key,
+                old_value,
+                new_value,
+                entity_set,
+                options,
+                &full_path,
+            )?;
+            continue;
+        }
+        match (old_value, new_value) {
+            (Value::Object(old_map), Value::Object(new_map)) => {
+                let nested = diff_properties(old_map, new_map, options, &full_path)?;
+                payload.insert(key.clone(), Value::Object(nested));
+            }
+            _ => {
+                payload.insert(key.clone(), new_value.clone());
+            }
+        }
+    }
+    Ok(payload)
+}
+
+fn diff_navigation(
+    payload: &mut Map<String, Value>,
+    key: &str,
+    old: &Value,
+    new: &Value,
+    entity_set: &EntitySet,
+    options: &ODataOptions,
+    current_path: &str,
+) -> Result<(), serde_json::Error> {
+    match (old, new) {
+        (_, Value::Null) => {
+            payload.insert(key.to_string(), Value::Null);
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            let entries = diff_collection(old_items, new_items, entity_set, options, current_path)?;
+            if !entries.is_empty() {
+                payload.insert(format!("{key}@delta"), Value::Array(entries));
+            }
+        }
+        (_, Value::Array(new_items)) => {
+            let entries = diff_collection(&[], new_items, entity_set, options, current_path)?;
+            payload.insert(format!("{key}@delta"), Value::Array(entries));
+        }
+        (Value::Object(old_map), Value::Object(new_map))
+            if key_of(old, entity_set, current_path)? == key_of(new, entity_set, current_path)? =>
+        {
+            let id = entity_set.id(key_of(new, entity_set, current_path)?);
+            payload.insert(
+                key.to_string(),
+                nested_delta(&id, old_map, new_map, options, current_path)?,
+            );
+        }
+        _ => {
+            let id = entity_set.id(key_of(new, entity_set, current_path)?);
+            payload.insert(format!("{key}@odata.bind"), Value::String(id));
+        }
+    }
+    Ok(())
+}
+
+/// `@delta` entries turning one entity collection into another (internal).
+fn diff_collection(
+    old: &[Value],
+    new: &[Value],
+    entity_set: &EntitySet,
+    options: &ODataOptions,
+    current_path: &str,
+) -> Result<Vec<Value>, serde_json::Error> {
+    let old_keys = old
+        .iter()
+        .map(|item| key_of(item, entity_set, current_path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut new_keys = Vec::new();
+    let mut entries = Vec::new();
+    for item in new {
+        let key = key_of(item, entity_set, current_path)?;
+        new_keys.push(key);
+        let id = entity_set.id(key);
+        match old_keys.iter().position(|old_key| *old_key == key) {
+            None => {
+                let mut entry = Map::new();
+                entry.insert(ODATA_ID.to_string(), Value::String(id));
+                if let Value::Object(properties) = item {
+                    entry.extend(properties.clone());
+                }
+                entries.push(Value::Object(entry));
+            }
+            Some(index) if old[index] != *item => {
+                if let (Value::Object(old_map), Value::Object(new_map)) = (&old[index], item) {
+                    entries.push(nested_delta(&id, old_map, new_map, options, current_path)?);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+    for key in old_keys.iter().filter(|key| !new_keys.contains(key)) {
+        let mut entry = Map::new();
+        entry.insert(ODATA_ID.to_string(), Value::String(entity_set.id(key)));
+        let mut removed = Map::new();
+        removed.insert("reason".to_string(), Value::String("changed".to_string()));
+        entry.insert("@odata.removed".to_string(), Value::Object(removed));
+        entries.push(Value::Object(entry));
+    }
+    Ok(entries)
+}
+
+fn nested_delta(
+    id: &str,
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    options: &ODataOptions,
+    current_path: &str,
+) -> Result<Value, serde_json::Error> {
+    let mut entry = Map::new();
+    entry.insert(ODATA_ID.to_string(), Value::String(id.to_string()));
+    entry.extend(diff_properties(old, new, options, current_path)?);
+    Ok(Value::Object(entry))
+}
+
+/// The key of a navigation entity (internal).
+fn key_of<'a>(
+    entity: &'a Value,
+    entity_set: &EntitySet,
+    current_path: &str,
+) -> Result<&'a Value, serde_json::Error> {
+    entity
+        .get(&entity_set.key)
+        .filter(|key| !key.is_null())
+        .ok_or_else(|| {
+            serde_json::Error::custom(format_args!(
+                "entity without key `{}` at `{current_path}`",
+                entity_set.key
+            ))
+        })
+}
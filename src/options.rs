@@ -0,0 +1,80 @@
+use crate::apply_patch::NullSemantics;
+use crate::array_diff::ArrayStrategy;
+use crate::comparator::Comparators;
+use crate::non_finite::NonFinite;
+use crate::representation::Representations;
+use crate::validate::Validators;
+#[cfg(feature = "external-refs")]
+use crate::{ExternalFetch, ExternalStore};
+use std::collections::{HashMap, HashSet};
+
+/// Configuration for [`diff_with`](crate::diff_with).
+///
+/// Paths use the same dotted syntax as [`diff_including`](crate::diff_including)
+/// (e.g. `"profile.bio"`); a `.` or `\` inside a key is escaped with a backslash
+/// (`"hosts.example\.com"`). In `including` and `ignoring`, a `*` segment matches
+/// any key or array element and `**` any number of segments (e.g. `"items.*.id"`,
+/// `"profile.**"`), and paths starting with `/` are JSON Pointers (RFC 6901,
+/// e.g. `"/hosts/example.com"`), matched literally.
+#[derive(Clone, Debug, Default)]
+pub struct DiffOptions {
+    /// Fields included in the patch even if unchanged.
+    pub including: HashSet<String>,
+    /// Fields left out of the patch even if changed or removed.
+    pub ignoring: HashSet<String>,
+    /// Fields holding base64-encoded blobs (dotted paths, globs or pointers);
+    /// changes are emitted as `{ "$delta": "<base64>" }` binary deltas when
+    /// smaller than the new blob.
+    #[cfg(feature = "binary-delta")]
+    pub binary_delta: HashSet<String>,
+    /// Moves large values out of the patch into external storage.
+    #[cfg(feature = "external-refs")]
+    pub external: Option<ExternalStore>,
+    /// Views applied to adapted fields before diffing (see [`Representation`](crate::Representation)).
+    pub representations: Representations,
+    /// Equivalence checks for fields whose values have several spellings
+    /// (see [`Comparator`](crate::Comparator)).
+    pub comparators: Comparators,
+    /// How changed arrays are encoded; anything but `Replace` needs the same
+    /// strategy in [`ApplyOptions::array_strategy`].
+    pub array_strategy: ArrayStrategy,
+    /// Arrays of objects diffed by a key field (path of the array to field
+    /// name), overriding `array_strategy` for those arrays.
+    pub array_keys: HashMap<String, String>,
+    /// What to do with `NaN` and infinite floats (fails by default).
+    pub non_finite: NonFinite,
+    /// Rejects documents nested deeper than this, e.g. untrusted ones given to
+    /// [`diff_values`](crate::diff_values).
+    pub max_depth: Option<usize>,
+}
+
+/// Configuration for [`apply_with`](crate::apply_with).
+#[derive(Clone, Debug, Default)]
+pub struct ApplyOptions {
+    /// Fields (dotted paths, globs or pointers) whose `{ "$delta": ... }`
+    /// values are decoded against the current blob.
+    #[cfg(feature = "binary-delta")]
+    pub binary_delta: HashSet<String>,
+    /// Resolves external references produced by [`DiffOptions::external`].
+    #[cfg(feature = "external-refs")]
+    pub external: Option<ExternalFetch>,
+    /// Views applied to adapted fields while merging; must match the ones used to diff.
+    pub representations: Representations,
+    /// Decodes element-level array patches; must match [`DiffOptions::array_strategy`].
+    pub array_strategy: ArrayStrategy,
+    /// Key fields of keyed arrays; must match [`DiffOptions::array_keys`].
+    pub array_keys: HashMap<String, String>,
+    /// Fields the patch may not set or remove, directly or through an ancestor.
+    pub protected: HashSet<String>,
+    /// Checks run on patched fields after merging (see [`Validator`](crate::Validator)).
+    pub validators: Validators,
+    /// Rejects patches setting fields the target type doesn't have, listing
+    /// their paths, instead of silently dropping them.
+    pub deny_unknown_fields: bool,
+    /// What `null` in the patch does (removes the field by default).
+    pub nulls: NullSemantics,
+    /// Rejects patches nested deeper than this. Merging itself never recurses,
+    /// but deserializing and validating do; set it for untrusted patches that
+    /// weren't parsed from text (which `serde_json` caps at 128 levels).
+    pub max_depth: Option<usize>,
+}
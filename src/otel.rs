@@ -0,0 +1,50 @@
+use opentelemetry::KeyValue;
+use serde_json::Value;
+
+/// Span attributes summarizing a merge patch applied to (or diffed from) a `T`.
+///
+/// Returns:
+/// - `serde_patch.entity_type` – the type name of `T`, without its module path;
+/// - `serde_patch.changed_paths` – number of leaf paths the patch sets or removes;
+/// - `serde_patch.removed_paths` – number of those set to `null` (removals);
+/// - `serde_patch.patch_bytes` – size of the patch serialized as compact JSON.
+///
+/// # Example
+///
+/// ```
+/// use opentelemetry::KeyValue;
+/// use serde_json::json;
+///
+/// struct User;
+///
+/// let patch = json!({ "name": "new", "profile": { "bio": null, "avatar": "a.png" } });
+/// let attributes = serde_patch::otel_attributes::<User>(&patch);
+///
+/// assert!(attributes.contains(&KeyValue::new("serde_patch.entity_type", "User")));
+/// assert!(attributes.contains(&KeyValue::new("serde_patch.changed_paths", 3)));
+/// assert!(attributes.contains(&KeyValue::new("serde_patch.removed_paths", 1)));
+/// ```
+pub fn otel_attributes<T: ?Sized>(patch: &Value) -> Vec<KeyValue> {
+    let review = crate::Review::new(patch);
+    let removed = review
+        .entries()
+        .iter()
+        .filter(|entry| entry.new.is_null())
+        .count();
+    let bytes = serde_json::to_vec(patch).map_or(0, |bytes| bytes.len());
+
+    vec![
+        KeyValue::new("serde_patch.entity_type", entity_type::<T>()),
+        KeyValue::new("serde_patch.changed_paths", review.entries().len() as i64),
+        KeyValue::new("serde_patch.removed_paths", removed as i64),
+        KeyValue::new("serde_patch.patch_bytes", bytes as i64),
+    ]
+}
+
+/// Type name of `T` with the module path of the outer type stripped.
+fn entity_type<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let outer_end = name.find('<').unwrap_or(name.len());
+    let start = name[..outer_end].rfind("::").map_or(0, |i| i + 2);
+    &name[start..]
+}
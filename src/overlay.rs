@@ -0,0 +1,364 @@
+//! Deserializing a patched value without building it (internal).
+//!
+//! [`Patched`] is a `Deserializer` over a document and a merge patch laid on
+//! top of it: objects are merged as they are read, so the target type is
+//! built straight from the two values instead of from a merged copy. Reading
+//! it gives the same result as deserializing the output of
+//! [`merge_patch`](crate::apply_patch::merge_patch).
+//!
+//! The document is still a `Value`: typed callers serialize the current value
+//! first, so applying costs that traversal plus this one, but no third pass
+//! over a merged copy.
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, Error as _, MapAccess, VariantAccess, Visitor,
+};
+use serde_json::{Map, Value};
+
+static NULL: Value = Value::Null;
+
+/// A document with a merge patch applied on read (internal).
+#[derive(Clone, Copy)]
+pub(crate) struct Patched<'de> {
+    base: &'de Value,
+    patch: Option<&'de Value>,
+}
+
+impl<'de> Patched<'de> {
+    pub(crate) fn new(base: &'de Value, patch: &'de Value) -> Self {
+        Self {
+            base,
+            patch: Some(patch),
+        }
+    }
+
+    /// What the value reads as: an unchanged or replaced value, or an object
+    /// merged from the base's members (if it is an object) and the patch's.
+    fn view(self) -> View<'de> {
+        match self.patch {
+            None => View::Value(self.base),
+            Some(Value::Object(patch)) => View::Object(self.base.as_object(), patch),
+            Some(patch) => View::Value(patch),
+        }
+    }
+
+    /// Builds the merged value, for the few types that need it whole.
+    fn to_value(self) -> Value {
+        let mut value = self.base.clone();
+        if let Some(patch) = self.patch {
+            crate::apply_patch::merge_patch(&mut value, patch);
+        }
+        value
+    }
+}
+
+enum View<'de> {
+    Value(&'de Value),
+    Object(Option<&'de Map<String, Value>>, &'de Map<String, Value>),
+}
+
+/// Members of a merged object: the base's in order, less those the patch
+/// removes, then those the patch adds.
+fn members<'de>(
+    base: Option<&'de Map<String, Value>>,
+    patch: &'de Map<String, Value>,
+) -> Vec<(&'de str, Patched<'de>)> {
+    let mut members = Vec::with_capacity(base.map_or(0, Map::len) + patch.len());
+    for (key, value) in base.into_iter().flatten() {
+        match patch.get(key) {
+            Some(Value::Null) => {}
+            patch => members.push((key.as_str(), Patched { base: value, patch })),
+        }
+    }
+    for (key, value) in patch {
+        if !value.is_null() && !base.is_some_and(|base| base.contains_key(key)) {
+            members.push((
+                key.as_str(),
+                Patched {
+                    base: &NULL,
+                    patch: Some(value),
+                },
+            ));
+        }
+    }
+    members
+}
+
+macro_rules! forward_to_view {
+    ($($method:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, serde_json::Error> {
+            match self.view() {
+                View::Value(value) => value.$method(visitor),
+                View::Object(base, patch) => visitor.visit_map(Members::new(base, patch)),
+            }
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for Patched<'de> {
+    type Error = serde_json::Error;
+
+    forward_to_view! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_seq deserialize_map deserialize_identifier
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.view() {
+            View::Value(value) => value.deserialize_option(visitor),
+            View::Object(..) => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.view() {
+            View::Value(value) => value.deserialize_unit_struct(name, visitor),
+            View::Object(base, patch) => visitor.visit_map(Members::new(base, patch)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.view() {
+            View::Value(value) => value.deserialize_newtype_struct(name, visitor),
+            // `RawValue` and friends are recognized by name and read whole.
+            View::Object(..) if name.starts_with("$serde_json::") => {
+                self.to_value().deserialize_newtype_struct(name, visitor)
+            }
+            View::Object(..) => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.view() {
+            View::Value(value) => value.deserialize_tuple(len, visitor),
+            View::Object(base, patch) => visitor.visit_map(Members::new(base, patch)),
+        }
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.view() {
+            View::Value(value) => value.deserialize_tuple_struct(name, len, visitor),
+            View::Object(base, patch) => visitor.visit_map(Members::new(base, patch)),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.view() {
+            View::Value(value) => value.deserialize_struct(name, fields, visitor),
+            View::Object(..) if name.starts_with("$serde_json::") => {
+                self.to_value().deserialize_struct(name, fields, visitor)
+            }
+            View::Object(base, patch) => visitor.visit_map(Members::new(base, patch)),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.view() {
+            View::Value(value) => value.deserialize_enum(name, variants, visitor),
+            View::Object(base, patch) => match members(base, patch).as_slice() {
+                [(variant, value)] => visitor.visit_enum(Variant {
+                    variant,
+                    value: *value,
+                }),
+                _ => Err(serde_json::Error::invalid_value(
+                    de::Unexpected::Map,
+                    &"map with a single key",
+                )),
+            },
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// The members of a merged object, read as a map (internal).
+struct Members<'de> {
+    members: std::vec::IntoIter<(&'de str, Patched<'de>)>,
+    value: Option<Patched<'de>>,
+}
+
+impl<'de> Members<'de> {
+    fn new(base: Option<&'de Map<String, Value>>, patch: &'de Map<String, Value>) -> Self {
+        Self {
+            members: members(base, patch).into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for Members<'de> {
+    type Error = serde_json::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, value)) = self.members.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        seed.deserialize(Key(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(serde_json::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.members.len())
+    }
+}
+
+/// The single member of a merged object read as an enum (internal).
+struct Variant<'de> {
+    variant: &'de str,
+    value: Patched<'de>,
+}
+
+impl<'de> EnumAccess<'de> for Variant<'de> {
+    type Error = serde_json::Error;
+    type Variant = Patched<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        Ok((seed.deserialize(Key(self.variant))?, self.value))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Patched<'de> {
+    type Error = serde_json::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        serde::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+}
+
+/// An object key, which also reads as a number or `bool` when the target
+/// expects one, as `serde_json` allows for map keys (internal).
+struct Key<'de>(&'de str);
+
+macro_rules! parse_key {
+    ($($method:ident => $visit:ident)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, serde_json::Error> {
+            match self.0.parse() {
+                Ok(parsed) => visitor.$visit(parsed),
+                Err(_) => visitor.visit_borrowed_str(self.0),
+            }
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for Key<'de> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    parse_key! {
+        deserialize_bool => visit_bool
+        deserialize_i8 => visit_i8
+        deserialize_i16 => visit_i16
+        deserialize_i32 => visit_i32
+        deserialize_i64 => visit_i64
+        deserialize_i128 => visit_i128
+        deserialize_u8 => visit_u8
+        deserialize_u16 => visit_u16
+        deserialize_u32 => visit_u32
+        deserialize_u64 => visit_u64
+        deserialize_u128 => visit_u128
+        deserialize_f32 => visit_f32
+        deserialize_f64 => visit_f64
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        BorrowedStrDeserializer::<serde_json::Error>::new(self.0)
+            .deserialize_enum(name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
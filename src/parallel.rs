@@ -0,0 +1,69 @@
+//! Diffing and patching large batches on the rayon thread pool.
+
+use rayon::prelude::*;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+/// Computes the merge patch of every `(old, new)` pair, in parallel.
+///
+/// Patches come back in the order of `pairs`, each one what [`diff`](crate::diff)
+/// returns for its pair. Fails with the error of the first pair (in order)
+/// that can't be serialized.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(serde::Serialize)]
+/// struct Record { id: u32, total: u32 }
+///
+/// let pairs = vec![
+///     (Record { id: 1, total: 10 }, Record { id: 1, total: 12 }),
+///     (Record { id: 2, total: 5 }, Record { id: 2, total: 5 }),
+/// ];
+///
+/// let patches = serde_patch::diff_many(&pairs).unwrap();
+/// assert_eq!(patches, [json!({ "total": 12 }), json!({})]);
+/// ```
+pub fn diff_many<T>(pairs: &[(T, T)]) -> Result<Vec<Value>, serde_json::Error>
+where
+    T: Serialize + Sync,
+{
+    pairs
+        .par_iter()
+        .map(|(old, new)| crate::diff(old, new))
+        .collect()
+}
+
+/// Applies one merge patch to every document in place, in parallel.
+///
+/// The patch is parsed once. Each document is patched as by
+/// [`apply_mut`](crate::apply_mut): one that the patch doesn't fit is left
+/// unchanged while the others are still patched, and the error of the first
+/// such document (in order) is returned.
+///
+/// # Example
+///
+/// ```
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Record { id: u32, archived: bool }
+///
+/// let mut records: Vec<Record> = (0..4).map(|id| Record { id, archived: false }).collect();
+///
+/// serde_patch::apply_many(&mut records, r#"{ "archived": true }"#).unwrap();
+/// assert!(records.iter().all(|record| record.archived));
+/// ```
+pub fn apply_many<T, P>(documents: &mut [T], patch: P) -> Result<(), crate::Error>
+where
+    T: Serialize + DeserializeOwned + Send,
+    P: AsRef<[u8]>,
+{
+    let patch_val: Value =
+        crate::backend::from_slice(patch.as_ref()).map_err(crate::Error::ParsePatch)?;
+    let results: Vec<Result<(), crate::Error>> = documents
+        .par_iter_mut()
+        .map(|document| crate::apply_mut_value(document, &patch_val))
+        .collect();
+    results.into_iter().collect()
+}
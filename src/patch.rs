@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// Media type of JSON Merge Patch documents (RFC 7396).
+pub const MERGE_PATCH_MEDIA_TYPE: &str = "application/merge-patch+json";
+
+/// A parsed JSON Merge Patch (RFC 7396).
+///
+/// Wraps the `serde_json::Value` returned by the diff functions so a patch
+/// can be told apart from a document and inspected by path. Serializes as
+/// the bare patch object.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::Patch;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// let old = User { name: "alice".into(), age: 30 };
+/// let new = User { name: "alice".into(), age: 31 };
+///
+/// let patch = Patch::diff(&old, &new).unwrap();
+/// assert!(!patch.is_empty());
+/// assert!(patch.touches("age"));
+/// assert_eq!(patch.get("age"), Some(&json!(31)));
+/// assert_eq!(patch.paths(), ["age"]);
+///
+/// let updated = patch.apply_to(old).unwrap();
+/// assert_eq!(updated.age, 31);
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Patch(Value);
+
+impl Patch {
+    /// Wraps an existing patch value.
+    pub fn new(value: Value) -> Self {
+        Self(value)
+    }
+
+    /// Computes the patch turning `old` into `new`, like [`diff`](crate::diff).
+    pub fn diff<T: Serialize>(old: &T, new: &T) -> Result<Self, serde_json::Error> {
+        crate::diff(old, new).map(Self)
+    }
+
+    /// Returns `true` if applying the patch changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.0.as_object().is_some_and(Map::is_empty)
+    }
+
+    /// Dotted paths of every value the patch sets or removes, in sorted order.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        crate::paths::collect_leaves(&self.0, "", &mut paths);
+        paths.sort();
+        paths
+    }
+
+    /// The patch value at a dotted `path`, if the patch contains it.
+    ///
+    /// `null` means the patch removes the field.
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        if path.is_empty() {
+            return Some(&self.0);
+        }
+        crate::paths::split(path)
+            .iter()
+            .try_fold(&self.0, |value, key| value.as_object()?.get(key))
+    }
+
+    /// Returns `true` if the patch sets or removes `path` or anything below it.
+    pub fn touches(&self, path: &str) -> bool {
+        self.get(path).is_some()
+    }
+
+    /// Applies the patch, like [`apply_value`](crate::apply_value).
+    pub fn apply_to<T>(&self, current: T) -> Result<T, crate::Error>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        crate::apply_value(current, &self.0)
+    }
+
+    /// The underlying JSON value.
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+
+    /// Unwraps the underlying JSON value.
+    pub fn into_inner(self) -> Value {
+        self.0
+    }
+}
+
+/// The empty patch, `{}`.
+impl Default for Patch {
+    fn default() -> Self {
+        Self(Value::Object(Map::new()))
+    }
+}
+
+impl From<Value> for Patch {
+    fn from(value: Value) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Patch> for Value {
+    fn from(patch: Patch) -> Self {
+        patch.0
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
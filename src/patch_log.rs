@@ -0,0 +1,370 @@
+use crate::PatchEnvelope;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A stored copy of the document at some revision, so replays start there
+/// instead of at the base.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Revision the document is at.
+    pub revision: u64,
+    /// The document at that revision.
+    pub state: Value,
+}
+
+/// When a [`PatchLog`] takes a [`Snapshot`] on its own; the default never does.
+///
+/// Either threshold triggers one, counted since the previous snapshot.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotPolicy {
+    /// Snapshot after this many patches.
+    pub every_patches: Option<usize>,
+    /// Snapshot once the patches add up to this many bytes of compact JSON.
+    pub every_bytes: Option<usize>,
+}
+
+/// Append-only journal of merge patches over a base snapshot of a `T`.
+///
+/// Each [`append`](PatchLog::append) records a patch, or a [`PatchEnvelope`]
+/// with its metadata, under the next revision, after checking that the
+/// patched document still fits `T`; [`replay`](PatchLog::replay)
+/// reconstructs the current state from the base snapshot, or from the latest [`Snapshot`] taken under its
+/// [`SnapshotPolicy`] so long-lived documents don't replay their whole
+/// history. The log serializes as `{ "base": ..., "snapshot": ...,
+/// "entries": [...] }` for storage; the policy is configuration and isn't
+/// stored.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{PatchEnvelope, PatchLog, SnapshotPolicy};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Ticket { title: String, status: String }
+///
+/// let mut log = PatchLog::new(&Ticket { title: "Crash".into(), status: "open".into() }).unwrap();
+///
+/// log.append(PatchEnvelope {
+///     author: Some("alice".into()),
+///     ..PatchEnvelope::new(json!({ "status": "triaged" }))
+/// }).unwrap();
+/// log.append(json!({ "status": "closed" })).unwrap();
+///
+/// assert_eq!(log.revision(), 2);
+/// assert_eq!(log.replay().unwrap().status, "closed");
+/// assert_eq!(log.iter().next().unwrap().author.as_deref(), Some("alice"));
+///
+/// // A patch that would break the document is not recorded.
+/// assert!(log.append(json!({ "title": null })).is_err());
+/// assert_eq!(log.revision(), 2);
+///
+/// // Snapshot every 2 patches: the 3 since the base trigger one at revision 3.
+/// log.set_snapshot_policy(SnapshotPolicy { every_patches: Some(2), every_bytes: None });
+/// log.append(json!({ "status": "reopened" })).unwrap();
+/// log.append(json!({ "title": "Crash on start" })).unwrap();
+/// assert_eq!(log.latest_snapshot().unwrap().revision, 3);
+/// assert_eq!(log.replay().unwrap().title, "Crash on start");
+/// ```
+#[derive(Serialize, Deserialize)]
+#[serde(from = "Parts", bound = "")]
+pub struct PatchLog<T> {
+    base: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snapshot: Option<Snapshot>,
+    entries: Vec<PatchEnvelope>,
+    /// The base with every entry merged in.
+    #[serde(skip)]
+    head: Value,
+    #[serde(skip)]
+    policy: SnapshotPolicy,
+    /// Bytes of the entries since the latest snapshot.
+    #[serde(skip)]
+    pending_bytes: usize,
+    #[serde(skip)]
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for PatchLog<T> {
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            snapshot: self.snapshot.clone(),
+            entries: self.entries.clone(),
+            head: self.head.clone(),
+            policy: self.policy.clone(),
+            pending_bytes: self.pending_bytes,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for PatchLog<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PatchLog")
+            .field("base", &self.base)
+            .field("snapshot", &self.snapshot)
+            .field("entries", &self.entries)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+/// Stored form of a [`PatchLog`] (internal).
+#[derive(Deserialize)]
+struct Parts {
+    base: Value,
+    #[serde(default)]
+    snapshot: Option<Snapshot>,
+    #[serde(default)]
+    entries: Vec<PatchEnvelope>,
+}
+
+impl<T> From<Parts> for PatchLog<T> {
+    fn from(parts: Parts) -> Self {
+        let mut log = Self {
+            head: Value::Null,
+            base: parts.base,
+            snapshot: parts.snapshot,
+            entries: parts.entries,
+            policy: SnapshotPolicy::default(),
+            pending_bytes: 0,
+            marker: PhantomData,
+        };
+        // Entries stored without a revision follow the one before them.
+        let mut revision = 0;
+        for entry in &mut log.entries {
+            revision = entry.revision.unwrap_or(revision + 1);
+            entry.revision = Some(revision);
+        }
+        let mut head = log.start().clone();
+        for entry in log.since_snapshot() {
+            crate::apply_patch::merge_patch(&mut head, &entry.patch);
+        }
+        log.head = head;
+        log.pending_bytes = log
+            .since_snapshot()
+            .iter()
+            .map(|entry| patch_bytes(&entry.patch))
+            .sum();
+        log
+    }
+}
+
+impl<T> PatchLog<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Starts a log at revision 0 with `base` as its snapshot.
+    pub fn new(base: &T) -> Result<Self, crate::Error> {
+        Self::with_snapshot_policy(base, SnapshotPolicy::default())
+    }
+
+    /// Starts a log that takes snapshots under `policy`.
+    pub fn with_snapshot_policy(base: &T, policy: SnapshotPolicy) -> Result<Self, crate::Error> {
+        let base = crate::error::serialize(base)?;
+        Ok(Self {
+            head: base.clone(),
+            base,
+            snapshot: None,
+            entries: Vec::new(),
+            policy,
+            pending_bytes: 0,
+            marker: PhantomData,
+        })
+    }
+
+    /// Records a patch or envelope under the next revision, which it sets as
+    /// the envelope's `revision`, and returns that revision.
+    ///
+    /// Fails, recording nothing, if the patched document would no longer
+    /// deserialize as `T`.
+    pub fn append(&mut self, entry: impl Into<PatchEnvelope>) -> Result<u64, crate::Error> {
+        let mut entry = entry.into();
+        crate::error::deserialize_patched::<T>(&self.head, &entry.patch)?;
+        crate::apply_patch::merge_patch(&mut self.head, &entry.patch);
+        let revision = self.revision() + 1;
+        self.pending_bytes += patch_bytes(&entry.patch);
+        entry.revision = Some(revision);
+        self.entries.push(entry);
+
+        let pending = self.since_snapshot().len();
+        if self.policy.every_patches.is_some_and(|max| pending >= max)
+            || self
+                .policy
+                .every_bytes
+                .is_some_and(|max| self.pending_bytes >= max)
+        {
+            self.take_snapshot();
+        }
+        Ok(revision)
+    }
+
+    /// Reconstructs the state at `revision`, starting from the latest snapshot
+    /// at or before it; revisions past the end give the latest state.
+    ///
+    /// Snapshots only speed this up: every revision stays reachable.
+    pub fn state_at(&self, revision: u64) -> Result<T, crate::Error> {
+        let (start, after) = match &self.snapshot {
+            Some(snapshot) if snapshot.revision <= revision => (&snapshot.state, snapshot.revision),
+            _ => (&self.base, 0),
+        };
+        let mut state = start.clone();
+        for entry in self
+            .entries
+            .iter()
+            .filter(|entry| revision_of(entry) > after && revision_of(entry) <= revision)
+        {
+            crate::apply_patch::merge_patch(&mut state, &entry.patch);
+        }
+        crate::error::deserialize_patched(&state, &Value::Object(Map::new()))
+    }
+
+    /// Reconstructs the current state by applying every entry to the base
+    /// snapshot in order.
+    pub fn replay(&self) -> Result<T, crate::Error> {
+        let mut state = self.start().clone();
+        for entry in self.since_snapshot() {
+            crate::apply_patch::merge_patch(&mut state, &entry.patch);
+        }
+        crate::error::deserialize_patched(&state, &Value::Object(Map::new()))
+    }
+}
+
+impl<T> PatchLog<T> {
+    /// Revision of the latest entry; 0 while the log is empty.
+    pub fn revision(&self) -> u64 {
+        self.entries.last().map_or(0, revision_of)
+    }
+
+    /// The base snapshot.
+    pub fn base(&self) -> &Value {
+        &self.base
+    }
+
+    /// The effective merge patch from revision `i` to revision `j`, as
+    /// [`between`] computes it for a chain of patches.
+    pub fn between(&self, i: u64, j: u64) -> Value {
+        crate::coalesce::compact_all(
+            self.entries
+                .iter()
+                .filter(|entry| revision_of(entry) > i && revision_of(entry) <= j)
+                .map(|entry| &entry.patch),
+        )
+    }
+
+    /// The latest snapshot, if one was taken.
+    pub fn latest_snapshot(&self) -> Option<&Snapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// Snapshots the current state, so replays start from it.
+    pub fn take_snapshot(&mut self) {
+        self.snapshot = Some(Snapshot {
+            revision: self.revision(),
+            state: self.head.clone(),
+        });
+        self.pending_bytes = 0;
+    }
+
+    /// Replaces the snapshot policy, e.g. on a log loaded from storage.
+    pub fn set_snapshot_policy(&mut self, policy: SnapshotPolicy) {
+        self.policy = policy;
+    }
+
+    /// The recorded entries, oldest first.
+    pub fn iter(&self) -> std::slice::Iter<'_, PatchEnvelope> {
+        self.entries.iter()
+    }
+
+    /// The state replays start from: the latest snapshot, or the base (internal).
+    fn start(&self) -> &Value {
+        self.snapshot
+            .as_ref()
+            .map_or(&self.base, |snapshot| &snapshot.state)
+    }
+
+    /// The entries after the latest snapshot (internal).
+    fn since_snapshot(&self) -> &[PatchEnvelope] {
+        let after = self.snapshot.as_ref().map_or(0, |snapshot| {
+            self.entries
+                .partition_point(|entry| revision_of(entry) <= snapshot.revision)
+        });
+        &self.entries[after..]
+    }
+}
+
+/// Reconstructs the state after the first `n` of `patches` were applied to
+/// `base`; revision 0 is `base` itself.
+///
+/// `n` past the end gives the latest state.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Record { name: String, email: String }
+///
+/// let base = Record { name: "Ada".into(), email: "ada@old.example".into() };
+/// let patches = [json!({ "email": "ada@new.example" }), json!({ "name": "Ada L." })];
+///
+/// let then: Record = serde_patch::state_at(&base, &patches, 1).unwrap();
+/// assert_eq!((then.name.as_str(), then.email.as_str()), ("Ada", "ada@new.example"));
+/// ```
+pub fn state_at<T>(base: &T, patches: &[Value], n: usize) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut state = crate::error::serialize(base)?;
+    for patch in patches.iter().take(n) {
+        crate::apply_patch::merge_patch(&mut state, patch);
+    }
+    crate::error::deserialize_patched(&state, &Value::Object(Map::new()))
+}
+
+/// The effective merge patch from revision `i` to revision `j` of a chain of
+/// `patches`: the patches after the first `i`, up to and including the
+/// `j`-th, [compacted](crate::compact) into one.
+///
+/// Revisions past the end are clamped to the latest; `{}` when `j <= i`.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let patches = [
+///     json!({ "status": "paid" }),
+///     json!({ "note": "gift" }),
+///     json!({ "status": "shipped", "note": null }),
+/// ];
+///
+/// assert_eq!(serde_patch::between(&patches, 1, 3), json!({ "status": "shipped", "note": null }));
+/// assert_eq!(serde_patch::between(&patches, 0, 2), json!({ "status": "paid", "note": "gift" }));
+/// ```
+pub fn between(patches: &[Value], i: usize, j: usize) -> Value {
+    crate::coalesce::compact_all(patches.iter().take(j).skip(i))
+}
+
+/// Revision of a recorded entry; the log sets it on every entry (internal).
+fn revision_of(entry: &PatchEnvelope) -> u64 {
+    entry.revision.unwrap_or(0)
+}
+
+/// Size of a patch as compact JSON (internal).
+fn patch_bytes(patch: &Value) -> usize {
+    serde_json::to_vec(patch).map_or(0, |bytes| bytes.len())
+}
+
+impl<'a, T> IntoIterator for &'a PatchLog<T> {
+    type Item = &'a PatchEnvelope;
+    type IntoIter = std::slice::Iter<'a, PatchEnvelope>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
@@ -0,0 +1,104 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// A merge patch borrowing its values from the documents it was diffed from.
+///
+/// Returned by [`diff_borrowed`]. Serializes to the same JSON as the patch
+/// [`diff_values`](crate::diff_values) builds with default options, without
+/// cloning the changed values first.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchRef<'a> {
+    /// Replaces the value with one from the new document.
+    Set(&'a Value),
+    /// Removes the member (`null`).
+    Remove,
+    /// Merges changes into an object, by member name in sorted order.
+    Merge(BTreeMap<&'a str, PatchRef<'a>>),
+}
+
+impl PatchRef<'_> {
+    /// Returns `true` if the patch changes nothing.
+    pub fn is_empty(&self) -> bool {
+        matches!(self, PatchRef::Merge(members) if members.is_empty())
+    }
+
+    /// Clones the borrowed values into an owned patch.
+    pub fn to_value(&self) -> Value {
+        match self {
+            PatchRef::Set(value) => (*value).clone(),
+            PatchRef::Remove => Value::Null,
+            PatchRef::Merge(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|(key, patch)| (key.to_string(), patch.to_value()))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl Serialize for PatchRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PatchRef::Set(value) => value.serialize(serializer),
+            PatchRef::Remove => serializer.serialize_unit(),
+            PatchRef::Merge(members) => {
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (key, patch) in members {
+                    map.serialize_entry(key, patch)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// Computes a merge patch that borrows from `new` instead of cloning.
+///
+/// For callers that serialize the patch right away, e.g. to send it: changed
+/// values, however large, are written straight from `new`. Same patch as
+/// [`diff_values`](crate::diff_values) with default options; array strategies
+/// and the other [`DiffOptions`](crate::DiffOptions) build new values and need
+/// `diff_values`.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let old = json!({ "name": "old", "tags": ["a"], "blob": { "size": 1 } });
+/// let new = json!({ "name": "old", "blob": { "size": 2, "data": [1, 2, 3] } });
+///
+/// let patch = serde_patch::diff_borrowed(&old, &new);
+/// assert_eq!(
+///     serde_json::to_string(&patch).unwrap(),
+///     r#"{"blob":{"data":[1,2,3],"size":2},"tags":null}"#
+/// );
+/// assert!(serde_patch::diff_borrowed(&new, &new).is_empty());
+/// ```
+pub fn diff_borrowed<'a>(old: &'a Value, new: &'a Value) -> PatchRef<'a> {
+    diff_ref(old, new).unwrap_or_else(|| PatchRef::Merge(BTreeMap::new()))
+}
+
+fn diff_ref<'a>(old: &'a Value, new: &'a Value) -> Option<PatchRef<'a>> {
+    let (Value::Object(old_map), Value::Object(new_map)) = (old, new) else {
+        return (old != new).then_some(PatchRef::Set(new));
+    };
+    let mut members = BTreeMap::new();
+    for (key, new_value) in new_map {
+        let patch = match old_map.get(key) {
+            Some(old_value) => diff_ref(old_value, new_value),
+            None => Some(PatchRef::Set(new_value)),
+        };
+        if let Some(patch) = patch {
+            members.insert(key.as_str(), patch);
+        }
+    }
+    for key in old_map.keys() {
+        if !new_map.contains_key(key) {
+            members.insert(key.as_str(), PatchRef::Remove);
+        }
+    }
+    (!members.is_empty()).then_some(PatchRef::Merge(members))
+}
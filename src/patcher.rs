@@ -0,0 +1,148 @@
+#[cfg(feature = "external-refs")]
+use crate::ExternalFetch;
+use crate::{ApplyOptions, ArrayStrategy, NullSemantics, Representation, Validator};
+use serde::{Serialize, de::DeserializeOwned};
+use std::sync::Arc;
+
+/// Reusable apply configuration, built step by step.
+///
+/// Collects everything [`ApplyOptions`] can express behind chained calls and
+/// keeps it for any number of [`apply`](Patcher::apply) /
+/// [`apply_mut`](Patcher::apply_mut) calls.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::{NullSemantics, Patcher};
+///
+/// #[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// struct User { id: u32, name: String, nickname: Option<String> }
+///
+/// let patcher = Patcher::new()
+///     .deny_unknown_fields()
+///     .nulls(NullSemantics::Ignore)
+///     .max_depth(8)
+///     .deny("id");
+///
+/// let mut user = User { id: 1, name: "alice".into(), nickname: Some("al".into()) };
+/// patcher.apply_mut(&mut user, r#"{ "name": "bob", "nickname": null }"#).unwrap();
+/// assert_eq!(user.name, "bob");
+/// assert_eq!(user.nickname.as_deref(), Some("al"));
+///
+/// assert!(patcher.apply_mut(&mut user, r#"{ "id": 2 }"#).is_err());
+///
+/// let err = patcher.apply(user, r#"{ "nick": "b" }"#).err().unwrap();
+/// assert_eq!(err.to_string(), "unknown field `nick`");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Patcher {
+    options: ApplyOptions,
+}
+
+impl Patcher {
+    /// Creates a patcher applying plain merge patches, like [`apply_with`](crate::apply_with)
+    /// with default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects patches setting fields the target type doesn't have.
+    pub fn deny_unknown_fields(mut self) -> Self {
+        self.options.deny_unknown_fields = true;
+        self
+    }
+
+    /// Sets what `null` in a patch does.
+    pub fn nulls(mut self, nulls: NullSemantics) -> Self {
+        self.options.nulls = nulls;
+        self
+    }
+
+    /// Rejects patches nested deeper than `depth`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.options.max_depth = Some(depth);
+        self
+    }
+
+    /// Forbids setting or removing a field, directly or through an ancestor.
+    pub fn deny(mut self, path: impl Into<String>) -> Self {
+        self.options.protected.insert(path.into());
+        self
+    }
+
+    /// Sets how element-level array patches are decoded.
+    pub fn array_strategy(mut self, strategy: ArrayStrategy) -> Self {
+        self.options.array_strategy = strategy;
+        self
+    }
+
+    /// Matches the elements of the array at `path` by their `key` field.
+    pub fn array_key(mut self, path: impl Into<String>, key: impl Into<String>) -> Self {
+        self.options.array_keys.insert(path.into(), key.into());
+        self
+    }
+
+    /// Registers a check run on a field after merging.
+    pub fn validator(
+        mut self,
+        path: impl Into<String>,
+        validator: impl Validator + 'static,
+    ) -> Self {
+        self.options
+            .validators
+            .insert(path.into(), Arc::new(validator));
+        self
+    }
+
+    /// Registers a view for a field whose serde adapter hides its structure.
+    pub fn representation(
+        mut self,
+        path: impl Into<String>,
+        representation: impl Representation + 'static,
+    ) -> Self {
+        self.options
+            .representations
+            .insert(path.into(), Arc::new(representation));
+        self
+    }
+
+    /// Resolves external references in patches.
+    #[cfg(feature = "external-refs")]
+    pub fn external(mut self, fetch: ExternalFetch) -> Self {
+        self.options.external = Some(fetch);
+        self
+    }
+
+    /// The accumulated options.
+    pub fn options(&self) -> &ApplyOptions {
+        &self.options
+    }
+
+    /// Applies `patch`, consuming the current value and returning the updated one.
+    pub fn apply<T, P>(&self, current: T, patch: P) -> Result<T, crate::Error>
+    where
+        T: Serialize + DeserializeOwned,
+        P: AsRef<[u8]>,
+    {
+        crate::apply_with(current, patch, &self.options)
+    }
+
+    /// Applies `patch` in place; on error the current value is left unchanged.
+    pub fn apply_mut<T, P>(&self, current: &mut T, patch: P) -> Result<(), crate::Error>
+    where
+        T: Serialize + DeserializeOwned,
+        P: AsRef<[u8]>,
+    {
+        let patch_val =
+            crate::backend::from_slice(patch.as_ref()).map_err(crate::Error::ParsePatch)?;
+        let (updated, _) = crate::apply_patch::apply_resolved(&*current, patch_val, &self.options)?;
+        *current = updated;
+        Ok(())
+    }
+}
+
+impl From<ApplyOptions> for Patcher {
+    fn from(options: ApplyOptions) -> Self {
+        Self { options }
+    }
+}
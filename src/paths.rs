@@ -0,0 +1,107 @@
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Whether a dotted path is listed in `patterns`, directly, through a glob or
+/// as a JSON Pointer (internal).
+///
+/// In dotted patterns, a `*` segment matches any single segment (an object
+/// key or array element) and a `**` segment matches any number of segments,
+/// so `items.*.id` covers the `id` of every element and `profile.**` the
+/// whole `profile` subtree. Patterns starting with `/` are RFC 6901 pointers
+/// (`/profile/bio`), whose tokens always match literally.
+pub(crate) fn matches_any(patterns: &HashSet<String>, path: &str) -> bool {
+    if patterns.contains(path) {
+        return true;
+    }
+    if path.is_empty() {
+        return false;
+    }
+    let mut segments = None;
+    patterns
+        .iter()
+        .filter(|pattern| pattern.starts_with('/') || pattern.contains(['*', '\\']))
+        .any(|pattern| {
+            let segments = segments.get_or_insert_with(|| split(path));
+            segments_match(&parse_pattern(pattern), segments)
+        })
+}
+
+/// Appends a key to a dotted path, escaping `.` and `\` inside it (internal).
+pub(crate) fn join(path: &str, key: &str) -> String {
+    let key = key.replace('\\', "\\\\").replace('.', "\\.");
+    if path.is_empty() {
+        key
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Dotted paths of every value a merge patch sets or removes (internal).
+///
+/// A nested empty object counts as a value: merged into a non-object it sets
+/// an empty object. An empty patch has no paths.
+pub(crate) fn collect_leaves(patch: &Value, current_path: &str, leaves: &mut Vec<String>) {
+    match patch {
+        Value::Object(map) if !map.is_empty() || current_path.is_empty() => {
+            for (key, value) in map {
+                collect_leaves(value, &join(current_path, key), leaves);
+            }
+        }
+        _ => leaves.push(current_path.to_string()),
+    }
+}
+
+/// Splits a dotted path into its unescaped segments (internal).
+pub(crate) fn split(path: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => segment.extend(chars.next()),
+            '.' => segments.push(std::mem::take(&mut segment)),
+            c => segment.push(c),
+        }
+    }
+    segments.push(segment);
+    segments
+}
+
+enum Segment {
+    Literal(String),
+    Any,
+    AnyDepth,
+}
+
+fn parse_pattern(pattern: &str) -> Vec<Segment> {
+    if let Some(pointer) = pattern.strip_prefix('/') {
+        return pointer
+            .split('/')
+            .map(|token| Segment::Literal(token.replace("~1", "/").replace("~0", "~")))
+            .collect();
+    }
+    split(pattern)
+        .into_iter()
+        .map(|segment| match segment.as_str() {
+            "*" => Segment::Any,
+            "**" => Segment::AnyDepth,
+            _ => Segment::Literal(segment),
+        })
+        .collect()
+}
+
+fn segments_match(pattern: &[Segment], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((Segment::AnyDepth, rest)) => {
+            (0..=path.len()).any(|skip| segments_match(rest, &path[skip..]))
+        }
+        Some((segment, rest)) => path.split_first().is_some_and(|(first, path_rest)| {
+            let matched = match segment {
+                Segment::Literal(literal) => literal == first,
+                _ => true,
+            };
+            matched && segments_match(rest, path_rest)
+        }),
+    }
+}
@@ -0,0 +1,236 @@
+use crate::ApplyOptions;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Approval state of a [`PendingPatch`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum PatchState {
+    /// Awaiting a decision.
+    Pending,
+    /// Approved and ready to apply.
+    Approved {
+        /// Who approved the patch.
+        by: String,
+    },
+    /// Rejected; it can no longer be applied.
+    Rejected {
+        /// Who rejected the patch.
+        by: String,
+        /// Why, if a reason was given.
+        reason: Option<String>,
+    },
+    /// Approved and applied.
+    Applied {
+        /// Who approved the patch.
+        approved_by: String,
+    },
+}
+
+/// Error returned by [`PendingPatch`] state transitions and [`PendingPatch::apply_approved`].
+#[derive(Debug)]
+pub enum ApprovalError {
+    /// The patch is not in the state the operation requires.
+    InvalidState(PatchState),
+    /// The document changed since the patch was requested.
+    VersionMismatch {
+        /// Version the patch was requested against.
+        expected: String,
+        /// Version of the document passed in.
+        actual: String,
+    },
+    /// Serializing the document or applying the patch failed.
+    Patch(crate::Error),
+}
+
+impl fmt::Display for ApprovalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApprovalError::InvalidState(state) => write!(f, "patch is in state {:?}", state),
+            ApprovalError::VersionMismatch { expected, actual } => write!(
+                f,
+                "document changed since the patch was requested (expected version {}, found {})",
+                expected, actual
+            ),
+            ApprovalError::Patch(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ApprovalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApprovalError::Patch(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<crate::Error> for ApprovalError {
+    fn from(err: crate::Error) -> Self {
+        ApprovalError::Patch(err)
+    }
+}
+
+/// A merge patch awaiting approval before it may be applied.
+///
+/// The patch records the version of the document it was requested against;
+/// [`apply_approved`](PendingPatch::apply_approved) only applies it once
+/// approved, and only if the document hasn't changed since. The version is
+/// whatever the caller already tracks (a revision number, an ETag, ...),
+/// passed to [`with_version`](PendingPatch::with_version) and again when
+/// applying. Without one, [`new`](PendingPatch::new) uses the document's
+/// [`fingerprint`](crate::fingerprint) (a 64-bit FNV-1a hash) in hex, which
+/// detects concurrent edits, not deliberate collisions.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::PendingPatch;
+///
+/// #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+/// struct Profile { name: String, bio: String }
+///
+/// let current = Profile { name: "a".into(), bio: "old".into() };
+/// let mut pending: PendingPatch<Profile> =
+///     PendingPatch::new(json!({ "bio": "new" }), &current, "user:42").unwrap();
+///
+/// pending.approve("moderator:7").unwrap();
+/// let updated = pending.apply_approved(&current).unwrap();
+/// assert_eq!(updated.bio, "new");
+///
+/// // With a revision number the application already keeps:
+/// let mut pending: PendingPatch<Profile, u64> =
+///     PendingPatch::with_version(json!({ "bio": "newer" }), 7, "user:42");
+/// pending.approve("moderator:7").unwrap();
+/// assert!(pending.apply_approved_with_version(&updated, 8).is_err());
+/// let updated = pending.apply_approved_with_version(&updated, 7).unwrap();
+/// assert_eq!(updated.bio, "newer");
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(bound(serialize = "V: Serialize", deserialize = "V: Deserialize<'de>"))]
+pub struct PendingPatch<T, V = String> {
+    /// The merge patch awaiting approval.
+    pub patch: Value,
+    /// Version of the document the patch was requested against: the one
+    /// given to [`with_version`](PendingPatch::with_version), or for
+    /// [`new`](PendingPatch::new) its fingerprint as 16 hex digits.
+    pub base_version: V,
+    /// Who requested the change.
+    pub requester: String,
+    /// Free-form request metadata (reason, ticket, client, ...).
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+    state: PatchState,
+    #[serde(skip)]
+    target: PhantomData<fn() -> T>,
+}
+
+impl<T> PendingPatch<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Creates a pending patch against the current state of `base`, using
+    /// its fingerprint as the version.
+    pub fn new(patch: Value, base: &T, requester: impl Into<String>) -> Result<Self, crate::Error> {
+        Ok(Self::with_version(patch, version_of(base)?, requester))
+    }
+
+    /// Applies an approved patch to `current`, returning the updated value,
+    /// and marks it as applied.
+    ///
+    /// Fails with [`ApprovalError::InvalidState`] unless the patch is approved,
+    /// and with [`ApprovalError::VersionMismatch`] if `current` is not the
+    /// document the patch was requested against. `current` is left untouched
+    /// either way.
+    pub fn apply_approved(&mut self, current: &T) -> Result<T, ApprovalError> {
+        let version = version_of(current)?;
+        self.apply_approved_with_version(current, version)
+    }
+}
+
+impl<T, V> PendingPatch<T, V>
+where
+    T: Serialize + DeserializeOwned,
+    V: PartialEq + fmt::Display,
+{
+    /// Creates a pending patch against the document at `base_version`.
+    pub fn with_version(patch: Value, base_version: V, requester: impl Into<String>) -> Self {
+        Self {
+            patch,
+            base_version,
+            requester: requester.into(),
+            metadata: Map::new(),
+            state: PatchState::Pending,
+            target: PhantomData,
+        }
+    }
+
+    /// Current approval state.
+    pub fn state(&self) -> &PatchState {
+        &self.state
+    }
+
+    /// Approves a pending patch.
+    pub fn approve(&mut self, by: impl Into<String>) -> Result<(), ApprovalError> {
+        self.transition(PatchState::Approved { by: by.into() })
+    }
+
+    /// Rejects a pending patch.
+    pub fn reject(
+        &mut self,
+        by: impl Into<String>,
+        reason: Option<String>,
+    ) -> Result<(), ApprovalError> {
+        self.transition(PatchState::Rejected {
+            by: by.into(),
+            reason,
+        })
+    }
+
+    /// Applies an approved patch to `current`, which is at `version`, as
+    /// [`apply_approved`](PendingPatch::apply_approved) does with the
+    /// fingerprint.
+    pub fn apply_approved_with_version(
+        &mut self,
+        current: &T,
+        version: V,
+    ) -> Result<T, ApprovalError> {
+        let PatchState::Approved { by } = &self.state else {
+            return Err(ApprovalError::InvalidState(self.state.clone()));
+        };
+
+        if version != self.base_version {
+            return Err(ApprovalError::VersionMismatch {
+                expected: self.base_version.to_string(),
+                actual: version.to_string(),
+            });
+        }
+
+        let (updated, _) = crate::apply_patch::apply_resolved(
+            current,
+            self.patch.clone(),
+            &ApplyOptions::default(),
+        )?;
+        self.state = PatchState::Applied {
+            approved_by: by.clone(),
+        };
+        Ok(updated)
+    }
+
+    fn transition(&mut self, next: PatchState) -> Result<(), ApprovalError> {
+        if self.state != PatchState::Pending {
+            return Err(ApprovalError::InvalidState(self.state.clone()));
+        }
+        self.state = next;
+        Ok(())
+    }
+}
+
+fn version_of<T: Serialize>(value: &T) -> Result<String, crate::Error> {
+    let value = crate::error::serialize(value)?;
+    Ok(format!("{:016x}", crate::fingerprint::hash_value(&value)))
+}
@@ -0,0 +1,859 @@
+//! Format-preserving patching of JSONC, YAML and (with the `toml-edit`
+//! feature) TOML documents.
+//!
+//! The patch is applied by editing the original text: untouched members keep
+//! their comments, ordering and formatting, changed values are rewritten in
+//! place, removed members are cut out with their lines and the comment lines
+//! directly above them, and new members are appended to their object using
+//! the indentation of their siblings.
+
+use crate::jsonc::{JsonNode, JsonObject, JsoncParser, line_col, line_start};
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+
+/// Applies a merge patch to a JSON or JSONC document, preserving its formatting.
+///
+/// Comments (`//`, `/* */`) and trailing commas are allowed in the input and
+/// kept in the output.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let config = r#"{
+///     // how many workers to start
+///     "workers": 4,
+///     "debug": true,
+/// }"#;
+///
+/// let patched = serde_patch::preserve::apply_jsonc_preserving(config, &json!({ "workers": 8, "debug": null })).unwrap();
+/// assert_eq!(patched, r#"{
+///     // how many workers to start
+///     "workers": 8,
+/// }"#);
+/// ```
+pub fn apply_jsonc_preserving(text: &str, patch: &Value) -> Result<String, serde_json::Error> {
+    let mut parser = JsoncParser { src: text, pos: 0 };
+    parser.skip_trivia()?;
+    let root_start = parser.pos;
+    let root = parser.parse_value()?;
+    let root_end = parser.pos;
+    parser.skip_trivia()?;
+    if parser.pos < text.len() {
+        return Err(parser.error("trailing characters"));
+    }
+
+    let mut edits = Vec::new();
+    match (&root, patch) {
+        (JsonNode::Object(object), Value::Object(patch_map)) => {
+            patch_object(text, object, patch_map, &mut edits);
+        }
+        _ => edits.push((root_start, root_end, render_json(&merged(patch), Some("")))),
+    }
+    Ok(splice(text, edits))
+}
+
+/// Applies a merge patch to a YAML document, preserving its formatting.
+///
+/// Block mappings are patched key by key; other values touched by the patch
+/// are rewritten in block style (or in place, for scalars and JSON-style flow
+/// collections). The document root must be a block mapping.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let config = "\
+/// ## service settings
+/// server:
+///   port: 8080 # public port
+///   host: localhost
+/// debug: true
+/// ";
+///
+/// let patch = json!({ "server": { "port": 9090 }, "debug": null, "tags": ["a", "b"] });
+/// let patched = serde_patch::preserve::apply_yaml_preserving(config, &patch).unwrap();
+/// assert_eq!(patched, "\
+/// ## service settings
+/// server:
+///   port: 9090 # public port
+///   host: localhost
+/// tags:
+///   - a
+///   - b
+/// ");
+/// ```
+pub fn apply_yaml_preserving(text: &str, patch: &Value) -> Result<String, serde_json::Error> {
+    let Value::Object(patch_map) = patch else {
+        return Err(serde_json::Error::custom(
+            "format-preserving YAML apply needs an object patch",
+        ));
+    };
+
+    let lines = yaml_lines(text);
+    let mut from = 0;
+    while let Some(line) = lines.get(from) {
+        let content = &text[line.start + line.indent..line.end];
+        if line.kind == LineKind::Content && content.trim_end() != "---" {
+            break;
+        }
+        from += 1;
+    }
+    let indent = lines.get(from).map_or(0, |line| line.indent);
+
+    let mut edits = Vec::new();
+    let entries = parse_mapping(text, &lines, from, lines.len(), indent)?;
+    let emptied = patch_mapping(text, &lines, &entries, indent, patch_map, &mut edits)?;
+    let mut patched = splice(text, edits);
+    if emptied {
+        // An empty YAML document is `null`, not an empty mapping.
+        if !patched.is_empty() && !patched.ends_with('\n') {
+            patched.push('\n');
+        }
+        patched.push_str("{}\n");
+    }
+    Ok(patched)
+}
+
+/// Applies a merge patch to a TOML document through `toml_edit`, preserving its formatting.
+///
+/// Only the keys touched by the patch change; comments, ordering and
+/// whitespace elsewhere are kept, and replaced values keep their surrounding
+/// decoration (e.g. trailing comments). New objects become tables, or inline
+/// tables inside inline tables and arrays. TOML has no `null`, so `null`
+/// inside arrays is rejected.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let manifest = r#"[package]
+/// name = "app"
+/// version = "0.1.0" # bumped by CI
+///
+/// [dependencies]
+/// serde = "1"
+/// "#;
+///
+/// let patch = json!({ "package": { "version": "0.2.0" }, "dependencies": { "serde": null } });
+/// let patched = serde_patch::preserve::apply_toml_preserving(manifest, &patch).unwrap();
+/// assert_eq!(patched, r#"[package]
+/// name = "app"
+/// version = "0.2.0" # bumped by CI
+///
+/// [dependencies]
+/// "#);
+/// ```
+#[cfg(feature = "toml-edit")]
+pub fn apply_toml_preserving(text: &str, patch: &Value) -> Result<String, serde_json::Error> {
+    let Value::Object(patch_map) = patch else {
+        return Err(serde_json::Error::custom(
+            "format-preserving TOML apply needs an object patch",
+        ));
+    };
+    let mut document: toml_edit::DocumentMut = text.parse().map_err(serde_json::Error::custom)?;
+    patch_toml_table(document.as_table_mut(), false, patch_map)?;
+    Ok(document.to_string())
+}
+
+/// A text replacement: `(start, end, replacement)`.
+type Edit = (usize, usize, String);
+
+/// Applies non-overlapping edits from the back so earlier offsets stay valid.
+fn splice(text: &str, mut edits: Vec<Edit>) -> String {
+    edits.sort_by_key(|edit| std::cmp::Reverse((edit.0, edit.1)));
+    let mut out = text.to_string();
+    for (start, end, replacement) in edits {
+        out.replace_range(start..end, &replacement);
+    }
+    out
+}
+
+/// The value a patch leaves behind when merged into a non-object (or absent) value.
+fn merged(patch: &Value) -> Value {
+    let mut value = Value::Null;
+    crate::apply_patch::merge_patch(&mut value, patch);
+    value
+}
+
+// ---------------------------------------------------------------------------
+// JSONC
+// ---------------------------------------------------------------------------
+
+/// Start of the comment lines directly above the line starting at `start`,
+/// which belong to the member on that line and go with it; stops at blank
+/// lines, other content and the line of the enclosing `{` at `open`.
+fn leading_comments(text: &str, mut start: usize, open: usize) -> usize {
+    while start > open + 1 {
+        let previous = line_start(text, start - 1);
+        let line = text[previous..start].trim();
+        let comment = line.starts_with("//") || (line.starts_with("/*") && line.ends_with("*/"));
+        if previous <= open || !comment {
+            break;
+        }
+        start = previous;
+    }
+    start
+}
+
+/// Indentation of a member that starts its own line, or `None` for inline members.
+fn own_line_indent(text: &str, pos: usize) -> Option<&str> {
+    let prefix = &text[line_start(text, pos)..pos];
+    prefix.trim().is_empty().then_some(prefix)
+}
+
+/// Position of the line break ending the line at `pos`, if only whitespace or comments follow.
+fn line_end_after(text: &str, pos: usize) -> Option<usize> {
+    let rest = &text[pos..];
+    let trimmed = rest.trim_start_matches([' ', '\t']);
+    let offset = pos + rest.len() - trimmed.len();
+    if trimmed.starts_with("//") || trimmed.starts_with('\n') || trimmed.starts_with("\r\n") {
+        let newline = offset + trimmed.find('\n')?;
+        return Some(if text[..newline].ends_with('\r') {
+            newline - 1
+        } else {
+            newline
+        });
+    }
+    None
+}
+
+/// Renders a value pretty-printed at `indent`, or on one line for inline members.
+fn render_json(value: &Value, indent: Option<&str>) -> String {
+    match indent {
+        Some(indent) => serde_json::to_string_pretty(value)
+            .unwrap_or_else(|_| "null".to_string())
+            .replace('\n', &format!("\n{}", indent)),
+        None => value.to_string(),
+    }
+}
+
+fn patch_object(
+    text: &str,
+    object: &JsonObject,
+    patch: &Map<String, Value>,
+    edits: &mut Vec<Edit>,
+) {
+    let members = &object.members;
+    let mut removed = vec![false; members.len()];
+    let mut additions = Vec::new();
+
+    for (key, patch_value) in patch {
+        let Some(index) = members.iter().rposition(|member| &member.key == key) else {
+            if !patch_value.is_null() {
+                additions.push((key, merged(patch_value)));
+            }
+            continue;
+        };
+        let member = &members[index];
+        match (&member.value, patch_value) {
+            (_, Value::Null) => removed[index] = true,
+            (JsonNode::Object(child), Value::Object(child_patch)) => {
+                patch_object(text, child, child_patch, edits);
+            }
+            _ => {
+                edits.push((
+                    member.value_start,
+                    member.value_end,
+                    render_json(
+                        &merged(patch_value),
+                        own_line_indent(text, member.key_start),
+                    ),
+                ));
+            }
+        }
+    }
+
+    for (member, _) in members
+        .iter()
+        .zip(&removed)
+        .filter(|(_, removed)| **removed)
+    {
+        let end = member.comma.map_or(member.value_end, |comma| comma + 1);
+        let range = match own_line_indent(text, member.key_start) {
+            Some(_) => (
+                leading_comments(text, line_start(text, member.key_start), object.open),
+                line_end_after(text, end).map_or(end, |newline| {
+                    newline + text[newline..].find('\n').map_or(0, |i| i + 1)
+                }),
+            ),
+            None => (
+                member.key_start,
+                end + (text[end..].len() - text[end..].trim_start().len()),
+            ),
+        };
+        edits.push((range.0, range.1.min(object.close), String::new()));
+    }
+
+    let trailing_comma = members.last().is_some_and(|member| member.comma.is_some());
+    let rendered = |indent: Option<&str>| -> Vec<String> {
+        additions
+            .iter()
+            .map(|(key, value)| {
+                format!(
+                    "{}: {}",
+                    serde_json::to_string(key).unwrap_or_default(),
+                    render_json(value, indent)
+                )
+            })
+            .collect()
+    };
+
+    let Some(last) = members
+        .iter()
+        .zip(&removed)
+        .rposition(|(_, removed)| !removed)
+    else {
+        if additions.is_empty() {
+            return;
+        }
+        let inner = &text[object.open + 1..object.close];
+        let text_to_insert = if inner.contains('\n') {
+            let indent = members
+                .first()
+                .and_then(|member| own_line_indent(text, member.key_start))
+                .map(str::to_string)
+                .unwrap_or_else(|| {
+                    let open_line = &text[line_start(text, object.open)..object.open];
+                    let outer = &open_line[..open_line.len() - open_line.trim_start().len()];
+                    format!("{}  ", outer)
+                });
+            rendered(Some(&indent))
+                .iter()
+                .map(|member| format!("\n{}{}", indent, member))
+                .collect::<Vec<_>>()
+                .join(",")
+        } else {
+            rendered(None).join(", ")
+        };
+        edits.push((object.open + 1, object.open + 1, text_to_insert));
+        return;
+    };
+
+    let member = &members[last];
+    match member.comma {
+        Some(comma) if additions.is_empty() && !trailing_comma => {
+            edits.push((comma, comma + 1, String::new()));
+            return;
+        }
+        _ if additions.is_empty() => return,
+        _ => {}
+    }
+
+    let anchor = member.comma.map_or(member.value_end, |comma| comma + 1);
+    let suffix = if trailing_comma { "," } else { "" };
+    let comma_prefix = if member.comma.is_none() { "," } else { "" };
+    match (
+        own_line_indent(text, member.key_start),
+        line_end_after(text, anchor),
+    ) {
+        (Some(indent), Some(newline)) if newline < object.close => {
+            let insertion = rendered(Some(indent))
+                .iter()
+                .map(|added| format!("\n{}{}", indent, added))
+                .collect::<Vec<_>>()
+                .join(",")
+                + suffix;
+            if newline == anchor {
+                edits.push((anchor, anchor, format!("{}{}", comma_prefix, insertion)));
+            } else {
+                if !comma_prefix.is_empty() {
+                    edits.push((anchor, anchor, comma_prefix.to_string()));
+                }
+                edits.push((newline, newline, insertion));
+            }
+        }
+        _ => edits.push((
+            anchor,
+            anchor,
+            format!("{} {}{}", comma_prefix, rendered(None).join(", "), suffix),
+        )),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// YAML
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    Blank,
+    Comment,
+    Content,
+}
+
+struct YamlLine {
+    start: usize,
+    /// End of the content, excluding the line break.
+    end: usize,
+    /// Start of the next line.
+    next: usize,
+    indent: usize,
+    kind: LineKind,
+}
+
+struct YamlEntry {
+    key: String,
+    line: usize,
+    /// End of the `:` separating key and value.
+    colon_end: usize,
+    /// Span of the value written on the key's line, without its comment.
+    inline: Option<(usize, usize)>,
+    /// Lines `(from, to)` of a nested block.
+    block: Option<(usize, usize)>,
+    last_line: usize,
+}
+
+fn yaml_lines(text: &str) -> Vec<YamlLine> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let (end, next) = match text[start..].find('\n') {
+            Some(i) => (start + i, start + i + 1),
+            None => (text.len(), text.len()),
+        };
+        let end = if text[..end].ends_with('\r') {
+            end - 1
+        } else {
+            end
+        };
+        let line = &text[start..end];
+        let content = line.trim_start_matches(' ');
+        let kind = if content.trim().is_empty() {
+            LineKind::Blank
+        } else if content.starts_with('#') {
+            LineKind::Comment
+        } else {
+            LineKind::Content
+        };
+        lines.push(YamlLine {
+            start,
+            end,
+            next,
+            indent: line.len() - content.len(),
+            kind,
+        });
+        start = next;
+    }
+    lines
+}
+
+fn yaml_error(text: &str, pos: usize, msg: &str) -> serde_json::Error {
+    let (line, column) = line_col(text, pos);
+    serde_json::Error::custom(format!("{} at line {} column {}", msg, line, column))
+}
+
+fn is_sequence_item(content: &str) -> bool {
+    content == "-" || content.starts_with("- ")
+}
+
+/// Parses the entries of a block mapping spanning lines `from..to`.
+fn parse_mapping(
+    text: &str,
+    lines: &[YamlLine],
+    from: usize,
+    to: usize,
+    indent: usize,
+) -> Result<Vec<YamlEntry>, serde_json::Error> {
+    let mut entries = Vec::new();
+    let mut index = from;
+    while index < to {
+        let line = &lines[index];
+        if line.kind != LineKind::Content {
+            index += 1;
+            continue;
+        }
+        let content_start = line.start + line.indent;
+        let content = &text[content_start..line.end];
+        if line.indent != indent || is_sequence_item(content) {
+            return Err(yaml_error(text, content_start, "expected a mapping key"));
+        }
+
+        let (key, colon) = parse_yaml_key(content)
+            .ok_or_else(|| yaml_error(text, content_start, "expected `key: value`"))?;
+        let colon_end = content_start + colon;
+        let inline = inline_value(text, colon_end, line.end);
+
+        let mut last_line = index;
+        let mut next = index + 1;
+        while next < to {
+            let candidate = &lines[next];
+            if candidate.kind == LineKind::Content {
+                let candidate_content = &text[candidate.start + candidate.indent..candidate.end];
+                let nested = candidate.indent > indent
+                    || (candidate.indent == indent
+                        && inline.is_none()
+                        && is_sequence_item(candidate_content));
+                if !nested {
+                    break;
+                }
+                last_line = next;
+            }
+            next += 1;
+        }
+
+        entries.push(YamlEntry {
+            key,
+            line: index,
+            colon_end,
+            inline,
+            block: (last_line > index).then_some((index + 1, last_line + 1)),
+            last_line,
+        });
+        index = last_line + 1;
+    }
+    Ok(entries)
+}
+
+/// Parses `key:` at the start of `content`, returning the key and the offset after the colon.
+fn parse_yaml_key(content: &str) -> Option<(String, usize)> {
+    let (key, rest_offset) = match content.as_bytes().first()? {
+        b'"' => {
+            let mut escaped = false;
+            let end = content[1..].char_indices().find_map(|(i, c)| {
+                let found = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                found.then_some(i + 2)
+            })?;
+            (serde_json::from_str(&content[..end]).ok()?, end)
+        }
+        b'\'' => {
+            let mut i = 1;
+            loop {
+                let offset = content[i..].find('\'')? + i;
+                if content[offset + 1..].starts_with('\'') {
+                    i = offset + 2;
+                } else {
+                    break (content[1..offset].replace("''", "'"), offset + 1);
+                }
+            }
+        }
+        _ => {
+            let colon = content.match_indices(':').map(|(i, _)| i).find(|&i| {
+                content[i + 1..].is_empty() || content[i + 1..].starts_with([' ', '\t'])
+            })?;
+            return Some((content[..colon].trim_end().to_string(), colon + 1));
+        }
+    };
+    let rest = &content[rest_offset..];
+    let colon = rest.len() - rest.trim_start().len();
+    rest[colon..]
+        .starts_with(':')
+        .then(|| (key, rest_offset + colon + 1))
+}
+
+/// Span of the value between `from` and `end`, excluding surrounding spaces and a trailing comment.
+fn inline_value(text: &str, from: usize, end: usize) -> Option<(usize, usize)> {
+    let segment = &text[from..end];
+    let mut quote = None;
+    let mut escaped = false;
+    let mut previous = ' ';
+    let mut value_end = segment.len();
+    for (i, c) in segment.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '#' && previous.is_whitespace() => {
+                value_end = i;
+                break;
+            }
+            None if (c == '"' || c == '\'') && previous.is_whitespace() => quote = Some(c),
+            None => {}
+        }
+        previous = c;
+    }
+    let value = &segment[..value_end];
+    let start = value.len() - value.trim_start().len();
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then_some((from + start, from + start + trimmed.len()))
+}
+
+fn patch_mapping(
+    text: &str,
+    lines: &[YamlLine],
+    entries: &[YamlEntry],
+    indent: usize,
+    patch: &Map<String, Value>,
+    edits: &mut Vec<Edit>,
+) -> Result<bool, serde_json::Error> {
+    let mut additions = Vec::new();
+
+    for (key, patch_value) in patch {
+        let Some(entry) = entries.iter().rev().find(|entry| &entry.key == key) else {
+            if !patch_value.is_null() {
+                additions.push(render_yaml_entry(
+                    &render_yaml_key(key),
+                    &merged(patch_value),
+                    indent,
+                ));
+            }
+            continue;
+        };
+        let entry_start = lines[entry.line].start;
+        let entry_end = lines[entry.last_line].next;
+        let key_text = text[entry_start + indent..entry.colon_end - 1].trim_end();
+
+        if patch_value.is_null() {
+            // Comment lines directly above the key go with it, unless they
+            // open the document and describe it as a whole.
+            let mut first = entry.line;
+            while first > 0
+                && lines[first - 1].kind == LineKind::Comment
+                && lines[first - 1].indent == indent
+            {
+                first -= 1;
+            }
+            let start = if first > 0 {
+                lines[first].start
+            } else {
+                entry_start
+            };
+            edits.push((start, entry_end, String::new()));
+            continue;
+        }
+
+        if let (Value::Object(child_patch), None, Some((from, to))) =
+            (patch_value, entry.inline, entry.block)
+            && let Some(first) = lines[from..to]
+                .iter()
+                .find(|line| line.kind == LineKind::Content)
+            && !is_sequence_item(&text[first.start + first.indent..first.end])
+        {
+            let children = parse_mapping(text, lines, from, to, first.indent)?;
+            if patch_mapping(text, lines, &children, first.indent, child_patch, edits)? {
+                edits.push((entry.colon_end, entry.colon_end, " {}".to_string()));
+            }
+            continue;
+        }
+
+        let new_value = match (patch_value, entry.inline) {
+            (Value::Object(_), Some((start, end))) if text[start..end].starts_with('{') => {
+                let mut current: Value = serde_json::from_str(&text[start..end]).map_err(|_| {
+                    yaml_error(text, start, "only JSON-style flow mappings can be patched")
+                })?;
+                crate::apply_patch::merge_patch(&mut current, patch_value);
+                edits.push((start, end, current.to_string()));
+                continue;
+            }
+            _ => merged(patch_value),
+        };
+
+        match (yaml_scalar(&new_value), entry.inline, entry.block) {
+            (Some(scalar), Some((start, end)), None) => edits.push((start, end, scalar)),
+            (Some(scalar), None, None) => {
+                edits.push((entry.colon_end, entry.colon_end, format!(" {}", scalar)))
+            }
+            _ => {
+                let newline = if lines[entry.last_line].next > lines[entry.last_line].end {
+                    "\n"
+                } else {
+                    ""
+                };
+                edits.push((
+                    entry_start,
+                    entry_end,
+                    render_yaml_entry(key_text, &new_value, indent) + newline,
+                ));
+            }
+        }
+    }
+
+    if additions.is_empty() {
+        // Tells the caller to write `{}` for a mapping left without entries.
+        let emptied = entries
+            .iter()
+            .all(|entry| patch.get(&entry.key).is_some_and(Value::is_null));
+        return Ok(emptied);
+    }
+    let (position, needs_newline) = match entries.last() {
+        Some(entry) => {
+            let line = &lines[entry.last_line];
+            (line.next, line.next == line.end)
+        }
+        None => (text.len(), !text.is_empty() && !text.ends_with('\n')),
+    };
+    let mut insertion = if needs_newline {
+        "\n".to_string()
+    } else {
+        String::new()
+    };
+    for addition in additions {
+        insertion.push_str(&addition);
+        insertion.push('\n');
+    }
+    edits.push((position, position, insertion));
+    Ok(false)
+}
+
+fn render_yaml_key(key: &str) -> String {
+    if is_plain_yaml(key) {
+        key.to_string()
+    } else {
+        serde_json::to_string(key).unwrap_or_default()
+    }
+}
+
+/// Renders scalars and empty collections on one line; `None` for block values.
+fn yaml_scalar(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::String(s) if is_plain_yaml(s) => s.clone(),
+        Value::Array(items) if items.is_empty() => "[]".to_string(),
+        Value::Object(map) if map.is_empty() => "{}".to_string(),
+        Value::Array(_) | Value::Object(_) => return None,
+        other => other.to_string(),
+    })
+}
+
+/// Returns `true` if `s` can be written as a plain (unquoted) YAML string.
+fn is_plain_yaml(s: &str) -> bool {
+    const RESERVED: &[&str] = &["true", "false", "yes", "no", "on", "off", "null", "y", "n"];
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || " _-./@".contains(c))
+        && !s.ends_with(' ')
+        && !RESERVED.contains(&s.to_ascii_lowercase().as_str())
+}
+
+fn render_yaml_entry(key: &str, value: &Value, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    if let Some(scalar) = yaml_scalar(value) {
+        return format!("{}{}: {}", pad, key, scalar);
+    }
+    let mut out = format!("{}{}:", pad, key);
+    match value {
+        Value::Object(map) => {
+            for (child_key, child) in map {
+                out.push('\n');
+                out.push_str(&render_yaml_entry(
+                    &render_yaml_key(child_key),
+                    child,
+                    indent + 2,
+                ));
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                out.push('\n');
+                out.push_str(&render_yaml_item(item, indent + 2));
+            }
+        }
+        _ => {}
+    }
+    out
+}
+
+fn render_yaml_item(value: &Value, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    if let Some(scalar) = yaml_scalar(value) {
+        return format!("{}- {}", pad, scalar);
+    }
+    let body = match value {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, child)| render_yaml_entry(&render_yaml_key(key), child, indent + 2))
+            .collect::<Vec<_>>(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| render_yaml_item(item, indent + 2))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let joined = body.join("\n");
+    format!("{}- {}", pad, &joined[indent + 2..])
+}
+
+// ---------------------------------------------------------------------------
+// TOML
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "toml-edit")]
+fn patch_toml_table(
+    table: &mut dyn toml_edit::TableLike,
+    inline: bool,
+    patch: &Map<String, Value>,
+) -> Result<(), serde_json::Error> {
+    use toml_edit::Item;
+
+    for (key, patch_value) in patch {
+        if patch_value.is_null() {
+            table.remove(key);
+            continue;
+        }
+
+        if let Value::Object(child_patch) = patch_value
+            && let Some(item) = table.get_mut(key)
+            && item.is_table_like()
+        {
+            let child_inline = item.is_inline_table();
+            if let Some(child) = item.as_table_like_mut() {
+                patch_toml_table(child, child_inline, child_patch)?;
+            }
+            continue;
+        }
+
+        let new_value = merged(patch_value);
+        let new_item = match &new_value {
+            Value::Object(map) if !inline => Item::Table(toml_table(map)?),
+            other => Item::Value(toml_value(other)?),
+        };
+        match (table.get_mut(key), new_item) {
+            (Some(Item::Value(old)), Item::Value(mut new)) => {
+                *new.decor_mut() = old.decor().clone();
+                *old = new;
+            }
+            (_, new_item) => {
+                table.insert(key, new_item);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "toml-edit")]
+fn toml_table(map: &Map<String, Value>) -> Result<toml_edit::Table, serde_json::Error> {
+    let mut table = toml_edit::Table::new();
+    for (key, value) in map {
+        let item = match value {
+            Value::Object(child) => toml_edit::Item::Table(toml_table(child)?),
+            other => toml_edit::Item::Value(toml_value(other)?),
+        };
+        table.insert(key, item);
+    }
+    Ok(table)
+}
+
+#[cfg(feature = "toml-edit")]
+fn toml_value(value: &Value) -> Result<toml_edit::Value, serde_json::Error> {
+    Ok(match value {
+        Value::Null => return Err(serde_json::Error::custom("TOML cannot represent null")),
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => i.into(),
+            (None, Some(f)) if n.is_f64() => f.into(),
+            _ => {
+                return Err(serde_json::Error::custom(format!(
+                    "number {} is out of range for TOML",
+                    n
+                )));
+            }
+        },
+        Value::String(s) => s.as_str().into(),
+        Value::Array(items) => items
+            .iter()
+            .map(toml_value)
+            .collect::<Result<toml_edit::Array, _>>()?
+            .into(),
+        Value::Object(map) => {
+            let mut table = toml_edit::InlineTable::new();
+            for (key, value) in map {
+                table.insert(key, toml_value(value)?);
+            }
+            table.into()
+        }
+    })
+}
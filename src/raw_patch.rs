@@ -0,0 +1,266 @@
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use serde_json::value::RawValue;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// A merge patch kept as unparsed JSON text.
+///
+/// Meant for proxies and gateways that receive a patch, look at which paths
+/// it touches, and forward it unchanged: the original bytes are kept, and
+/// inspection only parses the levels it walks through. Convert it with
+/// [`RawPatch::to_patch`] or [`RawPatch::to_value`] when the patch needs to
+/// be modified or applied.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::RawPatch;
+///
+/// let raw = RawPatch::from_string(r#"{ "profile": { "bio": "new" }, "age": 31 }"#.to_string()).unwrap();
+///
+/// assert!(raw.touches("profile.bio").unwrap());
+/// assert!(!raw.touches("username").unwrap());
+/// assert_eq!(raw.paths().unwrap(), ["age", "profile.bio"]);
+///
+/// // Forwarded byte for byte.
+/// assert_eq!(raw.get(), r#"{ "profile": { "bio": "new" }, "age": 31 }"#);
+/// ```
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RawPatch(Box<RawValue>);
+
+impl RawPatch {
+    /// Validates `json` and wraps it without re-serializing.
+    pub fn from_string(json: String) -> Result<Self, serde_json::Error> {
+        RawValue::from_string(json).map(Self)
+    }
+
+    /// The original JSON text.
+    pub fn get(&self) -> &str {
+        self.0.get()
+    }
+
+    /// Returns `true` if the patch sets or removes `path` or anything below it.
+    ///
+    /// Only the objects along `path` are parsed.
+    pub fn touches(&self, path: &str) -> Result<bool, serde_json::Error> {
+        self.at(path).map(|raw| raw.is_some())
+    }
+
+    /// The raw patch value at a dotted `path`, if the patch contains it.
+    pub fn at(&self, path: &str) -> Result<Option<&RawValue>, serde_json::Error> {
+        let mut current: &RawValue = &self.0;
+        if path.is_empty() {
+            return Ok(Some(current));
+        }
+        for segment in crate::paths::split(path) {
+            let Some(mut members) = members(current)? else {
+                return Ok(None);
+            };
+            let Some(next) = members.remove(segment.as_str()) else {
+                return Ok(None);
+            };
+            current = next;
+        }
+        Ok(Some(current))
+    }
+
+    /// Dotted paths of every leaf the patch sets or removes, in sorted order.
+    pub fn paths(&self) -> Result<Vec<String>, serde_json::Error> {
+        let mut paths = Vec::new();
+        collect_paths(&self.0, "", &mut paths)?;
+        Ok(paths)
+    }
+
+    /// Parses the patch into an owned value.
+    pub fn to_value(&self) -> Result<Value, serde_json::Error> {
+        crate::backend::from_slice(self.0.get().as_bytes())
+    }
+
+    /// Parses the patch into an owned [`Patch`](crate::Patch).
+    pub fn to_patch(&self) -> Result<crate::Patch, serde_json::Error> {
+        self.to_value().map(crate::Patch::new)
+    }
+
+    /// Unwraps the underlying raw value.
+    pub fn into_inner(self) -> Box<RawValue> {
+        self.0
+    }
+}
+
+impl From<Box<RawValue>> for RawPatch {
+    fn from(raw: Box<RawValue>) -> Self {
+        Self(raw)
+    }
+}
+
+impl TryFrom<&RawPatch> for crate::Patch {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: &RawPatch) -> Result<Self, Self::Error> {
+        raw.to_patch()
+    }
+}
+
+impl AsRef<[u8]> for RawPatch {
+    fn as_ref(&self) -> &[u8] {
+        self.0.get().as_bytes()
+    }
+}
+
+impl fmt::Debug for RawPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RawPatch").field(&self.0.get()).finish()
+    }
+}
+
+impl fmt::Display for RawPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.get())
+    }
+}
+
+/// Applies a JSON Merge Patch (RFC 7396) to a document kept as JSON text.
+///
+/// Only the objects the patch walks into are parsed, one level at a time;
+/// every member the patch doesn't mention is copied through byte for byte,
+/// without being parsed into a `Value` or serialized again. For very large
+/// documents of which a patch changes a small part, e.g. blobs stored as
+/// JSON text. Touched objects are written compactly, with their members in
+/// their original order followed by the added ones.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::{json, value::RawValue};
+///
+/// let document = RawValue::from_string(
+///     r#"{"id": 7, "history": [ {"at": 1}, {"at": 2} ], "profile": {"bio": "hi", "tags": ["a"]}}"#.to_string(),
+/// )
+/// .unwrap();
+///
+/// let patched = serde_patch::apply_raw(&document, &json!({ "profile": { "bio": "hello", "tags": null } })).unwrap();
+/// assert_eq!(
+///     patched.get(),
+///     r#"{"id":7,"history":[ {"at": 1}, {"at": 2} ],"profile":{"bio":"hello"}}"#
+/// );
+/// ```
+pub fn apply_raw(document: &RawValue, patch: &Value) -> Result<Box<RawValue>, serde_json::Error> {
+    let mut out = String::with_capacity(document.get().len());
+    write_patched(document, patch, &mut out)?;
+    RawValue::from_string(out)
+}
+
+/// Writes `document` with `patch` merged in (internal).
+fn write_patched(
+    document: &RawValue,
+    patch: &Value,
+    out: &mut String,
+) -> Result<(), serde_json::Error> {
+    let Value::Object(patch_map) = patch else {
+        out.push_str(&serde_json::to_string(patch)?);
+        return Ok(());
+    };
+    let members = match document.get().trim_start().starts_with('{') {
+        true => serde_json::from_str::<Ordered>(document.get())?.0,
+        false => Vec::new(),
+    };
+    let existing: HashSet<&str> = members.iter().map(|(key, _)| key.as_str()).collect();
+    out.push('{');
+    let mut first = true;
+    let mut separate = |out: &mut String| {
+        if !std::mem::take(&mut first) {
+            out.push(',');
+        }
+    };
+    for (key, value) in &members {
+        match patch_map.get(key) {
+            Some(Value::Null) => continue,
+            Some(member_patch) => {
+                separate(out);
+                write_key(key, out)?;
+                write_patched(value, member_patch, out)?;
+            }
+            None => {
+                separate(out);
+                write_key(key, out)?;
+                out.push_str(value.get());
+            }
+        }
+    }
+    for (key, member_patch) in patch_map {
+        if member_patch.is_null() || existing.contains(key.as_str()) {
+            continue;
+        }
+        let mut value = Value::Null;
+        crate::merge_into(&mut value, member_patch);
+        separate(out);
+        write_key(key, out)?;
+        out.push_str(&serde_json::to_string(&value)?);
+    }
+    out.push('}');
+    Ok(())
+}
+
+fn write_key(key: &str, out: &mut String) -> Result<(), serde_json::Error> {
+    out.push_str(&serde_json::to_string(key)?);
+    out.push(':');
+    Ok(())
+}
+
+/// The members of an object in document order, values left unparsed (internal).
+struct Ordered<'a>(Vec<(String, &'a RawValue)>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for Ordered<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OrderedVisitor<'a>(std::marker::PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for OrderedVisitor<'a> {
+            type Value = Ordered<'a>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut members = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(member) = map.next_entry::<String, &'de RawValue>()? {
+                    members.push(member);
+                }
+                Ok(Ordered(members))
+            }
+        }
+
+        deserializer.deserialize_map(OrderedVisitor(std::marker::PhantomData))
+    }
+}
+
+/// Parses one level of an object, or returns `None` for non-objects (internal).
+fn members(raw: &RawValue) -> Result<Option<BTreeMap<String, &RawValue>>, serde_json::Error> {
+    if !raw.get().trim_start().starts_with('{') {
+        return Ok(None);
+    }
+    serde_json::from_str(raw.get()).map(Some)
+}
+
+fn collect_paths(
+    raw: &RawValue,
+    current_path: &str,
+    paths: &mut Vec<String>,
+) -> Result<(), serde_json::Error> {
+    let Some(members) = members(raw)? else {
+        paths.push(current_path.to_string());
+        return Ok(());
+    };
+    // Like `Patch::paths`, a nested `{}` is reported as a leaf.
+    if members.is_empty() && !current_path.is_empty() {
+        paths.push(current_path.to_string());
+    }
+    for (key, value) in members {
+        let full_path = crate::paths::join(current_path, &key);
+        collect_paths(value, &full_path, paths)?;
+    }
+    Ok(())
+}
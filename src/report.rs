@@ -0,0 +1,183 @@
+use crate::ApplyOptions;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+/// A patched field whose value did not survive the round trip through the target type.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LossyConversion {
+    /// Dotted path of the field.
+    pub path: String,
+    /// Value requested by the patch.
+    pub requested: Value,
+    /// Value the field ended up with, or `None` if the target type dropped it.
+    pub applied: Option<Value>,
+}
+
+/// Outcome details of [`apply_with_report`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ApplyReport {
+    /// Fields that were narrowed, coerced or dropped while applying.
+    pub lossy: Vec<LossyConversion>,
+}
+
+impl ApplyReport {
+    /// Returns `true` if every patched value was applied exactly.
+    pub fn is_lossless(&self) -> bool {
+        self.lossy.is_empty()
+    }
+}
+
+/// Applies a JSON Merge Patch and reports fields that were not applied exactly.
+///
+/// After applying, the result is serialized again and every value set by the
+/// patch is compared with what the field actually holds. Differences reveal
+/// lossy conversions: float narrowing (`0.1` into an `f32`), coercing
+/// deserializers (`3.999` truncated into a `u8`), or fields the target type
+/// doesn't have and silently dropped. Integers and integral floats compare
+/// equal (`3` vs `3.0`).
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::ApplyOptions;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Reading { sensor: String, value: f32 }
+///
+/// let current = Reading { sensor: "t1".to_string(), value: 20.5 };
+/// let patch = r#"{ "unit": "C", "value": 21.123456789 }"#;
+///
+/// let (updated, report) =
+///     serde_patch::apply_with_report(current, patch, &ApplyOptions::default()).unwrap();
+/// assert_eq!(updated.value, 21.123457_f32);
+///
+/// let paths: Vec<_> = report.lossy.iter().map(|l| l.path.as_str()).collect();
+/// assert_eq!(paths, ["unit", "value"]);
+/// assert_eq!(report.lossy[0].applied, None);
+/// ```
+pub fn apply_with_report<T, P>(
+    current: T,
+    patch: P,
+    options: &ApplyOptions,
+) -> Result<(T, ApplyReport), crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+    P: AsRef<[u8]>,
+{
+    let patch_val: Value =
+        crate::backend::from_slice(patch.as_ref()).map_err(crate::Error::ParsePatch)?;
+    apply_value_with_report(&current, patch_val, options)
+}
+
+/// Applies an already-parsed patch and builds its [`ApplyReport`] (internal).
+pub(crate) fn apply_value_with_report<T>(
+    current: &T,
+    patch_val: Value,
+    options: &ApplyOptions,
+) -> Result<(T, ApplyReport), crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let (updated, resolved_patch) =
+        crate::apply_patch::apply_resolved(current, patch_val, options)?;
+
+    let mut applied = crate::error::serialize(&updated)?;
+    crate::representation::to_views(&mut applied, &options.representations);
+
+    let mut report = ApplyReport::default();
+    collect_lossy(&resolved_patch, &applied, "", &mut report.lossy);
+    Ok((updated, report))
+}
+
+/// Compares patched values with the applied result (internal).
+fn collect_lossy(
+    patch: &Value,
+    applied: &Value,
+    current_path: &str,
+    out: &mut Vec<LossyConversion>,
+) {
+    let Value::Object(patch_map) = patch else {
+        if !values_equal(patch, applied) {
+            out.push(LossyConversion {
+                path: current_path.to_string(),
+                requested: patch.clone(),
+                applied: Some(applied.clone()),
+            });
+        }
+        return;
+    };
+
+    for (key, patch_value) in patch_map {
+        if patch_value.is_null() {
+            continue;
+        }
+        let full_path = crate::paths::join(current_path, key);
+        match applied.get(key) {
+            None => out.push(LossyConversion {
+                path: full_path,
+                requested: patch_value.clone(),
+                applied: None,
+            }),
+            Some(applied_value) => collect_lossy(patch_value, applied_value, &full_path, out),
+        }
+    }
+}
+
+/// JSON equality where integers and integral floats of the same value are equal.
+pub(crate) fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        // Numbers keep their digits, which `f64` would round away.
+        #[cfg(feature = "arbitrary-precision")]
+        (Value::Number(a), Value::Number(b)) => decimal(&a.to_string()) == decimal(&b.to_string()),
+        #[cfg(not(feature = "arbitrary-precision"))]
+        (Value::Number(a), Value::Number(b)) => {
+            let as_int = |n: &serde_json::Number| {
+                n.as_i64()
+                    .map(i128::from)
+                    .or_else(|| n.as_u64().map(i128::from))
+            };
+            match (as_int(a), as_int(b), a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b), _, _) => a == b,
+                (Some(i), None, _, Some(f)) | (None, Some(i), Some(f), _) => {
+                    f.fract() == 0.0 && f as i128 == i
+                }
+                (_, _, Some(a), Some(b)) => a == b,
+                _ => a == b,
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| values_equal(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+/// A decimal literal as `(negative, significant digits, exponent)`, so equal
+/// values compare equal whatever their notation, e.g. `3`, `3.0` and `0.3e1`
+/// (internal).
+#[cfg(feature = "arbitrary-precision")]
+fn decimal(literal: &str) -> (bool, String, i64) {
+    let (negative, unsigned) = match literal.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, literal),
+    };
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().unwrap_or(0)),
+        None => (unsigned, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{int}{frac}");
+    let trimmed = digits.trim_end_matches('0');
+    let exponent = exponent - frac.len() as i64 + (digits.len() - trimmed.len()) as i64;
+    let trimmed = trimmed.trim_start_matches('0');
+    if trimmed.is_empty() {
+        return (false, String::new(), 0);
+    }
+    (negative, trimmed.to_string(), exponent)
+}
@@ -0,0 +1,204 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Maps a field's serialized form to the form used for diffing and patching.
+///
+/// Serde adapters (such as those from `serde_with`) can serialize a field in a
+/// shape that hides its structure, e.g. a map written as a list of pairs. A
+/// representation registered for that field's path converts it into a "view"
+/// before diffing or merging, so nested paths resolve through it, and converts
+/// it back after merging.
+///
+/// Implementations must round-trip: `unview(view(v)) == v` up to
+/// ordering that the field's deserializer ignores.
+pub trait Representation: fmt::Debug + Send + Sync {
+    /// Converts the serialized form into the view.
+    fn view(&self, value: Value) -> Value;
+
+    /// Converts the view back into the serialized form.
+    fn unview(&self, view: Value) -> Value;
+}
+
+/// Views a list of `[key, value]` pairs as an object keyed by `key`.
+///
+/// Matches maps serialized as `Vec<(_, _)>` via `serde_with`. Lists with
+/// non-string keys are left as they are, since their keys can't be recovered
+/// from an object; register a custom [`Representation`] for those.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{DiffOptions, PairsAsMap};
+/// use std::sync::Arc;
+///
+/// #[derive(serde::Serialize)]
+/// struct Service { labels: Vec<(String, String)> }
+///
+/// let old = Service { labels: vec![("env".into(), "dev".into()), ("team".into(), "core".into())] };
+/// let new = Service { labels: vec![("team".into(), "core".into()), ("env".into(), "prod".into())] };
+///
+/// let options = DiffOptions {
+///     representations: [("labels".to_string(), Arc::new(PairsAsMap) as _)].into(),
+///     ..Default::default()
+/// };
+/// let patch = serde_patch::diff_with(&old, &new, &options).unwrap();
+/// assert_eq!(patch, json!({ "labels": { "env": "prod" } }));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PairsAsMap;
+
+impl Representation for PairsAsMap {
+    fn view(&self, value: Value) -> Value {
+        match value {
+            Value::Array(items) if items.iter().all(is_string_pair) => {
+                Value::Object(items.into_iter().filter_map(into_pair).collect())
+            }
+            other => other,
+        }
+    }
+
+    fn unview(&self, view: Value) -> Value {
+        match view {
+            Value::Object(map) => Value::Array(
+                map.into_iter()
+                    .map(|(key, value)| Value::Array(vec![Value::String(key), value]))
+                    .collect(),
+            ),
+            other => other,
+        }
+    }
+}
+
+/// Treats a string field holding serialized JSON as the JSON it contains.
+///
+/// As a [`Representation`], strings holding a JSON object or array are parsed
+/// before diffing, so a change to one inner field yields a nested patch rather
+/// than a whole new string; apply with the same representation to merge
+/// inside the string. As a [`Comparator`](crate::Comparator), it only ignores
+/// formatting differences and still emits the full new string on change.
+/// Strings that are not JSON are compared and patched as plain strings.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::{ApplyOptions, DiffOptions, EmbeddedJson};
+/// use std::sync::Arc;
+///
+/// #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+/// struct Hook { url: String, payload: String }
+///
+/// let old = Hook { url: "https://a".into(), payload: r#"{"event":"push","retries":3}"#.into() };
+/// let new = Hook { url: "https://a".into(), payload: r#"{"event":"push","retries":5}"#.into() };
+///
+/// let options = DiffOptions {
+///     representations: [("payload".to_string(), Arc::new(EmbeddedJson) as _)].into(),
+///     ..Default::default()
+/// };
+/// let patch = serde_patch::diff_with(&old, &new, &options).unwrap();
+/// assert_eq!(patch, json!({ "payload": { "retries": 5 } }));
+///
+/// let options = ApplyOptions {
+///     representations: [("payload".to_string(), Arc::new(EmbeddedJson) as _)].into(),
+///     ..Default::default()
+/// };
+/// let updated = serde_patch::apply_with(old, patch.to_string(), &options).unwrap();
+/// assert_eq!(updated, new);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EmbeddedJson;
+
+impl Representation for EmbeddedJson {
+    fn view(&self, value: Value) -> Value {
+        match &value {
+            Value::String(s) => match serde_json::from_str::<Value>(s) {
+                Ok(parsed @ (Value::Object(_) | Value::Array(_))) => parsed,
+                _ => value,
+            },
+            _ => value,
+        }
+    }
+
+    fn unview(&self, view: Value) -> Value {
+        match view {
+            Value::Object(_) | Value::Array(_) => Value::String(view.to_string()),
+            other => other,
+        }
+    }
+}
+
+impl crate::Comparator for EmbeddedJson {
+    fn equivalent(&self, old: &Value, new: &Value) -> bool {
+        let parse = |value: &Value| {
+            value
+                .as_str()
+                .and_then(|s| serde_json::from_str::<Value>(s).ok())
+        };
+        match (parse(old), parse(new)) {
+            (Some(old), Some(new)) => old == new,
+            _ => old == new,
+        }
+    }
+}
+
+fn is_string_pair(item: &Value) -> bool {
+    matches!(
+        item.as_array().map(Vec::as_slice),
+        Some([Value::String(_), _])
+    )
+}
+
+fn into_pair(item: Value) -> Option<(String, Value)> {
+    let Value::Array(pair) = item else {
+        return None;
+    };
+    match <[Value; 2]>::try_from(pair).ok()? {
+        [Value::String(key), value] => Some((key, value)),
+        _ => None,
+    }
+}
+
+/// Representations registered by dotted path.
+pub type Representations = HashMap<String, Arc<dyn Representation>>;
+
+/// Converts every registered path into its view, outermost first (internal).
+pub fn to_views(root: &mut Value, representations: &Representations) {
+    for (path, representation) in sorted(representations, false) {
+        if let Some(value) = value_at_mut(root, path) {
+            *value = representation.view(std::mem::take(value));
+        }
+    }
+}
+
+/// Converts every registered path back from its view, innermost first (internal).
+pub fn from_views(root: &mut Value, representations: &Representations) {
+    for (path, representation) in sorted(representations, true) {
+        if let Some(value) = value_at_mut(root, path) {
+            *value = representation.unview(std::mem::take(value));
+        }
+    }
+}
+
+fn sorted(
+    representations: &Representations,
+    innermost_first: bool,
+) -> Vec<(&str, &dyn Representation)> {
+    let mut entries: Vec<_> = representations
+        .iter()
+        .map(|(path, representation)| (path.as_str(), representation.as_ref()))
+        .collect();
+    entries.sort_by_key(|(path, _)| crate::paths::split(path).len());
+    if innermost_first {
+        entries.reverse();
+    }
+    entries
+}
+
+fn value_at_mut<'a>(root: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    crate::paths::split(path)
+        .iter()
+        .try_fold(root, |value, key| value.get_mut(key))
+}
@@ -0,0 +1,204 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Reviewer decision on a [`ReviewEntry`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Decision {
+    /// Not decided yet; left out of the reviewed patch.
+    #[default]
+    Pending,
+    /// Kept in the reviewed patch.
+    Accepted,
+    /// Left out of the reviewed patch.
+    Rejected,
+}
+
+/// One reviewable change of a [`Review`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReviewEntry {
+    /// Dotted path of the field, with `.` and `\` in keys escaped.
+    pub path: String,
+    /// Current value of the field, if the review was built with old values.
+    pub old: Option<Value>,
+    /// Value proposed by the patch (`null` for removals).
+    pub new: Value,
+    /// The reviewer's decision, [`Decision::Pending`] until one is made.
+    pub decision: Decision,
+    segments: Vec<String>,
+}
+
+/// Field-by-field review of a merge patch.
+///
+/// Each changed leaf of the patch becomes an entry that can be accepted or
+/// rejected; [`Review::to_patch`] then builds a patch containing only the
+/// accepted changes. Entries start out [`Decision::Pending`], and pending
+/// entries are left out of the resulting patch.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::Review;
+///
+/// #[derive(serde::Serialize)]
+/// struct Profile { name: String, bio: String, website: String }
+///
+/// let current = Profile { name: "a".into(), bio: "old".into(), website: "https://a".into() };
+/// let patch = json!({ "bio": "new", "website": "https://spam.example" });
+///
+/// let mut review = Review::with_old(&patch, &current).unwrap();
+/// assert_eq!(review.entries()[1].old, Some(json!("https://a")));
+///
+/// review.accept("bio");
+/// review.reject("website");
+/// assert_eq!(review.to_patch(), json!({ "bio": "new" }));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Review {
+    entries: Vec<ReviewEntry>,
+}
+
+impl Review {
+    /// Builds a review with one entry per changed leaf of `patch`, counting
+    /// nested empty objects as leaves.
+    pub fn new(patch: &Value) -> Self {
+        let mut entries = Vec::new();
+        collect_entries(patch, None, &mut Vec::new(), &mut entries);
+        Self { entries }
+    }
+
+    /// Builds a review that also records the current value of each field.
+    ///
+    /// Objects in the patch that replace a non-object value are reviewed as a
+    /// single entry rather than per key.
+    pub fn with_old<T: Serialize>(patch: &Value, old: &T) -> Result<Self, serde_json::Error> {
+        Ok(Self::with_old_value(patch, &crate::value::to_value(old)?))
+    }
+
+    /// Same as [`Review::with_old`] for an already-serialized value (internal).
+    pub(crate) fn with_old_value(patch: &Value, old: &Value) -> Self {
+        let mut entries = Vec::new();
+        collect_entries(patch, Some(old), &mut Vec::new(), &mut entries);
+        Self { entries }
+    }
+
+    /// All entries, in path order.
+    pub fn entries(&self) -> &[ReviewEntry] {
+        &self.entries
+    }
+
+    /// Accepts the entry at `path` and every entry below it; returns how many were affected.
+    ///
+    /// `path` is matched segment by segment, so `a` doesn't cover `a\.b`.
+    pub fn accept(&mut self, path: &str) -> usize {
+        self.decide(path, Decision::Accepted)
+    }
+
+    /// Rejects the entry at `path` and every entry below it; returns how many were affected.
+    pub fn reject(&mut self, path: &str) -> usize {
+        self.decide(path, Decision::Rejected)
+    }
+
+    /// Accepts every entry that is still pending.
+    pub fn accept_pending(&mut self) {
+        self.decide_pending(Decision::Accepted);
+    }
+
+    /// Rejects every entry that is still pending.
+    pub fn reject_pending(&mut self) {
+        self.decide_pending(Decision::Rejected);
+    }
+
+    /// Returns `true` once every entry has been accepted or rejected.
+    pub fn is_complete(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| entry.decision != Decision::Pending)
+    }
+
+    /// Builds a merge patch containing only the accepted entries.
+    pub fn to_patch(&self) -> Value {
+        let mut patch = Map::new();
+        'entries: for entry in self
+            .entries
+            .iter()
+            .filter(|e| e.decision == Decision::Accepted)
+        {
+            let Some((last, parents)) = entry.segments.split_last() else {
+                return entry.new.clone();
+            };
+            let mut target = &mut patch;
+            for segment in parents {
+                let Value::Object(child) = target
+                    .entry(segment.clone())
+                    .or_insert_with(|| Value::Object(Map::new()))
+                else {
+                    continue 'entries;
+                };
+                target = child;
+            }
+            target.insert(last.clone(), entry.new.clone());
+        }
+        Value::Object(patch)
+    }
+
+    fn decide(&mut self, path: &str, decision: Decision) -> usize {
+        let segments = if path.is_empty() {
+            Vec::new()
+        } else {
+            crate::paths::split(path)
+        };
+        let mut affected = 0;
+        for entry in &mut self.entries {
+            if entry.segments.starts_with(&segments) {
+                entry.decision = decision;
+                affected += 1;
+            }
+        }
+        affected
+    }
+
+    fn decide_pending(&mut self, decision: Decision) {
+        for entry in &mut self.entries {
+            if entry.decision == Decision::Pending {
+                entry.decision = decision;
+            }
+        }
+    }
+}
+
+fn collect_entries(
+    patch: &Value,
+    old: Option<&Value>,
+    segments: &mut Vec<String>,
+    out: &mut Vec<ReviewEntry>,
+) {
+    // A nested `{}` has no leaves but still creates the object if it is
+    // missing, so it is reviewed as an entry of its own.
+    let descend = match (patch, old) {
+        (Value::Object(map), _) if map.is_empty() => segments.is_empty(),
+        (Value::Object(_), None) => true,
+        (Value::Object(_), Some(old)) => old.is_object() || segments.is_empty(),
+        _ => false,
+    };
+
+    if let (true, Value::Object(patch_map)) = (descend, patch) {
+        for (key, patch_value) in patch_map {
+            segments.push(key.clone());
+            let old_value = old.map(|old| old.get(key).unwrap_or(&Value::Null));
+            collect_entries(patch_value, old_value, segments, out);
+            segments.pop();
+        }
+        return;
+    }
+
+    out.push(ReviewEntry {
+        path: segments
+            .iter()
+            .fold(String::new(), |path, key| crate::paths::join(&path, key)),
+        old: old.filter(|old| !old.is_null()).cloned(),
+        new: patch.clone(),
+        decision: Decision::Pending,
+        segments: segments.clone(),
+    });
+}
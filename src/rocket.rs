@@ -0,0 +1,174 @@
+//! Rocket data guard for JSON Merge Patch (RFC 7396) request bodies.
+//!
+//! [`MergePatch`] checks the `application/merge-patch+json` content type,
+//! reads the body within the `merge-patch` limit (falling back to `json`) and
+//! parses it; [`MergePatchError`] answers failures with a JSON error body.
+
+use ::rocket::data::{self, Data, FromData, Limits};
+use ::rocket::http::{ContentType, Status};
+use ::rocket::request::Request;
+use ::rocket::response::{self, Responder, Response};
+use serde::{Serialize, de::DeserializeOwned};
+use std::fmt;
+use std::io::{self, Cursor};
+
+/// Extracts a merge patch request body.
+///
+/// `T` is what the body is parsed into: a [`Patch`](crate::Patch) by default,
+/// ready for [`Patch::apply_to`](crate::Patch::apply_to), or a typed patch
+/// struct from `#[derive(Patch)]`. The body may be as large as the
+/// `merge-patch` limit, e.g. `limits.merge-patch = "64 KiB"` in
+/// `Rocket.toml`, or else the `json` limit (1 MiB by default).
+///
+/// Failures are [`MergePatchError`]s. Take a `Result<MergePatch, MergePatchError>`
+/// to answer with the error's JSON body; otherwise Rocket hands the status to
+/// its catchers.
+///
+/// Returned from a handler, it responds with the patch and the merge patch
+/// content type.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::rocket::{MergePatch, MergePatchError};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// #[rocket::patch("/users/<_id>", data = "<patch>")]
+/// fn update_user(_id: u32, patch: Result<MergePatch, MergePatchError>) -> Result<String, MergePatchError> {
+///     let user = User { name: "alice".into(), age: 30 };
+///     let user = patch?.0.apply_to(user).map_err(|err| MergePatchError::Data(err.to_string()))?;
+///     Ok(format!("{} is {}", user.name, user.age))
+/// }
+///
+/// # fn main() {
+/// let rocket = rocket::build().mount("/", rocket::routes![update_user]);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MergePatch<T = crate::Patch>(pub T);
+
+impl<T> MergePatch<T> {
+    /// Unwraps the extracted value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[::rocket::async_trait]
+impl<'r, T: DeserializeOwned> FromData<'r> for MergePatch<T> {
+    type Error = MergePatchError;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        match parse(req, data).await {
+            Ok(patch) => data::Outcome::Success(MergePatch(patch)),
+            Err(err) => data::Outcome::Error((err.status(), err)),
+        }
+    }
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for MergePatch<T> {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let body = serde_json::to_vec(&self.0).map_err(|_| Status::InternalServerError)?;
+        Response::build()
+            .header(ContentType::new("application", "merge-patch+json"))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+/// Why [`MergePatch`] rejected a request.
+///
+/// Responds with the matching status code and a `{ "error": message }` body.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MergePatchError {
+    /// The `Content-Type` isn't `application/merge-patch+json` (415).
+    UnsupportedMediaType,
+    /// The body is larger than the limit, in bytes (413).
+    TooLarge { limit: u64 },
+    /// The body couldn't be read (400).
+    Io(io::Error),
+    /// The body isn't valid JSON (400).
+    Syntax(serde_json::Error),
+    /// The JSON doesn't fit the patch type, with the offending path (422).
+    Data(String),
+}
+
+impl MergePatchError {
+    /// The status code the error responds with.
+    pub fn status(&self) -> Status {
+        match self {
+            Self::UnsupportedMediaType => Status::UnsupportedMediaType,
+            Self::TooLarge { .. } => Status::PayloadTooLarge,
+            Self::Io(_) | Self::Syntax(_) => Status::BadRequest,
+            Self::Data(_) => Status::UnprocessableEntity,
+        }
+    }
+}
+
+impl fmt::Display for MergePatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedMediaType => write!(
+                f,
+                "expected request with `Content-Type: {}`",
+                crate::MERGE_PATCH_MEDIA_TYPE
+            ),
+            Self::TooLarge { limit } => {
+                write!(f, "merge patch is larger than the {limit} byte limit")
+            }
+            Self::Io(err) => write!(f, "failed to read merge patch: {err}"),
+            Self::Syntax(err) => write!(f, "invalid merge patch: {err}"),
+            Self::Data(message) => write!(f, "invalid merge patch: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for MergePatchError {}
+
+impl<'r> Responder<'r, 'static> for MergePatchError {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let body = serde_json::json!({ "error": self.to_string() }).to_string();
+        Response::build()
+            .status(self.status())
+            .header(ContentType::JSON)
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+/// Checks, reads and parses a request body (internal).
+async fn parse<T: DeserializeOwned>(
+    req: &Request<'_>,
+    data: Data<'_>,
+) -> Result<T, MergePatchError> {
+    if !req.content_type().is_some_and(is_merge_patch) {
+        return Err(MergePatchError::UnsupportedMediaType);
+    }
+    let limit = req
+        .limits()
+        .get("merge-patch")
+        .or_else(|| req.limits().get("json"))
+        .unwrap_or(Limits::JSON);
+    let bytes = data
+        .open(limit)
+        .into_bytes()
+        .await
+        .map_err(MergePatchError::Io)?;
+    if !bytes.is_complete() {
+        return Err(MergePatchError::TooLarge {
+            limit: limit.as_u64(),
+        });
+    }
+    let value: serde_json::Value =
+        crate::backend::from_slice(&bytes).map_err(MergePatchError::Syntax)?;
+    serde_path_to_error::deserialize(value).map_err(|err| MergePatchError::Data(err.to_string()))
+}
+
+/// Whether a content type is the merge patch media type, with any parameters
+/// (internal).
+fn is_merge_patch(content_type: &ContentType) -> bool {
+    content_type.top() == "application" && content_type.sub() == "merge-patch+json"
+}
@@ -0,0 +1,329 @@
+//! SCIM PATCH (RFC 7644, section 3.5.2) operation lists.
+//!
+//! Identity-provider APIs take partial updates as a `PatchOp` message of
+//! `add`, `remove` and `replace` operations whose paths can select elements
+//! of multi-valued attributes with filters (`emails[type eq "work"].value`).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Schema URN of a SCIM `PatchOp` message.
+pub const PATCH_OP_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+
+/// A SCIM `PatchOp` request body.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PatchOp {
+    /// Always `[PATCH_OP_SCHEMA]`.
+    pub schemas: Vec<String>,
+    /// The operations, applied in order.
+    #[serde(rename = "Operations")]
+    pub operations: Vec<Operation>,
+}
+
+impl PatchOp {
+    /// Wraps operations in a `PatchOp` message.
+    pub fn new(operations: Vec<Operation>) -> Self {
+        Self {
+            schemas: vec![PATCH_OP_SCHEMA.to_string()],
+            operations,
+        }
+    }
+
+    /// Returns `true` if the message has no operations.
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
+
+/// Kind of a SCIM patch operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpKind {
+    Add,
+    Remove,
+    Replace,
+}
+
+/// One SCIM patch operation.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Operation {
+    pub op: OpKind,
+    /// Attribute path, possibly with a value filter; absent for operations on
+    /// the whole resource.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// New value; absent for `remove`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+/// Options for [`diff`].
+#[derive(Clone, Debug, Default)]
+pub struct ScimOptions {
+    /// Identifying sub-attribute per multi-valued attribute path, e.g.
+    /// `emails` → `type`. Changes to such lists address individual elements
+    /// through filters; other lists are replaced as a whole.
+    pub filters: HashMap<String, String>,
+}
+
+impl ScimOptions {
+    /// Registers the sub-attribute identifying elements of the list at `path`.
+    pub fn filter(mut self, path: impl Into<String>, key: impl Into<String>) -> Self {
+        self.filters.insert(path.into(), key.into());
+        self
+    }
+}
+
+/// Computes the SCIM operations turning `old` into `new`.
+///
+/// New attributes are `add`ed, changed ones `replace`d and missing or `null`
+/// ones `remove`d; complex attributes are compared sub-attribute by
+/// sub-attribute. Extension schemas (`urn:...`) use the `urn:...:attribute`
+/// path form.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::scim::{OpKind, ScimOptions};
+///
+/// let old = json!({
+///     "displayName": "Alice",
+///     "emails": [{ "type": "work", "value": "alice@corp.example" }],
+/// });
+/// let new = json!({
+///     "displayName": "Alice",
+///     "emails": [{ "type": "work", "value": "a.smith@corp.example" }],
+///     "title": "Engineer",
+/// });
+///
+/// let options = ScimOptions::default().filter("emails", "type");
+/// let patch = serde_patch::scim::diff(&old, &new, &options).unwrap();
+/// assert_eq!(
+///     serde_json::to_value(&patch).unwrap(),
+///     json!({
+///         "schemas": ["urn:ietf:params:scim:api:messages:2.0:PatchOp"],
+///         "Operations": [
+///             { "op": "replace", "path": "emails[type eq \"work\"].value", "value": "a.smith@corp.example" },
+///             { "op": "add", "path": "title", "value": "Engineer" },
+///         ]
+///     })
+/// );
+/// ```
+pub fn diff<T: Serialize>(
+    old: &T,
+    new: &T,
+    options: &ScimOptions,
+) -> Result<PatchOp, serde_json::Error> {
+    let old = crate::value::to_value(old)?;
+    let new = crate::value::to_value(new)?;
+    let mut operations = Vec::new();
+    match (&old, &new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            diff_attributes(old_map, new_map, "", "", options, &mut operations);
+        }
+        _ if old == new => {}
+        _ => operations.push(Operation {
+            op: OpKind::Replace,
+            path: None,
+            value: Some(new),
+        }),
+    }
+    Ok(PatchOp::new(operations))
+}
+
+/// Renders a JSON Merge Patch (RFC 7396) as SCIM operations.
+///
+/// Without the previous resource, additions can't be told from changes, so
+/// every value becomes a `replace` (which SCIM defines to add missing
+/// attributes) and every `null` a `remove`.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let patch = json!({ "active": false, "name": { "middleName": null } });
+/// let ops = serde_patch::scim::from_merge_patch(&patch);
+/// assert_eq!(
+///     serde_json::to_value(&ops.operations).unwrap(),
+///     json!([
+///         { "op": "replace", "path": "active", "value": false },
+///         { "op": "remove", "path": "name.middleName" },
+///     ])
+/// );
+/// ```
+pub fn from_merge_patch(patch: &Value) -> PatchOp {
+    let mut operations = Vec::new();
+    collect_merge_patch(patch, "", &mut operations);
+    PatchOp::new(operations)
+}
+
+fn collect_merge_patch(patch: &Value, current_path: &str, out: &mut Vec<Operation>) {
+    match patch {
+        Value::Object(map) if !map.is_empty() || current_path.is_empty() => {
+            for (key, value) in map {
+                let path = join(current_path, key);
+                let nested = match value {
+                    Value::Object(_) => parent(&path, current_path.is_empty()),
+                    _ => path,
+                };
+                collect_merge_patch(value, &nested, out);
+            }
+        }
+        Value::Null => out.push(remove(current_path.to_string())),
+        _ => out.push(Operation {
+            op: OpKind::Replace,
+            path: Some(current_path.trim_end_matches(':').to_string()),
+            value: Some(patch.clone()),
+        }),
+    }
+}
+
+/// Compares the attributes of two complex values (internal).
+///
+/// `current_path` is the SCIM path operations are emitted under (possibly
+/// with a filter), `option_path` the plain attribute path looked up in
+/// [`ScimOptions::filters`].
+fn diff_attributes(
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    current_path: &str,
+    option_path: &str,
+    options: &ScimOptions,
+    out: &mut Vec<Operation>,
+) {
+    for (key, old_value) in old {
+        if !old_value.is_null() && new.get(key).is_none_or(Value::is_null) {
+            out.push(remove(join(current_path, key)));
+        }
+    }
+    for (key, new_value) in new {
+        let path = join(current_path, key);
+        let option_path = join(option_path, key);
+        match (old.get(key).filter(|value| !value.is_null()), new_value) {
+            (_, Value::Null) => {}
+            (None, _) => out.push(Operation {
+                op: OpKind::Add,
+                path: Some(path),
+                value: Some(new_value.clone()),
+            }),
+            (Some(old_value), _) if old_value == new_value => {}
+            (Some(Value::Object(old_map)), Value::Object(new_map)) => {
+                let nested = parent(&path, current_path.is_empty());
+                diff_attributes(old_map, new_map, &nested, &option_path, options, out);
+            }
+            (Some(Value::Array(old_items)), Value::Array(new_items))
+                if let Some(key) = options.filters.get(&option_path)
+                    && let Some(operations) =
+                        diff_list(old_items, new_items, &path, &option_path, key, options) =>
+            {
+                out.extend(operations);
+            }
+            _ => out.push(Operation {
+                op: OpKind::Replace,
+                path: Some(path),
+                value: Some(new_value.clone()),
+            }),
+        }
+    }
+}
+
+/// Element-level operations for a filtered list, or `None` if an element
+/// lacks the identifying sub-attribute and the list must be replaced
+/// (internal).
+fn diff_list(
+    old: &[Value],
+    new: &[Value],
+    path: &str,
+    option_path: &str,
+    key: &str,
+    options: &ScimOptions,
+) -> Option<Vec<Operation>> {
+    let old_keys = old
+        .iter()
+        .map(|item| filter_value(item, key))
+        .collect::<Option<Vec<_>>>()?;
+    let new_keys = new
+        .iter()
+        .map(|item| filter_value(item, key))
+        .collect::<Option<Vec<_>>>()?;
+
+    let mut operations = Vec::new();
+    for (old_item, old_key) in old.iter().zip(&old_keys) {
+        match new_keys.iter().position(|new_key| new_key == old_key) {
+            None => operations.push(remove(filtered(path, key, old_key))),
+            Some(index) => {
+                if let (Value::Object(old_map), Value::Object(new_map)) = (old_item, &new[index]) {
+                    let element_path = filtered(path, key, old_key);
+                    diff_attributes(
+                        old_map,
+                        new_map,
+                        &element_path,
+                        option_path,
+                        options,
+                        &mut operations,
+                    );
+                }
+            }
+        }
+    }
+    let added: Vec<Value> = new
+        .iter()
+        .zip(&new_keys)
+        .filter(|(_, new_key)| !old_keys.contains(new_key))
+        .map(|(item, _)| item.clone())
+        .collect();
+    if !added.is_empty() {
+        operations.push(Operation {
+            op: OpKind::Add,
+            path: Some(path.to_string()),
+            value: Some(Value::Array(added)),
+        });
+    }
+    Some(operations)
+}
+
+/// The scalar value a filter can compare an element on (internal).
+fn filter_value<'a>(item: &'a Value, key: &str) -> Option<&'a Value> {
+    item.get(key)
+        .filter(|value| !value.is_array() && !value.is_object())
+}
+
+fn remove(path: String) -> Operation {
+    Operation {
+        op: OpKind::Remove,
+        path: Some(path),
+        value: None,
+    }
+}
+
+/// Appends an attribute to a SCIM path; a parent ending in `:` is an extension
+/// schema URN (internal).
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() || path.ends_with(':') {
+        format!("{path}{key}")
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+/// The path attributes of a complex value at `path` are joined to: extension
+/// schemas (top-level `urn:` keys) take `urn:...:attribute` paths (internal).
+fn parent(path: &str, is_top_level: bool) -> String {
+    if is_top_level && path.starts_with("urn:") {
+        format!("{path}:")
+    } else {
+        path.to_string()
+    }
+}
+
+/// A path selecting the list element whose `key` equals `value` (internal).
+fn filtered(path: &str, key: &str, value: &Value) -> String {
+    let literal = match value {
+        Value::String(text) => format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")),
+        other => other.to_string(),
+    };
+    format!("{path}[{key} eq {literal}]")
+}
@@ -0,0 +1,134 @@
+use crate::PatchEnvelope;
+use hmac::{Hmac, KeyInit, Mac};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs a patch, or an envelope with its metadata, with HMAC-SHA256 under
+/// `key`, for relaying through untrusted intermediaries (webhooks, queues).
+///
+/// The signature covers every field of the envelope but itself, in a
+/// canonical JSON form (sorted keys, no whitespace), so re-serializing the
+/// envelope on the way doesn't invalidate it. Fails only if the envelope
+/// can't be serialized, e.g. for a timestamp too far from the Unix epoch to
+/// fit in milliseconds.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { id: u32, name: String }
+///
+/// let key = b"shared secret";
+/// let signed = serde_patch::sign_patch(json!({ "name": "new" }), key).unwrap();
+/// let relayed: serde_patch::PatchEnvelope =
+///     serde_json::from_str(&serde_json::to_string(&signed).unwrap()).unwrap();
+///
+/// let user = User { id: 1, name: "old".into() };
+/// let user = serde_patch::verify_and_apply(&relayed, key, user).unwrap();
+/// assert_eq!(user.name, "new");
+///
+/// let mut tampered = relayed.clone();
+/// tampered.patch = json!({ "name": "mallory" });
+/// let err = serde_patch::verify_and_apply(&tampered, key, user).err().unwrap();
+/// assert!(matches!(err, serde_patch::Error::InvalidSignature));
+/// ```
+pub fn sign_patch(
+    envelope: impl Into<PatchEnvelope>,
+    key: &[u8],
+) -> Result<PatchEnvelope, crate::Error> {
+    let mut envelope = envelope.into();
+    let tag = mac(&envelope, key)?.finalize().into_bytes();
+    envelope.signature = Some(tag.iter().map(|byte| format!("{byte:02x}")).collect());
+    Ok(envelope)
+}
+
+/// Checks the signature [`sign_patch`] put on `envelope` against `key`, then
+/// applies its patch to `current`.
+///
+/// A missing or mismatched signature is reported as
+/// [`Error::InvalidSignature`](crate::Error::InvalidSignature), and nothing
+/// is applied. The comparison is constant-time.
+pub fn verify_and_apply<T>(
+    envelope: &PatchEnvelope,
+    key: &[u8],
+    current: T,
+) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let tag = envelope
+        .signature
+        .as_deref()
+        .and_then(decode_hex)
+        .ok_or(crate::Error::InvalidSignature)?;
+    mac(envelope, key)
+        .map_err(|_| crate::Error::InvalidSignature)?
+        .verify_slice(&tag)
+        .map_err(|_| crate::Error::InvalidSignature)?;
+    envelope.apply_to(current)
+}
+
+/// HMAC over the canonical form of `envelope` without its signature (internal).
+fn mac(envelope: &PatchEnvelope, key: &[u8]) -> Result<HmacSha256, crate::Error> {
+    let mut value = crate::error::serialize(envelope)?;
+    if let Some(map) = value.as_object_mut() {
+        crate::apply_patch::remove_member(map, "signature");
+    }
+    let mut canonical = Vec::new();
+    write_canonical(&value, &mut canonical).map_err(|source| crate::Error::Serialize {
+        pointer: String::new(),
+        source,
+    })?;
+    let mut mac = HmacSha256::new_from_slice(key).map_err(|_| crate::Error::InvalidSignature)?;
+    mac.update(&canonical);
+    Ok(mac)
+}
+
+/// Writes `value` as compact JSON with object keys sorted, whatever order
+/// the map keeps them in (internal).
+fn write_canonical(value: &Value, out: &mut Vec<u8>) -> Result<(), serde_json::Error> {
+    match value {
+        Value::Object(map) => {
+            let mut members: Vec<_> = map.iter().collect();
+            members.sort_by_key(|(key, _)| *key);
+            out.push(b'{');
+            for (i, (key, member)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, key)?;
+                out.push(b':');
+                write_canonical(member, out)?;
+            }
+            out.push(b'}');
+        }
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_canonical(item, out)?;
+            }
+            out.push(b']');
+        }
+        scalar => serde_json::to_writer(&mut *out, scalar)?,
+    }
+    Ok(())
+}
+
+/// Decodes a hex string; `None` if it isn't one (internal).
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,303 @@
+//! SQL `UPDATE` fragments generated from merge patches.
+//!
+//! [`set_clause`] turns a patch into a parameterized `SET` clause for the
+//! columns it touches, leaving the `UPDATE` target and `WHERE` condition to
+//! the caller. Values are never spliced into the SQL: each one becomes a
+//! placeholder, and column names are quoted for the chosen [`Dialect`].
+
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// The SQL flavour placeholders and quoted identifiers are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// `"column" = $1`
+    #[default]
+    Postgres,
+    /// `` `column` = ? ``
+    MySql,
+    /// `"column" = ?`
+    Sqlite,
+}
+
+/// Options for [`set_clause`].
+#[derive(Clone, Debug)]
+pub struct SqlOptions {
+    /// Placeholder and quoting style.
+    pub dialect: Dialect,
+    /// Column names by dotted patch path. Unmapped top-level fields use their
+    /// own name. Mapping paths below a field (e.g. `profile.bio`) spreads that
+    /// object over several columns, all set to `NULL` when it is removed;
+    /// otherwise it is a single JSON column (see
+    /// [`replace_objects`](Self::replace_objects)).
+    pub columns: HashMap<String, String>,
+    /// Rejects patches touching fields without an entry in `columns`, so
+    /// patches coming straight from clients can't assign arbitrary columns.
+    /// On by default; turn it off to let unmapped top-level fields assign the
+    /// column of the same name.
+    pub deny_unmapped: bool,
+    /// Binds an object patched into a single JSON column as the column's new
+    /// value. A merge patch object only lists the members it changes, so
+    /// this replaces the stored document and drops every member the patch
+    /// leaves out; such patches are rejected unless this is turned on.
+    pub replace_objects: bool,
+    /// Number of the first Postgres placeholder, so parameters can follow
+    /// others already in the statement.
+    pub first_param: usize,
+}
+
+impl Default for SqlOptions {
+    fn default() -> Self {
+        Self {
+            dialect: Dialect::default(),
+            columns: HashMap::new(),
+            deny_unmapped: true,
+            replace_objects: false,
+            first_param: 1,
+        }
+    }
+}
+
+/// A parameterized `SET` clause produced by [`set_clause`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SetClause {
+    /// The clause, e.g. `SET "age" = $1, "bio" = NULL`; empty if the patch
+    /// changes nothing.
+    pub sql: String,
+    /// Values for the placeholders, in order.
+    pub params: Vec<Value>,
+    /// Unquoted names of the assigned columns, in order.
+    pub columns: Vec<String>,
+}
+
+impl SetClause {
+    /// Returns `true` if no column is assigned, in which case no `UPDATE`
+    /// should be issued.
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+}
+
+/// Builds a parameterized `SET` clause from a merge patch.
+///
+/// Every field in the patch assigns one column: `null` becomes `NULL`, any
+/// other value a placeholder whose value is pushed to [`SetClause::params`].
+/// Columns are sorted by name, so equal patches give equal statements. Fails
+/// if a field has no column (see [`SqlOptions::deny_unmapped`]), if an
+/// object would overwrite a JSON column (see [`SqlOptions::replace_objects`])
+/// or if two fields would assign the same column.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::sql::{Dialect, SqlOptions};
+///
+/// let patch = json!({ "age": 31, "profile": { "bio": "hello", "avatar_url": null } });
+/// let options = SqlOptions {
+///     columns: [
+///         ("age".to_string(), "age".to_string()),
+///         ("profile.bio".to_string(), "bio".to_string()),
+///         ("profile.avatar_url".to_string(), "avatar".to_string()),
+///     ]
+///     .into(),
+///     ..Default::default()
+/// };
+///
+/// let clause = serde_patch::sql::set_clause(&patch, &options).unwrap();
+/// assert_eq!(clause.sql, r#"SET "age" = $1, "avatar" = NULL, "bio" = $2"#);
+/// assert_eq!(clause.params, [json!(31), json!("hello")]);
+///
+/// let mysql = SqlOptions { dialect: Dialect::MySql, ..options };
+/// let clause = serde_patch::sql::set_clause(&patch, &mysql).unwrap();
+/// assert_eq!(clause.sql, "SET `age` = ?, `avatar` = NULL, `bio` = ?");
+/// ```
+pub fn set_clause(patch: &Value, options: &SqlOptions) -> Result<SetClause, crate::Error> {
+    let Value::Object(map) = patch else {
+        return Err(crate::Error::Rejected(serde_json::Error::custom(
+            "a SET clause can only be built from an object patch",
+        )));
+    };
+    let mut clause = SetClause::default();
+    for (column, value) in assignments(map, options)? {
+        clause
+            .sql
+            .push_str(if clause.sql.is_empty() { "SET " } else { ", " });
+        clause.sql.push_str(&quote(&column, options.dialect));
+        clause.sql.push_str(" = ");
+        if value.is_null() {
+            clause.sql.push_str("NULL");
+        } else {
+            clause.sql.push_str(&placeholder(
+                options.first_param + clause.params.len(),
+                options.dialect,
+            ));
+            clause.params.push(value.clone());
+        }
+        clause.columns.push(column);
+    }
+    Ok(clause)
+}
+
+/// Appends a `SET` clause for a typed or untyped patch to an `sqlx` query.
+///
+/// Assigns the same columns as [`set_clause`], but binds each value on the
+/// builder: integers, floats, booleans and strings as themselves, arrays and
+/// objects as JSON. Typed patches (e.g. from `#[derive(Patch)]`) only touch the
+/// fields they contain. Returns `false`, without pushing anything, if the
+/// patch changes nothing, in which case the query must not be run.
+///
+/// [`SqlOptions::dialect`] only selects the identifier quoting; the builder
+/// writes its own placeholders.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "sqlx-postgres")] {
+/// use serde_json::json;
+/// use serde_patch::sql::SqlOptions;
+/// use sqlx::{Postgres, QueryBuilder};
+///
+/// let patch = json!({ "age": 31, "bio": null });
+/// let options = SqlOptions { deny_unmapped: false, ..Default::default() };
+///
+/// let mut query = QueryBuilder::<Postgres>::new("UPDATE users ");
+/// assert!(serde_patch::sql::push_set(&mut query, &patch, &options).unwrap());
+/// query.push(" WHERE id = ").push_bind(7_i64);
+///
+/// assert_eq!(query.sql().as_str(), r#"UPDATE users SET "age" = $1, "bio" = NULL WHERE id = $2"#);
+/// # }
+/// ```
+#[cfg(feature = "sqlx")]
+pub fn push_set<DB, P>(
+    builder: &mut sqlx::QueryBuilder<DB>,
+    patch: &P,
+    options: &SqlOptions,
+) -> Result<bool, crate::Error>
+where
+    DB: sqlx::Database,
+    P: serde::Serialize,
+    i64: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    f64: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    bool: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    String: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    sqlx::types::Json<Value>: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+{
+    let patch = crate::error::serialize(patch)?;
+    let Value::Object(map) = &patch else {
+        return Err(crate::Error::Rejected(serde_json::Error::custom(
+            "a SET clause can only be built from an object patch",
+        )));
+    };
+    let assignments = assignments(map, options)?;
+    for (index, (column, value)) in assignments.iter().enumerate() {
+        builder.push(if index == 0 { "SET " } else { ", " });
+        builder.push(quote(column, options.dialect));
+        builder.push(" = ");
+        match value {
+            Value::Null => builder.push("NULL"),
+            Value::Bool(flag) => builder.push_bind(*flag),
+            Value::String(text) => builder.push_bind(text.clone()),
+            Value::Number(number) => match (number.as_i64(), number.as_f64()) {
+                (Some(integer), _) => builder.push_bind(integer),
+                (None, Some(float)) if !number.is_u64() => builder.push_bind(float),
+                _ => {
+                    return Err(crate::Error::Rejected(serde_json::Error::custom(
+                        format_args!("{number} does not fit a 64-bit column at `{column}`"),
+                    )));
+                }
+            },
+            Value::Array(_) | Value::Object(_) => {
+                builder.push_bind(sqlx::types::Json((*value).clone()))
+            }
+        };
+    }
+    Ok(!assignments.is_empty())
+}
+
+/// Column assignments of an object patch, sorted by column (internal).
+fn assignments<'a>(
+    map: &'a Map<String, Value>,
+    options: &SqlOptions,
+) -> Result<Vec<(String, &'a Value)>, crate::Error> {
+    let mut out = Vec::new();
+    collect(map, "", options, &mut out).map_err(crate::Error::Rejected)?;
+    out.sort_by(|a, b| a.0.cmp(&b.0));
+    if let Some(pair) = out.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(crate::Error::Rejected(serde_json::Error::custom(
+            format_args!("column `{}` is assigned by several patch fields", pair[0].0),
+        )));
+    }
+    Ok(out)
+}
+
+/// Maps patch fields to `(column, value)` assignments (internal).
+fn collect<'a>(
+    map: &'a Map<String, Value>,
+    current_path: &str,
+    options: &SqlOptions,
+    out: &mut Vec<(String, &'a Value)>,
+) -> Result<(), serde_json::Error> {
+    for (key, value) in map {
+        let full_path = crate::paths::join(current_path, key);
+        if let Some(column) = options.columns.get(&full_path) {
+            out.push((column.clone(), whole(value, &full_path, options)?));
+            continue;
+        }
+        let prefix = format!("{full_path}.");
+        let spread = options.columns.keys().any(|path| path.starts_with(&prefix));
+        match value {
+            Value::Object(nested) if spread => collect(nested, &full_path, options, out)?,
+            Value::Null if spread => {
+                for (path, column) in &options.columns {
+                    if path.starts_with(&prefix) {
+                        out.push((column.clone(), value));
+                    }
+                }
+            }
+            _ if spread => {
+                return Err(serde_json::Error::custom(format_args!(
+                    "`{full_path}` is mapped to several columns and can only be patched with an object"
+                )));
+            }
+            _ if options.deny_unmapped || !current_path.is_empty() => {
+                return Err(serde_json::Error::custom(format_args!(
+                    "no column for `{full_path}`"
+                )));
+            }
+            _ => out.push((key.clone(), whole(value, &full_path, options)?)),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `value` may be bound as the whole value of one column: a
+/// partial object may not, unless [`SqlOptions::replace_objects`] says so
+/// (internal).
+fn whole<'a>(
+    value: &'a Value,
+    path: &str,
+    options: &SqlOptions,
+) -> Result<&'a Value, serde_json::Error> {
+    if value.is_object() && !options.replace_objects {
+        return Err(serde_json::Error::custom(format_args!(
+            "`{path}` patches part of a JSON column; map its fields to columns or turn on `replace_objects`"
+        )));
+    }
+    Ok(value)
+}
+
+fn quote(column: &str, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::MySql => format!("`{}`", column.replace('`', "``")),
+        Dialect::Postgres | Dialect::Sqlite => format!("\"{}\"", column.replace('"', "\"\"")),
+    }
+}
+
+fn placeholder(index: usize, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::Postgres => format!("${index}"),
+        Dialect::MySql | Dialect::Sqlite => "?".to_string(),
+    }
+}
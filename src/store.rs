@@ -0,0 +1,215 @@
+use crate::{ApplyOptions, ApplyReport};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError, RwLock};
+
+type Listener = Arc<dyn Fn(u64, &Value) + Send + Sync>;
+
+/// Thread-safe owner of a value that is updated through merge patches.
+///
+/// Every successful [`apply`](Store::apply) bumps the revision by one and
+/// notifies the registered listeners with the new revision and the applied
+/// patch. Listeners are called in revision order, after the store lock is
+/// released, so they may read the store; they must not apply patches to the
+/// same store.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::Store;
+/// use std::sync::{Arc, Mutex};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Counter { count: u32 }
+///
+/// let store = Store::new(Counter { count: 0 });
+/// let seen = Arc::new(Mutex::new(Vec::new()));
+/// let sink = seen.clone();
+/// store.subscribe(move |revision, patch| sink.lock().unwrap().push((revision, patch.clone())));
+///
+/// store.apply(r#"{ "count": 1 }"#).unwrap();
+/// store.apply(r#"{ "count": 2 }"#).unwrap();
+///
+/// assert_eq!(store.revision(), 2);
+/// assert_eq!(store.read(|counter| counter.count), 2);
+/// assert_eq!(seen.lock().unwrap()[1], (2, serde_json::json!({ "count": 2 })));
+/// ```
+pub struct Store<T> {
+    state: RwLock<State<T>>,
+    options: ApplyOptions,
+    listeners: RwLock<Vec<(u64, Listener)>>,
+    next_listener: AtomicU64,
+    /// Last revision whose listeners have run; later revisions wait their turn.
+    delivered: Mutex<u64>,
+    turn: Condvar,
+}
+
+struct State<T> {
+    value: T,
+    revision: u64,
+}
+
+impl<T> Store<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Creates a store at revision 0.
+    pub fn new(value: T) -> Self {
+        Self::with_options(value, ApplyOptions::default())
+    }
+
+    /// Creates a store that applies patches with the given options.
+    pub fn with_options(value: T, options: ApplyOptions) -> Self {
+        Self {
+            state: RwLock::new(State { value, revision: 0 }),
+            options,
+            listeners: RwLock::new(Vec::new()),
+            next_listener: AtomicU64::new(0),
+            delivered: Mutex::new(0),
+            turn: Condvar::new(),
+        }
+    }
+
+    /// Applies a merge patch, bumps the revision, and notifies listeners.
+    ///
+    /// On error the value and revision are left unchanged and no listener is called.
+    pub fn apply<P: AsRef<[u8]>>(&self, patch: P) -> Result<ApplyReport, crate::Error> {
+        let patch_val: Value =
+            crate::backend::from_slice(patch.as_ref()).map_err(crate::Error::ParsePatch)?;
+
+        let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+        let (updated, report) =
+            crate::report::apply_value_with_report(&state.value, patch_val.clone(), &self.options)?;
+        state.value = updated;
+        state.revision += 1;
+        let revision = state.revision;
+
+        drop(state);
+
+        // Deliver in revision order; no store lock is held while waiting or
+        // while listeners run.
+        let _turn = self.wait_turn(revision);
+        let listeners: Vec<Listener> = self
+            .listeners
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(_, listener)| listener.clone())
+            .collect();
+        for listener in listeners {
+            listener(revision, &patch_val);
+        }
+        Ok(report)
+    }
+
+    fn wait_turn(&self, revision: u64) -> Turn<'_> {
+        let delivered = self
+            .delivered
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        drop(
+            self.turn
+                .wait_while(delivered, |delivered| *delivered + 1 < revision)
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        Turn {
+            delivered: &self.delivered,
+            turn: &self.turn,
+            revision,
+        }
+    }
+
+    /// Registers a listener and returns its id for [`unsubscribe`](Store::unsubscribe).
+    pub fn subscribe<F>(&self, listener: F) -> u64
+    where
+        F: Fn(u64, &Value) + Send + Sync + 'static,
+    {
+        let id = self.next_listener.fetch_add(1, Ordering::Relaxed);
+        self.listeners
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((id, Arc::new(listener)));
+        id
+    }
+
+    /// Removes a listener; returns `false` if it was not registered.
+    pub fn unsubscribe(&self, id: u64) -> bool {
+        let mut listeners = self
+            .listeners
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let before = listeners.len();
+        listeners.retain(|(listener_id, _)| *listener_id != id);
+        listeners.len() != before
+    }
+
+    /// Current revision (number of patches applied).
+    pub fn revision(&self) -> u64 {
+        self.state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .revision
+    }
+
+    /// Calls `f` with the current value under the read lock.
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self
+            .state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .value)
+    }
+
+    /// Clones the current value together with its revision.
+    pub fn snapshot(&self) -> (u64, T)
+    where
+        T: Clone,
+    {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        (state.revision, state.value.clone())
+    }
+
+    /// Consumes the store and returns the current value.
+    pub fn into_inner(self) -> T {
+        self.state
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+            .value
+    }
+}
+
+/// Marks a revision's notifications as delivered when dropped, even if a
+/// listener panics, so later revisions are not blocked.
+struct Turn<'a> {
+    delivered: &'a Mutex<u64>,
+    turn: &'a Condvar,
+    revision: u64,
+}
+
+impl Drop for Turn<'_> {
+    fn drop(&mut self) {
+        *self
+            .delivered
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = self.revision;
+        self.turn.notify_all();
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Store<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        let listeners = self
+            .listeners
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len();
+        f.debug_struct("Store")
+            .field("value", &state.value)
+            .field("revision", &state.revision)
+            .field("listeners", &listeners)
+            .finish_non_exhaustive()
+    }
+}
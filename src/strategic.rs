@@ -0,0 +1,363 @@
+//! Kubernetes strategic merge patches.
+//!
+//! A superset of JSON Merge Patch (RFC 7396) in which lists of objects
+//! registered in [`StrategicOptions::merge_keys`] are merged element by
+//! element, matched on a key field, instead of being replaced. Patches can
+//! also carry the directives `kubectl` understands:
+//!
+//! - `"$patch": "delete"` in a list element or object removes it;
+//! - `"$patch": "replace"` replaces the object (or, as a list element, the
+//!   whole list) instead of merging;
+//! - `"$retainKeys": [...]` drops every key of the object not listed;
+//! - `"$setElementOrder/<list>": [...]` gives the final order of a merged list.
+
+use serde::de::Error as _;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+const PATCH: &str = "$patch";
+const RETAIN_KEYS: &str = "$retainKeys";
+const SET_ELEMENT_ORDER: &str = "$setElementOrder/";
+
+/// Options for [`diff`] and [`apply`].
+#[derive(Clone, Debug, Default)]
+pub struct StrategicOptions {
+    /// Merge key by list path. List paths are dotted field paths that skip
+    /// list levels, as in Kubernetes' API reference:
+    /// `spec.template.spec.containers` → `name`,
+    /// `spec.template.spec.containers.ports` → `containerPort`.
+    ///
+    /// Lists without a merge key are replaced as a whole.
+    pub merge_keys: HashMap<String, String>,
+}
+
+impl StrategicOptions {
+    /// Registers the merge key of the list at `path`.
+    pub fn merge_key(mut self, path: impl Into<String>, key: impl Into<String>) -> Self {
+        self.merge_keys.insert(path.into(), key.into());
+        self
+    }
+}
+
+/// Computes a strategic merge patch turning `old` into `new`.
+///
+/// Keyed lists yield one entry per added or changed element (carrying its
+/// merge key), `{ "<key>": ..., "$patch": "delete" }` per removed element, and
+/// a `$setElementOrder` directive when the merged order would differ from
+/// `new`.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::strategic::StrategicOptions;
+///
+/// let old = json!({ "spec": { "containers": [
+///     { "name": "app", "image": "app:1" },
+///     { "name": "sidecar", "image": "proxy:1" },
+/// ] } });
+/// let new = json!({ "spec": { "containers": [
+///     { "name": "app", "image": "app:2" },
+/// ] } });
+///
+/// let options = StrategicOptions::default().merge_key("spec.containers", "name");
+/// let patch = serde_patch::strategic::diff(&old, &new, &options).unwrap();
+/// assert_eq!(patch, json!({ "spec": { "containers": [
+///     { "name": "app", "image": "app:2" },
+///     { "name": "sidecar", "$patch": "delete" },
+/// ] } }));
+/// assert_eq!(serde_patch::strategic::apply(old, &patch, &options).unwrap(), new);
+/// ```
+pub fn diff<T: Serialize>(
+    old: &T,
+    new: &T,
+    options: &StrategicOptions,
+) -> Result<Value, serde_json::Error> {
+    let old = crate::value::to_value(old)?;
+    let new = crate::value::to_value(new)?;
+    Ok(match (&old, &new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            Value::Object(diff_objects(old_map, new_map, options, ""))
+        }
+        _ if old == new => Value::Object(Map::new()),
+        _ => new,
+    })
+}
+
+/// Applies a strategic merge patch.
+///
+/// Consumes the current value and returns the updated value. Malformed
+/// directives and keyed list elements without their merge key are reported
+/// as [`Error::Rejected`](crate::Error::Rejected) naming the list or object
+/// path.
+pub fn apply<T>(current: T, patch: &Value, options: &StrategicOptions) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut value = crate::error::serialize(&current)?;
+    merge(&mut value, patch, options, "").map_err(crate::Error::Rejected)?;
+    crate::error::deserialize_merged(&value, patch)
+}
+
+fn diff_objects(
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    options: &StrategicOptions,
+    current_path: &str,
+) -> Map<String, Value> {
+    let mut patch = Map::new();
+    for key in old.keys().filter(|key| !new.contains_key(*key)) {
+        patch.insert(key.clone(), Value::Null);
+    }
+    for (key, new_value) in new {
+        let full_path = crate::paths::join(current_path, key);
+        match (old.get(key), new_value) {
+            (None, _) => {
+                patch.insert(key.clone(), new_value.clone());
+            }
+            (Some(old_value), _) if old_value == new_value => {}
+            (Some(Value::Object(old_map)), Value::Object(new_map)) => {
+                let nested = diff_objects(old_map, new_map, options, &full_path);
+                if !nested.is_empty() {
+                    patch.insert(key.clone(), Value::Object(nested));
+                }
+            }
+            (Some(Value::Array(old_items)), Value::Array(new_items)) => {
+                match options.merge_keys.get(&full_path).and_then(|merge_key| {
+                    diff_lists(old_items, new_items, merge_key, options, &full_path)
+                }) {
+                    Some((items, order)) => {
+                        if !items.is_empty() {
+                            patch.insert(key.clone(), Value::Array(items));
+                        }
+                        if let Some(order) = order {
+                            patch.insert(format!("{SET_ELEMENT_ORDER}{key}"), Value::Array(order));
+                        }
+                    }
+                    None => {
+                        patch.insert(key.clone(), new_value.clone());
+                    }
+                }
+            }
+            _ => {
+                patch.insert(key.clone(), new_value.clone());
+            }
+        }
+    }
+    patch
+}
+
+/// Patch entries and optional element order of a keyed list, or `None` if an
+/// element lacks its merge key and the list must be replaced (internal).
+fn diff_lists(
+    old: &[Value],
+    new: &[Value],
+    merge_key: &str,
+    options: &StrategicOptions,
+    current_path: &str,
+) -> Option<(Vec<Value>, Option<Vec<Value>>)> {
+    let key_of = |item: &Value| item.get(merge_key).cloned();
+    let old_keys = old.iter().map(key_of).collect::<Option<Vec<_>>>()?;
+    let new_keys = new.iter().map(key_of).collect::<Option<Vec<_>>>()?;
+
+    let mut items = Vec::new();
+    for (item, key) in new.iter().zip(&new_keys) {
+        match old_keys.iter().position(|old_key| old_key == key) {
+            None => items.push(item.clone()),
+            Some(index) => {
+                if let (Value::Object(old_map), Value::Object(new_map)) = (&old[index], item) {
+                    let mut nested = diff_objects(old_map, new_map, options, current_path);
+                    if !nested.is_empty() {
+                        nested.insert(merge_key.to_string(), key.clone());
+                        items.push(Value::Object(nested));
+                    }
+                }
+            }
+        }
+    }
+    let mut kept = Vec::new();
+    for key in &old_keys {
+        if new_keys.contains(key) {
+            kept.push(key.clone());
+        } else {
+            let mut deletion = Map::new();
+            deletion.insert(merge_key.to_string(), key.clone());
+            deletion.insert(PATCH.to_string(), Value::String("delete".to_string()));
+            items.push(Value::Object(deletion));
+        }
+    }
+
+    // Merged lists keep surviving elements in place and append new ones.
+    kept.extend(
+        new_keys
+            .iter()
+            .filter(|key| !old_keys.contains(key))
+            .cloned(),
+    );
+    let order = (kept != new_keys).then(|| {
+        new_keys
+            .iter()
+            .map(|key| {
+                let mut entry = Map::new();
+                entry.insert(merge_key.to_string(), key.clone());
+                Value::Object(entry)
+            })
+            .collect()
+    });
+    Some((items, order))
+}
+
+fn merge(
+    target: &mut Value,
+    patch: &Value,
+    options: &StrategicOptions,
+    current_path: &str,
+) -> Result<(), serde_json::Error> {
+    let Value::Object(patch_map) = patch else {
+        *target = patch.clone();
+        return Ok(());
+    };
+    if let Some(directive) = patch_map.get(PATCH) {
+        match directive.as_str() {
+            Some("replace") => {
+                let mut replacement = patch_map.clone();
+                replacement.remove(PATCH);
+                *target = Value::Object(replacement);
+                return Ok(());
+            }
+            Some("merge") => {}
+            _ => {
+                return Err(invalid(
+                    current_path,
+                    format_args!("unsupported `$patch` {directive}"),
+                ));
+            }
+        }
+    }
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target_map) = target else {
+        return Ok(());
+    };
+
+    for (key, value) in patch_map {
+        if key == PATCH || key == RETAIN_KEYS || key.starts_with(SET_ELEMENT_ORDER) {
+            continue;
+        }
+        let full_path = crate::paths::join(current_path, key);
+        match value {
+            Value::Null => crate::apply_patch::remove_member(target_map, key),
+            Value::Object(nested)
+                if nested.get(PATCH).and_then(Value::as_str) == Some("delete") =>
+            {
+                crate::apply_patch::remove_member(target_map, key)
+            }
+            Value::Array(items) => match options.merge_keys.get(&full_path) {
+                Some(merge_key) => {
+                    let list = target_map.entry(key.clone()).or_insert(Value::Null);
+                    merge_list(list, items, merge_key, options, &full_path)?;
+                }
+                None => {
+                    target_map.insert(key.clone(), value.clone());
+                }
+            },
+            _ => merge(
+                target_map.entry(key.clone()).or_insert(Value::Null),
+                value,
+                options,
+                &full_path,
+            )?,
+        }
+    }
+
+    for (key, order) in patch_map {
+        if let Some(list_key) = key.strip_prefix(SET_ELEMENT_ORDER) {
+            let full_path = crate::paths::join(current_path, list_key);
+            let (Some(merge_key), Value::Array(order)) =
+                (options.merge_keys.get(&full_path), order)
+            else {
+                return Err(invalid(&full_path, "`$setElementOrder` needs a keyed list"));
+            };
+            if let Some(Value::Array(items)) = target_map.get_mut(list_key) {
+                let rank = |item: &Value| {
+                    order
+                        .iter()
+                        .position(|entry| entry.get(merge_key) == item.get(merge_key))
+                        .unwrap_or(order.len())
+                };
+                items.sort_by_key(rank);
+            }
+        }
+    }
+
+    if let Some(retain) = patch_map.get(RETAIN_KEYS) {
+        let Some(retain) = retain
+            .as_array()
+            .and_then(|keys| keys.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+        else {
+            return Err(invalid(
+                current_path,
+                "`$retainKeys` must be a list of keys",
+            ));
+        };
+        target_map.retain(|key, _| retain.contains(&key.as_str()));
+    }
+    Ok(())
+}
+
+fn merge_list(
+    target: &mut Value,
+    patch_items: &[Value],
+    merge_key: &str,
+    options: &StrategicOptions,
+    current_path: &str,
+) -> Result<(), serde_json::Error> {
+    let replace = patch_items
+        .iter()
+        .any(|item| item.get(PATCH).and_then(Value::as_str) == Some("replace"));
+    if replace || !target.is_array() {
+        *target = Value::Array(Vec::new());
+    }
+    let Value::Array(items) = target else {
+        return Ok(());
+    };
+
+    for patch_item in patch_items {
+        let directive = patch_item.get(PATCH).and_then(Value::as_str);
+        if directive == Some("replace") {
+            continue;
+        }
+        let Some(key) = patch_item.get(merge_key) else {
+            return Err(invalid(
+                current_path,
+                format_args!("list element without merge key `{merge_key}`"),
+            ));
+        };
+        let position = items
+            .iter()
+            .position(|item| item.get(merge_key) == Some(key));
+        match (directive, position) {
+            (Some("delete"), Some(index)) => {
+                items.remove(index);
+            }
+            (Some("delete"), None) => {}
+            (_, Some(index)) => merge(&mut items[index], patch_item, options, current_path)?,
+            (_, None) => {
+                let mut item = Value::Null;
+                merge(&mut item, patch_item, options, current_path)?;
+                items.push(item);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn invalid(path: &str, message: impl std::fmt::Display) -> serde_json::Error {
+    if path.is_empty() {
+        serde_json::Error::custom(message)
+    } else {
+        serde_json::Error::custom(format_args!("{message} at `{path}`"))
+    }
+}
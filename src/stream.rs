@@ -0,0 +1,404 @@
+//! Diffing and patching JSON documents read from streams, without loading
+//! them whole.
+
+use crate::DiffOptions;
+use serde::de::{self, DeserializeSeed, Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer as _, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hasher};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+/// Computes the merge patch turning the document read from `old` into the one
+/// read from `new`, without holding either in memory.
+///
+/// Both documents must be JSON objects. They are read one top-level member at
+/// a time: `old` is read once to hash its members, `new` once to compare
+/// against those hashes, and, if some members changed, `old` again (from the
+/// position it started at) to diff those members. Memory use is bounded by
+/// the largest member and the patch itself, not by the documents.
+///
+/// Streaming stops at the top level: a changed member is held whole, first
+/// its new value and then its old one, so a document made of one huge member
+/// (an export shaped like `{ "records": [...] }`) still takes about twice its
+/// size in memory. Such arrays end up whole in the patch anyway, since a merge
+/// patch replaces arrays rather than editing them.
+///
+/// The patch is the one [`diff_values`](crate::diff_values) computes for the
+/// same documents and `options`. Readers are buffered internally.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use serde_patch::DiffOptions;
+/// use std::io::Cursor;
+///
+/// let old = r#"{ "a": { "x": 1, "y": [1, 2] }, "b": "same", "c": true }"#;
+/// let new = r#"{ "a": { "x": 2, "y": [1, 2] }, "b": "same", "d": null }"#;
+///
+/// let patch = serde_patch::diff_readers(
+///     Cursor::new(old),
+///     Cursor::new(new),
+///     &DiffOptions::default(),
+/// )
+/// .unwrap();
+/// assert_eq!(patch, json!({ "a": { "x": 2 }, "c": null, "d": null }));
+/// ```
+pub fn diff_readers<O, N>(
+    mut old: O,
+    new: N,
+    options: &DiffOptions,
+) -> Result<Value, serde_json::Error>
+where
+    O: Read + Seek,
+    N: Read,
+{
+    let start = old.stream_position().map_err(serde_json::Error::io)?;
+
+    // Hash of each member of `old`, and its position to order removals.
+    let mut old_members: HashMap<String, (usize, u64)> = HashMap::new();
+    for_each_member(BufReader::new(&mut old), |key, value| {
+        let (key, value) = view(key, value, options);
+        let position = old_members.len();
+        old_members.insert(key, (position, hash(&value)));
+    })?;
+
+    let mut patch = Map::new();
+    let mut changed = HashSet::new();
+    for_each_member(BufReader::new(new), |key, value| {
+        let (key, value) = view(key, value, options);
+        let old_hash = old_members.remove(&key).map(|(_, hash)| hash);
+        if crate::paths::matches_any(&options.ignoring, &crate::paths::join("", &key)) {
+            return;
+        }
+        match old_hash {
+            // Unchanged: only fields forced in by `including` are kept.
+            Some(old_hash) if old_hash == hash(&value) => {
+                if let Some(forced) = crate::diff_patch::compute_diff(
+                    Some(&value),
+                    &value,
+                    options,
+                    &crate::paths::join("", &key),
+                ) {
+                    patch.insert(key, forced);
+                }
+            }
+            // Changed: diffed once `old` is read again.
+            Some(_) => {
+                changed.insert(key.clone());
+                patch.insert(key, value);
+            }
+            None => {
+                patch.insert(key, value);
+            }
+        }
+    })?;
+
+    let mut removed: Vec<_> = old_members.into_iter().collect();
+    removed.sort_by_key(|(_, (position, _))| *position);
+    for (key, _) in removed {
+        if !crate::paths::matches_any(&options.ignoring, &crate::paths::join("", &key)) {
+            patch.insert(key, Value::Null);
+        }
+    }
+
+    if !changed.is_empty() {
+        old.seek(SeekFrom::Start(start))
+            .map_err(serde_json::Error::io)?;
+        let mut unchanged = Vec::new();
+        for_each_member(BufReader::new(&mut old), |key, old_value| {
+            let (key, old_value) = view(key, old_value, options);
+            if !changed.remove(&key) {
+                return;
+            }
+            let Some(new_value) = patch.get_mut(&key) else {
+                return;
+            };
+            let path = crate::paths::join("", &key);
+            match crate::diff_patch::compute_diff(Some(&old_value), new_value, options, &path) {
+                Some(diff) => *new_value = diff,
+                None => unchanged.push(key),
+            }
+        })?;
+        for key in unchanged {
+            crate::apply_patch::remove_member(&mut patch, &key);
+        }
+    }
+
+    let patch = Value::Object(patch);
+    #[cfg(feature = "external-refs")]
+    let patch = crate::external_ref::externalized(patch, options)?;
+    Ok(patch)
+}
+
+/// Calls `f` with each top-level member of the object read from `reader`
+/// (internal).
+fn for_each_member<R, F>(reader: R, f: F) -> Result<(), serde_json::Error>
+where
+    R: Read,
+    F: FnMut(String, Value),
+{
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    deserializer.deserialize_map(Members(f))?;
+    deserializer.end()
+}
+
+/// Visits an object member by member (internal).
+struct Members<F>(F);
+
+impl<'de, F: FnMut(String, Value)> Visitor<'de> for Members<F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a JSON object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            let value = map.next_value::<Value>()?;
+            (self.0)(key, value);
+        }
+        Ok(())
+    }
+}
+
+/// Converts registered fields of one member into their views (internal).
+fn view(key: String, value: Value, options: &DiffOptions) -> (String, Value) {
+    if options.representations.is_empty() {
+        return (key, value);
+    }
+    let mut member = Value::Object(Map::from_iter([(key, value)]));
+    crate::representation::to_views(&mut member, &options.representations);
+    match member {
+        Value::Object(map) => map.into_iter().next().unwrap_or_default(),
+        _ => (String::new(), Value::Null),
+    }
+}
+
+/// Hash of a member's compact JSON (internal).
+///
+/// Equal values always hash alike; the rare values that hash alike without
+/// being equal are only possible by collision.
+fn hash(value: &Value) -> u64 {
+    let mut hasher = HashWriter(DefaultHasher::new());
+    // Writing to a hasher can't fail, and a `Value` always serializes.
+    let _ = serde_json::to_writer(&mut hasher, value);
+    hasher.0.finish()
+}
+
+/// Feeds written bytes to a hasher (internal).
+struct HashWriter(DefaultHasher);
+
+impl io::Write for HashWriter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.write(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Applies a merge patch to the document read from `document`, writing the
+/// result to `writer` as it goes.
+///
+/// The document is copied through token by token and merged where the patch
+/// reaches into it, so only the patch is held in memory. Members keep the
+/// document's order; those the patch adds follow the existing ones. Output is
+/// compact JSON. Fails if the document isn't valid JSON or writing fails, in
+/// which case `writer` holds a truncated document.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let document = r#"{ "name": "old", "items": [1, 2, 3], "meta": { "a": 1, "b": 2 } }"#;
+/// let patch = json!({ "name": "new", "meta": { "a": null, "c": 3 } });
+///
+/// let mut out = Vec::new();
+/// serde_patch::apply_streaming(document.as_bytes(), &patch, &mut out).unwrap();
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     r#"{"name":"new","items":[1,2,3],"meta":{"b":2,"c":3}}"#
+/// );
+/// ```
+pub fn apply_streaming<R, W>(document: R, patch: &Value, writer: W) -> Result<(), serde_json::Error>
+where
+    R: Read,
+    W: Write,
+{
+    let mut out = BufWriter::new(writer);
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(document));
+    Copy {
+        patch: Some(patch),
+        before: "",
+        out: &mut out,
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    out.flush().map_err(serde_json::Error::io)
+}
+
+/// Copies one value from a deserializer to `out`, merging `patch` into it
+/// (internal).
+struct Copy<'p, 'w, W> {
+    patch: Option<&'p Value>,
+    /// Written before the value, if one is written.
+    before: &'static str,
+    out: &'w mut W,
+}
+
+impl<W: Write> Copy<'_, '_, W> {
+    fn write<T: Serialize + ?Sized, E: de::Error>(self, value: &T) -> Result<(), E> {
+        self.out
+            .write_all(self.before.as_bytes())
+            .map_err(E::custom)?;
+        serde_json::to_writer(&mut *self.out, value).map_err(E::custom)
+    }
+
+    /// Writes a scalar, or the patch if it replaces it with an object.
+    fn scalar<T: Serialize + ?Sized, E: de::Error>(self, value: &T) -> Result<(), E> {
+        match self.patch {
+            Some(patch) => {
+                let replacement = merged(patch);
+                self.write(&replacement)
+            }
+            None => self.write(value),
+        }
+    }
+}
+
+/// What an object patch becomes over a non-object value (internal).
+fn merged(patch: &Value) -> Value {
+    let mut value = Value::Null;
+    crate::apply_patch::merge_patch(&mut value, patch);
+    value
+}
+
+impl<'de, W: Write> DeserializeSeed<'de> for Copy<'_, '_, W> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        match self.patch {
+            Some(patch) if !patch.is_object() => {
+                IgnoredAny::deserialize(deserializer)?;
+                self.write(patch)
+            }
+            _ => deserializer.deserialize_any(self),
+        }
+    }
+}
+
+impl<'de, W: Write> Visitor<'de> for Copy<'_, '_, W> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<(), E> {
+        self.scalar(&value)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<(), E> {
+        self.scalar(&value)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<(), E> {
+        self.scalar(&value)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<(), E> {
+        self.scalar(&value)
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<(), E> {
+        self.scalar(value)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.scalar(&())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        if self.patch.is_some() {
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
+            return self.scalar(&());
+        }
+        self.out
+            .write_all(format!("{}[", self.before).as_bytes())
+            .map_err(A::Error::custom)?;
+        let mut before = "";
+        while seq
+            .next_element_seed(Copy {
+                patch: None,
+                before,
+                out: &mut *self.out,
+            })?
+            .is_some()
+        {
+            before = ",";
+        }
+        self.out.write_all(b"]").map_err(A::Error::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut pending = map.next_key::<String>()?;
+        // With `arbitrary-precision`, numbers arrive as a single-member map.
+        #[cfg(feature = "arbitrary-precision")]
+        if pending.as_deref() == Some(crate::value::NUMBER_TOKEN) {
+            let digits = map.next_value::<String>()?;
+            let number: serde_json::Number = digits.parse().map_err(A::Error::custom)?;
+            return self.scalar(&number);
+        }
+        let patch = self.patch.and_then(Value::as_object);
+        self.out
+            .write_all(format!("{}{{", self.before).as_bytes())
+            .map_err(A::Error::custom)?;
+        let mut seen = HashSet::new();
+        let mut first = true;
+        while let Some(key) = match pending.take() {
+            Some(key) => Some(key),
+            None => map.next_key::<String>()?,
+        } {
+            let member_patch = patch.and_then(|patch| patch.get_key_value(&key));
+            if let Some((patch_key, patch_value)) = member_patch {
+                seen.insert(patch_key.as_str());
+                if patch_value.is_null() {
+                    map.next_value::<IgnoredAny>()?;
+                    continue;
+                }
+            }
+            write_key(self.out, &key, &mut first).map_err(A::Error::custom)?;
+            map.next_value_seed(Copy {
+                patch: member_patch.map(|(_, value)| value),
+                before: "",
+                out: &mut *self.out,
+            })?;
+        }
+        for (key, value) in patch.into_iter().flatten() {
+            if value.is_null() || seen.contains(key.as_str()) {
+                continue;
+            }
+            write_key(self.out, key, &mut first).map_err(A::Error::custom)?;
+            let written = if value.is_object() {
+                serde_json::to_writer(&mut *self.out, &merged(value))
+            } else {
+                serde_json::to_writer(&mut *self.out, value)
+            };
+            written.map_err(A::Error::custom)?;
+        }
+        self.out.write_all(b"}").map_err(A::Error::custom)
+    }
+}
+
+fn write_key<W: Write>(out: &mut W, key: &str, first: &mut bool) -> Result<(), serde_json::Error> {
+    if !std::mem::take(first) {
+        out.write_all(b",").map_err(serde_json::Error::io)?;
+    }
+    serde_json::to_writer(&mut *out, key)?;
+    out.write_all(b":").map_err(serde_json::Error::io)
+}
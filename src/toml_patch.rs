@@ -0,0 +1,135 @@
+use crate::DiffOptions;
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+use toml::value::Datetime;
+
+/// Computes a JSON Merge Patch (RFC 7396) between two TOML documents.
+///
+/// TOML has no `null`, so the patch is returned as JSON; datetimes appear as
+/// their RFC 3339 strings. Non-finite floats (`nan`, `inf`) can't be
+/// represented and are an error.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let old = "\
+/// [server]
+/// port = 8080
+/// debug = true
+/// ";
+/// let new = "\
+/// [server]
+/// port = 9090
+/// started = 2024-05-01T09:30:00Z
+/// ";
+///
+/// let patch = serde_patch::diff_toml(old, new).unwrap();
+/// assert_eq!(
+///     patch,
+///     json!({ "server": { "port": 9090, "debug": null, "started": "2024-05-01T09:30:00Z" } })
+/// );
+/// ```
+pub fn diff_toml(old: &str, new: &str) -> Result<Value, serde_json::Error> {
+    let old = to_json(&toml::Value::Table(parse(old)?))?;
+    let new = to_json(&toml::Value::Table(parse(new)?))?;
+    crate::diff_values(&old, &new, &DiffOptions::default())
+}
+
+/// Applies a JSON Merge Patch (RFC 7396) to a TOML document.
+///
+/// Returns the updated document re-serialized as TOML. A string that replaces
+/// a datetime is parsed back into a datetime, so patches produced by
+/// [`diff_toml`] keep datetime types. Comments and layout are not kept; use
+/// `preserve::apply_toml_preserving` (feature `toml-edit`) for a
+/// formatting-preserving edit.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let document = "\
+/// [server]
+/// port = 8080
+/// started = 2024-05-01T09:30:00Z
+/// ";
+///
+/// let patch = json!({ "server": { "port": null, "started": "2024-06-01T00:00:00Z" } });
+/// let updated = serde_patch::apply_toml(document, &patch).unwrap();
+/// assert_eq!(updated, "[server]\nstarted = 2024-06-01T00:00:00Z\n");
+/// ```
+pub fn apply_toml(document: &str, patch: &Value) -> Result<String, serde_json::Error> {
+    let Value::Object(patch) = patch else {
+        return Err(serde_json::Error::custom(
+            "a TOML document can only be patched with an object",
+        ));
+    };
+    let mut table = parse(document)?;
+    merge(&mut table, patch, "")?;
+    toml::to_string(&table).map_err(serde_json::Error::custom)
+}
+
+fn parse(document: &str) -> Result<toml::Table, serde_json::Error> {
+    toml::from_str(document).map_err(serde_json::Error::custom)
+}
+
+/// Merges an object patch into a TOML table (internal).
+fn merge(
+    target: &mut toml::Table,
+    patch: &Map<String, Value>,
+    current_path: &str,
+) -> Result<(), serde_json::Error> {
+    for (key, value) in patch {
+        let full_path = crate::paths::join(current_path, key);
+        match value {
+            Value::Null => {
+                target.remove(key);
+            }
+            Value::Object(nested) => {
+                if let Some(toml::Value::Table(table)) = target.get_mut(key) {
+                    merge(table, nested, &full_path)?;
+                } else {
+                    let mut table = toml::Table::new();
+                    merge(&mut table, nested, &full_path)?;
+                    target.insert(key.clone(), toml::Value::Table(table));
+                }
+            }
+            _ => {
+                let value = match (target.get(key), value) {
+                    (Some(toml::Value::Datetime(_)), Value::String(text)) => text
+                        .parse::<Datetime>()
+                        .map_or_else(|_| toml::Value::String(text.clone()), toml::Value::Datetime),
+                    _ => toml::Value::try_from(value).map_err(|err| {
+                        serde_json::Error::custom(format_args!("{err} at `{full_path}`"))
+                    })?,
+                };
+                target.insert(key.clone(), value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Converts a TOML value to JSON, writing datetimes as strings (internal).
+fn to_json(value: &toml::Value) -> Result<Value, serde_json::Error> {
+    Ok(match value {
+        toml::Value::String(text) => Value::String(text.clone()),
+        toml::Value::Integer(number) => Value::from(*number),
+        toml::Value::Float(number) => serde_json::Number::from_f64(*number)
+            .map(Value::Number)
+            .ok_or_else(|| serde_json::Error::custom(format_args!("non-finite float {number}")))?,
+        toml::Value::Boolean(flag) => Value::Bool(*flag),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(items) => {
+            Value::Array(items.iter().map(to_json).collect::<Result<_, _>>()?)
+        }
+        toml::Value::Table(table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), to_json(value)?)))
+                .collect::<Result<_, serde_json::Error>>()?,
+        ),
+    })
+}
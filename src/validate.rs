@@ -0,0 +1,230 @@
+use crate::ApplyOptions;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Checks the patched value of a field.
+///
+/// Registered per dotted path in [`ApplyOptions::validators`](crate::ApplyOptions::validators).
+/// Runs after merging, whenever the patch sets or removes the path, a field
+/// below it, or one of its ancestors; the value is `Null` if the field is
+/// absent after the merge.
+pub trait Validator: fmt::Debug + Send + Sync {
+    /// Returns a message describing why `value` is rejected.
+    fn validate(&self, value: &Value) -> Result<(), String>;
+}
+
+/// Validators registered by dotted path.
+pub type Validators = HashMap<String, Arc<dyn Validator>>;
+
+/// Why a field could not be patched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldErrorKind {
+    /// The patch as a whole could not be used (malformed JSON, unresolvable reference).
+    Patch,
+    /// The patched value doesn't fit the target type, or a required field is missing.
+    Type,
+    /// The patch touches a path listed in [`ApplyOptions::protected`](crate::ApplyOptions::protected).
+    Protected,
+    /// A [`Validator`] rejected the patched value.
+    Invalid,
+    /// The target type has no such field, with [`ApplyOptions::deny_unknown_fields`](crate::ApplyOptions::deny_unknown_fields) set.
+    Unknown,
+}
+
+/// A field-level failure reported by [`apply_collecting`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    /// Dotted path of the field; empty for errors about the whole patch.
+    pub path: String,
+    /// Category of the failure.
+    pub kind: FieldErrorKind,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl FieldError {
+    fn patch(err: serde_json::Error) -> Vec<Self> {
+        vec![Self {
+            path: String::new(),
+            kind: FieldErrorKind::Patch,
+            message: err.to_string(),
+        }]
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{} at `{}`", self.message, self.path)
+        }
+    }
+}
+
+impl std::error::Error for FieldError {}
+
+/// Applies a JSON Merge Patch (RFC 7396), collecting every field-level failure.
+///
+/// Like [`apply_with`](crate::apply_with), which stops at the first problem,
+/// but reports all type mismatches, protected-field violations and validation
+/// failures at once, so an endpoint can answer with a complete list in a single
+/// round trip. Errors are sorted by path.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::{ApplyOptions, FieldErrorKind};
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { id: u32, name: String, age: u8 }
+///
+/// let current = User { id: 1, name: "alice".into(), age: 30 };
+/// let patch = r#"{ "id": 2, "name": 7, "age": "old" }"#;
+/// let options = ApplyOptions {
+///     protected: ["id".to_string()].into(),
+///     ..Default::default()
+/// };
+///
+/// let errors = serde_patch::apply_collecting(current, patch, &options).err().unwrap();
+/// let kinds: Vec<_> = errors.iter().map(|e| (e.path.as_str(), e.kind)).collect();
+/// assert_eq!(
+///     kinds,
+///     [
+///         ("age", FieldErrorKind::Type),
+///         ("id", FieldErrorKind::Protected),
+///         ("name", FieldErrorKind::Type),
+///     ]
+/// );
+/// ```
+pub fn apply_collecting<T, P>(
+    current: T,
+    patch: P,
+    options: &ApplyOptions,
+) -> Result<T, Vec<FieldError>>
+where
+    T: Serialize + DeserializeOwned,
+    P: AsRef<[u8]>,
+{
+    let patch_val: Value = crate::backend::from_slice(patch.as_ref()).map_err(FieldError::patch)?;
+    let original = crate::value::to_value(&current).map_err(FieldError::patch)?;
+    let (mut updated, resolved, mut errors) =
+        crate::apply_patch::apply_unchecked(&current, patch_val, options)
+            .map_err(|err| FieldError::patch(err.into()))?;
+
+    // Each type error reverts the offending field to its current value and
+    // retries, until the result deserializes or nothing more can be reverted.
+    let result = loop {
+        let err = match serde_path_to_error::deserialize::<_, T>(&updated) {
+            Ok(value) => break Some(value),
+            Err(err) => err,
+        };
+        let (pointer, path) = crate::error::field_of(&err);
+        errors.push(FieldError {
+            path,
+            kind: FieldErrorKind::Type,
+            message: err.into_inner().to_string(),
+        });
+        if !revert(&mut updated, &original, &pointer) {
+            break None;
+        }
+    };
+
+    if let Some(value) = &result
+        && options.deny_unknown_fields
+    {
+        let unknown = crate::apply_patch::unknown_fields(value, &resolved, options)
+            .map_err(FieldError::patch)?;
+        errors.extend(unknown.into_iter().map(|path| FieldError {
+            path,
+            kind: FieldErrorKind::Unknown,
+            message: "unknown field".to_string(),
+        }));
+    }
+
+    match result {
+        Some(value) if errors.is_empty() => Ok(value),
+        _ => {
+            errors.sort_by(|a, b| a.path.cmp(&b.path));
+            Err(errors)
+        }
+    }
+}
+
+/// Protected-path and validator failures of a merged patch (internal).
+pub(crate) fn check(patch: &Value, updated: &Value, options: &ApplyOptions) -> Vec<FieldError> {
+    if options.protected.is_empty() && options.validators.is_empty() {
+        return Vec::new();
+    }
+    let mut leaves = Vec::new();
+    crate::paths::collect_leaves(patch, "", &mut leaves);
+    let touched = |path: &str| leaves.iter().any(|leaf| overlaps(leaf, path));
+
+    let mut errors = Vec::new();
+    for path in &options.protected {
+        if touched(path) {
+            errors.push(FieldError {
+                path: path.clone(),
+                kind: FieldErrorKind::Protected,
+                message: "field is protected".to_string(),
+            });
+        }
+    }
+    for (path, validator) in &options.validators {
+        if !touched(path) {
+            continue;
+        }
+        let value = crate::paths::split(path)
+            .iter()
+            .try_fold(updated, |value, key| value.get(key))
+            .unwrap_or(&Value::Null);
+        if let Err(message) = validator.validate(value) {
+            errors.push(FieldError {
+                path: path.clone(),
+                kind: FieldErrorKind::Invalid,
+                message,
+            });
+        }
+    }
+    errors.sort_by(|a, b| a.path.cmp(&b.path));
+    errors
+}
+
+/// Returns `true` if one path equals or contains the other (internal).
+fn overlaps(a: &str, b: &str) -> bool {
+    let contains = |outer: &str, inner: &str| {
+        outer.is_empty()
+            || inner
+                .strip_prefix(outer)
+                .is_some_and(|rest| rest.starts_with('.'))
+    };
+    a == b || contains(a, b) || contains(b, a)
+}
+
+/// Restores the value at `pointer` to its original state; `false` if nothing changed (internal).
+fn revert(updated: &mut Value, original: &Value, pointer: &str) -> bool {
+    let Some((parent, key)) = pointer.rsplit_once('/') else {
+        return false;
+    };
+    let key = key.replace("~1", "/").replace("~0", "~");
+    let before = original.pointer(pointer).cloned();
+    match (updated.pointer_mut(parent), before) {
+        (Some(Value::Object(map)), Some(before)) if map.get(&key) != Some(&before) => {
+            map.insert(key, before);
+            true
+        }
+        (Some(Value::Object(map)), None) => map.remove(&key).is_some(),
+        (Some(Value::Array(items)), Some(before)) => match key.parse::<usize>() {
+            Ok(index) if index < items.len() && items[index] != before => {
+                items[index] = before;
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
@@ -0,0 +1,24 @@
+use serde::Serialize;
+use serde::ser::Error as _;
+use serde_json::Value;
+
+/// The key `serde_json` wraps numbers in with `arbitrary_precision`, when
+/// they are read through `deserialize_any` (internal).
+#[cfg(feature = "arbitrary-precision")]
+pub(crate) const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Serializes a value into a `serde_json::Value`, naming the failing field on error (internal).
+///
+/// Plain `serde_json::to_value` reports e.g. "path contains invalid UTF-8
+/// characters" with no hint of which field caused it.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, serde_json::Error> {
+    serde_path_to_error::serialize(value, serde_json::value::Serializer).map_err(|err| {
+        let path = err.path().to_string();
+        let inner = err.into_inner();
+        if path == "." {
+            inner
+        } else {
+            serde_json::Error::custom(format!("{} at `{}`", inner, path))
+        }
+    })
+}
@@ -0,0 +1,145 @@
+use crate::{ApplyOptions, DiffOptions, PatchEnvelope};
+use serde::de::Error as _;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+/// Schema version and migration hook of a patchable type.
+///
+/// Implement this for types whose patches are persisted, so that patches
+/// written against an older layout can be rewritten before they are applied.
+pub trait Migrate {
+    /// Current schema version of the type.
+    const SCHEMA_VERSION: u32;
+
+    /// Rewrites a patch written for `from_version` into the layout of `to_version`.
+    ///
+    /// Only called when `from_version < to_version`.
+    fn migrate(
+        patch: &mut Value,
+        from_version: u32,
+        to_version: u32,
+    ) -> Result<(), serde_json::Error>;
+}
+
+/// Computes a JSON diff wrapped in a [`PatchEnvelope`] stamped with
+/// `T::SCHEMA_VERSION`.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::{Value, json};
+/// use serde_patch::Migrate;
+///
+/// #[derive(serde::Serialize)]
+/// struct User { id: u32, name: String }
+///
+/// impl Migrate for User {
+///     const SCHEMA_VERSION: u32 = 2;
+///
+///     fn migrate(_: &mut Value, _: u32, _: u32) -> Result<(), serde_json::Error> {
+///         Ok(())
+///     }
+/// }
+///
+/// let old = User { id: 1, name: "old".to_string() };
+/// let new = User { id: 1, name: "new".to_string() };
+///
+/// let versioned = serde_patch::diff_versioned(&old, &new).unwrap();
+/// assert_eq!(versioned.schema_version, Some(2));
+/// assert_eq!(versioned.patch, json!({ "name": "new" }));
+/// ```
+pub fn diff_versioned<T>(old: &T, new: &T) -> Result<PatchEnvelope, serde_json::Error>
+where
+    T: Serialize + Migrate,
+{
+    Ok(PatchEnvelope {
+        schema_version: Some(T::SCHEMA_VERSION),
+        ..PatchEnvelope::new(crate::diff_with(old, new, &DiffOptions::default())?)
+    })
+}
+
+/// Applies a serialized [`PatchEnvelope`], migrating its patch first if it
+/// targets an older schema.
+///
+/// Consumes the current value and returns the updated value.
+///
+/// Fails with [`Error::Rejected`](crate::Error::Rejected) instead of guessing when:
+/// - the envelope has no `schema_version`;
+/// - the patch targets a newer schema version than `T::SCHEMA_VERSION`;
+/// - a field set by the (migrated) patch does not exist on `T` and would
+///   otherwise be silently dropped.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::Value;
+/// use serde_patch::Migrate;
+///
+/// #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+/// struct User { id: u32, display_name: String }
+///
+/// impl Migrate for User {
+///     const SCHEMA_VERSION: u32 = 2;
+///
+///     fn migrate(patch: &mut Value, from: u32, _to: u32) -> Result<(), serde_json::Error> {
+///         // v1 called the field "name"
+///         if let (1, Some(map)) = (from, patch.as_object_mut()) {
+///             if let Some(name) = map.remove("name") {
+///                 map.insert("display_name".to_string(), name);
+///             }
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let current = User { id: 1, display_name: "old".to_string() };
+/// let patch = r#"{ "schema_version": 1, "patch": { "name": "new" } }"#;
+///
+/// let updated = serde_patch::apply_versioned(current, patch).unwrap();
+/// assert_eq!(updated.display_name, "new");
+/// ```
+pub fn apply_versioned<T, P>(current: T, patch: P) -> Result<T, crate::Error>
+where
+    T: Serialize + DeserializeOwned + Migrate,
+    P: AsRef<[u8]>,
+{
+    let envelope: PatchEnvelope =
+        crate::backend::from_slice(patch.as_ref()).map_err(crate::Error::ParsePatch)?;
+    let mut patch_val = envelope.patch;
+    let Some(schema_version) = envelope.schema_version else {
+        return Err(crate::Error::Rejected(serde_json::Error::custom(
+            "patch has no schema version",
+        )));
+    };
+
+    if schema_version > T::SCHEMA_VERSION {
+        return Err(crate::Error::Rejected(serde_json::Error::custom(format!(
+            "patch targets schema version {} but the current schema version is {}",
+            schema_version,
+            T::SCHEMA_VERSION
+        ))));
+    }
+    if schema_version < T::SCHEMA_VERSION {
+        T::migrate(&mut patch_val, schema_version, T::SCHEMA_VERSION)
+            .map_err(crate::Error::Rejected)?;
+    }
+
+    let (updated, report) =
+        crate::report::apply_value_with_report(&current, patch_val, &ApplyOptions::default())?;
+
+    let dropped: Vec<&str> = report
+        .lossy
+        .iter()
+        .filter(|lossy| lossy.applied.is_none())
+        .map(|lossy| lossy.path.as_str())
+        .collect();
+    if !dropped.is_empty() {
+        return Err(crate::Error::Rejected(serde_json::Error::custom(format!(
+            "patch fields do not exist in schema version {}: {}",
+            T::SCHEMA_VERSION,
+            dropped.join(", ")
+        ))));
+    }
+
+    Ok(updated)
+}
@@ -0,0 +1,122 @@
+//! `warp` filter for JSON Merge Patch (RFC 7396) request bodies.
+//!
+//! [`merge_patch`] gives warp routes what the `axum` and `actix` extractors
+//! give theirs: a content type check, a body size cap and parsing, with
+//! failures as [`PatchRejection`]s a `recover` handler can turn into
+//! responses.
+
+use ::warp::http::StatusCode;
+use ::warp::{Filter, Rejection};
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// Extracts a merge patch request body.
+///
+/// Requires `Content-Type: application/merge-patch+json` and a
+/// `Content-Length` of at most `limit` bytes (warp rejects larger bodies with
+/// `413` and bodies without a length with `411`), then parses the body into
+/// `T`: a [`Patch`](crate::Patch), ready for
+/// [`Patch::apply_to`](crate::Patch::apply_to), or a typed patch struct from
+/// `#[derive(Patch)]`. Other failures are [`PatchRejection`]s.
+///
+/// # Example
+///
+/// ```
+/// use serde_patch::Patch;
+/// use warp::Filter;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct User { name: String, age: u8 }
+///
+/// let route = warp::patch()
+///     .and(warp::path!("users" / u32))
+///     .and(serde_patch::warp::merge_patch::<Patch>(64 * 1024))
+///     .map(|_id: u32, patch: Patch| {
+///         let user = User { name: "alice".into(), age: 30 };
+///         match patch.apply_to(user) {
+///             Ok(user) => format!("{} is {}", user.name, user.age),
+///             Err(err) => err.to_string(),
+///         }
+///     });
+/// ```
+pub fn merge_patch<T>(limit: u64) -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    ::warp::header::optional::<String>("content-type")
+        .and_then(|content_type: Option<String>| async move {
+            if content_type.as_deref().is_some_and(is_merge_patch) {
+                Ok(())
+            } else {
+                Err(::warp::reject::custom(PatchRejection::UnsupportedMediaType))
+            }
+        })
+        .untuple_one()
+        .and(::warp::body::content_length_limit(limit))
+        .and(::warp::body::bytes())
+        .and_then(parse)
+}
+
+/// Why [`merge_patch`] rejected a request.
+///
+/// Find it with `Rejection::find` in a `recover` handler; [`status`] gives
+/// the status code to answer with.
+///
+/// [`status`]: PatchRejection::status
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PatchRejection {
+    /// The `Content-Type` isn't `application/merge-patch+json` (415).
+    UnsupportedMediaType,
+    /// The body isn't valid JSON (400).
+    Syntax(serde_json::Error),
+    /// The JSON doesn't fit the patch type, with the offending path (422).
+    Data(String),
+}
+
+impl PatchRejection {
+    /// The status code the rejection should be answered with.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Syntax(_) => StatusCode::BAD_REQUEST,
+            Self::Data(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl fmt::Display for PatchRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedMediaType => write!(
+                f,
+                "expected request with `Content-Type: {}`",
+                crate::MERGE_PATCH_MEDIA_TYPE
+            ),
+            Self::Syntax(err) => write!(f, "invalid merge patch: {err}"),
+            Self::Data(message) => write!(f, "invalid merge patch: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for PatchRejection {}
+
+impl ::warp::reject::Reject for PatchRejection {}
+
+/// Parses a request body into the patch type (internal).
+async fn parse<T: DeserializeOwned>(body: impl AsRef<[u8]>) -> Result<T, Rejection> {
+    let value: serde_json::Value = crate::backend::from_slice(body.as_ref())
+        .map_err(|err| ::warp::reject::custom(PatchRejection::Syntax(err)))?;
+    serde_path_to_error::deserialize(value)
+        .map_err(|err| ::warp::reject::custom(PatchRejection::Data(err.to_string())))
+}
+
+/// Whether a `Content-Type` value declares a merge patch; parameters such as
+/// `charset` are allowed (internal).
+fn is_merge_patch(content_type: &str) -> bool {
+    content_type.split(';').next().is_some_and(|media_type| {
+        media_type
+            .trim()
+            .eq_ignore_ascii_case(crate::MERGE_PATCH_MEDIA_TYPE)
+    })
+}
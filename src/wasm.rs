@@ -0,0 +1,84 @@
+//! WebAssembly bindings, so browser clients compute and apply patches with
+//! the same semantics as the Rust backend.
+//!
+//! Documents and patches cross the boundary as JSON strings (`JSON.stringify`
+//! / `JSON.parse` on the JavaScript side), which keeps numbers and key order
+//! exactly as this crate sees them. Failures are thrown as `Error`s carrying
+//! this crate's message.
+//!
+//! ```js
+//! import init, { diff, diffIncluding, apply } from "./pkg/serde_patch.js";
+//!
+//! await init();
+//! const patch = diff(JSON.stringify(saved), JSON.stringify(edited));
+//! const updated = JSON.parse(apply(JSON.stringify(saved), patch));
+//! ```
+
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+/// Computes the merge patch turning `old` into `new`, as
+/// [`diff`](crate::diff) does for the deserialized documents.
+///
+/// # Example
+///
+/// ```
+/// let patch = serde_patch::wasm::diff(r#"{"id":1,"name":"old"}"#, r#"{"id":1,"name":"new"}"#);
+/// assert_eq!(patch.ok().as_deref(), Some(r#"{"name":"new"}"#));
+/// ```
+#[wasm_bindgen]
+pub fn diff(old: &str, new: &str) -> Result<String, JsError> {
+    diff_json(old, new, &[]).map_err(js_error)
+}
+
+/// Computes the merge patch turning `old` into `new`, always including the
+/// fields at `including`, as [`diff_including`](crate::diff_including) does.
+///
+/// # Example
+///
+/// ```
+/// let patch = serde_patch::wasm::diff_including(
+///     r#"{"id":1,"name":"old"}"#,
+///     r#"{"id":1,"name":"new"}"#,
+///     vec!["id".to_string()],
+/// );
+/// assert_eq!(patch.ok().as_deref(), Some(r#"{"id":1,"name":"new"}"#));
+/// ```
+#[wasm_bindgen(js_name = diffIncluding)]
+pub fn diff_including(old: &str, new: &str, including: Vec<String>) -> Result<String, JsError> {
+    diff_json(old, new, &including).map_err(js_error)
+}
+
+/// Applies a merge patch to a document, returning the patched document, as
+/// [`apply`](crate::apply) does for a `serde_json::Value`.
+///
+/// # Example
+///
+/// ```
+/// let updated = serde_patch::wasm::apply(r#"{"id":1,"name":"old"}"#, r#"{"name":"new"}"#);
+/// assert_eq!(updated.ok().as_deref(), Some(r#"{"id":1,"name":"new"}"#));
+/// ```
+#[wasm_bindgen]
+pub fn apply(current: &str, patch: &str) -> Result<String, JsError> {
+    apply_json(current, patch).map_err(js_error)
+}
+
+pub(crate) fn diff_json(
+    old: &str,
+    new: &str,
+    including: &[String],
+) -> Result<String, serde_json::Error> {
+    let old: Value = serde_json::from_str(old)?;
+    let new: Value = serde_json::from_str(new)?;
+    let including: Vec<&str> = including.iter().map(String::as_str).collect();
+    serde_json::to_string(&crate::diff_including(&old, &new, &including)?)
+}
+
+pub(crate) fn apply_json(current: &str, patch: &str) -> Result<String, serde_json::Error> {
+    let current: Value = serde_json::from_str(current)?;
+    serde_json::to_string(&crate::apply(current, patch)?)
+}
+
+fn js_error(err: serde_json::Error) -> JsError {
+    JsError::new(&err.to_string())
+}
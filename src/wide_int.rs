@@ -0,0 +1,100 @@
+//! Serde helpers for `u128` / `i128` fields in patched types.
+//!
+//! Patches pass through `serde_json::Value`, whose numbers stop at 64 bits:
+//! without the `arbitrary-precision` feature, diffing or applying a patch to
+//! a value with a 128-bit field outside that range fails with "number out of
+//! range". `#[serde(with = "serde_patch::wide_int")]` writes such a field as
+//! a string of digits instead, so every value survives, and reads it back
+//! from a string or from any JSON integer.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//!
+//! #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+//! struct Account {
+//!     #[serde(with = "serde_patch::wide_int")]
+//!     balance: u128,
+//! }
+//!
+//! let old = Account { balance: u128::MAX - 1 };
+//! let new = Account { balance: u128::MAX };
+//!
+//! let patch = serde_patch::diff(&old, &new).unwrap();
+//! assert_eq!(patch, json!({ "balance": u128::MAX.to_string() }));
+//! assert_eq!(serde_patch::apply(old, &patch.to_string()).unwrap(), new);
+//!
+//! // Clients may also send small values as plain numbers.
+//! let small = serde_patch::apply(new, r#"{ "balance": 42 }"#).unwrap();
+//! assert_eq!(small.balance, 42);
+//! ```
+
+use serde::de::{self, Visitor};
+use serde::{Deserializer, Serializer};
+use std::fmt::{self, Display};
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// Serializes an integer as a string of digits.
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Display,
+    S: Serializer,
+{
+    serializer.collect_str(value)
+}
+
+/// Deserializes an integer from a string of digits or a JSON integer.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr,
+    T::Err: Display,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(WideIntVisitor(PhantomData))
+}
+
+struct WideIntVisitor<T>(PhantomData<T>);
+
+impl<T> WideIntVisitor<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn parse<E: de::Error>(digits: &str) -> Result<T, E> {
+        digits.parse().map_err(E::custom)
+    }
+}
+
+impl<'de, T> Visitor<'de> for WideIntVisitor<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an integer or a string of digits")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        Self::parse(value)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<T, E> {
+        Self::parse(&value.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<T, E> {
+        Self::parse(&value.to_string())
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<T, E> {
+        Self::parse(&value.to_string())
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<T, E> {
+        Self::parse(&value.to_string())
+    }
+}
@@ -0,0 +1,90 @@
+use crate::Format;
+use serde::de::Error as _;
+use serde_json::Value;
+
+/// YAML as a [`Format`].
+///
+/// Mapping keys must be strings; anchors and aliases are expanded, and
+/// comments are not kept.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Yaml;
+
+impl Format for Yaml {
+    fn parse(bytes: &[u8]) -> Result<Value, serde_json::Error> {
+        serde_yaml::from_slice(bytes).map_err(serde_json::Error::custom)
+    }
+
+    fn serialize(value: &Value) -> Result<Vec<u8>, serde_json::Error> {
+        serde_yaml::to_string(value)
+            .map(String::into_bytes)
+            .map_err(serde_json::Error::custom)
+    }
+}
+
+/// Parses a YAML document into a JSON value.
+///
+/// Mapping keys must be strings, as in Kubernetes manifests and most config
+/// files; anchors and aliases are expanded.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+///
+/// let value = serde_patch::parse_yaml("replicas: 3\nimage: app:1.4\n").unwrap();
+/// assert_eq!(value, json!({ "replicas": 3, "image": "app:1.4" }));
+/// ```
+pub fn parse_yaml(document: &str) -> Result<Value, serde_json::Error> {
+    Yaml::parse(document.as_bytes())
+}
+
+/// Computes a JSON Merge Patch (RFC 7396) between two YAML documents.
+///
+/// The patch is returned as YAML text, with removed keys written as `null`.
+///
+/// # Example
+///
+/// ```
+/// let old = "\
+/// spec:
+///   replicas: 2
+///   paused: true
+/// ";
+/// let new = "\
+/// spec:
+///   replicas: 5
+/// ";
+///
+/// let patch = serde_patch::diff_yaml(old, new).unwrap();
+/// assert_eq!(patch, "spec:\n  paused: null\n  replicas: 5\n");
+/// ```
+pub fn diff_yaml(old: &str, new: &str) -> Result<String, serde_json::Error> {
+    text(Yaml::diff_documents(old.as_bytes(), new.as_bytes())?)
+}
+
+/// Applies a JSON Merge Patch (RFC 7396) written in YAML to a YAML document.
+///
+/// Returns the updated document re-serialized as YAML. Comments and key order
+/// are not kept; use `preserve::apply_yaml_preserving` (feature `preserve`)
+/// for a formatting-preserving edit.
+///
+/// # Example
+///
+/// ```
+/// let document = "\
+/// spec:
+///   replicas: 2
+///   paused: true
+/// ";
+///
+/// let updated = serde_patch::apply_yaml(document, "spec: { replicas: 5, paused: null }").unwrap();
+/// assert_eq!(updated, "spec:\n  replicas: 5\n");
+/// ```
+pub fn apply_yaml(document: &str, patch: &str) -> Result<String, serde_json::Error> {
+    text(Yaml::apply_document(document.as_bytes(), patch.as_bytes())?)
+}
+
+/// YAML output as text; `serde_yaml` only writes UTF-8 (internal).
+fn text(bytes: Vec<u8>) -> Result<String, serde_json::Error> {
+    String::from_utf8(bytes).map_err(serde_json::Error::custom)
+}